@@ -19,9 +19,13 @@ pub fn build_tauri_app() -> Builder<tauri::Wry> {
         .invoke_handler(tauri::generate_handler![
             // 基础应用命令
             get_app_info,
+            crate::app::safe_mode::get_safe_mode_status,
+            crate::app::safe_mode::set_safe_mode_on_next_start,
             get_always_on_top,
             set_always_on_top,
             sync_window_state,
+            get_close_to_tray,
+            set_close_to_tray,
             reload_config,
 
             // 音频命令
@@ -48,7 +52,10 @@ pub fn build_tauri_app() -> Builder<tauri::Wry> {
             get_window_constraints_cmd,
             get_current_window_size,
             apply_window_constraints,
+            set_window_always_on_top,
             update_window_size,
+            get_monitors,
+            center_main_window,
 
             // 字体命令
             get_font_config,
@@ -74,28 +81,86 @@ pub fn build_tauri_app() -> Builder<tauri::Wry> {
             
             // acemcp命令（迁移至 tools::acemcp::commands）
             crate::mcp::tools::acemcp::commands::get_acemcp_config,
+            crate::mcp::tools::acemcp::commands::export_acemcp_settings,
+            crate::mcp::tools::acemcp::commands::import_acemcp_settings,
+            crate::mcp::tools::acemcp::commands::is_acemcp_configured,
             crate::mcp::tools::acemcp::commands::save_acemcp_config,
             crate::mcp::tools::acemcp::commands::test_acemcp_connection,
+            crate::mcp::tools::acemcp::commands::retry_acemcp_connection,
+            crate::mcp::tools::acemcp::commands::get_acemcp_health,
+            crate::mcp::tools::acemcp::commands::format_acemcp_diagnostics_text,
             crate::mcp::tools::acemcp::commands::read_acemcp_logs,
+            crate::mcp::tools::acemcp::commands::read_acemcp_log_entries,
+            crate::mcp::tools::acemcp::commands::start_acemcp_log_stream,
+            crate::mcp::tools::acemcp::commands::stop_acemcp_log_stream,
             crate::mcp::tools::acemcp::commands::clear_acemcp_cache,
+            crate::mcp::tools::acemcp::commands::open_acemcp_data_dir,
+            crate::mcp::tools::acemcp::commands::open_acemcp_log_dir,
             crate::mcp::tools::acemcp::commands::debug_acemcp_search,
+            crate::mcp::tools::acemcp::commands::debug_acemcp_search_batch,
+            crate::mcp::tools::acemcp::commands::assert_acemcp_search_contains,
+            crate::mcp::tools::acemcp::commands::get_last_test_query,
+            crate::mcp::tools::acemcp::commands::debug_acemcp_search_streaming,
+            crate::mcp::tools::acemcp::commands::cancel_acemcp_search,
+            crate::mcp::tools::acemcp::commands::get_debug_search_history,
+            crate::mcp::tools::acemcp::commands::save_debug_search_query,
+            crate::mcp::tools::acemcp::commands::clear_debug_search_history,
+            crate::mcp::tools::acemcp::commands::get_debug_search_templates,
+            crate::mcp::tools::acemcp::commands::save_debug_search_template,
+            crate::mcp::tools::acemcp::commands::delete_debug_search_template,
+            crate::mcp::tools::acemcp::commands::render_debug_search_template,
             crate::mcp::tools::acemcp::commands::execute_acemcp_tool,
+            crate::mcp::tools::acemcp::commands::list_acemcp_tools,
             crate::mcp::tools::acemcp::commands::get_acemcp_index_status,
+            crate::mcp::tools::acemcp::commands::reset_acemcp_project_status,
             crate::mcp::tools::acemcp::commands::get_all_acemcp_index_status,
+            crate::mcp::tools::acemcp::commands::get_acemcp_index_summary,
             crate::mcp::tools::acemcp::commands::get_acemcp_project_files_status,
+            crate::mcp::tools::acemcp::commands::get_acemcp_index_coverage,
+            crate::mcp::tools::acemcp::commands::get_acemcp_directory_stats,
+            crate::mcp::tools::acemcp::commands::stream_acemcp_project_files_status,
+            crate::mcp::tools::acemcp::commands::cancel_project_files_status,
+            crate::mcp::tools::acemcp::commands::preview_acemcp_exclusions,
+            crate::mcp::tools::acemcp::commands::preview_acemcp_file_chunks,
+            crate::mcp::tools::acemcp::commands::estimate_acemcp_index,
+            crate::mcp::tools::acemcp::commands::verify_acemcp_index,
+            crate::mcp::tools::acemcp::commands::set_project_acemcp_override,
+            crate::mcp::tools::acemcp::commands::clear_project_acemcp_override,
+            crate::mcp::tools::acemcp::commands::list_project_acemcp_overrides,
             crate::mcp::tools::acemcp::commands::trigger_acemcp_index_update,
+            crate::mcp::tools::acemcp::commands::resume_acemcp_index,
+            crate::mcp::tools::acemcp::commands::reindex_all_projects,
+            crate::mcp::tools::acemcp::commands::get_acemcp_usage_today,
             crate::mcp::tools::acemcp::commands::get_auto_index_enabled,
             crate::mcp::tools::acemcp::commands::set_auto_index_enabled,
+            crate::mcp::tools::acemcp::commands::get_project_auto_index,
+            crate::mcp::tools::acemcp::commands::set_project_auto_index,
             crate::mcp::tools::acemcp::commands::get_watching_projects,
+            crate::mcp::tools::acemcp::commands::get_watcher_stats,
             crate::mcp::tools::acemcp::commands::is_project_watching,
             crate::mcp::tools::acemcp::commands::start_project_watching,
             crate::mcp::tools::acemcp::commands::stop_project_watching,
             crate::mcp::tools::acemcp::commands::stop_all_watching,
+            crate::mcp::tools::acemcp::commands::flush_project_watch,
+            crate::mcp::tools::acemcp::commands::add_project_watch_exclusion,
+            crate::mcp::tools::acemcp::commands::remove_project_watch_exclusion,
             crate::mcp::tools::acemcp::commands::remove_acemcp_project_index,
             crate::mcp::tools::acemcp::commands::check_directory_exists,
+            crate::mcp::tools::acemcp::commands::list_orphaned_acemcp_projects,
+            crate::mcp::tools::acemcp::commands::prune_orphaned_acemcp_projects,
             // acemcp 代理命令
             crate::mcp::tools::acemcp::commands::detect_acemcp_proxy,
+            crate::mcp::tools::acemcp::commands::get_last_known_good_proxy,
+            crate::mcp::tools::acemcp::commands::auto_configure_acemcp_proxy,
             crate::mcp::tools::acemcp::commands::test_acemcp_proxy_speed,
+            crate::mcp::tools::acemcp::commands::test_proxy_connectivity,
+            crate::mcp::tools::acemcp::commands::get_proxy_recommendation_trend,
+            crate::mcp::tools::acemcp::commands::measure_acemcp_bandwidth,
+            crate::mcp::tools::acemcp::commands::benchmark_local_read,
+            crate::mcp::tools::acemcp::commands::list_proxy_profiles,
+            crate::mcp::tools::acemcp::commands::save_proxy_profile,
+            crate::mcp::tools::acemcp::commands::delete_proxy_profile,
+            crate::mcp::tools::acemcp::commands::activate_proxy_profile,
 
             // context7命令（迁移至 tools::context7::commands）
             crate::mcp::tools::context7::commands::test_context7_connection,
@@ -118,6 +183,7 @@ pub fn build_tauri_app() -> Builder<tauri::Wry> {
 
             // 配置管理命令
             get_config_file_path,
+            get_config_file_info,
 
             // Telegram 命令
             get_telegram_config,
@@ -136,13 +202,22 @@ pub fn build_tauri_app() -> Builder<tauri::Wry> {
             // 更新命令
             check_for_updates,
             download_and_install_update,
+            pause_update_download,
+            resume_update_download,
             get_current_version,
+            can_self_update,
             restart_app,
+            check_disk_space,
+            get_last_update_result,
+            skip_update_version,
+            clear_skipped_update,
+            explain_proxy_decision,
 
             // 代理配置命令
             crate::network::commands::get_proxy_config,
             crate::network::commands::set_proxy_config,
             crate::network::commands::test_proxy_connection,
+            crate::network::commands::test_proxy_dns,
             crate::network::commands::detect_available_proxy
         ])
         .setup(|app| {
@@ -0,0 +1,89 @@
+// 安全模式：配置损坏或代理卡死导致应用无法使用时的逃生通道
+// 启用方式：环境变量 SANSHU_SAFE_MODE=1，或在配置目录下创建 safe_mode 标记文件
+// 安全模式下只加载内存中的默认配置，并跳过代理检测、文件监听和更新检查，便于进入界面修复设置
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::path::PathBuf;
+
+/// 启用安全模式的环境变量
+pub const SAFE_MODE_ENV: &str = "SANSHU_SAFE_MODE";
+
+/// 配置目录下的安全模式标记文件名
+const SAFE_MODE_FLAG_FILE: &str = "safe_mode";
+
+/// 安全模式状态（前端据此显示提示横幅）
+#[derive(Debug, Clone, Serialize)]
+pub struct SafeModeStatus {
+    /// 本次启动是否处于安全模式
+    pub active: bool,
+    /// 触发来源："env" / "flag_file"
+    pub source: Option<String>,
+    /// 标记文件路径（用于提示用户手动删除）
+    pub flag_file: Option<String>,
+}
+
+/// 进程启动时确定一次，运行期间不变
+static SAFE_MODE_SOURCE: Lazy<Option<&'static str>> = Lazy::new(detect_safe_mode);
+
+fn safe_mode_flag_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("sanshu").join(SAFE_MODE_FLAG_FILE))
+}
+
+fn env_enabled(value: &str) -> bool {
+    matches!(value.trim().to_ascii_lowercase().as_str(), "1" | "true" | "yes" | "on")
+}
+
+fn detect_safe_mode() -> Option<&'static str> {
+    if std::env::var(SAFE_MODE_ENV).map(|v| env_enabled(&v)).unwrap_or(false) {
+        return Some("env");
+    }
+    if safe_mode_flag_path().map(|p| p.exists()).unwrap_or(false) {
+        return Some("flag_file");
+    }
+    None
+}
+
+/// 当前进程是否处于安全模式
+pub fn is_safe_mode() -> bool {
+    SAFE_MODE_SOURCE.is_some()
+}
+
+/// 获取安全模式状态
+#[tauri::command]
+pub fn get_safe_mode_status() -> Result<SafeModeStatus, String> {
+    Ok(SafeModeStatus {
+        active: is_safe_mode(),
+        source: SAFE_MODE_SOURCE.map(|s| s.to_string()),
+        flag_file: safe_mode_flag_path().map(|p| p.to_string_lossy().to_string()),
+    })
+}
+
+/// 设置下次启动是否进入安全模式（创建/删除标记文件，本次运行不受影响）
+#[tauri::command]
+pub fn set_safe_mode_on_next_start(enabled: bool) -> Result<(), String> {
+    let path = safe_mode_flag_path().ok_or("无法获取配置目录")?;
+    if enabled {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| format!("创建配置目录失败: {}", e))?;
+        }
+        std::fs::write(&path, b"").map_err(|e| format!("创建安全模式标记失败: {}", e))?;
+        log::info!("🛟 已设置下次启动进入安全模式: {:?}", path);
+    } else if path.exists() {
+        std::fs::remove_file(&path).map_err(|e| format!("删除安全模式标记失败: {}", e))?;
+        log::info!("🛟 已取消安全模式标记，下次正常启动");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_env_enabled() {
+        assert!(env_enabled("1"));
+        assert!(env_enabled(" TRUE "));
+        assert!(!env_enabled("0"));
+        assert!(!env_enabled(""));
+    }
+}
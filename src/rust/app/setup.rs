@@ -1,3 +1,4 @@
+use crate::app::safe_mode::{is_safe_mode, SAFE_MODE_ENV};
 use crate::config::{AppState, load_config_and_apply_window_settings};
 use crate::ui::{initialize_audio_asset_manager, setup_window_event_listeners};
 use crate::ui::exit_handler::setup_exit_handlers;
@@ -10,8 +11,15 @@ use tokio::time::sleep;
 pub async fn setup_application(app_handle: &AppHandle) -> Result<(), String> {
     let state = app_handle.state::<AppState>();
 
-    // 加载配置并应用窗口设置
-    if let Err(e) = load_config_and_apply_window_settings(&state, app_handle).await {
+    // 安全模式：不读取配置文件，使用内存中的默认配置；代理检测、文件监听和更新检查由各自入口跳过
+    if is_safe_mode() {
+        log_important!(warn, "==============================================");
+        log_important!(warn, "🛟 安全模式已启用（{} 或配置目录下的 safe_mode 标记文件）", SAFE_MODE_ENV);
+        log_important!(warn, "🛟 已使用默认配置启动，跳过代理检测、文件监听和更新检查");
+        log_important!(warn, "🛟 修复设置后保存即可覆盖配置文件（首次保存前原配置会备份为 config.json.safe-mode-<时间>.bak）");
+        log_important!(warn, "==============================================");
+    } else if let Err(e) = load_config_and_apply_window_settings(&state, app_handle).await {
+        // 加载配置并应用窗口设置
         log_important!(warn, "加载配置失败: {}", e);
     }
 
@@ -30,7 +38,7 @@ pub async fn setup_application(app_handle: &AppHandle) -> Result<(), String> {
 
     // 应用设置后显示窗口，避免启动时闪烁到默认位置
     if let Some(window) = app_handle.get_webview_window("main") {
-        let (target_width, target_height, pos) = {
+        let (target_width, target_height, pos, always_on_top) = {
             let config = state
                 .config
                 .lock()
@@ -45,6 +53,7 @@ pub async fn setup_application(app_handle: &AppHandle) -> Result<(), String> {
                 width,
                 height,
                 (window_config.position_x, window_config.position_y),
+                config.ui_config.always_on_top,
             )
         };
 
@@ -59,6 +68,10 @@ pub async fn setup_application(app_handle: &AppHandle) -> Result<(), String> {
             if let Err(e) = window.show() {
                 log_important!(warn, "显示主窗口失败: {}", e);
             }
+            // 部分平台 hide/show 后会丢失置顶属性，显示后再应用一次已保存的状态
+            if let Err(e) = window.set_always_on_top(always_on_top) {
+                log_important!(warn, "恢复窗口置顶状态失败: {}", e);
+            }
         });
     }
 
@@ -0,0 +1,89 @@
+use crate::constants::mcp;
+
+use super::settings::{
+    AppConfig, default_acemcp_exclude_patterns, default_acemcp_text_extensions, default_mcp_tools,
+};
+
+/// 当前配置结构版本
+/// 新增需要回填默认值的字段时递增，并在 migrate_config 中补充对应的升级步骤
+pub const CURRENT_CONFIG_VERSION: u32 = 1;
+
+/// 将旧版本配置升级到当前版本，返回配置是否发生变化（需要写回文件）
+///
+/// 每个版本的升级步骤只执行一次：已由用户设置的值不会被覆盖
+pub fn migrate_config(config: &mut AppConfig) -> bool {
+    let from_version = config.config_version;
+    if from_version >= CURRENT_CONFIG_VERSION {
+        return false;
+    }
+
+    if from_version < 1 {
+        migrate_v0_to_v1(config);
+    }
+
+    config.config_version = CURRENT_CONFIG_VERSION;
+    log::info!("配置已从 v{} 升级到 v{}", from_version, CURRENT_CONFIG_VERSION);
+    true
+}
+
+/// v0 -> v1：回填 acemcp 默认值，补齐新增的 MCP 工具开关
+fn migrate_v0_to_v1(config: &mut AppConfig) {
+    for (tool, enabled) in default_mcp_tools() {
+        config.mcp_config.tools.entry(tool).or_insert(enabled);
+    }
+
+    let mcp_config = &mut config.mcp_config;
+    mcp_config.acemcp_batch_size.get_or_insert(mcp::DEFAULT_ACEMCP_BATCH_SIZE);
+    mcp_config
+        .acemcp_max_lines_per_blob
+        .get_or_insert(mcp::DEFAULT_ACEMCP_MAX_LINES_PER_BLOB);
+    mcp_config
+        .acemcp_text_extensions
+        .get_or_insert_with(default_acemcp_text_extensions);
+    mcp_config
+        .acemcp_exclude_patterns
+        .get_or_insert_with(default_acemcp_exclude_patterns);
+    mcp_config
+        .acemcp_watch_debounce_ms
+        .get_or_insert(mcp::DEFAULT_ACEMCP_WATCH_DEBOUNCE_MS);
+    mcp_config
+        .acemcp_max_concurrent_uploads
+        .get_or_insert(mcp::DEFAULT_ACEMCP_MAX_CONCURRENT_UPLOADS);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrate_v0_config() {
+        // v0 配置：没有 config_version，acemcp 字段只填了一部分，工具列表缺少 context7
+        let v0 = r#"{
+            "mcp_config": {
+                "tools": { "zhi": true, "ji": false, "sou": true },
+                "acemcp_base_url": "https://ace.example.com",
+                "acemcp_batch_size": 20
+            }
+        }"#;
+        let mut config: AppConfig = serde_json::from_str(v0).unwrap();
+        assert_eq!(config.config_version, 0);
+
+        assert!(migrate_config(&mut config));
+        assert_eq!(config.config_version, CURRENT_CONFIG_VERSION);
+
+        let mcp_config = &config.mcp_config;
+        // 用户已设置的值保持不变
+        assert_eq!(mcp_config.acemcp_batch_size, Some(20));
+        assert_eq!(mcp_config.tools.get("ji"), Some(&false));
+        assert_eq!(mcp_config.acemcp_base_url.as_deref(), Some("https://ace.example.com"));
+        // 缺失的值被回填为默认值
+        assert_eq!(mcp_config.tools.get("context7"), Some(&true));
+        assert_eq!(mcp_config.acemcp_max_lines_per_blob, Some(mcp::DEFAULT_ACEMCP_MAX_LINES_PER_BLOB));
+        assert_eq!(mcp_config.acemcp_text_extensions, Some(default_acemcp_text_extensions()));
+        assert_eq!(mcp_config.acemcp_exclude_patterns, Some(default_acemcp_exclude_patterns()));
+        assert_eq!(mcp_config.acemcp_watch_debounce_ms, Some(mcp::DEFAULT_ACEMCP_WATCH_DEBOUNCE_MS));
+
+        // 再次迁移为空操作
+        assert!(!migrate_config(&mut config));
+    }
+}
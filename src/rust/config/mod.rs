@@ -1,5 +1,7 @@
 pub mod settings;
 pub mod storage;
+pub mod migration;
 
 pub use settings::*;
 pub use storage::*;
+pub use migration::*;
@@ -5,6 +5,8 @@ use crate::constants::{window, theme, audio, mcp, telegram, font};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct AppConfig {
+    #[serde(default)]
+    pub config_version: u32, // 配置结构版本（缺省为 0，由 migrate_config 升级）
     #[serde(default = "default_ui_config")]
     pub ui_config: UiConfig, // UI相关配置（主题、窗口、置顶等）
     #[serde(default = "default_audio_config")]
@@ -40,6 +42,18 @@ pub struct UiConfig {
     // 置顶设置
     #[serde(default = "default_always_on_top")]
     pub always_on_top: bool,
+
+    // 关闭按钮行为：启用后隐藏到系统托盘而不是退出
+    #[serde(default = "default_close_to_tray")]
+    pub close_to_tray: bool,
+
+    // 自动更新时同一平台有多个资源：true 优先安装包（msi/dmg/deb），false 优先压缩包
+    #[serde(default = "default_prefer_installer_update")]
+    pub prefer_installer_update: bool,
+
+    // 用户选择跳过的更新版本：最新版本等于该版本时不再提示更新
+    #[serde(default)]
+    pub skipped_update_version: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -117,13 +131,36 @@ pub struct McpConfig {
     #[serde(default = "default_mcp_tools")]
     pub tools: HashMap<String, bool>, // MCP工具启用状态
     pub acemcp_base_url: Option<String>, // acemcp API端点URL
-    pub acemcp_token: Option<String>, // acemcp认证令牌
+    pub acemcp_token: Option<String>, // acemcp认证令牌（支持 env:变量名 / file:路径 间接引用，使用时解析）
     pub acemcp_batch_size: Option<u32>, // acemcp批处理大小
     pub acemcp_max_lines_per_blob: Option<u32>, // acemcp最大行数/块
+    #[serde(default)]
+    pub acemcp_max_lines_by_extension: Option<HashMap<String, u32>>, // 按扩展名覆盖最大行数/块（如 .md、.min.js），未覆盖时使用全局值
     pub acemcp_text_extensions: Option<Vec<String>>, // acemcp文件扩展名
     pub acemcp_exclude_patterns: Option<Vec<String>>, // acemcp排除模式
+    #[serde(default)]
+    pub acemcp_follow_symlinks: Option<bool>, // 扫描项目时是否跟随符号链接目录（默认不跟随）
     pub acemcp_watch_debounce_ms: Option<u64>, // 文件监听防抖延迟（毫秒），默认 180000 (3分钟)
     pub acemcp_auto_index_enabled: Option<bool>, // 全局自动索引开关（默认启用）
+    pub acemcp_log_json: Option<bool>, // acemcp.log 使用结构化 JSON 行格式（默认文本格式）
+    pub acemcp_log_max_size_mb: Option<u64>, // acemcp.log 单文件大小上限（MB，默认 10）
+    pub acemcp_log_max_files: Option<u32>, // acemcp.log 保留的轮转文件数（默认 3）
+    pub acemcp_client_cert_path: Option<String>, // mTLS 客户端证书路径（PEM，可选）
+    pub acemcp_client_key_path: Option<String>, // mTLS 客户端私钥路径（PKCS#8 PEM，可选）
+    pub acemcp_extra_ca_cert_path: Option<String>, // 额外信任的根证书路径（PEM，用于自签名部署）
+    pub acemcp_accept_invalid_certs: Option<bool>, // 危险：跳过证书校验（默认 false）
+    pub acemcp_max_concurrent_uploads: Option<u32>, // 自动索引同时上传的最大项目数（默认 2）
+    pub acemcp_keepalive_enabled: Option<bool>, // 后台定期 Ping ACE 保持连接池预热（默认关闭）
+    pub acemcp_keepalive_interval_secs: Option<u64>, // 保活 Ping 间隔（秒，默认 45）
+    pub acemcp_smart_wait_min_secs: Option<u64>, // 索引进行中时检索前的最短等待（秒，默认 1；与最大值同为 0 时禁用）
+    pub acemcp_smart_wait_max_secs: Option<u64>, // 索引进行中时检索前的最长等待（秒，默认 5）
+    pub acemcp_max_file_bytes: Option<u64>, // 文件监听忽略超过该大小的文件变更（字节，默认不限制）
+    pub acemcp_proxy_failover_enabled: Option<bool>, // 上传连续失败时自动切换到最近验证可用的代理（默认关闭）
+    pub acemcp_daily_blob_budget: Option<u32>, // 每日上传 blob 预算（仅本地统计与提醒，不阻止上传）
+    pub acemcp_max_blobs_per_request: Option<u32>, // 单次上传请求的最大 blob 数（默认 50，避免大文件切分后请求体过大被网关拒绝）
+    pub acemcp_user_agent_suffix: Option<String>, // User-Agent 附加标识（如企业/部门名），追加在 sanshu/<版本> 之后
+    pub acemcp_http2: Option<bool>, // 强制使用 HTTP/2（prior knowledge，需网关支持，默认关闭即 HTTP/1.1）
+    pub acemcp_pool_max_idle_per_host: Option<u32>, // 每个主机保留的最大空闲连接数（默认不限制）
     // Sou 代理配置
     pub acemcp_proxy_enabled: Option<bool>, // 代理启用开关
     pub acemcp_proxy_host: Option<String>, // 代理主机地址
@@ -131,9 +168,53 @@ pub struct McpConfig {
     pub acemcp_proxy_type: Option<String>, // 代理类型: "http" | "https" | "socks5"
     pub acemcp_proxy_username: Option<String>, // 代理用户名（可选）
     pub acemcp_proxy_password: Option<String>, // 代理密码（可选）
+    pub acemcp_proxy_profiles: Option<Vec<ProxyProfile>>, // 代理配置方案（家庭/公司/VPN 等）
+    pub acemcp_last_good_proxy: Option<LastKnownProxy>, // 最近一次验证可用的本地代理（加速启动检测）
+    pub acemcp_project_overrides: Option<HashMap<String, AcemcpProjectOverride>>, // 按项目覆盖租户地址/令牌（key 为规范化项目路径）
+    pub acemcp_last_test_queries: Option<HashMap<String, String>>, // 各项目最近一次测速/调试搜索的查询（key 为规范化项目路径）
+    pub acemcp_project_watch_exclusions: Option<HashMap<String, Vec<String>>>, // 各项目运行时排除的子目录（key 为规范化项目路径，值为相对子路径）
+    #[serde(default)]
+    pub acemcp_project_auto_index: Option<HashMap<String, bool>>, // 各项目自动索引开关（key 为规范化项目路径，仅记录关闭的项目；仍监听但不自动上传）
     pub context7_api_key: Option<String>, // Context7 API密钥 (可选，免费使用时可为空)
 }
 
+/// Sou 代理配置方案，激活时复制到 acemcp_proxy_* 字段
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProxyProfile {
+    pub name: String, // 方案名称（唯一）
+    #[serde(alias = "type", alias = "proxyType")]
+    pub proxy_type: String, // 代理类型: "http" | "https" | "socks5"
+    pub host: String, // 代理主机地址
+    pub port: u16, // 代理端口
+    pub username: Option<String>, // 代理用户名（可选）
+    pub password: Option<String>, // 代理密码（可选）
+}
+
+/// 单个项目的 ACE 租户覆盖配置，未设置的字段沿用全局配置
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct AcemcpProjectOverride {
+    pub base_url: Option<String>, // 覆盖的租户地址
+    pub token: Option<String>, // 覆盖的认证令牌
+}
+
+impl std::fmt::Debug for AcemcpProjectOverride {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use crate::utils::logger::REDACTED;
+        f.debug_struct("AcemcpProjectOverride")
+            .field("base_url", &self.base_url)
+            .field("token", &self.token.as_ref().map(|_| REDACTED))
+            .finish()
+    }
+}
+
+/// 最近一次验证可用的本地代理
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LastKnownProxy {
+    pub host: String, // 代理主机地址
+    pub port: u16, // 代理端口
+    pub proxy_type: String, // 代理类型: "http" | "socks5"
+    pub verified_at: String, // 最近验证通过的时间（RFC3339）
+}
 
 // 自定义prompt结构
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -235,6 +316,10 @@ pub struct ProxyConfig {
     /// 仅在中国大陆地区使用代理
     #[serde(default = "default_proxy_only_for_cn")]
     pub only_for_cn: bool,
+
+    /// IP 地理位置检测的总超时（秒），多个服务商并发请求，取最先成功的结果
+    #[serde(default = "default_proxy_geo_timeout_secs")]
+    pub geo_timeout_secs: u64,
 }
 
 #[derive(Debug)]
@@ -249,6 +334,7 @@ pub struct AppState {
 impl Default for AppConfig {
     fn default() -> Self {
         Self {
+            config_version: super::migration::CURRENT_CONFIG_VERSION,
             ui_config: default_ui_config(),
             audio_config: default_audio_config(),
             reply_config: default_reply_config(),
@@ -279,6 +365,9 @@ pub fn default_ui_config() -> UiConfig {
         font_config: default_font_config(),
         window_config: default_window_config(),
         always_on_top: default_always_on_top(),
+        close_to_tray: default_close_to_tray(),
+        prefer_installer_update: default_prefer_installer_update(),
+        skipped_update_version: None,
     }
 }
 
@@ -296,10 +385,31 @@ pub fn default_mcp_config() -> McpConfig {
         acemcp_token: None,
         acemcp_batch_size: None,
         acemcp_max_lines_per_blob: None,
+        acemcp_max_lines_by_extension: None, // 默认所有扩展名使用全局值
         acemcp_text_extensions: None,
         acemcp_exclude_patterns: None,
+        acemcp_follow_symlinks: None, // 默认跳过符号链接目录，避免循环或扫描到项目之外
         acemcp_watch_debounce_ms: None, // 使用默认值 180000ms (3分钟)
         acemcp_auto_index_enabled: None, // 默认启用（未设置时视为 true）
+        acemcp_log_json: None, // 默认文本格式，便于人工查看
+        acemcp_log_max_size_mb: None, // 默认 10MB
+        acemcp_log_max_files: None, // 默认保留 3 个
+        acemcp_client_cert_path: None, // 默认不使用客户端证书
+        acemcp_client_key_path: None,
+        acemcp_extra_ca_cert_path: None, // 默认仅信任系统根证书
+        acemcp_accept_invalid_certs: None, // 默认校验证书
+        acemcp_max_concurrent_uploads: None, // 使用默认值 2
+        acemcp_keepalive_enabled: None, // 默认关闭
+        acemcp_keepalive_interval_secs: None, // 使用默认值 45 秒
+        acemcp_smart_wait_min_secs: None, // 使用默认值 1 秒
+        acemcp_smart_wait_max_secs: None, // 使用默认值 5 秒
+        acemcp_max_file_bytes: None, // 默认不限制
+        acemcp_proxy_failover_enabled: None, // 默认关闭
+        acemcp_daily_blob_budget: None, // 默认不提醒
+        acemcp_max_blobs_per_request: None, // 使用默认值 50
+        acemcp_user_agent_suffix: None, // 默认仅 sanshu/<版本>
+        acemcp_http2: None, // 默认 HTTP/1.1
+        acemcp_pool_max_idle_per_host: None, // 使用 reqwest 默认值
         // 代理配置默认值
         acemcp_proxy_enabled: None,
         acemcp_proxy_host: None,
@@ -307,6 +417,12 @@ pub fn default_mcp_config() -> McpConfig {
         acemcp_proxy_type: None,
         acemcp_proxy_username: None,
         acemcp_proxy_password: None,
+        acemcp_proxy_profiles: None,
+        acemcp_last_good_proxy: None,
+        acemcp_project_overrides: None, // 默认所有项目使用全局租户配置
+        acemcp_last_test_queries: None,
+        acemcp_project_watch_exclusions: None, // 默认不排除任何子目录
+        acemcp_project_auto_index: None, // 默认所有项目跟随全局开关
         context7_api_key: None,
     }
 }
@@ -333,6 +449,14 @@ pub fn default_always_on_top() -> bool {
     window::DEFAULT_ALWAYS_ON_TOP
 }
 
+pub fn default_close_to_tray() -> bool {
+    window::DEFAULT_CLOSE_TO_TRAY
+}
+
+pub fn default_prefer_installer_update() -> bool {
+    false
+}
+
 pub fn default_audio_notification_enabled() -> bool {
     audio::DEFAULT_NOTIFICATION_ENABLED
 }
@@ -411,6 +535,14 @@ pub fn default_mcp_tools() -> HashMap<String, bool> {
     tools
 }
 
+pub fn default_acemcp_text_extensions() -> Vec<String> {
+    mcp::DEFAULT_ACEMCP_TEXT_EXTENSIONS.iter().map(|s| s.to_string()).collect()
+}
+
+pub fn default_acemcp_exclude_patterns() -> Vec<String> {
+    mcp::DEFAULT_ACEMCP_EXCLUDE_PATTERNS.iter().map(|s| s.to_string()).collect()
+}
+
 pub fn default_window_width() -> f64 {
     window::DEFAULT_WIDTH
 }
@@ -478,6 +610,16 @@ impl WindowConfig {
         }
     }
 
+    // 获取配置的尺寸约束（min/max 无效时回退默认值）
+    pub fn constraints(&self) -> window::WindowConstraints {
+        window::WindowConstraints::from_bounds(self.min_width, self.min_height, self.max_width, self.max_height)
+    }
+
+    // 将尺寸限制在配置的约束范围内
+    pub fn clamp_size(&self, width: f64, height: f64) -> (f64, f64) {
+        self.constraints().clamp_size(width, height)
+    }
+
     // 更新当前模式的尺寸
     pub fn update_current_size(&mut self, width: f64, height: f64) {
         if self.fixed {
@@ -748,6 +890,7 @@ pub fn default_proxy_config() -> ProxyConfig {
         host: default_proxy_host(),
         port: default_proxy_port(),
         only_for_cn: default_proxy_only_for_cn(),
+        geo_timeout_secs: default_proxy_geo_timeout_secs(),
     }
 }
 
@@ -771,6 +914,10 @@ pub fn default_proxy_port() -> u16 {
     7890 // 默认Clash混合代理端口
 }
 
+pub fn default_proxy_geo_timeout_secs() -> u64 {
+    crate::network::geo::DEFAULT_GEO_TIMEOUT_SECS
+}
+
 pub fn default_proxy_only_for_cn() -> bool {
     true // 默认仅在中国大陆地区使用代理
 }
@@ -1,8 +1,10 @@
 use anyhow::Result;
 use std::fs;
-use std::path::PathBuf;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use tauri::{AppHandle, LogicalSize, Manager, State};
 
+use super::migration::migrate_config;
 use super::settings::{AppConfig, AppState, default_shortcuts};
 
 pub fn get_config_path(_app: &AppHandle) -> Result<PathBuf> {
@@ -23,15 +25,16 @@ pub async fn save_config(state: &State<'_, AppState>, app: &AppHandle) -> Result
         .lock()
         .map_err(|e| anyhow::anyhow!("获取配置失败: {}", e))?;
     let config_json = serde_json::to_string_pretty(&*config)?;
+    register_config_secrets(&config);
 
-    // 写入文件
-    fs::write(&config_path, config_json)?;
-
-    // 强制刷新文件系统缓存
-    if let Ok(file) = std::fs::OpenOptions::new().write(true).open(&config_path) {
-        let _ = file.sync_all();
+    // 安全模式下内存中是默认配置，首次保存前无条件保留原文件（可能已损坏，不会进入 .bak）
+    if crate::app::safe_mode::is_safe_mode() {
+        backup_before_first_safe_mode_save(&config_path)?;
     }
 
+    // 原子写入（持有配置锁期间完成，避免并发保存互相覆盖）
+    write_config_atomically(&config_path, &config_json)?;
+
     log::debug!("配置已保存到: {:?}", config_path);
 
     Ok(())
@@ -42,11 +45,11 @@ pub async fn load_config(state: &State<'_, AppState>, app: &AppHandle) -> Result
     let config_path = get_config_path(app)?;
 
     if config_path.exists() {
-        let config_json = fs::read_to_string(&config_path)?;
-        let mut config: AppConfig = serde_json::from_str(&config_json)?;
+        let mut config = read_config_file(&config_path)?;
 
         // 合并默认快捷键配置，确保新的默认快捷键被添加
         merge_default_shortcuts(&mut config);
+        register_config_secrets(&config);
 
         let mut config_guard = state
             .config
@@ -65,6 +68,20 @@ pub async fn load_config_and_apply_window_settings(
     // 先加载配置
     load_config(state, app).await?;
 
+    // 升级旧版本配置，回填默认值后写回文件（每个版本只执行一次）
+    let migrated = {
+        let mut config = state
+            .config
+            .lock()
+            .map_err(|e| anyhow::anyhow!("获取配置失败: {}", e))?;
+        migrate_config(&mut config)
+    };
+    if migrated {
+        if let Err(e) = save_config(state, app).await {
+            log::warn!("保存迁移后的配置失败: {}", e);
+        }
+    }
+
     // 然后应用窗口设置
     let (always_on_top, window_config) = {
         let config = state
@@ -86,17 +103,18 @@ pub async fn load_config_and_apply_window_settings(
             log::info!("窗口置顶状态已设置为: {} (配置加载时)", always_on_top);
         }
 
-        // 应用窗口大小约束
+        // 应用窗口大小约束（配置的 min/max 无效时回退默认值）
+        let constraints = window_config.constraints();
         if let Err(e) = window.set_min_size(Some(LogicalSize::new(
-            window_config.min_width,
-            window_config.min_height,
+            constraints.min_width,
+            constraints.min_height,
         ))) {
             log::warn!("设置最小窗口大小失败: {}", e);
         }
 
         if let Err(e) = window.set_max_size(Some(LogicalSize::new(
-            window_config.max_width,
-            window_config.max_height,
+            constraints.max_width,
+            constraints.max_height,
         ))) {
             log::warn!("设置最大窗口大小失败: {}", e);
         }
@@ -109,6 +127,7 @@ pub async fn load_config_and_apply_window_settings(
             // 自由拉伸模式：使用自由拉伸尺寸
             (window_config.free_width, window_config.free_height)
         };
+        let (target_width, target_height) = constraints.clamp_size(target_width, target_height);
 
         // 应用窗口大小（移除调试信息）
         if let Err(_e) = window.set_size(LogicalSize::new(target_width, target_height)) {
@@ -133,11 +152,13 @@ pub fn load_standalone_config() -> Result<AppConfig> {
     let config_path = get_standalone_config_path()?;
 
     if config_path.exists() {
-        let config_json = fs::read_to_string(config_path)?;
-        let mut config: AppConfig = serde_json::from_str(&config_json)?;
+        let mut config = read_config_file(&config_path)?;
 
         // 合并默认快捷键配置
         merge_default_shortcuts(&mut config);
+        // 仅在内存中升级旧配置，写回文件由主程序启动时完成
+        migrate_config(&mut config);
+        register_config_secrets(&config);
 
         Ok(config)
     } else {
@@ -146,6 +167,173 @@ pub fn load_standalone_config() -> Result<AppConfig> {
     }
 }
 
+/// 本进程是否已为安全模式保存做过备份
+static SAFE_MODE_BACKUP_DONE: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
+/// 安全模式下首次保存前，将现有配置原样复制为带时间戳的备份
+/// 备份失败时拒绝保存，避免默认配置覆盖掉无法恢复的原配置
+fn backup_before_first_safe_mode_save(config_path: &Path) -> Result<()> {
+    use std::sync::atomic::Ordering;
+
+    if SAFE_MODE_BACKUP_DONE.load(Ordering::SeqCst) || !config_path.exists() {
+        return Ok(());
+    }
+    let mut name = config_path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".safe-mode-{}.bak", chrono::Local::now().format("%Y%m%d-%H%M%S")));
+    let backup = config_path.with_file_name(name);
+    fs::copy(config_path, &backup)
+        .map_err(|e| anyhow::anyhow!("安全模式下备份原配置失败，已取消保存: {}", e))?;
+    SAFE_MODE_BACKUP_DONE.store(true, Ordering::SeqCst);
+    log::warn!("🛟 安全模式首次保存，原配置已备份到: {:?}", backup);
+    Ok(())
+}
+
+/// 配置文件的滚动备份路径（config.json -> config.json.bak）
+pub(crate) fn backup_path(config_path: &Path) -> PathBuf {
+    let mut name = config_path.file_name().unwrap_or_default().to_os_string();
+    name.push(".bak");
+    config_path.with_file_name(name)
+}
+
+/// 原子写入配置：先写同目录临时文件并刷盘，再重命名覆盖目标文件
+/// 覆盖前将当前可解析的配置保存为唯一的滚动备份
+fn write_config_atomically(config_path: &Path, contents: &str) -> Result<()> {
+    let mut tmp_name = config_path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(format!(".{}.tmp", std::process::id()));
+    let tmp_path = config_path.with_file_name(tmp_name);
+
+    let write_tmp = || -> Result<()> {
+        let mut file = fs::File::create(&tmp_path)?;
+        file.write_all(contents.as_bytes())?;
+        file.sync_all()?;
+        Ok(())
+    };
+    if let Err(e) = write_tmp() {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(e);
+    }
+
+    // 只备份能正常解析的旧配置，避免用损坏的文件覆盖上一次的好备份
+    if let Ok(previous) = fs::read_to_string(config_path) {
+        if serde_json::from_str::<AppConfig>(&previous).is_ok() {
+            if let Err(e) = fs::write(backup_path(config_path), previous) {
+                log::warn!("写入配置备份失败: {}", e);
+            }
+        }
+    }
+
+    if let Err(e) = fs::rename(&tmp_path, config_path) {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(e.into());
+    }
+    Ok(())
+}
+
+/// 读取配置文件；主文件损坏时回退到最近一次的备份
+fn read_config_file(config_path: &Path) -> Result<AppConfig> {
+    let main_error = match fs::read_to_string(config_path) {
+        Ok(json) => match serde_json::from_str::<AppConfig>(&json) {
+            Ok(config) => return Ok(config),
+            Err(e) => anyhow::anyhow!("解析配置文件失败: {}", e),
+        },
+        Err(e) => anyhow::anyhow!("读取配置文件失败: {}", e),
+    };
+
+    let backup = backup_path(config_path);
+    let config = fs::read_to_string(&backup)
+        .ok()
+        .and_then(|json| serde_json::from_str::<AppConfig>(&json).ok())
+        .ok_or(main_error)?;
+    log::warn!("配置文件 {:?} 已损坏，已回退到备份 {:?}", config_path, backup);
+    Ok(config)
+}
+
+/// token 间接引用：从环境变量读取
+const TOKEN_ENV_PREFIX: &str = "env:";
+/// token 间接引用：从文件读取
+const TOKEN_FILE_PREFIX: &str = "file:";
+
+/// token 是否为间接引用（`env:` / `file:`），而非字面值
+pub fn is_token_reference(raw: &str) -> bool {
+    let raw = raw.trim();
+    raw.starts_with(TOKEN_ENV_PREFIX) || raw.starts_with(TOKEN_FILE_PREFIX)
+}
+
+/// 解析 acemcp token，在使用时调用，解析结果不会写回配置文件
+/// - `env:ACE_TOKEN`：读取环境变量 ACE_TOKEN
+/// - `file:/path/to/token`：读取文件内容（`~/` 开头时相对用户目录）
+/// - 其他值：按字面 token 使用（与旧版本行为一致）
+///
+/// 结果均去除首尾空白；引用解析出的明文会注册到日志脱敏列表。
+/// 生效优先级：项目级覆盖 token > 全局 acemcp_token，两者都支持上述引用形式。
+pub fn resolve_token(raw: &str) -> std::result::Result<String, String> {
+    let raw = raw.trim();
+    let resolved = if let Some(name) = raw.strip_prefix(TOKEN_ENV_PREFIX) {
+        let name = name.trim();
+        std::env::var(name).map_err(|_| format!("token 引用的环境变量未设置: {}", name))?
+    } else if let Some(path) = raw.strip_prefix(TOKEN_FILE_PREFIX) {
+        let path = path.trim();
+        let path = match (path.strip_prefix("~/"), dirs::home_dir()) {
+            (Some(rest), Some(home)) => home.join(rest),
+            _ => PathBuf::from(path),
+        };
+        fs::read_to_string(&path).map_err(|e| format!("读取 token 文件失败: {:?}, {}", path, e))?
+    } else {
+        return Ok(raw.to_string());
+    };
+
+    let resolved = resolved.trim().to_string();
+    if resolved.is_empty() {
+        return Err(format!("token 引用解析结果为空: {}", raw));
+    }
+    crate::utils::logger::register_secret(&resolved);
+    Ok(resolved)
+}
+
+/// 解析已配置的 token：未配置或为空时返回“未配置 ACE Token”，引用解析失败时返回具体原因
+pub fn resolve_configured_token(raw: Option<&str>) -> std::result::Result<String, String> {
+    match raw.map(str::trim).filter(|s| !s.is_empty()) {
+        Some(raw) => resolve_token(raw),
+        None => Err("未配置 ACE Token".to_string()),
+    }
+}
+
+/// 宽松解析：未配置或解析失败时返回 None（失败原因记录到日志）
+pub fn resolve_token_opt(raw: Option<&str>) -> Option<String> {
+    let raw = raw.map(str::trim).filter(|s| !s.is_empty())?;
+    match resolve_token(raw) {
+        Ok(token) => Some(token),
+        Err(e) => {
+            log::warn!("{}", e);
+            None
+        }
+    }
+}
+
+/// 将配置中的敏感值注册到日志脱敏列表，确保任何级别的日志都不会输出明文
+fn register_config_secrets(config: &AppConfig) {
+    use crate::utils::logger::register_secret;
+
+    let mcp = &config.mcp_config;
+    // 间接引用在解析时注册解析后的明文，引用字符串本身不是敏感值
+    if let Some(token) = mcp.acemcp_token.as_deref().filter(|t| !is_token_reference(t)) {
+        register_secret(token);
+    }
+    if let Some(password) = &mcp.acemcp_proxy_password {
+        register_secret(password);
+    }
+    for profile in mcp.acemcp_proxy_profiles.iter().flatten() {
+        if let Some(password) = &profile.password {
+            register_secret(password);
+        }
+    }
+    for project_override in mcp.acemcp_project_overrides.iter().flat_map(|m| m.values()) {
+        if let Some(token) = project_override.token.as_deref().filter(|t| !is_token_reference(t)) {
+            register_secret(token);
+        }
+    }
+}
+
 /// 独立加载Telegram配置（用于MCP模式下的配置检查）
 pub fn load_standalone_telegram_config() -> Result<super::settings::TelegramConfig> {
     let config = load_standalone_config()?;
@@ -190,3 +378,53 @@ fn merge_default_shortcuts(config: &mut AppConfig) {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partial_write_never_corrupts_live_config() {
+        let dir = std::env::temp_dir().join(format!("sanshu_config_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let config_path = dir.join("config.json");
+
+        let mut config = AppConfig::default();
+        config.ui_config.theme = "light".to_string();
+        write_config_atomically(&config_path, &serde_json::to_string_pretty(&config).unwrap()).unwrap();
+        config.ui_config.theme = "dark".to_string();
+        let full = serde_json::to_string_pretty(&config).unwrap();
+        write_config_atomically(&config_path, &full).unwrap();
+
+        // 模拟写临时文件途中崩溃：临时文件被截断，但尚未重命名
+        let tmp_path = dir.join(format!("config.json.{}.tmp", std::process::id()));
+        fs::write(&tmp_path, &full[..full.len() / 2]).unwrap();
+        assert_eq!(read_config_file(&config_path).unwrap().ui_config.theme, "dark");
+
+        // 模拟非原子写入导致主文件被截断：回退到上一次的好备份
+        fs::write(&config_path, &full[..full.len() / 2]).unwrap();
+        assert_eq!(read_config_file(&config_path).unwrap().ui_config.theme, "light");
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_resolve_token_reference() {
+        assert_eq!(resolve_token(" literal-token ").unwrap(), "literal-token");
+        assert!(!is_token_reference("literal-token"));
+
+        let var = format!("SANSHU_TEST_TOKEN_{}", std::process::id());
+        std::env::set_var(&var, "from-env\n");
+        assert_eq!(resolve_token(&format!("env:{}", var)).unwrap(), "from-env");
+        std::env::remove_var(&var);
+        assert!(resolve_token(&format!("env:{}", var)).is_err());
+
+        let path = std::env::temp_dir().join(format!("sanshu_token_test_{}", std::process::id()));
+        fs::write(&path, "from-file\n").unwrap();
+        assert_eq!(resolve_token(&format!("file:{}", path.display())).unwrap(), "from-file");
+        let _ = fs::remove_file(&path);
+
+        assert!(resolve_configured_token(Some("  ")).is_err());
+        assert_eq!(resolve_token_opt(None), None);
+    }
+}
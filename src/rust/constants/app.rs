@@ -43,6 +43,9 @@ pub const EXIT_CONFIRMATION_WINDOW_SECS: u64 = 3;
 /// 需要的连续退出尝试次数
 pub const REQUIRED_EXIT_ATTEMPTS: u32 = 2;
 
+/// 退出前等待进行中的上传批次完成的最长时间（秒）
+pub const SHUTDOWN_GRACE_SECS: u64 = 5;
+
 // 应用程序信息结构体
 #[derive(Debug, Clone)]
 pub struct AppInfo {
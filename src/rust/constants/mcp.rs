@@ -24,6 +24,38 @@ pub const DEFAULT_AUTO_CONTINUE_THRESHOLD: u32 = 1000;
 /// 默认继续提示词
 pub const DEFAULT_CONTINUE_PROMPT: &str = "请按照最佳实践继续";
 
+/// acemcp 默认批处理大小
+pub const DEFAULT_ACEMCP_BATCH_SIZE: u32 = 10;
+
+/// acemcp 默认每个 blob 的最大行数
+pub const DEFAULT_ACEMCP_MAX_LINES_PER_BLOB: u32 = 800;
+
+/// acemcp 默认文件监听防抖延迟 (ms)
+pub const DEFAULT_ACEMCP_WATCH_DEBOUNCE_MS: u64 = 180_000;
+
+/// acemcp 默认单次上传请求的最大 blob 数（与每批文件数共同限制请求体大小）
+pub const DEFAULT_ACEMCP_MAX_BLOBS_PER_REQUEST: u32 = 50;
+
+/// acemcp 默认同时上传的最大项目数
+pub const DEFAULT_ACEMCP_MAX_CONCURRENT_UPLOADS: u32 = 2;
+
+/// acemcp 智能等待默认区间（秒）：检测到索引进行中时，检索前随机等待 [min, max] 秒
+pub const DEFAULT_ACEMCP_SMART_WAIT_MIN_SECS: u64 = 1;
+pub const DEFAULT_ACEMCP_SMART_WAIT_MAX_SECS: u64 = 5;
+
+/// acemcp 代理故障转移阈值：连续失败的上传批次达到该数量时尝试切换到备用代理
+pub const DEFAULT_ACEMCP_PROXY_FAILOVER_THRESHOLD: u32 = 2;
+
+/// acemcp 默认索引的文件扩展名（与前端 McpToolsTab.vue 保持一致）
+pub const DEFAULT_ACEMCP_TEXT_EXTENSIONS: &[&str] = &[
+    ".py", ".js", ".ts", ".jsx", ".tsx", ".java", ".go", ".rs", ".cpp", ".c", ".h", ".hpp",
+    ".cs", ".rb", ".php", ".md", ".txt", ".json", ".yaml", ".yml", ".toml", ".xml", ".html",
+    ".css", ".scss", ".sql", ".sh", ".bash",
+];
+
+/// acemcp 默认排除模式
+pub const DEFAULT_ACEMCP_EXCLUDE_PATTERNS: &[&str] = &["node_modules", ".git", "target", "dist"];
+
 /// MCP 请求超时时间 (ms)
 pub const REQUEST_TIMEOUT_MS: u64 = 30000;
 
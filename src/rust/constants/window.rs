@@ -21,6 +21,9 @@ pub const DEFAULT_HEIGHT: f64 = 900.0;
 /// 默认置顶启用状态
 pub const DEFAULT_ALWAYS_ON_TOP: bool = true;
 
+/// 默认关闭按钮行为：直接退出（不最小化到托盘）
+pub const DEFAULT_CLOSE_TO_TRAY: bool = false;
+
 /// 默认窗口固定模式状态
 pub const DEFAULT_FIXED_MODE: bool = false;
 
@@ -52,6 +55,30 @@ impl Default for WindowConstraints {
 }
 
 impl WindowConstraints {
+    /// 按配置的尺寸范围构建约束
+    /// 某一方向的数值无效（非正数/非有限）或最小值大于最大值时，该方向回退到默认范围
+    pub fn from_bounds(min_width: f64, min_height: f64, max_width: f64, max_height: f64) -> Self {
+        let defaults = Self::default();
+        let valid = |min: f64, max: f64| min.is_finite() && max.is_finite() && min > 0.0 && min <= max;
+        let (min_width, max_width) = if valid(min_width, max_width) {
+            (min_width, max_width)
+        } else {
+            (defaults.min_width, defaults.max_width)
+        };
+        let (min_height, max_height) = if valid(min_height, max_height) {
+            (min_height, max_height)
+        } else {
+            (defaults.min_height, defaults.max_height)
+        };
+        Self {
+            min_width,
+            min_height,
+            max_width,
+            max_height,
+            ..defaults
+        }
+    }
+
     /// 验证窗口尺寸是否在约束范围内
     pub fn is_valid_size(&self, width: f64, height: f64) -> bool {
         width >= self.min_width
@@ -91,7 +118,7 @@ pub fn is_valid_window_size(width: f64, height: f64) -> bool {
     get_default_constraints().is_valid_size(width, height)
 }
 
-/// 将窗口尺寸限制在有效范围内
+/// 将窗口尺寸限制在默认范围内（有配置时使用 `WindowConfig::clamp_size`）
 pub fn clamp_window_size(width: f64, height: f64) -> (f64, f64) {
     get_default_constraints().clamp_size(width, height)
 }
@@ -278,6 +278,9 @@ impl ServerHandler for ZhiServer {
 
 /// 启动MCP服务器
 pub async fn run_server() -> Result<(), Box<dyn std::error::Error>> {
+    // 启用保活时提前预热 ACE 连接，降低首次检索延迟；之后在界面中开启保活也会被及时发现
+    crate::mcp::tools::acemcp::mcp::start_keepalive_supervisor();
+
     // 创建并运行服务器
     let service = ZhiServer::new()
         .serve(stdio())
@@ -1,9 +1,19 @@
 use tauri::{AppHandle, Emitter, State};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
 
-use crate::config::{AppState, save_config};
+use crate::config::{AppState, LastKnownProxy, ProxyProfile, save_config};
 use crate::network::proxy::{ProxyDetector, ProxyInfo, ProxyType};
+use crate::utils::disk::format_bytes;
+use crate::utils::logger::{LogEntry, parse_log_line};
+use crate::constants::mcp::{
+    DEFAULT_ACEMCP_BATCH_SIZE, DEFAULT_ACEMCP_MAX_BLOBS_PER_REQUEST, DEFAULT_ACEMCP_MAX_CONCURRENT_UPLOADS,
+    DEFAULT_ACEMCP_MAX_LINES_PER_BLOB, DEFAULT_ACEMCP_WATCH_DEBOUNCE_MS,
+};
 use super::AcemcpTool;
-use super::types::{AcemcpRequest, ProjectIndexStatus, ProjectsIndexStatus, ProjectFilesStatus, DetectedProxy, ProxySpeedTestResult, SpeedTestMetric, SpeedTestProgress, SpeedTestStageStatus};
+use super::mcp::{AcemcpHttpOptions, AcemcpTlsOptions, REQUEST_ID_HEADER, RateLimitBudget, new_request_id, send_with_rate_limit};
+use super::types::{AcemcpRequest, AcemcpUsage, AcemcpToolResponse, AcemcpToolInfo, AcemcpToolArgInfo, IndexResumeResult, AcemcpProxyOverride, ProjectIndexStatus, ProjectsIndexStatus, ProjectFilesStatus, DetectedProxy, ProxySpeedTestResult, SpeedTestMetric, PingPhaseTimings, FileErrorList, SpeedTestHistoryEntry, LocalReadBenchmark, ProxyRecommendationTrend, StreamProbeStats, SpeedTestProgress, SpeedTestStageStatus, SearchStreamChunk, WatcherStats, ProjectAutoIndexState, ExclusionPreview, FileChunkPreview, ProjectFilesStatusChunk, BandwidthMeasurement, LatencyStats, ProjectReindexResult, ReindexProgress, AcemcpLogLine, AcemcpHealth, ProxyConnectivityResult, AutoProxyConfigResult, AcemcpConfiguredStatus, ConnectionTestSnapshot, IndexStatusCounts, IndexStatus, IndexEstimate, IndexVerification, IndexSummary, IndexCoverage, DirectoryIndexStats, FileIndexStatusKind, DebugSearchHistoryEntry, DebugSearchTemplate};
 use reqwest;
 
 #[derive(Debug, serde::Deserialize)]
@@ -16,12 +26,53 @@ pub struct SaveAcemcpConfigArgs {
     pub batch_size: u32,
     #[serde(alias = "maxLinesPerBlob", alias = "_max_lines_per_blob")]
     pub max_lines_per_blob: u32,
+    #[serde(default, alias = "maxLinesByExtension", alias = "max_lines_by_extension")]
+    pub max_lines_by_extension: Option<std::collections::HashMap<String, u32>>, // 按扩展名覆盖最大行数（空表示清除）
     #[serde(alias = "textExtensions", alias = "_text_extensions")]
     pub text_extensions: Vec<String>,
     #[serde(alias = "excludePatterns", alias = "_exclude_patterns")]
     pub exclude_patterns: Vec<String>,
+    #[serde(default, alias = "followSymlinks", alias = "follow_symlinks")]
+    pub follow_symlinks: Option<bool>, // 扫描时是否跟随符号链接目录
     #[serde(alias = "watchDebounceMs", alias = "watch_debounce_ms")]
     pub watch_debounce_ms: Option<u64>, // 文件监听防抖延迟（毫秒）
+    #[serde(alias = "maxConcurrentUploads", alias = "max_concurrent_uploads")]
+    pub max_concurrent_uploads: Option<u32>, // 自动索引同时上传的最大项目数
+    #[serde(alias = "logJson", alias = "log_json")]
+    pub log_json: Option<bool>, // acemcp.log 使用 JSON 行格式（重启后生效）
+    #[serde(alias = "logMaxSizeMb", alias = "log_max_size_mb")]
+    pub log_max_size_mb: Option<u64>, // acemcp.log 单文件大小上限（MB，重启后生效）
+    #[serde(alias = "logMaxFiles", alias = "log_max_files")]
+    pub log_max_files: Option<u32>, // acemcp.log 保留的轮转文件数（重启后生效）
+    #[serde(alias = "clientCertPath", alias = "client_cert_path")]
+    pub client_cert_path: Option<String>, // mTLS 客户端证书路径（空字符串表示清除）
+    #[serde(alias = "clientKeyPath", alias = "client_key_path")]
+    pub client_key_path: Option<String>, // mTLS 客户端私钥路径（空字符串表示清除）
+    #[serde(alias = "extraCaCertPath", alias = "extra_ca_cert_path")]
+    pub extra_ca_cert_path: Option<String>, // 额外信任的根证书路径（空字符串表示清除）
+    #[serde(alias = "acceptInvalidCerts", alias = "accept_invalid_certs")]
+    pub accept_invalid_certs: Option<bool>, // 危险：跳过证书校验
+    #[serde(alias = "keepaliveEnabled", alias = "keepalive_enabled")]
+    pub keepalive_enabled: Option<bool>, // 后台保活 Ping
+    #[serde(alias = "keepaliveIntervalSecs", alias = "keepalive_interval_secs")]
+    pub keepalive_interval_secs: Option<u64>, // 保活 Ping 间隔（秒）
+    #[serde(alias = "maxFileBytes", alias = "max_file_bytes")]
+    pub max_file_bytes: Option<u64>, // 文件监听忽略的文件大小上限（字节，0 表示不限制）
+    #[serde(alias = "proxyFailoverEnabled", alias = "proxy_failover_enabled")]
+    pub proxy_failover_enabled: Option<bool>, // 上传连续失败时自动切换到备用代理
+    pub http2: Option<bool>, // 强制使用 HTTP/2（prior knowledge）
+    #[serde(alias = "poolMaxIdlePerHost", alias = "pool_max_idle_per_host")]
+    pub pool_max_idle_per_host: Option<u32>, // 每个主机的最大空闲连接数（0 表示恢复默认）
+    #[serde(alias = "maxBlobsPerRequest", alias = "max_blobs_per_request")]
+    pub max_blobs_per_request: Option<u32>, // 单次上传请求的最大 blob 数（0 表示恢复默认）
+    #[serde(alias = "dailyBlobBudget", alias = "daily_blob_budget")]
+    pub daily_blob_budget: Option<u32>, // 每日上传 blob 预算（0 表示关闭提醒）
+    #[serde(alias = "userAgentSuffix", alias = "user_agent_suffix")]
+    pub user_agent_suffix: Option<String>, // User-Agent 附加标识（空字符串表示清除）
+    #[serde(alias = "smartWaitMinSecs", alias = "smart_wait_min_secs")]
+    pub smart_wait_min_secs: Option<u64>, // 智能等待最短秒数（与最大值同为 0 时禁用）
+    #[serde(alias = "smartWaitMaxSecs", alias = "smart_wait_max_secs")]
+    pub smart_wait_max_secs: Option<u64>, // 智能等待最长秒数
     // 代理配置
     #[serde(alias = "proxyEnabled", alias = "proxy_enabled")]
     pub proxy_enabled: Option<bool>,
@@ -56,6 +107,39 @@ pub async fn save_acemcp_config(
     }
     log::info!("规范化后的 BASE_URL: {}", base_url);
 
+    // 规范化文件扩展名：补全前导点、统一小写、去重
+    let text_extensions = super::mcp::normalize_text_extensions(&args.text_extensions)?;
+    let max_lines_by_extension = args
+        .max_lines_by_extension
+        .as_ref()
+        .map(super::mcp::normalize_max_lines_overrides)
+        .transpose()?;
+
+    // 智能等待区间：只传入一端时另一端沿用已保存的值，保存前校验 min ≤ max
+    let smart_wait = if args.smart_wait_min_secs.is_some() || args.smart_wait_max_secs.is_some() {
+        let (stored_min, stored_max) = {
+            let config = state
+                .config
+                .lock()
+                .map_err(|e| format!("获取配置失败: {}", e))?;
+            (config.mcp_config.acemcp_smart_wait_min_secs, config.mcp_config.acemcp_smart_wait_max_secs)
+        };
+        let min = args
+            .smart_wait_min_secs
+            .or(stored_min)
+            .unwrap_or(crate::constants::mcp::DEFAULT_ACEMCP_SMART_WAIT_MIN_SECS);
+        let max = args
+            .smart_wait_max_secs
+            .or(stored_max)
+            .unwrap_or(crate::constants::mcp::DEFAULT_ACEMCP_SMART_WAIT_MAX_SECS);
+        if min > max {
+            return Err(format!("智能等待最短时间（{} 秒）不能大于最长时间（{} 秒）", min, max));
+        }
+        Some((min, max))
+    } else {
+        None
+    };
+
     {
         let mut config = state
             .config
@@ -66,9 +150,78 @@ pub async fn save_acemcp_config(
         config.mcp_config.acemcp_token = Some(args.token.clone());
         config.mcp_config.acemcp_batch_size = Some(args.batch_size);
         config.mcp_config.acemcp_max_lines_per_blob = Some(args.max_lines_per_blob);
-        config.mcp_config.acemcp_text_extensions = Some(args.text_extensions.clone());
+        if let Some(overrides) = max_lines_by_extension {
+            config.mcp_config.acemcp_max_lines_by_extension = if overrides.is_empty() { None } else { Some(overrides) };
+        }
+        config.mcp_config.acemcp_text_extensions = Some(text_extensions);
         config.mcp_config.acemcp_exclude_patterns = Some(args.exclude_patterns.clone());
+        if let Some(follow_symlinks) = args.follow_symlinks {
+            config.mcp_config.acemcp_follow_symlinks = Some(follow_symlinks);
+        }
         config.mcp_config.acemcp_watch_debounce_ms = args.watch_debounce_ms;
+        if let Some(max_uploads) = args.max_concurrent_uploads {
+            config.mcp_config.acemcp_max_concurrent_uploads = Some(max_uploads.max(1));
+        }
+        if let Some(log_json) = args.log_json {
+            config.mcp_config.acemcp_log_json = Some(log_json);
+        }
+        if let Some(max_size_mb) = args.log_max_size_mb {
+            config.mcp_config.acemcp_log_max_size_mb = Some(max_size_mb.max(1));
+        }
+        if let Some(max_files) = args.log_max_files {
+            config.mcp_config.acemcp_log_max_files = Some(max_files.max(1));
+        }
+        if let Some(cert_path) = &args.client_cert_path {
+            let cert_path = cert_path.trim();
+            config.mcp_config.acemcp_client_cert_path = (!cert_path.is_empty()).then(|| cert_path.to_string());
+        }
+        if let Some(key_path) = &args.client_key_path {
+            let key_path = key_path.trim();
+            config.mcp_config.acemcp_client_key_path = (!key_path.is_empty()).then(|| key_path.to_string());
+        }
+        if let Some(ca_path) = &args.extra_ca_cert_path {
+            let ca_path = ca_path.trim();
+            config.mcp_config.acemcp_extra_ca_cert_path = (!ca_path.is_empty()).then(|| ca_path.to_string());
+        }
+        if let Some(accept_invalid) = args.accept_invalid_certs {
+            if accept_invalid {
+                log::warn!("⚠️ 已开启跳过 ACE 证书校验（acemcp_accept_invalid_certs），请仅在受信任的内网环境中使用");
+            }
+            config.mcp_config.acemcp_accept_invalid_certs = Some(accept_invalid);
+        }
+        if let Some(max_bytes) = args.max_file_bytes {
+            config.mcp_config.acemcp_max_file_bytes = if max_bytes == 0 { None } else { Some(max_bytes) };
+        }
+        if let Some(failover) = args.proxy_failover_enabled {
+            config.mcp_config.acemcp_proxy_failover_enabled = Some(failover);
+        }
+        if let Some(http2) = args.http2 {
+            config.mcp_config.acemcp_http2 = Some(http2);
+        }
+        if let Some(suffix) = args.user_agent_suffix.as_deref() {
+            let suffix = suffix.trim();
+            config.mcp_config.acemcp_user_agent_suffix = if suffix.is_empty() { None } else { Some(suffix.to_string()) };
+        }
+        if let Some(max_blobs) = args.max_blobs_per_request {
+            config.mcp_config.acemcp_max_blobs_per_request = if max_blobs == 0 { None } else { Some(max_blobs) };
+        }
+        if let Some(budget) = args.daily_blob_budget {
+            config.mcp_config.acemcp_daily_blob_budget = if budget == 0 { None } else { Some(budget) };
+        }
+        if let Some(max_idle) = args.pool_max_idle_per_host {
+            config.mcp_config.acemcp_pool_max_idle_per_host = if max_idle == 0 { None } else { Some(max_idle) };
+        }
+        if let Some((min, max)) = smart_wait {
+            config.mcp_config.acemcp_smart_wait_min_secs = Some(min);
+            config.mcp_config.acemcp_smart_wait_max_secs = Some(max);
+        }
+        if let Some(keepalive) = args.keepalive_enabled {
+            config.mcp_config.acemcp_keepalive_enabled = Some(keepalive);
+        }
+        if let Some(interval) = args.keepalive_interval_secs {
+            config.mcp_config.acemcp_keepalive_interval_secs =
+                Some(interval.max(super::mcp::KEEPALIVE_MIN_INTERVAL_SECS));
+        }
         // 保存代理配置
         config.mcp_config.acemcp_proxy_enabled = args.proxy_enabled;
         config.mcp_config.acemcp_proxy_host = args.proxy_host.clone();
@@ -82,6 +235,16 @@ pub async fn save_acemcp_config(
         .await
         .map_err(|e| format!("保存配置失败: {}", e))?;
 
+    // 代理/TLS 可能已变化，下次请求时重建共享客户端
+    super::mcp::reset_shared_acemcp_client();
+    // 刚开启保活时立即开始预热（未开启时任务会立即退出）
+    super::mcp::ensure_keepalive_started();
+
+    // 并发上传上限即时生效
+    if let Some(max_uploads) = args.max_concurrent_uploads {
+        super::watcher::get_watcher_manager().set_max_concurrent_uploads(max_uploads as usize);
+    }
+
     Ok(())
 }
 
@@ -91,12 +254,17 @@ pub struct TestAcemcpArgs {
     pub base_url: String,
     #[serde(alias = "token", alias = "_token")]
     pub token: String,
+    #[serde(default, alias = "projectRootPath", alias = "project_root_path")]
+    pub project_root_path: Option<String>, // 指定项目时使用该项目的租户覆盖配置
 }
 
 #[derive(Debug, serde::Serialize)]
 pub struct TestConnectionResult {
     pub success: bool,
     pub message: String,
+    /// 本次测试使用的有效配置（token 与代理密码已脱敏），可传给 `retry_acemcp_connection` 重试
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub effective_config: Option<EffectiveAcemcpConnConfig>,
 }
 
 #[tauri::command]
@@ -104,50 +272,231 @@ pub async fn test_acemcp_connection(
     args: TestAcemcpArgs,
     state: State<'_, AppState>,
 ) -> Result<TestConnectionResult, String> {
-    // 获取配置并立即释放锁
-    let (
-        effective_base_url,
-        effective_token,
-        proxy_enabled,
-        proxy_host,
-        proxy_port,
-        proxy_type,
-        proxy_username,
-        proxy_password,
-    ) = {
-        let config = state.config
-            .lock()
-            .map_err(|e| format!("获取配置失败: {}", e))?;
-        
-        let base_url = config.mcp_config.acemcp_base_url.as_ref().unwrap_or(&args.base_url).clone();
-        let token = config.mcp_config.acemcp_token.as_ref().unwrap_or(&args.token).clone();
+    let conn_config = resolve_conn_config(&args, &state)?;
+    retry_acemcp_connection(conn_config).await
+}
 
-        // 代理配置（连接测试也需要遵循“所有 ACE 通信走代理”的要求）
-        let proxy_enabled = config.mcp_config.acemcp_proxy_enabled.unwrap_or(false);
-        let proxy_host = config.mcp_config.acemcp_proxy_host.clone().unwrap_or_else(|| "127.0.0.1".to_string());
-        let proxy_port = config.mcp_config.acemcp_proxy_port.unwrap_or(7890);
-        let proxy_type = config.mcp_config.acemcp_proxy_type.clone().unwrap_or_else(|| "http".to_string());
-        let proxy_username = config.mcp_config.acemcp_proxy_username.clone();
-        let proxy_password = config.mcp_config.acemcp_proxy_password.clone();
+/// 使用上次测试返回的有效配置重新测试连接（不读取配置、不加锁），结果幂等
+/// 传回的配置中脱敏的密钥从本进程最近一次测试使用的配置中恢复
+#[tauri::command]
+pub async fn retry_acemcp_connection(
+    mut config: EffectiveAcemcpConnConfig,
+) -> Result<TestConnectionResult, String> {
+    config.restore_redacted_secrets();
+    if config.token == crate::utils::logger::REDACTED
+        || is_redacted(config.proxy_password.as_deref())
+    {
+        return Ok(TestConnectionResult {
+            success: false,
+            message: "密钥已脱敏且无法恢复，请重新测试连接".to_string(),
+            effective_config: None,
+        });
+    }
+    let mut result = run_connection_test_with(&config).await?;
+    remember_connection_test(&config, &result);
+    result.effective_config = Some(config.redacted());
+    if let Ok(mut guard) = LAST_EFFECTIVE_CONN_CONFIG.lock() {
+        *guard = Some(config);
+    }
+    Ok(result)
+}
 
-        (
+/// 最近一次连接测试使用的有效配置（含明文密钥，只保存在进程内，用于重试时恢复脱敏字段）
+static LAST_EFFECTIVE_CONN_CONFIG: once_cell::sync::Lazy<std::sync::Mutex<Option<EffectiveAcemcpConnConfig>>> =
+    once_cell::sync::Lazy::new(|| std::sync::Mutex::new(None));
+
+/// 最近一次连接测试结果及对应配置的指纹（供健康概览复用，避免每次都访问网络）
+static LAST_CONNECTION_TEST: once_cell::sync::Lazy<std::sync::Mutex<Option<(String, ConnectionTestSnapshot)>>> =
+    once_cell::sync::Lazy::new(|| std::sync::Mutex::new(None));
+
+/// 连接测试缓存有效期（秒）
+const CONNECTION_TEST_CACHE_TTL_SECS: i64 = 300;
+
+fn remember_connection_test(config: &EffectiveAcemcpConnConfig, result: &TestConnectionResult) {
+    if let Ok(mut guard) = LAST_CONNECTION_TEST.lock() {
+        *guard = Some((config.fingerprint(), ConnectionTestSnapshot {
+            success: result.success,
+            message: result.message.clone(),
+            tested_at: chrono::Utc::now(),
+        }));
+    }
+}
+
+/// 仅当缓存结果是用同一份配置（base_url/token/代理）测得时才返回，配置变更后的旧结果不再展示
+fn cached_connection_test(config: &EffectiveAcemcpConnConfig) -> Option<ConnectionTestSnapshot> {
+    let fingerprint = config.fingerprint();
+    LAST_CONNECTION_TEST
+        .lock()
+        .ok()
+        .and_then(|g| g.as_ref().filter(|(key, _)| *key == fingerprint).map(|(_, snapshot)| snapshot.clone()))
+}
+
+/// 连接测试实际使用的有效配置（项目覆盖 > 已保存配置 > 测试参数）
+/// 随测试结果返回给前端，重试时原样传回，无需再次读取配置
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct EffectiveAcemcpConnConfig {
+    pub base_url: String,
+    pub token: String,
+    pub proxy_enabled: bool,
+    pub proxy_host: String,
+    pub proxy_port: u16,
+    pub proxy_type: String,
+    #[serde(default)]
+    pub proxy_username: Option<String>,
+    #[serde(default)]
+    pub proxy_password: Option<String>,
+    #[serde(default)]
+    pub client_cert_path: Option<String>,
+    #[serde(default)]
+    pub client_key_path: Option<String>,
+    #[serde(default)]
+    pub extra_ca_cert_path: Option<String>,
+    #[serde(default)]
+    pub accept_invalid_certs: bool,
+    #[serde(default)]
+    pub user_agent_suffix: Option<String>,
+}
+
+impl EffectiveAcemcpConnConfig {
+    /// 影响连接结果的字段（base_url/token/代理）的哈希，用作连接测试缓存的 key，避免在内存中多留一份明文密钥
+    fn fingerprint(&self) -> String {
+        let material = serde_json::json!([
+            self.base_url,
+            self.token,
+            self.proxy_enabled,
+            self.proxy_host,
+            self.proxy_port,
+            self.proxy_type,
+            self.proxy_username,
+            self.proxy_password,
+        ])
+        .to_string();
+        let digest = ring::digest::digest(&ring::digest::SHA256, material.as_bytes());
+        hex::encode(digest.as_ref())
+    }
+
+    /// 返回给前端的副本：token 与代理密码替换为占位符（`env:` / `file:` 引用保留）
+    fn redacted(&self) -> Self {
+        let mut config = self.clone();
+        let mut token = Some(std::mem::take(&mut config.token));
+        redact_secret(&mut token);
+        config.token = token.unwrap_or_default();
+        redact_secret(&mut config.proxy_password);
+        config
+    }
+
+    /// 用最近一次测试的配置恢复脱敏的密钥（其余字段以传入的为准）
+    fn restore_redacted_secrets(&mut self) {
+        let last = match LAST_EFFECTIVE_CONN_CONFIG.lock() {
+            Ok(guard) => guard.clone(),
+            Err(_) => None,
+        };
+        let Some(last) = last else { return };
+        if self.token == crate::utils::logger::REDACTED {
+            self.token = last.token;
+        }
+        if is_redacted(self.proxy_password.as_deref()) {
+            self.proxy_password = last.proxy_password;
+        }
+    }
+
+    /// 从 MCP 配置与测试参数中解析有效的连接配置
+    fn resolve(args: &TestAcemcpArgs, mcp_config: &crate::config::McpConfig) -> Self {
+        let project_override = args
+            .project_root_path
+            .as_deref()
+            .and_then(|p| super::mcp::find_project_override(mcp_config, p))
+            .unwrap_or_default();
+        let base_url = project_override
+            .base_url
+            .as_ref()
+            .or(mcp_config.acemcp_base_url.as_ref())
+            .unwrap_or(&args.base_url)
+            .clone();
+        let token = project_override
+            .token
+            .as_ref()
+            .or(mcp_config.acemcp_token.as_ref())
+            .unwrap_or(&args.token)
+            .clone();
+        let tls_options = AcemcpTlsOptions::from_mcp_config(mcp_config);
+
+        // 代理配置（连接测试也需要遵循“所有 ACE 通信走代理”的要求）
+        Self {
             base_url,
             token,
-            proxy_enabled,
-            proxy_host,
-            proxy_port,
-            proxy_type,
-            proxy_username,
-            proxy_password,
-        )
+            proxy_enabled: mcp_config.acemcp_proxy_enabled.unwrap_or(false),
+            proxy_host: mcp_config.acemcp_proxy_host.clone().unwrap_or_else(|| "127.0.0.1".to_string()),
+            proxy_port: mcp_config.acemcp_proxy_port.unwrap_or(7890),
+            proxy_type: mcp_config.acemcp_proxy_type.clone().unwrap_or_else(|| "http".to_string()),
+            proxy_username: mcp_config.acemcp_proxy_username.clone(),
+            proxy_password: mcp_config.acemcp_proxy_password.clone(),
+            client_cert_path: tls_options.client_cert_path,
+            client_key_path: tls_options.client_key_path,
+            extra_ca_cert_path: tls_options.extra_ca_cert_path,
+            accept_invalid_certs: tls_options.accept_invalid_certs,
+            user_agent_suffix: mcp_config.acemcp_user_agent_suffix.clone(),
+        }
+    }
+
+    fn tls_options(&self) -> AcemcpTlsOptions {
+        AcemcpTlsOptions {
+            client_cert_path: self.client_cert_path.clone(),
+            client_key_path: self.client_key_path.clone(),
+            extra_ca_cert_path: self.extra_ca_cert_path.clone(),
+            accept_invalid_certs: self.accept_invalid_certs,
+        }
+    }
+}
+
+/// 读取当前配置并解析出有效的连接配置（获取后立即释放锁）
+fn resolve_conn_config(
+    args: &TestAcemcpArgs,
+    state: &State<'_, AppState>,
+) -> Result<EffectiveAcemcpConnConfig, String> {
+    let config = state.config
+        .lock()
+        .map_err(|e| format!("获取配置失败: {}", e))?;
+    Ok(EffectiveAcemcpConnConfig::resolve(args, &config.mcp_config))
+}
+
+/// 使用给定的有效配置执行连接测试（不访问应用状态）
+async fn run_connection_test_with(
+    conn_config: &EffectiveAcemcpConnConfig,
+) -> Result<TestConnectionResult, String> {
+    let effective_base_url = &conn_config.base_url;
+    // token 可能是 env:/file: 引用，在此解析；effective_config 中仍保留引用形式，明文不会返回前端
+    let effective_token = match crate::config::resolve_token(&conn_config.token) {
+        Ok(token) => token,
+        Err(e) => {
+            return Ok(TestConnectionResult {
+                success: false,
+                message: e,
+                effective_config: None,
+            });
+        }
     };
-    
+    let proxy_enabled = conn_config.proxy_enabled;
+    let proxy_host = &conn_config.proxy_host;
+    let proxy_port = conn_config.proxy_port;
+    let proxy_type = &conn_config.proxy_type;
+    let proxy_username = conn_config.proxy_username.clone();
+    let proxy_password = conn_config.proxy_password.clone();
+    let tls_options = conn_config.tls_options();
+
+    // 测试用的 token/代理密码可能尚未保存，先注册脱敏，避免出现在错误日志中
+    crate::utils::logger::register_secret(&effective_token);
+    if let Some(password) = proxy_password.as_deref() {
+        crate::utils::logger::register_secret(password);
+    }
+
     // 验证 URL 格式
     if !effective_base_url.starts_with("http://") && !effective_base_url.starts_with("https://") {
         let msg = "无效的API端点URL格式，必须以 http:// 或 https:// 开头".to_string();
         return Ok(TestConnectionResult {
             success: false,
             message: msg,
+            effective_config: None,
         });
     }
     
@@ -157,6 +506,7 @@ pub async fn test_acemcp_connection(
         return Ok(TestConnectionResult {
             success: false,
             message: msg,
+            effective_config: None,
         });
     }
     
@@ -169,7 +519,20 @@ pub async fn test_acemcp_connection(
     
     // 实际测试连接 - 发送一个简单的健康检查请求
     let mut client_builder = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(10));
+        .timeout(std::time::Duration::from_secs(10))
+        .user_agent(super::mcp::acemcp_user_agent(conn_config.user_agent_suffix.as_deref()));
+
+    // mTLS 等 TLS 选项（证书缺失或格式错误时直接返回明确的失败原因）
+    client_builder = match tls_options.apply(client_builder) {
+        Ok(builder) => builder,
+        Err(e) => {
+            return Ok(TestConnectionResult {
+                success: false,
+                message: e.to_string(),
+                effective_config: None,
+            });
+        }
+    };
 
     // 如果启用代理，则连接测试也走代理（避免“设置了代理但测试仍失败”的误导）
     if proxy_enabled {
@@ -180,6 +543,7 @@ pub async fn test_acemcp_connection(
                 return Ok(TestConnectionResult {
                     success: false,
                     message: format!("不支持的代理类型: {}（仅支持 http/https/socks5）", other),
+                    effective_config: None,
                 });
             }
         }
@@ -205,11 +569,14 @@ pub async fn test_acemcp_connection(
         .map_err(|e| format!("创建 HTTP 客户端失败: {}", e))?;
     
     // 尝试访问一个常见的端点（如果存在健康检查端点）
-    let test_url = format!("{}/health", normalized_url);
+    let test_url = super::mcp::join_ace_url(&normalized_url, "health");
+    let request_id = new_request_id();
+    log::info!("🔗 [test_acemcp_connection] 健康检查: url={}, request_id={}", test_url, request_id);
     
     match client
         .get(&test_url)
         .header(reqwest::header::AUTHORIZATION, format!("Bearer {}", effective_token))
+        .header(REQUEST_ID_HEADER, &request_id)
         .send()
         .await
     {
@@ -221,6 +588,7 @@ pub async fn test_acemcp_connection(
                 return Ok(TestConnectionResult {
                     success: true,
                     message: msg,
+                    effective_config: None,
                 });
             }
         }
@@ -230,7 +598,7 @@ pub async fn test_acemcp_connection(
     }
     
     // 如果健康检查失败，尝试测试实际的代码库检索端点
-    let search_url = format!("{}/agents/codebase-retrieval", normalized_url);
+    let search_url = super::mcp::join_ace_url(&normalized_url, "agents/codebase-retrieval");
     
     // 发送一个最小的测试请求
     let test_payload = serde_json::json!({
@@ -242,10 +610,13 @@ pub async fn test_acemcp_connection(
         "enable_commit_retrieval": false,
     });
     
+    let request_id = new_request_id();
+    log::info!("🔗 [test_acemcp_connection] 检索端点测试: url={}, request_id={}", search_url, request_id);
     match client
         .post(&search_url)
         .header(reqwest::header::AUTHORIZATION, format!("Bearer {}", effective_token))
         .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header(REQUEST_ID_HEADER, &request_id)
         .json(&test_payload)
         .send()
         .await
@@ -258,6 +629,7 @@ pub async fn test_acemcp_connection(
                 Ok(TestConnectionResult {
                     success: true,
                     message: msg,
+                    effective_config: None,
                 })
             } else {
                 let body = response.text().await.unwrap_or_default();
@@ -265,6 +637,7 @@ pub async fn test_acemcp_connection(
                 Ok(TestConnectionResult {
                     success: false,
                     message: format!("{} - 响应: {}", msg, if body.len() > 200 { format!("{}...", &body[..200]) } else { body }),
+                    effective_config: None,
                 })
             }
         }
@@ -273,14 +646,193 @@ pub async fn test_acemcp_connection(
             Ok(TestConnectionResult {
                 success: false,
                 message: msg,
+                effective_config: None,
             })
         }
     }
 }
 
+/// 获取 ACE 健康概览：配置完整性、最近连接测试、各状态项目数、监听数与代理使用情况
+/// 连接测试结果会缓存 5 分钟，refresh 为 true 时强制重新测试
+#[tauri::command]
+pub async fn get_acemcp_health(
+    refresh: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<AcemcpHealth, String> {
+    let (base_url, token, proxy_in_use) = {
+        let config = state
+            .config
+            .lock()
+            .map_err(|e| format!("获取配置失败: {}", e))?;
+        let mcp = &config.mcp_config;
+        (
+            mcp.acemcp_base_url.clone().unwrap_or_default(),
+            crate::config::resolve_token_opt(mcp.acemcp_token.as_deref()).unwrap_or_default(),
+            proxy_in_use_of(mcp),
+        )
+    };
+
+    let config_complete = !base_url.trim().is_empty() && !token.trim().is_empty();
+    let conn_config = resolve_conn_config(&TestAcemcpArgs { base_url, token, project_root_path: None }, &state)?;
+
+    // 仅在强制刷新或缓存缺失/过期/配置已变更时访问网络
+    let cached = cached_connection_test(&conn_config);
+    let expired = match &cached {
+        Some(c) => (chrono::Utc::now() - c.tested_at).num_seconds() >= CONNECTION_TEST_CACHE_TTL_SECS,
+        None => true,
+    };
+    let connection = if config_complete && (refresh.unwrap_or(false) || expired) {
+        let result = run_connection_test_with(&conn_config).await?;
+        remember_connection_test(&conn_config, &result);
+        cached_connection_test(&conn_config)
+    } else {
+        cached
+    };
+
+    let mut index_counts = IndexStatusCounts::default();
+    for status in AcemcpTool::get_all_index_status().projects.values() {
+        index_counts.record(&status.status);
+    }
+
+    Ok(AcemcpHealth {
+        config_complete,
+        connection,
+        index_counts,
+        active_watchers: super::watcher::get_watcher_manager().get_watching_projects().len(),
+        proxy_in_use,
+    })
+}
+
+/// 当前启用的代理地址（未启用时为 None）
+fn proxy_in_use_of(mcp: &crate::config::McpConfig) -> Option<String> {
+    if !mcp.acemcp_proxy_enabled.unwrap_or(false) {
+        return None;
+    }
+    Some(format!(
+        "{}://{}:{}",
+        mcp.acemcp_proxy_type.clone().unwrap_or_else(|| "http".to_string()),
+        mcp.acemcp_proxy_host.clone().unwrap_or_else(|| "127.0.0.1".to_string()),
+        mcp.acemcp_proxy_port.unwrap_or(7890)
+    ))
+}
+
+/// 诊断文本中附带的最近错误日志条数
+const DIAGNOSTICS_ERROR_LINES: usize = 20;
+
+/// 生成可直接粘贴到 issue/聊天中的诊断文本（不访问网络，敏感信息已脱敏）
+#[tauri::command]
+pub async fn format_acemcp_diagnostics_text(
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    let (base_url, token, proxy_in_use) = {
+        let config = state
+            .config
+            .lock()
+            .map_err(|e| format!("获取配置失败: {}", e))?;
+        let mcp = &config.mcp_config;
+        (
+            mcp.acemcp_base_url.clone().unwrap_or_default(),
+            crate::config::resolve_token_opt(mcp.acemcp_token.as_deref()).unwrap_or_default(),
+            proxy_in_use_of(mcp),
+        )
+    };
+    // 确保 token 即使未出现在日志中也会被替换
+    crate::utils::logger::register_secret(&token);
+    // 与健康概览一致：只展示用当前配置测得的连接测试结果
+    let conn_config = resolve_conn_config(
+        &TestAcemcpArgs { base_url: base_url.clone(), token: token.clone(), project_root_path: None },
+        &state,
+    )?;
+
+    let mut counts = IndexStatusCounts::default();
+    for status in AcemcpTool::get_all_index_status().projects.values() {
+        counts.record(&status.status);
+    }
+
+    let errors: Vec<String> = read_acemcp_log_lines(Some("ERROR".to_string()), None, true)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(raw, entry)| entry.map(|e| e.to_text_line()).unwrap_or(raw))
+        .collect();
+
+    let mut lines = vec![
+        "=== 三术 acemcp 诊断信息 ===".to_string(),
+        format!("生成时间: {}", chrono::Local::now().format("%Y-%m-%d %H:%M:%S")),
+        format!("版本: {}", app.package_info().version),
+        format!("系统: {} / {}", std::env::consts::OS, std::env::consts::ARCH),
+        format!(
+            "Base URL: {}",
+            if base_url.trim().is_empty() { "(未配置)" } else { base_url.trim() }
+        ),
+        format!("Token: {}", if token.trim().is_empty() { "(未配置)" } else { "(已配置)" }),
+        format!("代理: {}", proxy_in_use.unwrap_or_else(|| "未启用".to_string())),
+        format!(
+            "索引状态: 空闲 {} / 索引中 {} / 已同步 {} / 失败 {}",
+            counts.idle, counts.indexing, counts.synced, counts.failed
+        ),
+        format!(
+            "活跃监听: {}",
+            super::watcher::get_watcher_manager().get_watching_projects().len()
+        ),
+    ];
+    lines.push(match cached_connection_test(&conn_config) {
+        Some(c) => format!(
+            "上次连接测试: {} ({}) - {}",
+            if c.success { "成功" } else { "失败" },
+            c.tested_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S"),
+            c.message
+        ),
+        None => "上次连接测试: 未测试".to_string(),
+    });
+
+    let recent = &errors[errors.len().saturating_sub(DIAGNOSTICS_ERROR_LINES)..];
+    lines.push(String::new());
+    lines.push(format!("--- 最近 {} 条错误日志 ---", recent.len()));
+    if recent.is_empty() {
+        lines.push("(无)".to_string());
+    }
+    lines.extend(recent.iter().cloned());
+
+    Ok(crate::utils::logger::redact(&lines.join("\n")))
+}
+
 /// 读取日志文件内容
 #[tauri::command]
-pub async fn read_acemcp_logs(_state: State<'_, AppState>) -> Result<Vec<String>, String> {
+pub async fn read_acemcp_logs(
+    level: Option<String>,
+    project: Option<String>,
+    include_rotated: Option<bool>,
+    _state: State<'_, AppState>,
+) -> Result<Vec<String>, String> {
+    let entries = read_acemcp_log_lines(level, project, include_rotated.unwrap_or(false))?;
+    // 统一返回文本格式，JSON 行转换为与文本模式一致的展示
+    Ok(entries
+        .into_iter()
+        .map(|(raw, entry)| entry.map(|e| e.to_text_line()).unwrap_or(raw))
+        .collect())
+}
+
+/// 读取结构化日志条目（兼容文本与 JSON 两种格式，无法解析的续行会被忽略）
+#[tauri::command]
+pub async fn read_acemcp_log_entries(
+    level: Option<String>,
+    project: Option<String>,
+    include_rotated: Option<bool>,
+    _state: State<'_, AppState>,
+) -> Result<Vec<LogEntry>, String> {
+    let entries = read_acemcp_log_lines(level, project, include_rotated.unwrap_or(false))?;
+    Ok(entries.into_iter().filter_map(|(_, entry)| entry).collect())
+}
+
+/// 读取 acemcp.log 最近 1000 行，并按级别/项目过滤
+/// include_rotated 为 true 时先读取最近一个轮转文件（acemcp.log.1），保证轮转前后日志连续
+/// 返回 (原始行, 解析结果)；过滤条件存在时，无法解析的行会被丢弃
+fn read_acemcp_log_lines(
+    level: Option<String>,
+    project: Option<String>,
+    include_rotated: bool,
+) -> Result<Vec<(String, Option<LogEntry>)>, String> {
     // 使用 dirs::config_dir() 获取系统配置目录，确保跨平台兼容性
     // Windows: C:\Users\<用户>\AppData\Roaming\sanshu\log\acemcp.log
     // Linux: ~/.config/sanshu/log/acemcp.log
@@ -298,23 +850,63 @@ pub async fn read_acemcp_logs(_state: State<'_, AppState>) -> Result<Vec<String>
         }
     }
 
+    let rotated_path = log_path.with_file_name("acemcp.log.1");
+    let read_rotated = include_rotated && rotated_path.exists();
+
     // 如果日志文件不存在，返回空数组
-    if !log_path.exists() {
+    if !log_path.exists() && !read_rotated {
         return Ok(vec![]);
     }
 
-    // 读取日志文件内容
-    let content = std::fs::read_to_string(&log_path)
-        .map_err(|e| format!("读取日志文件失败: {} (路径: {})", e, log_path.display()))?;
+    // 读取日志文件内容（轮转文件在前，当前文件在后）
+    let mut content = String::new();
+    if read_rotated {
+        let rotated = std::fs::read(&rotated_path)
+            .map_err(|e| format!("读取日志文件失败: {} (路径: {})", e, rotated_path.display()))?;
+        content.push_str(&String::from_utf8_lossy(&rotated));
+        if !content.is_empty() && !content.ends_with('\n') {
+            content.push('\n');
+        }
+    }
+    if log_path.exists() {
+        let current = std::fs::read_to_string(&log_path)
+            .map_err(|e| format!("读取日志文件失败: {} (路径: {})", e, log_path.display()))?;
+        content.push_str(&current);
+    }
+
+    let level = level
+        .map(|l| l.trim().to_uppercase())
+        .filter(|l| !l.is_empty());
+    let project = project
+        .map(|p| super::mcp::normalize_project_path(p.trim()))
+        .filter(|p| !p.is_empty());
+    let filtering = level.is_some() || project.is_some();
 
-    // 返回最近1000行日志
-    let all_lines: Vec<String> = content
+    let all_lines: Vec<(String, Option<LogEntry>)> = content
         .lines()
-        .map(|s| s.to_string())
+        .map(|s| (s.to_string(), parse_log_line(s)))
+        .filter(|(_, entry)| {
+            if !filtering {
+                return true;
+            }
+            let Some(entry) = entry else { return false };
+            if let Some(level) = &level {
+                if &entry.level != level {
+                    return false;
+                }
+            }
+            if let Some(project) = &project {
+                match &entry.project {
+                    Some(p) if &super::mcp::normalize_project_path(p) == project => {}
+                    _ => return false,
+                }
+            }
+            true
+        })
         .collect();
 
     // 只返回最后1000行
-    let lines: Vec<String> = if all_lines.len() > 1000 {
+    let lines = if all_lines.len() > 1000 {
         let skip_count = all_lines.len() - 1000;
         all_lines.into_iter().skip(skip_count).collect()
     } else {
@@ -324,12 +916,124 @@ pub async fn read_acemcp_logs(_state: State<'_, AppState>) -> Result<Vec<String>
     Ok(lines)
 }
 
+/// 实时日志推送的停止标记（同一时间只允许一个推送任务）
+static LOG_STREAM_STOP: once_cell::sync::Lazy<std::sync::Mutex<Option<Arc<AtomicBool>>>> =
+    once_cell::sync::Lazy::new(|| std::sync::Mutex::new(None));
+
+/// 日志文件轮询间隔
+const LOG_STREAM_POLL_MS: u64 = 500;
+
+/// 开始实时推送 acemcp.log 新增内容（`acemcp_log_line` 事件）
+/// 已有推送任务时会先停止旧任务；from_start 为 true 时从文件开头推送
+#[tauri::command]
+pub async fn start_acemcp_log_stream(from_start: Option<bool>, app: AppHandle) -> Result<String, String> {
+    let log_path = crate::utils::logger::get_gui_log_path()
+        .ok_or_else(|| "无法获取系统配置目录，请检查操作系统环境".to_string())?;
+
+    let stop = Arc::new(AtomicBool::new(false));
+    {
+        let mut guard = LOG_STREAM_STOP
+            .lock()
+            .map_err(|e| format!("获取日志推送状态失败: {}", e))?;
+        if let Some(old) = guard.replace(stop.clone()) {
+            old.store(true, Ordering::SeqCst);
+        }
+    }
+
+    // 默认从文件末尾开始，只推送新增内容
+    let mut offset = if from_start.unwrap_or(false) {
+        0
+    } else {
+        std::fs::metadata(&log_path).map(|m| m.len()).unwrap_or(0)
+    };
+
+    log::info!("开始实时推送日志: {} (起始偏移 {})", log_path.display(), offset);
+
+    tokio::spawn(async move {
+        // 未以换行结尾的残余内容，等待下次读取补全
+        let mut pending: Vec<u8> = Vec::new();
+
+        while !stop.load(Ordering::SeqCst) {
+            let len = match std::fs::metadata(&log_path) {
+                Ok(m) => m.len(),
+                // 文件暂不存在（如刚被轮转），下次重试
+                Err(_) => 0,
+            };
+
+            // 文件变小说明发生了轮转或截断，从头重新读取
+            if len < offset {
+                offset = 0;
+                pending.clear();
+            }
+
+            if len > offset {
+                match read_log_from_offset(&log_path, offset) {
+                    Ok(bytes) => {
+                        offset += bytes.len() as u64;
+                        pending.extend_from_slice(&bytes);
+
+                        let mut start = 0;
+                        while let Some(pos) = pending[start..].iter().position(|b| *b == b'\n') {
+                            let raw = String::from_utf8_lossy(&pending[start..start + pos]);
+                            let line = raw.trim_end_matches('\r').to_string();
+                            start += pos + 1;
+                            if line.is_empty() {
+                                continue;
+                            }
+                            let entry = parse_log_line(&line);
+                            let payload = AcemcpLogLine {
+                                level: entry.as_ref().map(|e| e.level.clone()),
+                                project: entry.and_then(|e| e.project),
+                                line,
+                            };
+                            let _ = app.emit("acemcp_log_line", &payload);
+                        }
+                        pending.drain(..start);
+                    }
+                    Err(e) => {
+                        log::warn!("读取日志增量失败: {}", e);
+                    }
+                }
+            }
+
+            tokio::time::sleep(Duration::from_millis(LOG_STREAM_POLL_MS)).await;
+        }
+
+        log::info!("实时日志推送已停止");
+    });
+
+    Ok(log_path.to_string_lossy().to_string())
+}
+
+/// 停止实时推送 acemcp.log，返回是否有正在运行的推送任务
+#[tauri::command]
+pub async fn stop_acemcp_log_stream() -> Result<bool, String> {
+    let mut guard = LOG_STREAM_STOP
+        .lock()
+        .map_err(|e| format!("获取日志推送状态失败: {}", e))?;
+    match guard.take() {
+        Some(stop) => {
+            stop.store(true, Ordering::SeqCst);
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+/// 从指定偏移读取日志文件剩余内容
+fn read_log_from_offset(path: &std::path::Path, offset: u64) -> std::io::Result<Vec<u8>> {
+    use std::io::{Read, Seek, SeekFrom};
+
+    let mut file = std::fs::File::open(path)?;
+    file.seek(SeekFrom::Start(offset))?;
+    let mut buf = Vec::new();
+    file.read_to_end(&mut buf)?;
+    Ok(buf)
+}
+
 #[tauri::command]
 pub async fn clear_acemcp_cache(_state: State<'_, AppState>) -> Result<String, String> {
-    // 使用 dirs::home_dir() 获取用户主目录，确保跨平台兼容性
-    // 如果获取失败，降级到当前目录（与项目中 home_projects_file() 保持一致）
-    let home = dirs::home_dir().unwrap_or_else(|| std::path::PathBuf::from("."));
-    let cache_dir = home.join(".acemcp").join("data");
+    let cache_dir = acemcp_data_dir();
 
     // 如果缓存目录存在，先删除
     if cache_dir.exists() {
@@ -346,15 +1050,80 @@ pub async fn clear_acemcp_cache(_state: State<'_, AppState>) -> Result<String, S
     Ok(cache_path)
 }
 
+/// acemcp 数据目录（projects.json 等索引数据所在位置）
+fn acemcp_data_dir() -> std::path::PathBuf {
+    // 使用 dirs::home_dir() 获取用户主目录，确保跨平台兼容性
+    // 如果获取失败，降级到当前目录（与项目中 home_projects_file() 保持一致）
+    let home = dirs::home_dir().unwrap_or_else(|| std::path::PathBuf::from("."));
+    home.join(".acemcp").join("data")
+}
+
+/// 创建目录（如不存在）并用系统文件管理器打开，返回目录路径
+fn open_dir_in_file_manager(dir: &std::path::Path) -> Result<String, String> {
+    use std::process::Command;
+
+    std::fs::create_dir_all(dir)
+        .map_err(|e| format!("创建目录失败: {} (路径: {})", e, dir.display()))?;
+
+    let result = if cfg!(target_os = "windows") {
+        Command::new("explorer").arg(dir).spawn()
+    } else if cfg!(target_os = "macos") {
+        Command::new("open").arg(dir).spawn()
+    } else {
+        Command::new("xdg-open").arg(dir).spawn()
+    };
+    result.map_err(|e| format!("无法打开目录: {} (路径: {})", e, dir.display()))?;
+
+    Ok(dir.to_string_lossy().to_string())
+}
+
+/// 在系统文件管理器中打开 acemcp 数据目录
+#[tauri::command]
+pub async fn open_acemcp_data_dir() -> Result<String, String> {
+    open_dir_in_file_manager(&acemcp_data_dir())
+}
+
+/// 在系统文件管理器中打开 acemcp 日志目录
+#[tauri::command]
+pub async fn open_acemcp_log_dir() -> Result<String, String> {
+    let log_path = crate::utils::logger::get_gui_log_path()
+        .ok_or_else(|| "无法获取系统配置目录，请检查操作系统环境".to_string())?;
+    let log_dir = log_path
+        .parent()
+        .ok_or_else(|| format!("无效的日志路径: {}", log_path.display()))?;
+    open_dir_in_file_manager(log_dir)
+}
+
 #[derive(Debug, serde::Serialize)]
 pub struct AcemcpConfigResponse {
     pub base_url: Option<String>,
     pub token: Option<String>,
     pub batch_size: u32,
+    pub max_blobs_per_request: u32,
+    pub daily_blob_budget: Option<u32>, // 每日上传 blob 预算（未设置时不提醒）
     pub max_lines_per_blob: u32,
+    pub max_lines_by_extension: std::collections::HashMap<String, u32>, // 按扩展名覆盖的最大行数
     pub text_extensions: Vec<String>,
     pub exclude_patterns: Vec<String>,
+    pub follow_symlinks: bool, // 扫描时是否跟随符号链接目录
     pub watch_debounce_ms: u64, // 文件监听防抖延迟（毫秒），默认 180000 (3分钟)
+    pub max_concurrent_uploads: u32, // 自动索引同时上传的最大项目数，默认 2
+    pub log_json: bool, // acemcp.log 是否使用 JSON 行格式
+    pub log_max_size_mb: u64, // acemcp.log 单文件大小上限（MB）
+    pub log_max_files: u32, // acemcp.log 保留的轮转文件数
+    pub client_cert_path: Option<String>, // mTLS 客户端证书路径
+    pub client_key_path: Option<String>, // mTLS 客户端私钥路径
+    pub extra_ca_cert_path: Option<String>, // 额外信任的根证书路径
+    pub accept_invalid_certs: bool, // 是否跳过证书校验
+    pub keepalive_enabled: bool, // 是否启用后台保活 Ping
+    pub keepalive_interval_secs: u64, // 保活 Ping 间隔（秒）
+    pub smart_wait_min_secs: u64, // 智能等待最短秒数（0/0 表示禁用）
+    pub smart_wait_max_secs: u64, // 智能等待最长秒数
+    pub max_file_bytes: u64, // 文件监听忽略的文件大小上限（字节，0 表示不限制）
+    pub proxy_failover_enabled: bool, // 上传连续失败时是否自动切换到备用代理
+    pub http2: bool, // 是否强制使用 HTTP/2
+    pub pool_max_idle_per_host: u32, // 每个主机的最大空闲连接数（0 表示默认）
+    pub user_agent_suffix: Option<String>, // User-Agent 附加标识
     // 代理配置
     pub proxy_enabled: bool,
     pub proxy_host: String,
@@ -364,6 +1133,23 @@ pub struct AcemcpConfigResponse {
     pub proxy_password: String,
 }
 
+/// 轻量检查 ACE 是否已配置，供前端启用按钮前调用，避免为判断是否存在而传输 token
+#[tauri::command]
+pub async fn is_acemcp_configured(state: State<'_, AppState>) -> Result<AcemcpConfiguredStatus, String> {
+    let config = state
+        .config
+        .lock()
+        .map_err(|e| format!("获取配置失败: {}", e))?;
+    let mcp = &config.mcp_config;
+    let has_base_url = mcp.acemcp_base_url.as_deref().is_some_and(|s| !s.trim().is_empty());
+    let has_token = crate::config::resolve_token_opt(mcp.acemcp_token.as_deref()).is_some();
+    Ok(AcemcpConfiguredStatus {
+        has_base_url,
+        has_token,
+        configured: has_base_url && has_token,
+    })
+}
+
 #[tauri::command]
 pub async fn get_acemcp_config(state: State<'_, AppState>) -> Result<AcemcpConfigResponse, String> {
     let config = state.config
@@ -372,28 +1158,73 @@ pub async fn get_acemcp_config(state: State<'_, AppState>) -> Result<AcemcpConfi
     Ok(AcemcpConfigResponse {
         base_url: config.mcp_config.acemcp_base_url.clone(),
         token: config.mcp_config.acemcp_token.clone(),
-        batch_size: config.mcp_config.acemcp_batch_size.unwrap_or(10),
-        max_lines_per_blob: config.mcp_config.acemcp_max_lines_per_blob.unwrap_or(800),
-        // 默认文件扩展名列表（与前端 McpToolsTab.vue 保持一致）
-        // 用户首次打开设置界面时，所有扩展名默认全部勾选
-        text_extensions: config.mcp_config.acemcp_text_extensions.clone().unwrap_or_else(|| {
-            vec![
-                ".py".to_string(), ".js".to_string(), ".ts".to_string(),
-                ".jsx".to_string(), ".tsx".to_string(), ".java".to_string(),
-                ".go".to_string(), ".rs".to_string(), ".cpp".to_string(),
-                ".c".to_string(), ".h".to_string(), ".hpp".to_string(),
-                ".cs".to_string(), ".rb".to_string(), ".php".to_string(),
-                ".md".to_string(), ".txt".to_string(), ".json".to_string(),
-                ".yaml".to_string(), ".yml".to_string(), ".toml".to_string(),
-                ".xml".to_string(), ".html".to_string(), ".css".to_string(),
-                ".scss".to_string(), ".sql".to_string(), ".sh".to_string(),
-                ".bash".to_string()
-            ]
-        }),
-        exclude_patterns: config.mcp_config.acemcp_exclude_patterns.clone().unwrap_or_else(|| {
-            vec!["node_modules".to_string(), ".git".to_string(), "target".to_string(), "dist".to_string()]
-        }),
-        watch_debounce_ms: config.mcp_config.acemcp_watch_debounce_ms.unwrap_or(180_000),
+        batch_size: config.mcp_config.acemcp_batch_size.unwrap_or(DEFAULT_ACEMCP_BATCH_SIZE),
+        max_blobs_per_request: config
+            .mcp_config
+            .acemcp_max_blobs_per_request
+            .unwrap_or(DEFAULT_ACEMCP_MAX_BLOBS_PER_REQUEST),
+        daily_blob_budget: config.mcp_config.acemcp_daily_blob_budget,
+        max_lines_per_blob: config
+            .mcp_config
+            .acemcp_max_lines_per_blob
+            .unwrap_or(DEFAULT_ACEMCP_MAX_LINES_PER_BLOB),
+        max_lines_by_extension: config
+            .mcp_config
+            .acemcp_max_lines_by_extension
+            .clone()
+            .unwrap_or_default(),
+        // 默认值与 migrate_config 共用同一来源，用户首次打开设置界面时所有扩展名默认全部勾选
+        text_extensions: config
+            .mcp_config
+            .acemcp_text_extensions
+            .as_deref()
+            .map(super::mcp::normalize_text_extensions_lenient)
+            .unwrap_or_else(crate::config::default_acemcp_text_extensions),
+        exclude_patterns: config
+            .mcp_config
+            .acemcp_exclude_patterns
+            .clone()
+            .unwrap_or_else(crate::config::default_acemcp_exclude_patterns),
+        follow_symlinks: config.mcp_config.acemcp_follow_symlinks.unwrap_or(false),
+        watch_debounce_ms: config
+            .mcp_config
+            .acemcp_watch_debounce_ms
+            .unwrap_or(DEFAULT_ACEMCP_WATCH_DEBOUNCE_MS),
+        max_concurrent_uploads: config
+            .mcp_config
+            .acemcp_max_concurrent_uploads
+            .unwrap_or(DEFAULT_ACEMCP_MAX_CONCURRENT_UPLOADS),
+        log_json: config.mcp_config.acemcp_log_json.unwrap_or(false),
+        log_max_size_mb: config
+            .mcp_config
+            .acemcp_log_max_size_mb
+            .unwrap_or(crate::utils::logger::DEFAULT_LOG_MAX_SIZE_MB),
+        log_max_files: config
+            .mcp_config
+            .acemcp_log_max_files
+            .unwrap_or(crate::utils::logger::DEFAULT_LOG_MAX_FILES),
+        client_cert_path: config.mcp_config.acemcp_client_cert_path.clone(),
+        client_key_path: config.mcp_config.acemcp_client_key_path.clone(),
+        extra_ca_cert_path: config.mcp_config.acemcp_extra_ca_cert_path.clone(),
+        accept_invalid_certs: config.mcp_config.acemcp_accept_invalid_certs.unwrap_or(false),
+        keepalive_enabled: config.mcp_config.acemcp_keepalive_enabled.unwrap_or(false),
+        keepalive_interval_secs: config
+            .mcp_config
+            .acemcp_keepalive_interval_secs
+            .unwrap_or(super::mcp::KEEPALIVE_DEFAULT_INTERVAL_SECS),
+        smart_wait_min_secs: config
+            .mcp_config
+            .acemcp_smart_wait_min_secs
+            .unwrap_or(crate::constants::mcp::DEFAULT_ACEMCP_SMART_WAIT_MIN_SECS),
+        smart_wait_max_secs: config
+            .mcp_config
+            .acemcp_smart_wait_max_secs
+            .unwrap_or(crate::constants::mcp::DEFAULT_ACEMCP_SMART_WAIT_MAX_SECS),
+        max_file_bytes: config.mcp_config.acemcp_max_file_bytes.unwrap_or(0),
+        proxy_failover_enabled: config.mcp_config.acemcp_proxy_failover_enabled.unwrap_or(false),
+        http2: config.mcp_config.acemcp_http2.unwrap_or(false),
+        pool_max_idle_per_host: config.mcp_config.acemcp_pool_max_idle_per_host.unwrap_or(0),
+        user_agent_suffix: config.mcp_config.acemcp_user_agent_suffix.clone(),
         // 代理配置
         proxy_enabled: config.mcp_config.acemcp_proxy_enabled.unwrap_or(false),
         proxy_host: config.mcp_config.acemcp_proxy_host.clone().unwrap_or_else(|| "127.0.0.1".to_string()),
@@ -404,35 +1235,637 @@ pub async fn get_acemcp_config(state: State<'_, AppState>) -> Result<AcemcpConfi
     })
 }
 
-#[derive(Debug, serde::Serialize)]
-pub struct DebugSearchResult {
-    /// 搜索是否成功
-    pub success: bool,
-    /// 搜索结果文本
-    pub result: Option<String>,
-    /// 错误信息
-    pub error: Option<String>,
-    /// 请求发送时间 ISO8601 格式
-    pub request_time: String,
-    /// 响应接收时间 ISO8601 格式
-    pub response_time: String,
-    /// 总耗时（毫秒）
-    pub total_duration_ms: u64,
-    /// 搜索结果数量
-    pub result_count: Option<usize>,
-    /// 项目路径
-    pub project_path: String,
+/// 导出的 acemcp 设置（用于在另一台机器上导入）
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct AcemcpSettingsExport {
+    pub app_version: String, // 导出时的应用版本
+    pub exported_at: String, // 导出时间（RFC3339）
+    pub secrets_included: bool, // 是否包含明文密钥（否则密钥字段为 ******）
+    pub mcp_config: serde_json::Value, // mcp_config 完整内容
+}
+
+/// 导入 acemcp 设置的结果
+#[derive(Debug, Default, serde::Serialize)]
+pub struct AcemcpSettingsImportResult {
+    pub applied: Vec<String>, // 已写入的字段
+    pub kept: Vec<String>, // 未覆盖（当前已有值）的字段
+    pub ignored: Vec<String>, // 忽略的字段（未知字段、空值或已脱敏的密钥）
+}
+
+/// 将密钥替换为占位符；`env:` / `file:` 引用不是明文，原样保留
+fn redact_secret(value: &mut Option<String>) {
+    if let Some(v) = value {
+        if !v.is_empty() && !crate::config::is_token_reference(v) {
+            *v = crate::utils::logger::REDACTED.to_string();
+        }
+    }
+}
+
+/// 导入值是否为导出时的脱敏占位符
+fn is_redacted(value: Option<&str>) -> bool {
+    value == Some(crate::utils::logger::REDACTED)
+}
+
+/// 校验导入后的 mcp_config（端口、代理类型、URL、分块参数等）
+fn validate_imported_mcp_config(mcp: &mut crate::config::McpConfig) -> Result<(), String> {
+    let known_proxy_type = |t: &str| matches!(t, "http" | "https" | "socks5");
+
+    if let Some(base_url) = mcp.acemcp_base_url.as_deref().map(str::trim).filter(|s| !s.is_empty()) {
+        if !(base_url.starts_with("http://") || base_url.starts_with("https://")) {
+            return Err(format!("无效的 acemcp_base_url: {}", base_url));
+        }
+    }
+    if mcp.acemcp_batch_size == Some(0) || mcp.acemcp_max_lines_per_blob == Some(0) {
+        return Err("acemcp_batch_size / acemcp_max_lines_per_blob 必须大于 0".to_string());
+    }
+    if let Some(exts) = &mcp.acemcp_text_extensions {
+        mcp.acemcp_text_extensions = Some(super::mcp::normalize_text_extensions(exts)?);
+    }
+    if let Some(overrides) = &mcp.acemcp_max_lines_by_extension {
+        mcp.acemcp_max_lines_by_extension = Some(super::mcp::normalize_max_lines_overrides(overrides)?);
+    }
+
+    if let Some(proxy_type) = &mcp.acemcp_proxy_type {
+        if !known_proxy_type(proxy_type) {
+            return Err(format!("不支持的代理类型: {}（仅支持 http/https/socks5）", proxy_type));
+        }
+    }
+    if mcp.acemcp_proxy_port == Some(0) {
+        return Err("代理端口无效: 0".to_string());
+    }
+    for profile in mcp.acemcp_proxy_profiles.iter().flatten() {
+        if profile.name.trim().is_empty() {
+            return Err("代理方案名称不能为空".to_string());
+        }
+        if !known_proxy_type(&profile.proxy_type) {
+            return Err(format!("代理方案 {} 的类型不受支持: {}", profile.name, profile.proxy_type));
+        }
+        if profile.host.trim().is_empty() || profile.port == 0 {
+            return Err(format!("代理方案 {} 的地址或端口无效", profile.name));
+        }
+    }
+    if let Some(last_good) = &mcp.acemcp_last_good_proxy {
+        if last_good.port == 0 {
+            return Err("acemcp_last_good_proxy 端口无效: 0".to_string());
+        }
+    }
+    Ok(())
+}
+
+/// 将导入的 mcp_config 按字段合并到当前配置
+/// - 未知字段、空值与脱敏占位符会被忽略，不会清除当前已有的值
+/// - overwrite 为 false 时只填充当前为空的字段
+/// - 代理方案密码 / 项目级 token 为占位符时沿用当前同名条目的值
+fn merge_imported_mcp_config(
+    current: &crate::config::McpConfig,
+    imported: serde_json::Value,
+    overwrite: bool,
+) -> Result<(crate::config::McpConfig, AcemcpSettingsImportResult), String> {
+    let imported = match imported {
+        serde_json::Value::Object(mut map) => match map.remove("mcp_config") {
+            // 兼容完整导出文件与只包含 mcp_config 的 JSON
+            Some(serde_json::Value::Object(inner)) => inner,
+            Some(_) => return Err("mcp_config 字段必须是对象".to_string()),
+            None => map,
+        },
+        _ => return Err("导入内容必须是 JSON 对象".to_string()),
+    };
+
+    let mut merged = match serde_json::to_value(current).map_err(|e| format!("序列化当前配置失败: {}", e))? {
+        serde_json::Value::Object(map) => map,
+        _ => return Err("当前配置格式异常".to_string()),
+    };
+
+    let mut result = AcemcpSettingsImportResult::default();
+    for (key, value) in imported {
+        let Some(existing) = merged.get(&key) else {
+            result.ignored.push(key);
+            continue;
+        };
+        if value.is_null() || is_redacted(value.as_str()) {
+            result.ignored.push(key);
+            continue;
+        }
+        if !overwrite && !existing.is_null() {
+            result.kept.push(key);
+            continue;
+        }
+        merged.insert(key.clone(), value);
+        result.applied.push(key);
+    }
+
+    let mut mcp: crate::config::McpConfig = serde_json::from_value(serde_json::Value::Object(merged))
+        .map_err(|e| format!("导入的配置格式无效: {}", e))?;
+
+    for profile in mcp.acemcp_proxy_profiles.iter_mut().flatten() {
+        if is_redacted(profile.password.as_deref()) {
+            profile.password = current
+                .acemcp_proxy_profiles
+                .iter()
+                .flatten()
+                .find(|p| p.name == profile.name)
+                .and_then(|p| p.password.clone());
+        }
+    }
+    for (root, project) in mcp.acemcp_project_overrides.iter_mut().flatten() {
+        if is_redacted(project.token.as_deref()) {
+            project.token = current
+                .acemcp_project_overrides
+                .as_ref()
+                .and_then(|o| o.get(root))
+                .and_then(|o| o.token.clone());
+        }
+    }
+
+    validate_imported_mcp_config(&mut mcp)?;
+    Ok((mcp, result))
+}
+
+/// 导出全部 acemcp 设置（mcp_config）；include_secrets 为 false 时密钥以 ****** 代替
+#[tauri::command]
+pub async fn export_acemcp_settings(
+    include_secrets: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<AcemcpSettingsExport, String> {
+    let include_secrets = include_secrets.unwrap_or(false);
+    let mut mcp = {
+        let config = state
+            .config
+            .lock()
+            .map_err(|e| format!("获取配置失败: {}", e))?;
+        config.mcp_config.clone()
+    };
+
+    if !include_secrets {
+        redact_secret(&mut mcp.acemcp_token);
+        redact_secret(&mut mcp.acemcp_proxy_password);
+        redact_secret(&mut mcp.context7_api_key);
+        for profile in mcp.acemcp_proxy_profiles.iter_mut().flatten() {
+            redact_secret(&mut profile.password);
+        }
+        for project in mcp.acemcp_project_overrides.iter_mut().flatten().map(|(_, o)| o) {
+            redact_secret(&mut project.token);
+        }
+    }
+
+    let mcp_config = serde_json::to_value(&mcp).map_err(|e| format!("导出配置失败: {}", e))?;
+    log::info!("📤 导出 acemcp 设置: secrets_included={}", include_secrets);
+    Ok(AcemcpSettingsExport {
+        app_version: crate::constants::app::VERSION.to_string(),
+        exported_at: chrono::Utc::now().to_rfc3339(),
+        secrets_included: include_secrets,
+        mcp_config,
+    })
+}
+
+/// 导入 acemcp 设置：校验后按字段合并到当前配置并保存
+/// overwrite 为 false 时仅填充当前未设置的字段
+#[tauri::command]
+pub async fn import_acemcp_settings(
+    json: String,
+    overwrite: bool,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<AcemcpSettingsImportResult, String> {
+    let imported: serde_json::Value =
+        serde_json::from_str(&json).map_err(|e| format!("解析导入内容失败: {}", e))?;
+
+    let result = {
+        let mut config = state
+            .config
+            .lock()
+            .map_err(|e| format!("获取配置失败: {}", e))?;
+        let (mcp, result) = merge_imported_mcp_config(&config.mcp_config, imported, overwrite)?;
+        config.mcp_config = mcp;
+        result
+    };
+
+    save_config(&state, &app)
+        .await
+        .map_err(|e| format!("保存配置失败: {}", e))?;
+
+    // 代理/TLS 可能已变化，下次请求时重建共享客户端
+    super::mcp::reset_shared_acemcp_client();
+    super::mcp::ensure_keepalive_started();
+
+    log::info!(
+        "📥 导入 acemcp 设置: 写入 {} 项, 保留 {} 项, 忽略 {} 项",
+        result.applied.len(),
+        result.kept.len(),
+        result.ignored.len()
+    );
+    Ok(result)
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct DebugSearchResult {
+    /// 搜索是否成功
+    pub success: bool,
+    /// 搜索结果文本
+    pub result: Option<String>,
+    /// 错误信息
+    pub error: Option<String>,
+    /// 请求发送时间 ISO8601 格式
+    pub request_time: String,
+    /// 响应接收时间 ISO8601 格式
+    pub response_time: String,
+    /// 总耗时（毫秒）
+    pub total_duration_ms: u64,
+    /// 搜索结果数量
+    pub result_count: Option<usize>,
+    /// 项目路径
+    pub project_path: String,
     /// 查询语句
     pub query: String,
+    /// 首字节接收时间 ISO8601 格式（仅流式调试搜索）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first_byte_time: Option<String>,
+    /// 首字节耗时（毫秒，仅流式调试搜索）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first_byte_ms: Option<u64>,
+    /// 服务端是否以流式返回（仅流式调试搜索）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub streamed: Option<bool>,
+    /// 搜索时项目索引仍在进行中（结果可能不完整，空结果不代表失败）
+    pub index_in_progress: bool,
+    /// 搜索被 `cancel_acemcp_search` 取消
+    pub cancelled: bool,
+}
+
+/// 索引进行中时搜索报错的提示文本
+const INDEX_IN_PROGRESS_NOTE: &str = "索引进行中，结果可能不完整";
+
+/// 项目当前是否处于索引中
+fn is_index_in_progress(project_root_path: &str) -> bool {
+    AcemcpTool::get_index_status(project_root_path.to_string()).status == IndexStatus::Indexing
+}
+
+/// 将搜索结果整理为 (success, result, error)
+/// 索引进行中导致的失败视为“未就绪”而非错误：success 为 true，result 附带说明
+fn classify_debug_search(is_error: bool, text: String, index_in_progress: bool) -> (bool, Option<String>, Option<String>) {
+    match (is_error, index_in_progress) {
+        (false, _) => (true, Some(text), None),
+        (true, true) => (true, Some(format!("{}：{}", INDEX_IN_PROGRESS_NOTE, text)), None),
+        (true, false) => (false, None, Some(text)),
+    }
+}
+
+/// 搜索被取消时的调试结果
+fn cancelled_debug_search_result(
+    project_path: String,
+    query: String,
+    request_time: String,
+    response_time: String,
+    total_duration_ms: u64,
+    index_in_progress: bool,
+) -> DebugSearchResult {
+    DebugSearchResult {
+        success: false,
+        result: None,
+        error: Some("已取消".to_string()),
+        request_time,
+        response_time,
+        total_duration_ms,
+        result_count: None,
+        project_path,
+        query,
+        first_byte_time: None,
+        first_byte_ms: None,
+        streamed: None,
+        index_in_progress,
+        cancelled: true,
+    }
+}
+
+/// 取消指定项目进行中的搜索（调试搜索与 MCP 搜索均适用），返回被取消的搜索数量
+/// 只影响该项目，其他项目的搜索继续执行
+#[tauri::command]
+pub fn cancel_acemcp_search(project_root_path: String) -> Result<usize, String> {
+    Ok(super::mcp::cancel_search(&project_root_path))
+}
+
+/// 获取调试搜索历史（最新的在前），传入项目路径时只返回该项目的记录
+#[tauri::command]
+pub fn get_debug_search_history(project_root_path: Option<String>) -> Vec<DebugSearchHistoryEntry> {
+    super::history::load_history(project_root_path.as_deref())
+}
+
+/// 保存一次调试搜索查询（同一项目的相同查询只保留最新一条）
+#[tauri::command]
+pub fn save_debug_search_query(project_root_path: String, query: String) -> Result<(), String> {
+    super::history::record_query(&project_root_path, &query)
+        .map_err(|e| format!("保存搜索历史失败: {}", e))
+}
+
+/// 清空调试搜索历史，传入项目路径时只清空该项目，返回删除的条数
+#[tauri::command]
+pub fn clear_debug_search_history(project_root_path: Option<String>) -> Result<usize, String> {
+    super::history::clear_history(project_root_path.as_deref())
+        .map_err(|e| format!("清空搜索历史失败: {}", e))
+}
+
+/// 获取调试搜索查询模板
+#[tauri::command]
+pub fn get_debug_search_templates() -> Vec<DebugSearchTemplate> {
+    super::history::load_templates()
+}
+
+/// 新增或覆盖同名查询模板，query 中可使用 `{name}` 占位符
+#[tauri::command]
+pub fn save_debug_search_template(name: String, query: String) -> Result<(), String> {
+    super::history::save_template(&name, &query)
+        .map_err(|e| format!("保存查询模板失败: {}", e))
+}
+
+/// 删除查询模板，返回模板是否存在
+#[tauri::command]
+pub fn delete_debug_search_template(name: String) -> Result<bool, String> {
+    super::history::delete_template(&name)
+        .map_err(|e| format!("删除查询模板失败: {}", e))
+}
+
+/// 用给定的值填充模板占位符，返回可直接传给 `debug_acemcp_search` 的查询
+#[tauri::command]
+pub fn render_debug_search_template(
+    name: String,
+    values: std::collections::HashMap<String, String>,
+) -> Result<String, String> {
+    let template = super::history::load_templates()
+        .into_iter()
+        .find(|t| t.name == name)
+        .ok_or_else(|| format!("查询模板不存在: {}", name))?;
+    super::history::render_template(&template.query, &values).map_err(|e| e.to_string())
 }
 
 /// 纯 Rust 的调试命令：直接执行 acemcp 搜索，返回结果及耗时统计
+/// 传入 proxy_host 时本次搜索经指定代理发出（参数与 `test_acemcp_proxy_speed` 一致），不写入配置；
+/// 未传入时使用已保存的代理配置
 #[tauri::command]
 pub async fn debug_acemcp_search(
     project_root_path: String,
     query: String,
-    _app: AppHandle,
+    proxy_host: Option<String>,
+    proxy_port: Option<u16>,
+    proxy_type: Option<String>,
+    proxy_username: Option<String>,
+    proxy_password: Option<String>,
+    app: AppHandle,
+    state: State<'_, AppState>,
 ) -> Result<DebugSearchResult, String> {
+    let proxy_override = proxy_host
+        .map(|h| h.trim().to_string())
+        .filter(|h| !h.is_empty())
+        .map(|host| AcemcpProxyOverride {
+            host,
+            port: proxy_port.unwrap_or(7890),
+            proxy_type: proxy_type.unwrap_or_else(|| "http".to_string()),
+            username: proxy_username.filter(|u| !u.is_empty()),
+            password: proxy_password.filter(|p| !p.is_empty()),
+        });
+    remember_last_test_query(&state, &app, &project_root_path, &query).await;
+    Ok(run_debug_search(project_root_path, query, proxy_override).await)
+}
+
+/// 获取项目最近一次测速/调试搜索使用的查询（用于前端预填）
+#[tauri::command]
+pub async fn get_last_test_query(
+    project_root_path: String,
+    state: State<'_, AppState>,
+) -> Result<Option<String>, String> {
+    let config = state
+        .config
+        .lock()
+        .map_err(|e| format!("获取配置失败: {}", e))?;
+    let key = super::mcp::normalize_project_path(&project_root_path);
+    Ok(config
+        .mcp_config
+        .acemcp_last_test_queries
+        .as_ref()
+        .and_then(|queries| queries.get(&key).cloned()))
+}
+
+/// 记录项目最近一次使用的测试查询（按规范化项目路径隔离），失败仅记录日志
+async fn remember_last_test_query(state: &State<'_, AppState>, app: &AppHandle, project_root_path: &str, query: &str) {
+    let query = query.trim();
+    let key = super::mcp::normalize_project_path(project_root_path);
+    if query.is_empty() || key.is_empty() {
+        return;
+    }
+
+    let updated = match state.config.lock() {
+        Ok(mut config) => {
+            let queries = config.mcp_config.acemcp_last_test_queries.get_or_insert_with(Default::default);
+            if queries.get(&key).map(|q| q.as_str()) == Some(query) {
+                false
+            } else {
+                queries.insert(key, query.to_string());
+                true
+            }
+        }
+        Err(e) => {
+            log::warn!("记录测试查询失败: {}", e);
+            false
+        }
+    };
+
+    if updated {
+        if let Err(e) = save_config(state, app).await {
+            log::warn!("保存测试查询失败: {}", e);
+        }
+    }
+}
+
+/// 批量调试搜索的默认并发数
+const DEBUG_SEARCH_BATCH_DEFAULT_CONCURRENCY: usize = 3;
+/// 批量调试搜索的最大并发数（避免触发 ACE 限流）
+const DEBUG_SEARCH_BATCH_MAX_CONCURRENCY: usize = 8;
+/// 单次批量调试搜索最多包含的查询数
+const DEBUG_SEARCH_BATCH_MAX_QUERIES: usize = 50;
+
+/// 搜索结果断言（"搜索仍然可用"的冒烟检查）
+#[derive(Debug, serde::Serialize)]
+pub struct SearchExpectationResult {
+    /// 期望的文件路径片段是否出现在结果中
+    pub found: bool,
+    /// 首个命中结果的排名（从 1 开始）
+    pub rank: Option<usize>,
+    /// 命中的完整文件路径
+    pub matched_path: Option<String>,
+    /// 结果中的代码块数量
+    pub total_results: usize,
+    /// 结果中识别出的文件路径（按排名）
+    pub result_paths: Vec<String>,
+    /// 搜索耗时（毫秒）
+    pub duration_ms: u64,
+    /// 搜索失败原因（失败时 found 为 false）
+    pub error: Option<String>,
+}
+
+/// 在结果代码块中查找期望的路径片段，返回 (排名, 命中路径)
+/// 路径统一为正斜杠并忽略大小写比较
+fn find_expected_path(blocks: &[&str], expected: &str) -> Option<(usize, String)> {
+    let expected = expected.trim().replace('\\', "/").to_lowercase();
+    blocks.iter().enumerate().find_map(|(idx, block)| {
+        let path = block_file_path(block)?;
+        path.replace('\\', "/")
+            .to_lowercase()
+            .contains(&expected)
+            .then(|| (idx + 1, path))
+    })
+}
+
+/// 执行一次搜索并断言结果中包含期望的文件路径片段（配置/代理变更后的回归检查）
+#[tauri::command]
+pub async fn assert_acemcp_search_contains(
+    project_root_path: String,
+    query: String,
+    expected_path_substring: String,
+) -> Result<SearchExpectationResult, String> {
+    if expected_path_substring.trim().is_empty() {
+        return Err("期望的路径片段不能为空".to_string());
+    }
+
+    let start = std::time::Instant::now();
+    let req = AcemcpRequest { project_root_path: project_root_path.clone(), query: query.clone() };
+    let search_result = AcemcpTool::search_context(req).await;
+    let duration_ms = start.elapsed().as_millis() as u64;
+
+    let text = match search_result {
+        Ok(result) if !result.is_error.unwrap_or(false) => result
+            .content
+            .iter()
+            .filter_map(|item| item.as_text().map(|t| t.text.clone()))
+            .collect::<String>(),
+        Ok(result) => {
+            let message = result
+                .content
+                .iter()
+                .filter_map(|item| item.as_text().map(|t| t.text.clone()))
+                .collect::<String>();
+            return Ok(SearchExpectationResult {
+                found: false,
+                rank: None,
+                matched_path: None,
+                total_results: 0,
+                result_paths: Vec::new(),
+                duration_ms,
+                error: Some(message),
+            });
+        }
+        Err(e) => {
+            return Ok(SearchExpectationResult {
+                found: false,
+                rank: None,
+                matched_path: None,
+                total_results: 0,
+                result_paths: Vec::new(),
+                duration_ms,
+                error: Some(e.to_string()),
+            });
+        }
+    };
+
+    let blocks = split_retrieval_blocks(&text);
+    let hit = find_expected_path(&blocks, &expected_path_substring);
+    log::info!(
+        "[搜索断言] project={}, query={}, expected={}, rank={:?}",
+        project_root_path,
+        query,
+        expected_path_substring,
+        hit.as_ref().map(|(rank, _)| *rank)
+    );
+    Ok(SearchExpectationResult {
+        found: hit.is_some(),
+        rank: hit.as_ref().map(|(rank, _)| *rank),
+        matched_path: hit.map(|(_, path)| path),
+        total_results: blocks.len(),
+        result_paths: blocks.iter().filter_map(|b| block_file_path(b)).collect(),
+        duration_ms,
+        error: None,
+    })
+}
+
+/// 批量调试搜索：以有限并发执行多个查询，结果顺序与输入一致，单条结果结构与 `debug_acemcp_search` 相同
+#[tauri::command]
+pub async fn debug_acemcp_search_batch(
+    project_root_path: String,
+    queries: Vec<String>,
+    max_concurrency: Option<usize>,
+) -> Result<Vec<DebugSearchResult>, String> {
+    let queries: Vec<String> = queries
+        .into_iter()
+        .map(|q| q.trim().to_string())
+        .filter(|q| !q.is_empty())
+        .collect();
+    if queries.is_empty() {
+        return Err("查询列表不能为空".to_string());
+    }
+    if queries.len() > DEBUG_SEARCH_BATCH_MAX_QUERIES {
+        return Err(format!("单次最多 {} 个查询，当前 {} 个", DEBUG_SEARCH_BATCH_MAX_QUERIES, queries.len()));
+    }
+
+    let concurrency = max_concurrency
+        .unwrap_or(DEBUG_SEARCH_BATCH_DEFAULT_CONCURRENCY)
+        .clamp(1, DEBUG_SEARCH_BATCH_MAX_CONCURRENCY);
+    log::info!(
+        "[调试搜索] 批量执行: project={}, queries={}, concurrency={}",
+        project_root_path,
+        queries.len(),
+        concurrency
+    );
+
+    let semaphore = Arc::new(tokio::sync::Semaphore::new(concurrency));
+    let mut tasks = tokio::task::JoinSet::new();
+    for (idx, query) in queries.iter().cloned().enumerate() {
+        let semaphore = semaphore.clone();
+        let project_root_path = project_root_path.clone();
+        tasks.spawn(async move {
+            let _permit = semaphore.acquire_owned().await;
+            (idx, run_debug_search(project_root_path, query, None).await)
+        });
+    }
+
+    let mut results: Vec<Option<DebugSearchResult>> = queries.iter().map(|_| None).collect();
+    while let Some(res) = tasks.join_next().await {
+        match res {
+            Ok((idx, result)) => results[idx] = Some(result),
+            Err(e) => log::error!("[调试搜索] 批量任务异常: {}", e),
+        }
+    }
+
+    Ok(results
+        .into_iter()
+        .zip(queries)
+        .map(|(result, query)| {
+            result.unwrap_or_else(|| {
+                let now = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string();
+                DebugSearchResult {
+                    success: false,
+                    result: None,
+                    error: Some("搜索任务异常退出".to_string()),
+                    request_time: now.clone(),
+                    response_time: now,
+                    total_duration_ms: 0,
+                    result_count: None,
+                    project_path: project_root_path.clone(),
+                    query,
+                    first_byte_time: None,
+                    first_byte_ms: None,
+                    streamed: None,
+                    index_in_progress: false,
+                    cancelled: false,
+                }
+            })
+        })
+        .collect())
+}
+
+/// 执行一次调试搜索并统计耗时（单条与批量调试搜索共用）
+async fn run_debug_search(
+    project_root_path: String,
+    query: String,
+    proxy_override: Option<AcemcpProxyOverride>,
+) -> DebugSearchResult {
     use std::time::Instant;
     
     // 记录请求开始时间
@@ -446,8 +1879,10 @@ pub async fn debug_acemcp_search(
     };
     
     // 调用搜索函数（日志会通过 log crate 输出到日志文件）
+    // 索引进行中时 search_context 会按 smart_wait_range 智能等待后再检索
     log::info!("[调试搜索] 开始执行: project={}, query={}", project_root_path, query);
-    let search_result = AcemcpTool::search_context(req).await;
+    let index_in_progress = is_index_in_progress(&project_root_path);
+    let search_result = AcemcpTool::search_context_cancellable_with_proxy(req, proxy_override).await;
     
     // 记录响应接收时间
     let response_time = chrono::Utc::now();
@@ -457,7 +1892,19 @@ pub async fn debug_acemcp_search(
     log::info!("[调试搜索] 执行完成: 耗时 {}ms", total_duration_ms);
     
     match search_result {
-        Ok(result) => {
+        Ok(None) => {
+            log::info!("[调试搜索] 已取消: project={}", project_root_path);
+            cancelled_debug_search_result(
+                project_root_path,
+                query,
+                request_time_str,
+                response_time_str,
+                total_duration_ms,
+                index_in_progress,
+            )
+        }
+        Ok(Some(result)) => {
+            let is_error = result.is_error.unwrap_or(false);
             let mut result_text = String::new();
             let mut result_count: Option<usize> = None;
             
@@ -474,23 +1921,29 @@ pub async fn debug_acemcp_search(
                 }
             }
             
-            Ok(DebugSearchResult {
-                success: true,
-                result: Some(result_text),
-                error: None,
+            let (success, result, error) = classify_debug_search(is_error, result_text, index_in_progress);
+            DebugSearchResult {
+                success,
+                result,
+                error,
                 request_time: request_time_str,
                 response_time: response_time_str,
                 total_duration_ms,
                 result_count,
                 project_path: project_root_path,
                 query,
-            })
+                first_byte_time: None,
+                first_byte_ms: None,
+                streamed: None,
+                index_in_progress,
+                cancelled: false,
+            }
         }
         Err(e) => {
             let error_msg = format!("执行失败: {}", e);
             log::error!("[调试搜索] 错误: {}", error_msg);
             
-            Ok(DebugSearchResult {
+            DebugSearchResult {
                 success: false,
                 result: None,
                 error: Some(error_msg),
@@ -500,53 +1953,332 @@ pub async fn debug_acemcp_search(
                 result_count: None,
                 project_path: project_root_path,
                 query,
+                first_byte_time: None,
+                first_byte_ms: None,
+                streamed: None,
+                index_in_progress,
+                cancelled: false,
+            }
+        }
+    }
+}
+
+/// 流式调试搜索：增量结果通过 `acemcp_search_chunk` 事件推送，返回值为完整结果及耗时统计
+/// response_time 记录末字节到达时间，另外附带首字节时间，便于区分“服务端处理慢”与“传输慢”
+#[tauri::command]
+pub async fn debug_acemcp_search_streaming(
+    project_root_path: String,
+    query: String,
+    app: AppHandle,
+) -> Result<DebugSearchResult, String> {
+    use std::time::Instant;
+
+    let request_time = chrono::Utc::now();
+    let request_time_str = request_time.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string();
+    let start_instant = Instant::now();
+
+    let req = AcemcpRequest {
+        project_root_path: project_root_path.clone(),
+        query: query.clone(),
+    };
+
+    log::info!("[调试流式搜索] 开始执行: project={}, query={}", project_root_path, query);
+    let index_in_progress = is_index_in_progress(&project_root_path);
+
+    let mut seq = 0usize;
+    let emit_app = app.clone();
+    let chunk_project = project_root_path.clone();
+    let chunk_query = query.clone();
+    let search_result = AcemcpTool::search_context_streaming(req, |text| {
+        let chunk = SearchStreamChunk {
+            project_path: chunk_project.clone(),
+            query: chunk_query.clone(),
+            seq,
+            text,
+            done: false,
+        };
+        seq += 1;
+        let _ = emit_app.emit("acemcp_search_chunk", &chunk);
+    }).await;
+
+    let total_duration_ms = start_instant.elapsed().as_millis() as u64;
+    log::info!("[调试流式搜索] 执行完成: 耗时 {}ms", total_duration_ms);
+
+    // 结束标记，前端据此停止拼接
+    let _ = app.emit("acemcp_search_chunk", &SearchStreamChunk {
+        project_path: project_root_path.clone(),
+        query: query.clone(),
+        seq,
+        text: String::new(),
+        done: true,
+    });
+
+    let offset_time = |ms: Option<u64>| {
+        ms.map(|ms| {
+            (request_time + chrono::Duration::milliseconds(ms as i64))
+                .format("%Y-%m-%dT%H:%M:%S%.3fZ")
+                .to_string()
+        })
+    };
+
+    match search_result {
+        Ok(None) => {
+            log::info!("[调试流式搜索] 已取消: project={}", project_root_path);
+            Ok(cancelled_debug_search_result(
+                project_root_path,
+                query,
+                request_time_str,
+                chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string(),
+                total_duration_ms,
+                index_in_progress,
+            ))
+        }
+        Ok(Some((result, stats))) => {
+            let is_error = result.is_error.unwrap_or(false);
+            let mut result_text = String::new();
+            for item in &result.content {
+                if let Some(text) = item.as_text() {
+                    result_text.push_str(&text.text);
+                }
+            }
+
+            // 末字节时间作为响应时间；未收到任何数据时退化为当前时间
+            let response_time_str = offset_time(stats.last_byte_ms).unwrap_or_else(|| {
+                chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string()
+            });
+
+            let (success, result_text, error) = classify_debug_search(is_error, result_text, index_in_progress);
+            Ok(DebugSearchResult {
+                success,
+                result: result_text,
+                error,
+                request_time: request_time_str,
+                response_time: response_time_str,
+                total_duration_ms,
+                result_count: Some(result.content.len()),
+                project_path: project_root_path,
+                query,
+                first_byte_time: offset_time(stats.first_byte_ms),
+                first_byte_ms: stats.first_byte_ms,
+                streamed: Some(stats.streamed),
+                index_in_progress,
+                cancelled: false,
+            })
+        }
+        Err(e) => {
+            let error_msg = format!("执行失败: {}", e);
+            log::error!("[调试流式搜索] 错误: {}", error_msg);
+
+            Ok(DebugSearchResult {
+                success: false,
+                result: None,
+                error: Some(error_msg),
+                request_time: request_time_str,
+                response_time: chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string(),
+                total_duration_ms,
+                result_count: None,
+                project_path: project_root_path,
+                query,
+                first_byte_time: None,
+                first_byte_ms: None,
+                streamed: None,
+                index_in_progress,
+                cancelled: false,
             })
         }
     }
 }
 
 
-/// 执行acemcp工具
+/// 执行acemcp工具（按注册表分发，见 `dispatch` 模块）
+/// 始终返回 Ok，成功/失败统一为 `{status, result?, error?, error_code?}` 结构
 #[tauri::command]
 pub async fn execute_acemcp_tool(
     tool_name: String,
     arguments: serde_json::Value,
-) -> Result<serde_json::Value, String> {
-    match tool_name.as_str() {
-        "search_context" => {
-            // 解析参数
-            let project_root_path = arguments.get("project_root_path")
-                .and_then(|v| v.as_str())
-                .ok_or_else(|| "缺少project_root_path参数".to_string())?
-                .to_string();
-            
-            let query = arguments.get("query")
-                .and_then(|v| v.as_str())
-                .ok_or_else(|| "缺少query参数".to_string())?
-                .to_string();
-            
-            // 执行搜索
-            let req = AcemcpRequest { project_root_path, query };
-            match AcemcpTool::search_context(req).await {
-                Ok(result) => {
-                    // 转换结果为JSON
-                    if let Ok(val) = serde_json::to_value(&result) {
-                        Ok(serde_json::json!({
-                            "status": "success",
-                            "result": val
-                        }))
-                    } else {
-                        Err("结果序列化失败".to_string())
-                    }
-                }
-                Err(e) => Ok(serde_json::json!({
-                    "status": "error",
-                    "error": e.to_string()
-                })),
+) -> Result<AcemcpToolResponse, String> {
+    let response = super::dispatch::execute_tool(&tool_name, arguments).await;
+    if let Some(code) = &response.error_code {
+        log::warn!("⚠️ 工具调用失败: tool={}, code={}, error={:?}", tool_name, code, response.error);
+    }
+    Ok(response)
+}
+
+/// 列出 `execute_acemcp_tool` 可调用的工具及其参数声明
+#[tauri::command]
+pub fn list_acemcp_tools() -> Result<Vec<AcemcpToolInfo>, String> {
+    Ok(super::dispatch::registered_tools()
+        .iter()
+        .map(|spec| AcemcpToolInfo {
+            name: spec.name.to_string(),
+            description: spec.description.to_string(),
+            args: spec
+                .args
+                .iter()
+                .map(|arg| AcemcpToolArgInfo {
+                    name: arg.name.to_string(),
+                    description: arg.description.to_string(),
+                    required: arg.required,
+                })
+                .collect(),
+        })
+        .collect())
+}
+
+/// 项目级租户覆盖（列表展示用，令牌已脱敏）
+#[derive(Debug, serde::Serialize)]
+pub struct ProjectAcemcpOverrideInfo {
+    pub project_root: String,
+    pub base_url: Option<String>,
+    pub token: Option<String>, // 已脱敏，仅表示是否设置
+}
+
+/// 设置项目级租户覆盖（base_url/token 均为空时等同于清除）
+#[tauri::command]
+pub async fn set_project_acemcp_override(
+    project_root_path: String,
+    base_url: Option<String>,
+    token: Option<String>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    let normalized_root = super::mcp::normalize_project_path(&project_root_path);
+    let base_url = base_url
+        .map(|u| u.trim().trim_end_matches('/').to_string())
+        .filter(|u| !u.is_empty());
+    if let Some(url) = &base_url {
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            return Err("无效的API端点URL格式，必须以 http:// 或 https:// 开头".to_string());
+        }
+    }
+    let token = token.map(|t| t.trim().to_string()).filter(|t| !t.is_empty());
+
+    {
+        let mut config = state
+            .config
+            .lock()
+            .map_err(|e| format!("获取配置失败: {}", e))?;
+        let overrides = config.mcp_config.acemcp_project_overrides.get_or_insert_with(Default::default);
+        if base_url.is_none() && token.is_none() {
+            overrides.remove(&normalized_root);
+        } else {
+            overrides.insert(
+                normalized_root.clone(),
+                crate::config::AcemcpProjectOverride { base_url, token },
+            );
+        }
+    }
+
+    save_config(&state, &app)
+        .await
+        .map_err(|e| format!("保存配置失败: {}", e))?;
+    log::info!("已更新项目级租户覆盖: {}", normalized_root);
+    Ok(())
+}
+
+/// 清除项目级租户覆盖，返回是否存在过覆盖
+#[tauri::command]
+pub async fn clear_project_acemcp_override(
+    project_root_path: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<bool, String> {
+    let normalized_root = super::mcp::normalize_project_path(&project_root_path);
+    let removed = {
+        let mut config = state
+            .config
+            .lock()
+            .map_err(|e| format!("获取配置失败: {}", e))?;
+        config
+            .mcp_config
+            .acemcp_project_overrides
+            .as_mut()
+            .map(|overrides| overrides.remove(&normalized_root).is_some())
+            .unwrap_or(false)
+    };
+
+    if removed {
+        save_config(&state, &app)
+            .await
+            .map_err(|e| format!("保存配置失败: {}", e))?;
+        log::info!("已清除项目级租户覆盖: {}", normalized_root);
+    }
+    Ok(removed)
+}
+
+/// 列出所有项目级租户覆盖（令牌脱敏）
+#[tauri::command]
+pub async fn list_project_acemcp_overrides(
+    state: State<'_, AppState>,
+) -> Result<Vec<ProjectAcemcpOverrideInfo>, String> {
+    let config = state
+        .config
+        .lock()
+        .map_err(|e| format!("获取配置失败: {}", e))?;
+    let mut list: Vec<ProjectAcemcpOverrideInfo> = config
+        .mcp_config
+        .acemcp_project_overrides
+        .iter()
+        .flatten()
+        .map(|(project_root, o)| ProjectAcemcpOverrideInfo {
+            project_root: project_root.clone(),
+            base_url: o.base_url.clone(),
+            token: o.token.as_ref().map(|_| crate::utils::logger::REDACTED.to_string()),
+        })
+        .collect();
+    list.sort_by(|a, b| a.project_root.cmp(&b.project_root));
+    Ok(list)
+}
+
+/// 校验项目索引一致性（本地记录 / 当前文件 / 服务端），供前端提示“修复索引”
+#[tauri::command]
+pub async fn verify_acemcp_index(project_root_path: String) -> Result<IndexVerification, String> {
+    let config = AcemcpTool::get_project_acemcp_config(&project_root_path)
+        .await
+        .map_err(|e| format!("获取配置失败: {}", e))?;
+
+    let result = super::mcp::verify_index(&config, &project_root_path)
+        .await
+        .map_err(|e| e.to_string())?;
+    log::info!("🩺 [verify_acemcp_index] {}: {}", result.project_root, result.summary);
+    Ok(result)
+}
+
+/// 获取所有项目索引状态的汇总（状态计数、文件总数、最近一次失败）
+/// 详细的项目状态表仍由 `get_all_acemcp_index_status` 提供
+#[tauri::command]
+pub fn get_acemcp_index_summary() -> Result<IndexSummary, String> {
+    Ok(summarize_index_status(&AcemcpTool::get_all_index_status()))
+}
+
+fn summarize_index_status(all_status: &ProjectsIndexStatus) -> IndexSummary {
+    let mut summary = IndexSummary {
+        project_count: all_status.projects.len(),
+        ..Default::default()
+    };
+
+    for (project, status) in &all_status.projects {
+        summary.counts.record(&status.status);
+        summary.total_files += status.total_files;
+        summary.indexed_files += status.indexed_files;
+
+        if let Some(failed_at) = status.last_failure_time {
+            if summary.last_failure_time.map_or(true, |latest| failed_at > latest) {
+                summary.last_failure_time = Some(failed_at);
+                summary.last_failure_error = status.last_error.clone();
+                summary.last_failure_project = Some(project.clone());
             }
         }
-        _ => Err(format!("未知的工具: {}", tool_name)),
     }
+
+    summary
+}
+
+/// 将项目索引状态重置为 Idle（不删除索引数据），返回重置前的状态
+#[tauri::command]
+pub fn reset_acemcp_project_status(project_root_path: String) -> Result<IndexStatus, String> {
+    AcemcpTool::reset_project_status(&project_root_path)
+        .map_err(|e| format!("重置项目状态失败: {}", e))
 }
 
 /// 获取指定项目的索引状态
@@ -579,14 +2311,238 @@ pub fn get_all_acemcp_index_status() -> Result<ProjectsIndexStatus, String> {
     Ok(status)
 }
 
-/// 获取指定项目内所有可索引文件的索引状态，用于前端构建文件树
+/// 获取指定项目内所有可索引文件的索引状态，用于前端构建文件树
+#[tauri::command]
+pub async fn get_acemcp_project_files_status(
+    project_root_path: String,
+) -> Result<ProjectFilesStatus, String> {
+    AcemcpTool::get_project_files_status(project_root_path)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 获取项目的实时索引完成度（重新扫描文件状态，避免使用可能过期的项目状态）
+#[tauri::command]
+pub async fn get_acemcp_index_coverage(
+    project_root_path: String,
+) -> Result<IndexCoverage, String> {
+    let files_status = AcemcpTool::get_project_files_status(project_root_path)
+        .await
+        .map_err(|e| e.to_string())?;
+    let indexed_paths = super::mcp::load_project_indexed_files(&files_status.project_root);
+    Ok(compute_index_coverage(&files_status, &indexed_paths))
+}
+
+/// 根据文件状态扫描结果计算索引完成度
+/// indexed_paths 为 projects.json 中已有 blob 的文件，待索引且不在其中的文件计为新增
+fn compute_index_coverage(
+    files_status: &ProjectFilesStatus,
+    indexed_paths: &std::collections::HashSet<String>,
+) -> IndexCoverage {
+    let total_files = files_status.files.len();
+    let indexed_files = files_status
+        .files
+        .iter()
+        .filter(|f| f.status == FileIndexStatusKind::Indexed)
+        .count();
+    let new_files = files_status
+        .files
+        .iter()
+        .filter(|f| f.status == FileIndexStatusKind::Pending && !indexed_paths.contains(&f.path))
+        .count();
+    let coverage_percent = if total_files == 0 {
+        0.0
+    } else {
+        (indexed_files as f64 * 1000.0 / total_files as f64).round() / 10.0
+    };
+
+    IndexCoverage {
+        project_root: files_status.project_root.clone(),
+        coverage_percent,
+        total_files,
+        indexed_files,
+        pending_files: total_files - indexed_files,
+        new_files,
+    }
+}
+
+/// 获取按目录汇总的索引状态树（用于展示"哪些目录索引滞后"）
+/// 基于与 get_project_files_status 相同的磁盘扫描；失败数来自最近一次索引记录的文件错误明细
+#[tauri::command]
+pub async fn get_acemcp_directory_stats(
+    project_root_path: String,
+) -> Result<DirectoryIndexStats, String> {
+    let failed_paths: std::collections::HashSet<String> = AcemcpTool::get_index_status(project_root_path.clone())
+        .file_errors
+        .items
+        .into_iter()
+        .map(|e| e.path.replace('\\', "/"))
+        .collect();
+    let files_status = AcemcpTool::get_project_files_status(project_root_path)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(build_directory_stats(&files_status, &failed_paths))
+}
+
+/// 目录树构建过程中的中间节点
+#[derive(Default)]
+struct DirectoryStatsNode {
+    indexed: usize,
+    pending: usize,
+    failed: usize,
+    children: std::collections::BTreeMap<String, DirectoryStatsNode>,
+}
+
+impl DirectoryStatsNode {
+    fn add(&mut self, status: &FileIndexStatusKind, failed: bool) {
+        if *status == FileIndexStatusKind::Indexed {
+            self.indexed += 1;
+        } else if failed {
+            self.failed += 1;
+        } else {
+            self.pending += 1;
+        }
+    }
+
+    fn into_stats(self, name: String, path: String) -> DirectoryIndexStats {
+        let children = self
+            .children
+            .into_iter()
+            .map(|(child_name, node)| {
+                let child_path = if path.is_empty() {
+                    child_name.clone()
+                } else {
+                    format!("{}/{}", path, child_name)
+                };
+                node.into_stats(child_name, child_path)
+            })
+            .collect();
+        DirectoryIndexStats {
+            name,
+            path,
+            indexed: self.indexed,
+            pending: self.pending,
+            failed: self.failed,
+            children,
+        }
+    }
+}
+
+/// 将文件状态列表汇总为目录树，每个文件计入其所有上级目录（含根节点）
+fn build_directory_stats(files_status: &ProjectFilesStatus, failed_paths: &std::collections::HashSet<String>) -> DirectoryIndexStats {
+    let mut root = DirectoryStatsNode::default();
+    for file in &files_status.files {
+        let path = file.path.replace('\\', "/");
+        let failed = failed_paths.contains(&path);
+        root.add(&file.status, failed);
+
+        let mut dirs: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        dirs.pop(); // 最后一段为文件名
+        let mut node = &mut root;
+        for dir in dirs {
+            node = node.children.entry(dir.to_string()).or_default();
+            node.add(&file.status, failed);
+        }
+    }
+    root.into_stats(String::new(), String::new())
+}
+
+/// 分块流式获取项目文件索引状态（大型仓库使用）
+/// 每块通过 `acemcp_project_files_chunk` 事件推送，最后发送 done=true 的结束块；返回推送的文件总数
+#[tauri::command]
+pub async fn stream_acemcp_project_files_status(
+    project_root_path: String,
+    chunk_size: Option<usize>,
+    app: AppHandle,
+) -> Result<usize, String> {
+    use std::sync::atomic::AtomicUsize;
+
+    let project_root = super::mcp::normalize_project_path(&project_root_path);
+    let seq = Arc::new(AtomicUsize::new(0));
+    let chunk_seq = seq.clone();
+    let emit_app = app.clone();
+    let chunk_root = project_root.clone();
+
+    let result = AcemcpTool::stream_project_files_status(
+        project_root_path,
+        chunk_size.unwrap_or(500),
+        move |files| {
+            let chunk = ProjectFilesStatusChunk {
+                project_root: chunk_root.clone(),
+                seq: chunk_seq.fetch_add(1, Ordering::SeqCst),
+                files,
+                done: false,
+                cancelled: false,
+                error: None,
+            };
+            let _ = emit_app.emit("acemcp_project_files_chunk", &chunk);
+        },
+    )
+    .await;
+
+    let (cancelled, error) = match &result {
+        Ok(_) => (false, None),
+        Err(e) if e.to_string() == super::mcp::FILES_STATUS_CANCELLED => (true, None),
+        Err(e) => (false, Some(e.to_string())),
+    };
+    // 结束块不携带文件，seq 紧随最后一个数据块
+    let _ = app.emit("acemcp_project_files_chunk", &ProjectFilesStatusChunk {
+        project_root,
+        seq: seq.load(Ordering::SeqCst),
+        files: Vec::new(),
+        done: true,
+        cancelled,
+        error,
+    });
+
+    result.map_err(|e| e.to_string())
+}
+
+/// 取消指定项目正在进行的文件状态扫描（一次性与流式命令均适用）
 #[tauri::command]
-pub async fn get_acemcp_project_files_status(
+pub fn cancel_project_files_status(project_root_path: String) -> Result<bool, String> {
+    Ok(super::mcp::cancel_files_status(&project_root_path))
+}
+
+/// 预览排除模式的实际效果：返回每个模式排除的文件数及示例路径
+/// 与索引阶段使用同一套匹配逻辑，便于设置界面展示“node_modules 排除了 N 个文件”
+#[tauri::command]
+pub async fn preview_acemcp_exclusions(
     project_root_path: String,
-) -> Result<ProjectFilesStatus, String> {
-    AcemcpTool::get_project_files_status(project_root_path)
+) -> Result<ExclusionPreview, String> {
+    let acemcp_config = AcemcpTool::get_acemcp_config()
         .await
-        .map_err(|e| e.to_string())
+        .map_err(|e| format!("获取配置失败: {}", e))?;
+    let exclude_patterns = acemcp_config.exclude_patterns.unwrap_or_default();
+    let follow_symlinks = acemcp_config.follow_symlinks.unwrap_or(false);
+
+    // 目录遍历为阻塞操作，放到阻塞线程池执行
+    tokio::task::spawn_blocking(move || {
+        super::mcp::preview_exclusions(&project_root_path, &exclude_patterns, follow_symlinks)
+    })
+    .await
+    .map_err(|e| format!("排除规则预览任务异常: {}", e))?
+    .map_err(|e| e.to_string())
+}
+
+/// 预览单个文件的分块边界（起止行、字节数、分块路径），不上传
+/// 便于理解搜索结果中的 `path#chunk2of5` 并调整 max_lines_per_blob
+#[tauri::command]
+pub async fn preview_acemcp_file_chunks(
+    project_root_path: String,
+    rel_path: String,
+) -> Result<FileChunkPreview, String> {
+    let acemcp_config = AcemcpTool::get_project_acemcp_config(&project_root_path)
+        .await
+        .map_err(|e| format!("获取配置失败: {}", e))?;
+    let max_lines = super::mcp::MaxLinesPolicy::from_config(&acemcp_config);
+
+    tokio::task::spawn_blocking(move || {
+        super::mcp::preview_file_chunks(&project_root_path, &rel_path, &max_lines)
+    })
+    .await
+    .map_err(|e| format!("分块预览任务异常: {}", e))?
+    .map_err(|e| e.to_string())
 }
 
 /// 手动触发索引更新
@@ -597,6 +2553,144 @@ pub async fn trigger_acemcp_index_update(project_root_path: String) -> Result<St
         .map_err(|e| e.to_string())
 }
 
+/// 从上次中断处继续索引，返回从检查点恢复的 blob 数
+#[tauri::command]
+pub async fn resume_acemcp_index(project_root_path: String) -> Result<IndexResumeResult, String> {
+    AcemcpTool::resume_index(project_root_path)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 重建所有已知项目的索引（正在监听的项目 + projects.json 中记录的项目）
+/// 修改扩展名/排除规则后使用；遵循自动索引的并发上传上限，
+/// force=true 时在取得上传许可后全量重新上传，成功后才替换项目已记录的 blob 列表
+#[tauri::command]
+pub async fn reindex_all_projects(
+    force: bool,
+    app: AppHandle,
+) -> Result<std::collections::HashMap<String, ProjectReindexResult>, String> {
+    use std::collections::{BTreeSet, HashMap};
+
+    let acemcp_config = AcemcpTool::get_acemcp_config()
+        .await
+        .map_err(|e| format!("获取配置失败: {}", e))?;
+
+    let watcher_manager = super::watcher::get_watcher_manager();
+    let projects: BTreeSet<String> = watcher_manager
+        .get_watching_projects()
+        .into_iter()
+        .chain(super::mcp::load_projects_file().0.into_keys())
+        .map(|p| super::mcp::normalize_project_path(&p))
+        .collect();
+
+    let total = projects.len();
+    log::info!("[reindex_all_projects] 开始重建索引: 项目数={}, force={}", total, force);
+
+    // 配置了每日预算时，统计全部项目需要上传的新 blob 数（强制重建时为全部 blob），超出预算仅提醒
+    if acemcp_config.daily_blob_budget.is_some() {
+        let mut planned = 0u64;
+        for project_root in &projects {
+            let config = AcemcpTool::get_project_acemcp_config(project_root)
+                .await
+                .unwrap_or_else(|_| acemcp_config.clone());
+            if let Ok(new_blobs) = super::mcp::count_new_blobs(&config, project_root, force).await {
+                planned += new_blobs;
+            }
+        }
+        if let Some(warning) = super::mcp::daily_budget_warning(
+            acemcp_config.daily_blob_budget,
+            super::mcp::load_usage_today().blobs_uploaded,
+            planned,
+        ) {
+            log::warn!("[reindex_all_projects] {}", warning);
+            let _ = app.emit("acemcp_usage_warning", &warning);
+        }
+    }
+
+    let mut results: HashMap<String, ProjectReindexResult> = HashMap::new();
+    let mut join_set = tokio::task::JoinSet::new();
+    let mut completed = 0usize;
+
+    for project_root in projects {
+        if !std::path::Path::new(&project_root).is_dir() {
+            completed += 1;
+            let _ = app.emit("acemcp_reindex_progress", &ReindexProgress {
+                total,
+                completed,
+                project_root: project_root.clone(),
+                success: false,
+            });
+            results.insert(project_root, ProjectReindexResult {
+                success: false,
+                message: "项目目录不存在，已跳过".to_string(),
+            });
+            continue;
+        }
+
+        let fallback_config = acemcp_config.clone();
+        join_set.spawn(async move {
+            // 按项目应用租户覆盖，读取失败时沿用全局配置
+            let config = AcemcpTool::get_project_acemcp_config(&project_root)
+                .await
+                .unwrap_or(fallback_config);
+            // 强制重建在上传许可内进行，上传成功后才替换原有 blob 记录
+            let watcher_manager = super::watcher::get_watcher_manager();
+            let result = if force {
+                watcher_manager.force_reindex_with_limit(&config, &project_root).await
+            } else {
+                watcher_manager.index_with_limit(&config, &project_root).await
+            };
+            (project_root, result)
+        });
+    }
+
+    while let Some(joined) = join_set.join_next().await {
+        let (project_root, result) = match joined {
+            Ok(v) => v,
+            Err(e) => {
+                log::error!("[reindex_all_projects] 重建任务异常: {}", e);
+                continue;
+            }
+        };
+
+        let entry = match result {
+            Ok(blob_names) => ProjectReindexResult {
+                success: true,
+                message: format!("索引更新成功，共 {} 个 blobs", blob_names.len()),
+            },
+            Err(e) => ProjectReindexResult {
+                success: false,
+                message: format!("索引更新失败: {}", e),
+            },
+        };
+
+        completed += 1;
+        log::info!("[reindex_all_projects] {}/{} {}: {}", completed, total, project_root, entry.message);
+        let _ = app.emit("acemcp_reindex_progress", &ReindexProgress {
+            total,
+            completed,
+            project_root: project_root.clone(),
+            success: entry.success,
+        });
+        results.insert(project_root, entry);
+    }
+
+    Ok(results)
+}
+
+/// 获取当日上传用量（本地统计）及剩余的每日 blob 预算
+#[tauri::command]
+pub fn get_acemcp_usage_today(state: State<'_, AppState>) -> Result<AcemcpUsage, String> {
+    let budget = {
+        let config = state.config.lock().map_err(|e| format!("获取配置失败: {}", e))?;
+        config.mcp_config.acemcp_daily_blob_budget
+    };
+    let mut usage = super::mcp::load_usage_today();
+    usage.daily_blob_budget = budget;
+    usage.remaining_blobs = budget.map(|b| (b as u64).saturating_sub(usage.blobs_uploaded));
+    Ok(usage)
+}
+
 /// 获取全局自动索引开关状态
 #[tauri::command]
 pub fn get_auto_index_enabled() -> Result<bool, String> {
@@ -629,6 +2723,64 @@ pub async fn set_auto_index_enabled(
     Ok(())
 }
 
+/// 计算项目自动索引的实际生效状态（全局开关且项目开关）
+fn project_auto_index_state(project_root_path: &str) -> ProjectAutoIndexState {
+    let watcher_manager = super::watcher::get_watcher_manager();
+    let global_enabled = watcher_manager.is_auto_index_enabled();
+    let project_enabled = watcher_manager.is_project_auto_index_enabled(project_root_path);
+    ProjectAutoIndexState {
+        project_root: super::mcp::normalize_project_path(project_root_path),
+        global_enabled,
+        project_enabled,
+        effective: global_enabled && project_enabled,
+    }
+}
+
+/// 获取项目的自动索引开关状态
+#[tauri::command]
+pub fn get_project_auto_index(project_root_path: String) -> Result<ProjectAutoIndexState, String> {
+    Ok(project_auto_index_state(&project_root_path))
+}
+
+/// 设置项目级自动索引开关：关闭后仍监听文件变更（用于状态统计），但不自动上传
+/// 返回实际生效状态（全局开关且项目开关）
+#[tauri::command]
+pub async fn set_project_auto_index(
+    project_root_path: String,
+    enabled: bool,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<ProjectAutoIndexState, String> {
+    let normalized_root = super::mcp::normalize_project_path(&project_root_path);
+
+    // 持久化到配置（仅记录关闭的项目），确保跨重启生效
+    {
+        let mut config = state
+            .config
+            .lock()
+            .map_err(|e| format!("获取配置失败: {}", e))?;
+        let all = config
+            .mcp_config
+            .acemcp_project_auto_index
+            .get_or_insert_with(Default::default);
+        if enabled {
+            all.remove(&normalized_root);
+        } else {
+            all.insert(normalized_root.clone(), false);
+        }
+        if all.is_empty() {
+            config.mcp_config.acemcp_project_auto_index = None;
+        }
+    }
+
+    save_config(&state, &app)
+        .await
+        .map_err(|e| format!("保存配置失败: {}", e))?;
+
+    super::watcher::get_watcher_manager().set_project_auto_index(&normalized_root, enabled);
+    Ok(project_auto_index_state(&normalized_root))
+}
+
 /// 获取当前正在监听的项目列表
 #[tauri::command]
 pub fn get_watching_projects() -> Result<Vec<String>, String> {
@@ -636,6 +2788,13 @@ pub fn get_watching_projects() -> Result<Vec<String>, String> {
     Ok(watcher_manager.get_watching_projects())
 }
 
+/// 获取文件监听统计信息（含上传并发上限与排队深度）
+#[tauri::command]
+pub fn get_watcher_stats() -> Result<WatcherStats, String> {
+    let watcher_manager = super::watcher::get_watcher_manager();
+    Ok(watcher_manager.get_stats())
+}
+
 /// 检查指定项目是否正在监听
 #[tauri::command]
 pub fn is_project_watching(project_root_path: String) -> Result<bool, String> {
@@ -650,14 +2809,19 @@ pub async fn start_project_watching(
     project_root_path: String,
     state: State<'_, AppState>,
 ) -> Result<(), String> {
+    if crate::app::safe_mode::is_safe_mode() {
+        log::warn!("🛟 安全模式下不启动文件监听: path={}", project_root_path);
+        return Err("安全模式下已禁用文件监听".to_string());
+    }
+
     // 从配置中读取防抖延迟
     let debounce_ms = {
         let config = state.config.lock().map_err(|e| format!("获取配置失败: {}", e))?;
         config.mcp_config.acemcp_watch_debounce_ms
     };
     
-    // 获取 acemcp 配置
-    let acemcp_config = super::AcemcpTool::get_acemcp_config()
+    // 获取 acemcp 配置（含项目级租户覆盖）
+    let acemcp_config = super::AcemcpTool::get_project_acemcp_config(&project_root_path)
         .await
         .map_err(|e| format!("获取 acemcp 配置失败: {}", e))?;
     
@@ -686,29 +2850,110 @@ pub fn stop_all_watching() -> Result<(), String> {
     Ok(())
 }
 
+/// 立即同步项目积压的文件变更（跳过剩余防抖时间），返回结果说明
+#[tauri::command]
+pub async fn flush_project_watch(project_root_path: String) -> Result<String, String> {
+    let watcher_manager = super::watcher::get_watcher_manager();
+    let pending = watcher_manager
+        .flush_pending(&project_root_path)
+        .await
+        .map_err(|e| format!("立即同步失败: {}", e))?;
+    if pending {
+        log::info!("⚡ 已触发立即同步: {}", project_root_path);
+        Ok("已触发索引更新，积压的文件变更将立即上传".to_string())
+    } else {
+        Ok("没有待同步的文件变更".to_string())
+    }
+}
+
+/// 修改项目运行时排除的子目录：持久化到配置并同步到正在运行的监听器，返回当前排除列表
+async fn update_project_watch_exclusions(
+    project_root_path: &str,
+    subpath: &str,
+    add: bool,
+    state: &State<'_, AppState>,
+    app: &AppHandle,
+) -> Result<Vec<String>, String> {
+    let normalized_root = super::mcp::normalize_project_path(project_root_path);
+    let subpath = super::mcp::normalize_exclusion_subpath(subpath)
+        .ok_or_else(|| "无效的子目录路径，需为项目内的相对路径".to_string())?;
+
+    let (exclusions, changed) = {
+        let mut config = state
+            .config
+            .lock()
+            .map_err(|e| format!("获取配置失败: {}", e))?;
+        let all = config
+            .mcp_config
+            .acemcp_project_watch_exclusions
+            .get_or_insert_with(Default::default);
+        let list = all.entry(normalized_root.clone()).or_default();
+        let changed = if add {
+            let missing = !list.contains(&subpath);
+            if missing {
+                list.push(subpath.clone());
+                list.sort();
+            }
+            missing
+        } else {
+            let before = list.len();
+            list.retain(|s| s != &subpath);
+            list.len() != before
+        };
+        let exclusions = list.clone();
+        if exclusions.is_empty() {
+            all.remove(&normalized_root);
+        }
+        (exclusions, changed)
+    };
+
+    if changed {
+        save_config(state, app)
+            .await
+            .map_err(|e| format!("保存配置失败: {}", e))?;
+        super::watcher::get_watcher_manager().set_project_exclusions(&normalized_root, exclusions.clone());
+        log::info!(
+            "🚫 已{}监听排除子目录: {} -> {}",
+            if add { "添加" } else { "移除" },
+            normalized_root,
+            subpath
+        );
+    }
+    Ok(exclusions)
+}
+
+/// 为项目添加运行时排除的子目录（监听、文件状态与索引均忽略该目录），返回当前排除列表
+#[tauri::command]
+pub async fn add_project_watch_exclusion(
+    project_root_path: String,
+    subpath: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Vec<String>, String> {
+    update_project_watch_exclusions(&project_root_path, &subpath, true, &state, &app).await
+}
+
+/// 移除项目运行时排除的子目录，返回当前排除列表
+#[tauri::command]
+pub async fn remove_project_watch_exclusion(
+    project_root_path: String,
+    subpath: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Vec<String>, String> {
+    update_project_watch_exclusions(&project_root_path, &subpath, false, &state, &app).await
+}
+
 /// 删除指定项目的索引记录
-/// 同时清理 projects.json 和 projects_status.json 中的数据
+/// 同时清理 projects.json、projects_status.json、blob_times.json、indexed_files.json 与检查点中的数据
 #[tauri::command]
 pub async fn remove_acemcp_project_index(project_root_path: String) -> Result<String, String> {
     use std::path::PathBuf;
     use std::fs;
     use std::collections::HashMap;
 
-    // 辅助函数：规范化路径 key（去除扩展路径前缀，统一使用正斜杠）
-    fn normalize_path_key(path: &str) -> String {
-        let mut normalized = path.to_string();
-        // 去除 Windows 扩展长度路径前缀
-        if normalized.starts_with("\\\\?\\") {
-            normalized = normalized[4..].to_string();
-        } else if normalized.starts_with("//?/") {
-            normalized = normalized[4..].to_string();
-        }
-        // 统一使用正斜杠
-        normalized.replace('\\', "/")
-    }
-
-    // 规范化传入的路径
-    let normalized_root = normalize_path_key(&project_root_path);
+    // 规范化传入的路径（与索引/监听使用同一套 key 规则）
+    let normalized_root = super::mcp::normalize_project_path(&project_root_path);
 
     log::info!("[remove_acemcp_project_index] 开始删除项目索引记录");
     log::info!("[remove_acemcp_project_index] 原始路径: {}", project_root_path);
@@ -731,7 +2976,7 @@ pub async fn remove_acemcp_project_index(project_root_path: String) -> Result<St
                 
                 // 遍历查找匹配的 key（对每个 key 也进行规范化后比较）
                 let key_to_remove: Option<String> = projects.keys()
-                    .find(|k| normalize_path_key(k) == normalized_root)
+                    .find(|k| super::mcp::normalize_path_key(k) == normalized_root)
                     .cloned();
                 
                 if let Some(key) = key_to_remove {
@@ -764,7 +3009,7 @@ pub async fn remove_acemcp_project_index(project_root_path: String) -> Result<St
                         
                         // 遍历查找匹配的 key（对每个 key 也进行规范化后比较）
                         let key_to_remove: Option<String> = map.keys()
-                            .find(|k| normalize_path_key(k) == normalized_root)
+                            .find(|k| super::mcp::normalize_path_key(k) == normalized_root)
                             .cloned();
                         
                         if let Some(key) = key_to_remove {
@@ -786,7 +3031,13 @@ pub async fn remove_acemcp_project_index(project_root_path: String) -> Result<St
         log::warn!("[remove_acemcp_project_index] projects_status.json 文件不存在: {:?}", status_path);
     }
 
-    // 3. 停止该项目的文件监听（如果有）
+    // 3. 同步清理 blob_times.json、indexed_files.json 与上传检查点，避免与 projects.json 不一致
+    let keys: std::collections::HashSet<String> = [normalized_root.clone()].into_iter().collect();
+    if let Err(e) = super::mcp::remove_project_side_records(&keys) {
+        log::warn!("[remove_acemcp_project_index] 清理附属索引记录失败: {}", e);
+    }
+
+    // 4. 停止该项目的文件监听（如果有）
     let watcher_manager = super::watcher::get_watcher_manager();
     let _ = watcher_manager.stop_watching(&normalized_root);
 
@@ -814,13 +3065,239 @@ pub fn check_directory_exists(directory_path: String) -> Result<bool, String> {
     Ok(normalized.exists() && normalized.is_dir())
 }
 
+/// 列出目录已不存在（被删除或移动）的已记录项目
+#[tauri::command]
+pub fn list_orphaned_acemcp_projects() -> Result<Vec<String>, String> {
+    let orphaned: Vec<String> = AcemcpTool::list_known_projects()
+        .into_iter()
+        .filter(|root| !check_directory_exists(root.clone()).unwrap_or(true))
+        .collect();
+    log::debug!("🧹 [list_orphaned_acemcp_projects] 孤立项目数: {}", orphaned.len());
+    Ok(orphaned)
+}
+
+/// 清理目录已不存在的项目：从 projects.json 与 projects_status.json 中移除，返回被清理的项目路径
+#[tauri::command]
+pub fn prune_orphaned_acemcp_projects() -> Result<Vec<String>, String> {
+    let orphaned = list_orphaned_acemcp_projects()?;
+    if orphaned.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let pruned = AcemcpTool::remove_projects(&orphaned)
+        .map_err(|e| format!("清理孤立项目失败: {}", e))?;
+
+    let watcher_manager = super::watcher::get_watcher_manager();
+    for root in &pruned {
+        let _ = watcher_manager.stop_watching(root);
+    }
+
+    log::info!("🧹 [prune_orphaned_acemcp_projects] 已清理 {} 个孤立项目: {:?}", pruned.len(), pruned);
+    Ok(pruned)
+}
+
+// ============ 代理配置方案命令 ============
+
+/// 列表输出中代替真实密码的占位符
+const REDACTED_PASSWORD: &str = "******";
+
+/// 列出所有代理配置方案（密码已脱敏）
+#[tauri::command]
+pub async fn list_proxy_profiles(state: State<'_, AppState>) -> Result<Vec<ProxyProfile>, String> {
+    let config = state.config
+        .lock()
+        .map_err(|e| format!("获取配置失败: {}", e))?;
+
+    let profiles = config.mcp_config.acemcp_proxy_profiles.clone().unwrap_or_default();
+    Ok(profiles
+        .into_iter()
+        .map(|mut p| {
+            if p.password.as_deref().map(|pw| !pw.is_empty()).unwrap_or(false) {
+                p.password = Some(REDACTED_PASSWORD.to_string());
+            }
+            p
+        })
+        .collect())
+}
+
+/// 新增或更新代理配置方案（按名称覆盖）
+/// 密码为脱敏占位符时保留原密码，便于前端直接回传列表中的方案
+#[tauri::command]
+pub async fn save_proxy_profile(
+    profile: ProxyProfile,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    let name = profile.name.trim().to_string();
+    if name.is_empty() {
+        return Err("代理方案名称不能为空".to_string());
+    }
+    let proxy_type = profile.proxy_type.trim().to_lowercase();
+    if !matches!(proxy_type.as_str(), "http" | "https" | "socks5") {
+        return Err(format!("不支持的代理类型: {}", profile.proxy_type));
+    }
+    if profile.host.trim().is_empty() || profile.port == 0 {
+        return Err("代理地址或端口无效".to_string());
+    }
+
+    {
+        let mut config = state
+            .config
+            .lock()
+            .map_err(|e| format!("获取配置失败: {}", e))?;
+
+        let profiles = config.mcp_config.acemcp_proxy_profiles.get_or_insert_with(Vec::new);
+        let existing = profiles.iter().position(|p| p.name == name);
+
+        let password = match profile.password.as_deref() {
+            Some(REDACTED_PASSWORD) => existing.and_then(|i| profiles[i].password.clone()),
+            Some("") | None => None,
+            Some(pw) => Some(pw.to_string()),
+        };
+
+        let new_profile = ProxyProfile {
+            name: name.clone(),
+            proxy_type,
+            host: profile.host.trim().to_string(),
+            port: profile.port,
+            username: profile.username.filter(|u| !u.is_empty()),
+            password,
+        };
+
+        match existing {
+            Some(i) => profiles[i] = new_profile,
+            None => profiles.push(new_profile),
+        }
+    }
+
+    save_config(&state, &app)
+        .await
+        .map_err(|e| format!("保存配置失败: {}", e))?;
+
+    log::info!("代理方案已保存: {}", name);
+    Ok(())
+}
+
+/// 删除代理配置方案，返回是否实际删除
+#[tauri::command]
+pub async fn delete_proxy_profile(
+    name: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<bool, String> {
+    let removed = {
+        let mut config = state
+            .config
+            .lock()
+            .map_err(|e| format!("获取配置失败: {}", e))?;
+
+        match config.mcp_config.acemcp_proxy_profiles.as_mut() {
+            Some(profiles) => {
+                let before = profiles.len();
+                profiles.retain(|p| p.name != name);
+                profiles.len() != before
+            }
+            None => false,
+        }
+    };
+
+    if removed {
+        save_config(&state, &app)
+            .await
+            .map_err(|e| format!("保存配置失败: {}", e))?;
+        log::info!("代理方案已删除: {}", name);
+    }
+
+    Ok(removed)
+}
+
+/// 激活代理配置方案：复制到当前生效的 acemcp_proxy_* 字段并启用代理
+#[tauri::command]
+pub async fn activate_proxy_profile(
+    name: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    {
+        let mut config = state
+            .config
+            .lock()
+            .map_err(|e| format!("获取配置失败: {}", e))?;
+
+        let profile = config
+            .mcp_config
+            .acemcp_proxy_profiles
+            .as_ref()
+            .and_then(|profiles| profiles.iter().find(|p| p.name == name))
+            .cloned()
+            .ok_or_else(|| format!("代理方案不存在: {}", name))?;
+
+        config.mcp_config.acemcp_proxy_enabled = Some(true);
+        config.mcp_config.acemcp_proxy_type = Some(profile.proxy_type);
+        config.mcp_config.acemcp_proxy_host = Some(profile.host);
+        config.mcp_config.acemcp_proxy_port = Some(profile.port);
+        config.mcp_config.acemcp_proxy_username = profile.username;
+        config.mcp_config.acemcp_proxy_password = profile.password;
+    }
+
+    save_config(&state, &app)
+        .await
+        .map_err(|e| format!("保存配置失败: {}", e))?;
+
+    log::info!("已激活代理方案: {}", name);
+    Ok(())
+}
+
 // ============ 代理检测和测速命令 ============
 
 /// 自动检测本地可用的代理
 /// 返回所有检测到的可用代理列表
 #[tauri::command]
-pub async fn detect_acemcp_proxy(extra_ports: Option<Vec<u16>>) -> Result<Vec<DetectedProxy>, String> {
+pub async fn detect_acemcp_proxy(
+    extra_ports: Option<Vec<u16>>,
+    full_scan: Option<bool>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Vec<DetectedProxy>, String> {
+    if crate::app::safe_mode::is_safe_mode() {
+        log::warn!("🛟 安全模式下跳过本地代理检测");
+        return Ok(Vec::new());
+    }
+
     log::info!("🔍 开始检测本地代理...");
+
+    // 优先复验上次可用的代理，通过则直接返回，避免每次启动都全量扫描端口
+    if !full_scan.unwrap_or(false) {
+        let cached = state
+            .config
+            .lock()
+            .map_err(|e| format!("获取配置失败: {}", e))?
+            .mcp_config
+            .acemcp_last_good_proxy
+            .clone();
+
+        if let Some(cached) = cached {
+            let proxy_type = if cached.proxy_type == "socks5" { ProxyType::Socks5 } else { ProxyType::Http };
+            let proxy_info = ProxyInfo::new(proxy_type, cached.host.clone(), cached.port);
+            let start = std::time::Instant::now();
+
+            if ProxyDetector::check_proxy(&proxy_info).await {
+                let response_time = start.elapsed().as_millis() as u64;
+                log::info!("✅ 上次可用代理复验通过: {}:{} ({}), {}ms", cached.host, cached.port, cached.proxy_type, response_time);
+                let detected = DetectedProxy {
+                    host: cached.host,
+                    port: cached.port,
+                    proxy_type: cached.proxy_type,
+                    response_time_ms: Some(response_time),
+                };
+                remember_good_proxy(&state, &app, Some(&detected)).await;
+                return Ok(vec![detected]);
+            }
+
+            log::info!("⚠️ 上次可用代理 {}:{} 复验失败，清除缓存并全量检测", cached.host, cached.port);
+            remember_good_proxy(&state, &app, None).await;
+        }
+    }
     
     // 常用代理端口列表
     let mut ports_to_check: Vec<(u16, &'static str)> = vec![
@@ -899,9 +3376,163 @@ pub async fn detect_acemcp_proxy(extra_ports: Option<Vec<u16>>) -> Result<Vec<De
     });
     
     log::info!("🔍 代理检测完成，找到 {} 个可用代理", detected_proxies.len());
+    if let Some(best) = detected_proxies.first() {
+        remember_good_proxy(&state, &app, Some(best)).await;
+    }
     Ok(detected_proxies)
 }
 
+/// 获取最近一次验证可用的代理（未缓存时返回 None）
+#[tauri::command]
+pub async fn get_last_known_good_proxy(state: State<'_, AppState>) -> Result<Option<LastKnownProxy>, String> {
+    let config = state.config
+        .lock()
+        .map_err(|e| format!("获取配置失败: {}", e))?;
+    Ok(config.mcp_config.acemcp_last_good_proxy.clone())
+}
+
+/// 自动配置代理时单个候选访问 ACE 的超时（秒）
+const AUTO_PROXY_ACE_TIMEOUT_SECS: u64 = 10;
+
+/// 一键自动配置代理：全量检测本地代理，逐个验证能否访问 ACE，
+/// 选择 ACE 延迟最低的代理写入 acemcp_proxy_* 配置并启用
+#[tauri::command]
+pub async fn auto_configure_acemcp_proxy(
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<AutoProxyConfigResult, String> {
+    let (base_url, token, tls_options, http_options) = {
+        let config = state.config.lock().map_err(|e| format!("获取配置失败: {}", e))?;
+        (
+            config.mcp_config.acemcp_base_url.clone().ok_or("未配置租户地址")?,
+            crate::config::resolve_configured_token(config.mcp_config.acemcp_token.as_deref())?,
+            AcemcpTlsOptions::from_mcp_config(&config.mcp_config),
+            AcemcpHttpOptions::from_mcp_config(&config.mcp_config),
+        )
+    };
+
+    let candidates = detect_acemcp_proxy(None, Some(true), state.clone(), app.clone()).await?;
+    log::info!("🔧 [AutoProxy] 检测到 {} 个候选代理，开始验证 ACE 可达性", candidates.len());
+
+    // 并发验证每个候选代理能否访问 ACE（本地端口可用不代表能连通 ACE）
+    let health_url = super::mcp::join_ace_url(&base_url, "health");
+    let mut tasks = tokio::task::JoinSet::new();
+    for candidate in candidates.iter().cloned() {
+        let settings = ProxySettings {
+            proxy_type: candidate.proxy_type.clone(),
+            host: candidate.host.clone(),
+            port: candidate.port,
+            username: None,
+            password: None,
+        };
+        let client = match build_speed_test_client(
+            Some(&settings),
+            AUTO_PROXY_ACE_TIMEOUT_SECS,
+            &tls_options,
+            &http_options,
+            &base_url,
+        ) {
+            Ok(client) => client,
+            Err(e) => {
+                log::warn!("⚠️ [AutoProxy] 构建客户端失败: {}:{} ({}), {}", candidate.host, candidate.port, candidate.proxy_type, e);
+                continue;
+            }
+        };
+        let url = health_url.clone();
+        let token = token.clone();
+        tasks.spawn(async move {
+            let result = ping_endpoint(&client, &url, &token).await;
+            (candidate, result)
+        });
+    }
+
+    let mut best: Option<(DetectedProxy, u64)> = None;
+    while let Some(res) = tasks.join_next().await {
+        match res {
+            Ok((candidate, Ok(latency))) => {
+                log::info!("✅ [AutoProxy] 经 {}:{} ({}) 访问 ACE 成功: {}ms", candidate.host, candidate.port, candidate.proxy_type, latency);
+                if best.as_ref().map(|(_, ms)| latency < *ms).unwrap_or(true) {
+                    best = Some((candidate, latency));
+                }
+            }
+            Ok((candidate, Err(e))) => {
+                log::info!("❌ [AutoProxy] 经 {}:{} ({}) 无法访问 ACE: {}", candidate.host, candidate.port, candidate.proxy_type, e);
+            }
+            Err(e) => log::debug!("代理验证任务异常（忽略）: {}", e),
+        }
+    }
+
+    let Some((proxy, latency)) = best else {
+        let message = if candidates.is_empty() {
+            "未找到可用代理：本地未检测到代理端口".to_string()
+        } else {
+            format!("未找到可用代理：检测到 {} 个本地代理，但均无法访问 ACE", candidates.len())
+        };
+        log::warn!("⚠️ [AutoProxy] {}", message);
+        return Ok(AutoProxyConfigResult {
+            configured: false,
+            proxy: None,
+            ace_latency_ms: None,
+            candidates: candidates.len(),
+            message,
+        });
+    };
+
+    {
+        let mut config = state.config.lock().map_err(|e| format!("获取配置失败: {}", e))?;
+        config.mcp_config.acemcp_proxy_enabled = Some(true);
+        config.mcp_config.acemcp_proxy_host = Some(proxy.host.clone());
+        config.mcp_config.acemcp_proxy_port = Some(proxy.port);
+        config.mcp_config.acemcp_proxy_type = Some(proxy.proxy_type.clone());
+        // 本地检测到的代理不带认证，清除旧代理的账号密码
+        config.mcp_config.acemcp_proxy_username = None;
+        config.mcp_config.acemcp_proxy_password = None;
+    }
+    save_config(&state, &app)
+        .await
+        .map_err(|e| format!("保存配置失败: {}", e))?;
+    super::mcp::reset_shared_acemcp_client();
+    remember_good_proxy(&state, &app, Some(&proxy)).await;
+
+    let message = format!(
+        "已自动配置代理 {}:{} ({})，ACE 延迟 {}ms",
+        proxy.host, proxy.port, proxy.proxy_type, latency
+    );
+    log::info!("🔧 [AutoProxy] {}", message);
+    Ok(AutoProxyConfigResult {
+        configured: true,
+        proxy: Some(proxy),
+        ace_latency_ms: Some(latency),
+        candidates: candidates.len(),
+        message,
+    })
+}
+
+/// 更新（或清除）最近一次验证可用的代理缓存，失败仅记录日志
+async fn remember_good_proxy(state: &State<'_, AppState>, app: &AppHandle, proxy: Option<&DetectedProxy>) {
+    let updated = match state.config.lock() {
+        Ok(mut config) => {
+            config.mcp_config.acemcp_last_good_proxy = proxy.map(|p| LastKnownProxy {
+                host: p.host.clone(),
+                port: p.port,
+                proxy_type: p.proxy_type.clone(),
+                verified_at: chrono::Utc::now().to_rfc3339(),
+            });
+            true
+        }
+        Err(e) => {
+            log::warn!("更新代理缓存失败: {}", e);
+            false
+        }
+    };
+
+    if updated {
+        if let Err(e) = save_config(state, app).await {
+            log::warn!("保存代理缓存失败: {}", e);
+        }
+    }
+}
+
 /// 代理测速命令
 /// 测试代理和直连模式下的网络延迟和搜索性能
 #[tauri::command]
@@ -917,9 +3548,16 @@ pub async fn test_acemcp_proxy_speed(
     project_root_path: String,
     project_upload_mode: Option<String>,      // "sample" | "full"
     project_upload_max_files: Option<u32>,    // 采样模式下的文件上限
+    ping_concurrent: Option<bool>,            // 是否在预热连接上并发 Ping（默认顺序执行）
+    ping_detailed: Option<bool>,              // 是否额外测量 DNS/TCP/TLS/首字节分阶段耗时（默认关闭）
     state: State<'_, AppState>,
 ) -> Result<ProxySpeedTestResult, String> {
+    let ping_concurrent = ping_concurrent.unwrap_or(false);
+    let ping_detailed = ping_detailed.unwrap_or(false);
     log::info!("🚀 [SpeedTest] 开始代理测速");
+    if !project_root_path.trim().is_empty() {
+        remember_last_test_query(&state, &app, &project_root_path, &test_query).await;
+    }
     log::info!("📋 [SpeedTest] 参数: mode={}, query={}, project={}", test_mode, test_query, project_root_path);
     
     // 进度发送辅助闭包
@@ -939,17 +3577,37 @@ pub async fn test_acemcp_proxy_speed(
     emit_progress(0, "初始化", 0, SpeedTestStageStatus::Running, Some("正在加载配置..."), None);
     
     // 获取配置
-    let (base_url, token, batch_size, max_lines_per_blob) = {
+    let (base_url, token, batch_size, max_blobs_per_request, max_lines_per_blob, tls_options, http_options) = {
         let config = state.config.lock().map_err(|e| format!("获取配置失败: {}", e))?;
+        // 测速针对指定项目，存在项目级租户覆盖时使用覆盖后的地址与令牌
+        let project_override = super::mcp::find_project_override(&config.mcp_config, &project_root_path)
+            .unwrap_or_default();
         (
-            config.mcp_config.acemcp_base_url.clone().ok_or("未配置租户地址")?,
-            config.mcp_config.acemcp_token.clone().ok_or("未配置 ACE Token")?,
-            config.mcp_config.acemcp_batch_size.unwrap_or(10) as usize,
-            config.mcp_config.acemcp_max_lines_per_blob.unwrap_or(800) as usize,
+            project_override
+                .base_url
+                .or_else(|| config.mcp_config.acemcp_base_url.clone())
+                .ok_or("未配置租户地址")?,
+            crate::config::resolve_configured_token(
+                project_override
+                    .token
+                    .as_deref()
+                    .or(config.mcp_config.acemcp_token.as_deref()),
+            )?,
+            config.mcp_config.acemcp_batch_size.unwrap_or(DEFAULT_ACEMCP_BATCH_SIZE) as usize,
+            config
+                .mcp_config
+                .acemcp_max_blobs_per_request
+                .unwrap_or(DEFAULT_ACEMCP_MAX_BLOBS_PER_REQUEST) as usize,
+            super::mcp::MaxLinesPolicy::new(
+                config.mcp_config.acemcp_max_lines_per_blob.unwrap_or(DEFAULT_ACEMCP_MAX_LINES_PER_BLOB) as usize,
+                config.mcp_config.acemcp_max_lines_by_extension.as_ref(),
+            ),
+            AcemcpTlsOptions::from_mcp_config(&config.mcp_config),
+            AcemcpHttpOptions::from_mcp_config(&config.mcp_config),
         )
     };
     
-    log::debug!("⚙️ [SpeedTest] 配置: base_url={}, batch_size={}, max_lines={}", base_url, batch_size, max_lines_per_blob);
+    log::debug!("⚙️ [SpeedTest] 配置: base_url={}, batch_size={}, max_lines={}", base_url, batch_size, max_lines_per_blob.default_lines());
     
     let mut metrics: Vec<SpeedTestMetric> = Vec::new();
     let test_proxy = test_mode == "proxy" || test_mode == "compare";
@@ -1035,7 +3693,7 @@ pub async fn test_acemcp_proxy_speed(
     // 说明：测速过程中会多次请求，如果每次都 build client 会有额外开销
     let proxy_client: Option<reqwest::Client> = if test_proxy {
         if let Some(ref ps) = proxy_settings {
-            Some(build_speed_test_client(Some(ps), 120)?)
+            Some(build_speed_test_client(Some(ps), 120, &tls_options, &http_options, &base_url)?)
         } else {
             None
         }
@@ -1044,7 +3702,7 @@ pub async fn test_acemcp_proxy_speed(
     };
 
     let direct_client: Option<reqwest::Client> = if test_direct {
-        Some(build_speed_test_client(None, 120)?)
+        Some(build_speed_test_client(None, 120, &tls_options, &http_options, &base_url)?)
     } else {
         None
     };
@@ -1058,7 +3716,7 @@ pub async fn test_acemcp_proxy_speed(
                   Some(&format!("项目文件: {} 个, HTTP Client 就绪", file_count)), None);
     
     // 1. Ping 测试 - 测量到 ACE 服务器的网络延迟
-    let health_url = format!("{}/health", base_url);
+    let health_url = super::mcp::join_ace_url(&base_url, "health");
     let mut ping_metric = SpeedTestMetric {
         name: "🌐 网络延迟".to_string(),
         metric_type: "ping".to_string(),
@@ -1067,6 +3725,13 @@ pub async fn test_acemcp_proxy_speed(
         success: true,
         error: None,
         search_result_preview: None,
+        proxy_latency: None,
+        direct_latency: None,
+        proxy_stream: None,
+        direct_stream: None,
+        proxy_phases: None,
+        direct_phases: None,
+        file_errors: None,
     };
     
     log::info!("📡 [SpeedTest] === 阶段1: Ping 测试 ===");
@@ -1081,16 +3746,8 @@ pub async fn test_acemcp_proxy_speed(
         emit_progress(1, "Ping 测试", 15, SpeedTestStageStatus::Running, 
                       Some("代理模式 Ping..."), Some("代理 Ping"));
         if let Some(ref client) = proxy_client {
-            let rounds = 3usize;
-            let mut ok: Vec<u64> = Vec::with_capacity(rounds);
-            let mut last_err: Option<String> = None;
-
-            for _ in 0..rounds {
-                match ping_endpoint(client, &health_url, &token).await {
-                    Ok(ms) => ok.push(ms),
-                    Err(e) => last_err = Some(e),
-                }
-            }
+            let (ok, last_err, rounds) = run_ping_rounds(client, &health_url, &token, ping_concurrent).await;
+            ping_metric.proxy_latency = latency_stats(&ok);
 
             if ok.is_empty() {
                 ping_metric.success = false;
@@ -1111,8 +3768,15 @@ pub async fn test_acemcp_proxy_speed(
                     );
                 }
             }
-            log::info!("📡 [SpeedTest] 代理 Ping 完成: avg={}ms, success={}/{}", 
-                       ping_metric.proxy_time_ms.unwrap_or(0), ok.len(), rounds);
+            log::info!("📡 [SpeedTest] 代理 Ping 完成: avg={}ms, p95={}ms, success={}/{}", 
+                       ping_metric.proxy_time_ms.unwrap_or(0),
+                       ping_metric.proxy_latency.as_ref().map(|l| l.p95_ms).unwrap_or(0), ok.len(), rounds);
+            if ping_detailed {
+                match detailed_ping(proxy_settings.as_ref(), &tls_options, &http_options, &base_url, &health_url, &token).await {
+                    Ok(phases) => ping_metric.proxy_phases = Some(phases),
+                    Err(e) => log::warn!("⚠️ [SpeedTest] 代理详细 Ping 失败: {}", e),
+                }
+            }
             
             emit_progress(1, "Ping 测试", 20, SpeedTestStageStatus::Running, 
                           Some(&format!("代理 Ping: avg={}ms, {}/{}", ping_metric.proxy_time_ms.unwrap_or(0), ok.len(), rounds)), 
@@ -1128,16 +3792,8 @@ pub async fn test_acemcp_proxy_speed(
         emit_progress(1, "Ping 测试", 25, SpeedTestStageStatus::Running, 
                       Some("直连模式 Ping..."), Some("直连 Ping"));
         let direct_client = direct_client.as_ref().ok_or_else(|| "直连 Ping 跳过：直连 client 未初始化".to_string())?;
-        let rounds = 3usize;
-        let mut ok: Vec<u64> = Vec::with_capacity(rounds);
-        let mut last_err: Option<String> = None;
-
-        for _ in 0..rounds {
-            match ping_endpoint(direct_client, &health_url, &token).await {
-                Ok(ms) => ok.push(ms),
-                Err(e) => last_err = Some(e),
-            }
-        }
+        let (ok, last_err, rounds) = run_ping_rounds(direct_client, &health_url, &token, ping_concurrent).await;
+        ping_metric.direct_latency = latency_stats(&ok);
 
         if ok.is_empty() {
             ping_metric.success = false;
@@ -1158,8 +3814,15 @@ pub async fn test_acemcp_proxy_speed(
                 );
             }
         }
-        log::info!("📡 [SpeedTest] 直连 Ping 完成: avg={}ms, success={}/{}", 
-                   ping_metric.direct_time_ms.unwrap_or(0), ok.len(), rounds);
+        log::info!("📡 [SpeedTest] 直连 Ping 完成: avg={}ms, p95={}ms, success={}/{}", 
+                   ping_metric.direct_time_ms.unwrap_or(0),
+                   ping_metric.direct_latency.as_ref().map(|l| l.p95_ms).unwrap_or(0), ok.len(), rounds);
+        if ping_detailed {
+            match detailed_ping(None, &tls_options, &http_options, &base_url, &health_url, &token).await {
+                Ok(phases) => ping_metric.direct_phases = Some(phases),
+                Err(e) => log::warn!("⚠️ [SpeedTest] 直连详细 Ping 失败: {}", e),
+            }
+        }
         
         emit_progress(1, "Ping 测试", 30, SpeedTestStageStatus::Running, 
                       Some(&format!("直连 Ping: avg={}ms, {}/{}", ping_metric.direct_time_ms.unwrap_or(0), ok.len(), rounds)), 
@@ -1175,25 +3838,12 @@ pub async fn test_acemcp_proxy_speed(
                   None);
     
     // 2. 语义搜索测试（支持多条查询：按换行/分号分隔）
-    let search_url = format!("{}/agents/codebase-retrieval", base_url);
+    let search_url = super::mcp::join_ace_url(&base_url, "agents/codebase-retrieval");
     
     // 从 projects.json 加载测试项目的 blob_names（与 mcp.rs::search_only 保持一致）
     let blob_names: Vec<String> = {
-        use std::path::PathBuf;
-        
-        let projects_path = super::mcp::home_projects_file();
-        let projects: super::mcp::ProjectsFile = if projects_path.exists() {
-            let data = std::fs::read_to_string(&projects_path).unwrap_or_default();
-            serde_json::from_str(&data).unwrap_or_default()
-        } else {
-            super::mcp::ProjectsFile::default()
-        };
-        
-        let normalized_root = PathBuf::from(&project_root_path)
-            .canonicalize()
-            .unwrap_or_else(|_| PathBuf::from(&project_root_path))
-            .to_string_lossy()
-            .replace('\\', "/");
+        let projects = super::mcp::load_projects_file();
+        let normalized_root = super::mcp::normalize_project_path(&project_root_path);
         
         projects.0.get(&normalized_root).cloned().unwrap_or_default()
     };
@@ -1232,6 +3882,9 @@ pub async fn test_acemcp_proxy_speed(
                   Some(&format!("查询数: {} 条, 索引: {} blobs", queries.len(), blob_names.len())), 
                   None);
 
+    // 流式测试复用第一条查询
+    let stream_query = queries[0].clone();
+
     for q in queries {
         let display_q = if q.len() > 30 {
             format!("{}...", &q[..30])
@@ -1249,6 +3902,13 @@ pub async fn test_acemcp_proxy_speed(
             success: true,
             error: None,
             search_result_preview: None,
+            proxy_latency: None,
+            direct_latency: None,
+            proxy_stream: None,
+            direct_stream: None,
+            proxy_phases: None,
+            direct_phases: None,
+            file_errors: None,
         };
 
         let search_payload = serde_json::json!({
@@ -1335,6 +3995,72 @@ pub async fn test_acemcp_proxy_speed(
         metrics.push(search_metric);
     }
     
+    // 流式检索测试：代理可能缓冲分块响应，普通请求/响应测速无法反映真实的首字节延迟
+    emit_progress(2, "语义搜索", 52, SpeedTestStageStatus::Running,
+                  Some("流式检索测试..."), Some("流式检索"));
+    let stream_payload = serde_json::json!({
+        "information_request": stream_query,
+        "blobs": {
+            "checkpoint_id": serde_json::Value::Null,
+            "added_blobs": blob_names.clone(),
+            "deleted_blobs": []
+        },
+        "dialog": [],
+        "max_output_length": 0,
+        "disable_codebase_retrieval": false,
+        "enable_commit_retrieval": false,
+    });
+    let mut stream_metric = SpeedTestMetric {
+        name: "📶 流式检索".to_string(),
+        metric_type: "stream".to_string(),
+        proxy_time_ms: None,
+        direct_time_ms: None,
+        success: true,
+        error: None,
+        search_result_preview: None,
+        proxy_latency: None,
+        direct_latency: None,
+        proxy_stream: None,
+        direct_stream: None,
+        proxy_phases: None,
+        direct_phases: None,
+        file_errors: None,
+    };
+    // 服务端返回非流式响应时该链路记为“不支持”，两条链路都不支持则跳过该指标
+    let mut stream_supported = false;
+    let stream_routes = [
+        ("代理", if test_proxy { proxy_client.as_ref() } else { None }),
+        ("直连", if test_direct { direct_client.as_ref() } else { None }),
+    ];
+    for (route, client) in stream_routes {
+        let Some(client) = client else { continue };
+        match stream_probe(client, &search_url, &token, &stream_payload).await {
+            Ok(Some(stats)) => {
+                stream_supported = true;
+                log::info!("📶 [SpeedTest] {}流式检索: 首字节={}ms, 总耗时={}ms, 分块={}, 吞吐={:.1}KB/s",
+                           route, stats.first_byte_ms, stats.total_ms, stats.chunk_count, stats.throughput_kbps);
+                if route == "代理" {
+                    stream_metric.proxy_time_ms = Some(stats.first_byte_ms);
+                    stream_metric.proxy_stream = Some(stats);
+                } else {
+                    stream_metric.direct_time_ms = Some(stats.first_byte_ms);
+                    stream_metric.direct_stream = Some(stats);
+                }
+            }
+            Ok(None) => {
+                log::info!("📶 [SpeedTest] {}流式检索: 服务端未以流式返回，跳过", route);
+            }
+            Err(e) => {
+                stream_supported = true;
+                stream_metric.success = false;
+                append_error(&mut stream_metric.error, format!("{}流式检索失败: {}", route, e));
+            }
+        }
+    }
+    if stream_supported {
+        metrics.push(stream_metric);
+    }
+
     // 阶段2: 语义搜索完成
     emit_progress(2, "语义搜索", 55, SpeedTestStageStatus::Completed, 
                   Some("所有搜索查询完成"), None);
@@ -1348,6 +4074,13 @@ pub async fn test_acemcp_proxy_speed(
         success: true,
         error: None,
         search_result_preview: None,
+        proxy_latency: None,
+        direct_latency: None,
+        proxy_stream: None,
+        direct_stream: None,
+        proxy_phases: None,
+        direct_phases: None,
+        file_errors: None,
     };
     
     log::info!("📤 [SpeedTest] === 阶段3: 单文件上传测试 ===");
@@ -1364,13 +4097,15 @@ pub async fn test_acemcp_proxy_speed(
         if !pfs.files.is_empty() {
             let random_index = fastrand::usize(0..pfs.files.len());
             let file = &pfs.files[random_index];
-            match build_single_file_blobs_for_speed_test(&project_root_path, &file.path, max_lines_per_blob) {
+            match build_single_file_blobs_for_speed_test(&project_root_path, &file.path, &max_lines_per_blob) {
                 Ok((blobs, file_bytes)) => {
-                    let upload_url = format!("{}/batch-upload", base_url);
+                    let upload_url = super::mcp::join_ace_url(&base_url, "batch-upload");
+                    let lossy_note = if blobs.iter().any(|b| b.lossy) { "，有损解码" } else { "" };
                     upload_single_metric.name = format!(
-                        "📤 单文件上传 ({}，{} blobs)",
+                        "📤 单文件上传 ({}，{} blobs{})",
                         format_bytes(file_bytes),
-                        blobs.len()
+                        blobs.len(),
+                        lossy_note
                     );
                     
                     log::debug!("📤 [SpeedTest] 单文件: path={}, size={}, blobs={}", 
@@ -1379,7 +4114,10 @@ pub async fn test_acemcp_proxy_speed(
                     if test_proxy {
                         if let Some(ref client) = proxy_client {
                             match upload_blobs_batch(client, &upload_url, &token, &blobs, 120).await {
-                                Ok(ms) => upload_single_metric.proxy_time_ms = Some(ms),
+                                Ok(ms) => {
+                                    upload_single_metric.proxy_time_ms = Some(ms);
+                                    remember_upload_throughput(file_bytes, ms);
+                                }
                                 Err(e) => {
                                     upload_single_metric.success = false;
                                     append_error(&mut upload_single_metric.error, format!("代理上传失败: {}", e));
@@ -1394,7 +4132,10 @@ pub async fn test_acemcp_proxy_speed(
                     if test_direct {
                         let direct_client = direct_client.as_ref().ok_or_else(|| "直连上传跳过：直连 client 未初始化".to_string())?;
                         match upload_blobs_batch(direct_client, &upload_url, &token, &blobs, 120).await {
-                            Ok(ms) => upload_single_metric.direct_time_ms = Some(ms),
+                            Ok(ms) => {
+                                upload_single_metric.direct_time_ms = Some(ms);
+                                remember_upload_throughput(file_bytes, ms);
+                            }
                             Err(e) => {
                                 upload_single_metric.success = false;
                                 append_error(&mut upload_single_metric.error, format!("直连上传失败: {}", e));
@@ -1430,6 +4171,13 @@ pub async fn test_acemcp_proxy_speed(
         success: true,
         error: None,
         search_result_preview: None,
+        proxy_latency: None,
+        direct_latency: None,
+        proxy_stream: None,
+        direct_stream: None,
+        proxy_phases: None,
+        direct_phases: None,
+        file_errors: None,
     };
     
     log::info!("📦 [SpeedTest] === 阶段4: 项目上传测试 ===");
@@ -1456,7 +4204,8 @@ pub async fn test_acemcp_proxy_speed(
                     &project_root_path,
                     pfs,
                     batch_size,
-                    max_lines_per_blob,
+                    max_blobs_per_request,
+                    &max_lines_per_blob,
                     project_upload_max_files_limit,
                 )
                 .await
@@ -1487,7 +4236,8 @@ pub async fn test_acemcp_proxy_speed(
                 &project_root_path,
                 pfs,
                 batch_size,
-                max_lines_per_blob,
+                max_blobs_per_request,
+                &max_lines_per_blob,
                 project_upload_max_files_limit,
             )
             .await
@@ -1517,6 +4267,14 @@ pub async fn test_acemcp_proxy_speed(
                 format_bytes(r.total_bytes),
                 r.blob_count
             );
+            if r.dedup_blobs > 0 {
+                upload_project_metric.name = format!(
+                    "{}（重复内容去重 {} blobs，节省 {}）",
+                    upload_project_metric.name,
+                    r.dedup_blobs,
+                    format_bytes(r.dedup_bytes)
+                );
+            }
 
             if r.skipped_files > 0 {
                 upload_project_metric.success = false;
@@ -1525,10 +4283,28 @@ pub async fn test_acemcp_proxy_speed(
                     append_error(&mut upload_project_metric.error, e);
                 }
             }
+            let upload_failed_files = r.file_errors.total - r.skipped_files;
+            if upload_failed_files > 0 {
+                upload_project_metric.success = false;
+                append_error(&mut upload_project_metric.error, format!("上传失败文件: {} 个", upload_failed_files));
+            }
+            if !r.file_errors.is_empty() {
+                upload_project_metric.file_errors = Some(r.file_errors);
+            }
 
             if r.truncated {
                 append_error(&mut upload_project_metric.error, "已按采样上限截断文件数量".to_string());
             }
+
+            if r.lossy_files > 0 {
+                append_error(
+                    &mut upload_project_metric.error,
+                    format!(
+                        "有损解码文件: {} 个（{} blobs，可能为混合编码或无 BOM 的 UTF-16，检索质量可能受影响）",
+                        r.lossy_files, r.lossy_blobs
+                    ),
+                );
+            }
         }
     }
     metrics.push(upload_project_metric);
@@ -1551,62 +4327,449 @@ pub async fn test_acemcp_proxy_speed(
     let mut recommendation = generate_recommendation(&metrics, &test_mode);
     let all_success = metrics.iter().all(|m| m.success);
 
-    let total = metrics.len().max(1);
-    let ok = metrics.iter().filter(|m| m.success).count();
-    recommendation = format!("{} | 总体成功率: {}/{}", recommendation, ok, total);
+    let total = metrics.len().max(1);
+    let ok = metrics.iter().filter(|m| m.success).count();
+    recommendation = format!("{} | 总体成功率: {}/{}", recommendation, ok, total);
+
+    if test_proxy {
+        let ok_proxy = metrics.iter().filter(|m| m.proxy_time_ms.is_some()).count();
+        recommendation = format!("{} | 代理成功: {}/{}", recommendation, ok_proxy, total);
+    }
+
+    if test_direct {
+        let ok_direct = metrics.iter().filter(|m| m.direct_time_ms.is_some()).count();
+        recommendation = format!("{} | 直连成功: {}/{}", recommendation, ok_direct, total);
+    }
+
+    if !all_success {
+        if let Some(first_fail) = metrics.iter().find(|m| !m.success) {
+            if let Some(err) = &first_fail.error {
+                let mut err_short = err.replace('\n', " / ");
+                if err_short.len() > 120 {
+                    err_short.truncate(120);
+                    err_short.push_str("...");
+                }
+                recommendation = format!("{} | 失败示例: {} - {}", recommendation, first_fail.name, err_short);
+            } else {
+                recommendation = format!("{} | 存在失败项", recommendation);
+            }
+        }
+    }
+    
+    if test_mode == "compare" {
+        record_speed_test_history(&metrics, proxy_info.as_ref());
+    }
+
+    let result = ProxySpeedTestResult {
+        mode: test_mode,
+        proxy_info,
+        metrics,
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        recommendation,
+        success: all_success,
+    };
+    
+    log::info!("✅ [SpeedTest] 代理测速完成: success={}, metrics={}, recommendation={}", 
+               all_success, result.metrics.len(), result.recommendation);
+    
+    // 输出每个指标的详细结果
+    for (i, m) in result.metrics.iter().enumerate() {
+        log::debug!("📈 [SpeedTest] 指标[{}] {}: proxy={}ms, direct={}ms, success={}",
+                   i, m.name,
+                   m.proxy_time_ms.map_or("-".to_string(), |v| v.to_string()),
+                   m.direct_time_ms.map_or("-".to_string(), |v| v.to_string()),
+                   m.success);
+    }
+    
+    // 阶段5: 全部完成
+    let final_status = if all_success { SpeedTestStageStatus::Completed } else { SpeedTestStageStatus::Failed };
+    emit_progress(5, "生成报告", 100, final_status, 
+                  Some(&format!("成功率: {}/{}", ok, total)), None);
+    
+    Ok(result)
+}
+
+/// 本地读取测试默认的文件数上限（与项目上传测速采样一致）
+const LOCAL_READ_BENCHMARK_DEFAULT_FILES: u32 = 200;
+
+/// 测试本地文件读取 + 解码吞吐（不访问网络）
+/// 与项目上传测速使用相同的文件列表与解码逻辑，两者对比即可判断瓶颈在磁盘还是网络
+#[tauri::command]
+pub async fn benchmark_local_read(
+    project_root_path: String,
+    max_files: Option<u32>,
+) -> Result<LocalReadBenchmark, String> {
+    let files_status = AcemcpTool::get_project_files_status(project_root_path.clone())
+        .await
+        .map_err(|e| format!("获取项目文件列表失败: {}", e))?;
+    let max_files = max_files.unwrap_or(LOCAL_READ_BENCHMARK_DEFAULT_FILES).max(1) as usize;
+
+    // 文件读取为阻塞操作，放到阻塞线程池执行
+    let result = tokio::task::spawn_blocking(move || {
+        let root = std::path::PathBuf::from(&project_root_path);
+        let files: Vec<&str> = files_status.files.iter().take(max_files).map(|f| f.path.as_str()).collect();
+
+        let mut read_files = 0usize;
+        let mut failed_files = 0usize;
+        let mut lossy_files = 0usize;
+        let mut total_bytes = 0u64;
+        let mut first_error: Option<String> = None;
+
+        let start = std::time::Instant::now();
+        for rel_path in &files {
+            match read_file_with_encoding_for_speed_test(&root.join(rel_path)) {
+                Ok(decoded) => {
+                    read_files += 1;
+                    total_bytes += decoded.content.len() as u64;
+                    if decoded.lossy {
+                        lossy_files += 1;
+                    }
+                }
+                Err(e) => {
+                    failed_files += 1;
+                    if first_error.is_none() {
+                        first_error = Some(format!("读取文件失败: path={}, error={}", rel_path, e));
+                    }
+                }
+            }
+        }
+        let elapsed = start.elapsed();
+        let secs = elapsed.as_secs_f64().max(1e-6);
+
+        LocalReadBenchmark {
+            project_root: files_status.project_root.clone(),
+            planned_files: files.len(),
+            read_files,
+            failed_files,
+            lossy_files,
+            total_bytes,
+            elapsed_ms: elapsed.as_millis() as u64,
+            mb_per_sec: total_bytes as f64 / (1024.0 * 1024.0) / secs,
+            files_per_sec: read_files as f64 / secs,
+            first_error,
+        }
+    })
+    .await
+    .map_err(|e| format!("本地读取测试任务异常: {}", e))?;
+
+    log::info!(
+        "💽 [LocalRead] 读取 {} 个文件（失败 {}），{}，耗时 {}ms，{:.2} MB/s，{:.1} 文件/s",
+        result.read_files,
+        result.failed_files,
+        format_bytes(result.total_bytes),
+        result.elapsed_ms,
+        result.mb_per_sec,
+        result.files_per_sec
+    );
+    Ok(result)
+}
+
+/// 带宽测量使用的单个合成负载大小（1MB）
+const BANDWIDTH_PAYLOAD_BYTES: usize = 1024 * 1024;
+
+/// 快速测量到 ACE 的上传带宽
+/// 分别上传 1MB 可压缩文本与 1MB 随机文本到 /batch-upload，结果与用户项目无关，便于重复对比
+#[tauri::command]
+pub async fn measure_acemcp_bandwidth(
+    mode: String,                 // "proxy" | "direct" | "compare"
+    proxy_host: Option<String>,
+    proxy_port: Option<u16>,
+    proxy_type: Option<String>,
+    proxy_username: Option<String>,
+    proxy_password: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<BandwidthMeasurement>, String> {
+    let (base_url, token, tls_options, http_options) = {
+        let config = state.config.lock().map_err(|e| format!("获取配置失败: {}", e))?;
+        (
+            config.mcp_config.acemcp_base_url.clone().ok_or("未配置租户地址")?,
+            crate::config::resolve_configured_token(config.mcp_config.acemcp_token.as_deref())?,
+            AcemcpTlsOptions::from_mcp_config(&config.mcp_config),
+            AcemcpHttpOptions::from_mcp_config(&config.mcp_config),
+        )
+    };
+
+    let test_proxy = mode == "proxy" || mode == "compare";
+    let test_direct = mode == "direct" || mode == "compare";
+    if !test_proxy && !test_direct {
+        return Err(format!("无效的测试模式: {}（仅支持 proxy/direct/compare）", mode));
+    }
+
+    let upload_url = super::mcp::join_ace_url(&base_url, "batch-upload");
+    let (compressible, random) = build_bandwidth_payloads();
+    log::info!("📶 [Bandwidth] 开始带宽测量: mode={}, payload={}", mode, format_bytes(BANDWIDTH_PAYLOAD_BYTES as u64));
+
+    let mut routes: Vec<(&str, Option<ProxySettings>)> = Vec::new();
+    if test_proxy {
+        routes.push(("proxy", Some(ProxySettings {
+            proxy_type: proxy_type.unwrap_or_else(|| "http".to_string()),
+            host: proxy_host.unwrap_or_else(|| "127.0.0.1".to_string()),
+            port: proxy_port.unwrap_or(7890),
+            username: proxy_username,
+            password: proxy_password,
+        })));
+    }
+    if test_direct {
+        routes.push(("direct", None));
+    }
+
+    let mut results = Vec::with_capacity(routes.len());
+    for (route, proxy) in routes {
+        let mut measurement = BandwidthMeasurement {
+            route: route.to_string(),
+            payload_bytes: BANDWIDTH_PAYLOAD_BYTES as u64,
+            compressible_ms: None,
+            compressible_mbps: None,
+            random_ms: None,
+            random_mbps: None,
+            success: true,
+            error: None,
+        };
+
+        let client = match build_speed_test_client(proxy.as_ref(), 120, &tls_options, &http_options, &base_url) {
+            Ok(c) => c,
+            Err(e) => {
+                measurement.success = false;
+                measurement.error = Some(e);
+                results.push(measurement);
+                continue;
+            }
+        };
+
+        for (kind, blob) in [("compressible", &compressible), ("random", &random)] {
+            match upload_blobs_batch(&client, &upload_url, &token, std::slice::from_ref(blob), 120).await {
+                Ok(ms) => {
+                    let mbps = to_mbps(BANDWIDTH_PAYLOAD_BYTES as u64, ms);
+                    remember_upload_throughput(BANDWIDTH_PAYLOAD_BYTES as u64, ms);
+                    log::info!("📶 [Bandwidth] {} {}: {}ms, {:.2}Mbps", route, kind, ms, mbps);
+                    if kind == "compressible" {
+                        measurement.compressible_ms = Some(ms);
+                        measurement.compressible_mbps = Some(mbps);
+                    } else {
+                        measurement.random_ms = Some(ms);
+                        measurement.random_mbps = Some(mbps);
+                    }
+                }
+                Err(e) => {
+                    log::warn!("⚠️ [Bandwidth] {} {} 上传失败: {}", route, kind, e);
+                    measurement.success = false;
+                    append_error(&mut measurement.error, format!("{} 负载上传失败: {}", kind, e));
+                }
+            }
+        }
+
+        results.push(measurement);
+    }
+
+    Ok(results)
+}
+
+/// 最近一次测速得到的上传吞吐量（字节/秒），供索引规模估算使用
+static LAST_UPLOAD_THROUGHPUT: once_cell::sync::Lazy<std::sync::Mutex<Option<f64>>> =
+    once_cell::sync::Lazy::new(|| std::sync::Mutex::new(None));
+
+/// 记录一次上传测速结果（耗时为 0 的异常数据会被忽略）
+fn remember_upload_throughput(bytes: u64, ms: u64) {
+    if bytes == 0 || ms == 0 {
+        return;
+    }
+    if let Ok(mut guard) = LAST_UPLOAD_THROUGHPUT.lock() {
+        *guard = Some(bytes as f64 * 1000.0 / ms as f64);
+    }
+}
+
+/// 估算项目索引的上传规模与耗时（仅读取文件元数据，不读取内容）
+#[tauri::command]
+pub async fn estimate_acemcp_index(project_root_path: String) -> Result<IndexEstimate, String> {
+    let mut estimate = AcemcpTool::estimate_index_size(project_root_path)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    estimate.throughput_bytes_per_sec = LAST_UPLOAD_THROUGHPUT.lock().ok().and_then(|g| *g);
+    estimate.estimated_secs = estimate
+        .throughput_bytes_per_sec
+        .filter(|t| *t > 0.0)
+        .map(|t| (estimate.total_bytes as f64 / t).ceil() as u64);
+
+    estimate.summary = match estimate.estimated_secs {
+        Some(secs) => format!(
+            "将上传约 {} 文件 / {}, 预计 {}",
+            format_thousands(estimate.file_count as u64),
+            format_bytes(estimate.total_bytes),
+            format_eta(secs)
+        ),
+        None => format!(
+            "将上传约 {} 文件 / {}（尚未测速，无法预计耗时）",
+            format_thousands(estimate.file_count as u64),
+            format_bytes(estimate.total_bytes)
+        ),
+    };
+
+    log::info!("📏 [Estimate] {}: {}", estimate.project_root, estimate.summary);
+    Ok(estimate)
+}
+
+/// 千分位格式化，如 8300 -> "8,300"
+fn format_thousands(n: u64) -> String {
+    let digits = n.to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// 将秒数格式化为易读的预计耗时
+fn format_eta(secs: u64) -> String {
+    if secs < 60 {
+        format!("{} 秒", secs.max(1))
+    } else if secs < 3600 {
+        format!("{} 分钟", secs.div_ceil(60))
+    } else {
+        format!("{} 小时 {} 分钟", secs / 3600, (secs % 3600) / 60)
+    }
+}
+
+/// 构造带宽测量用的两个合成负载：可压缩的重复代码文本 + 不可压缩的随机字符
+fn build_bandwidth_payloads() -> (UploadBlob, UploadBlob) {
+    use rand::distributions::Alphanumeric;
+    use rand::Rng;
+
+    let line = "fn sanshu_bandwidth_probe(value: usize) -> usize { value * 2 + 1 }\n";
+    let compressible: String = line
+        .repeat(BANDWIDTH_PAYLOAD_BYTES / line.len() + 1)
+        .chars()
+        .take(BANDWIDTH_PAYLOAD_BYTES)
+        .collect();
 
-    if test_proxy {
-        let ok_proxy = metrics.iter().filter(|m| m.proxy_time_ms.is_some()).count();
-        recommendation = format!("{} | 代理成功: {}/{}", recommendation, ok_proxy, total);
-    }
+    let random: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(BANDWIDTH_PAYLOAD_BYTES)
+        .map(char::from)
+        .collect();
 
-    if test_direct {
-        let ok_direct = metrics.iter().filter(|m| m.direct_time_ms.is_some()).count();
-        recommendation = format!("{} | 直连成功: {}/{}", recommendation, ok_direct, total);
+    (
+        UploadBlob { path: "__sanshu_bandwidth__/compressible.rs".to_string(), content: compressible, lossy: false },
+        UploadBlob { path: "__sanshu_bandwidth__/random.txt".to_string(), content: random, lossy: false },
+    )
+}
+
+/// 根据字节数与耗时计算 Mbps
+fn to_mbps(bytes: u64, elapsed_ms: u64) -> f64 {
+    if elapsed_ms == 0 {
+        return 0.0;
     }
+    (bytes as f64 * 8.0) / (elapsed_ms as f64 / 1000.0) / 1_000_000.0
+}
 
-    if !all_success {
-        if let Some(first_fail) = metrics.iter().find(|m| !m.success) {
-            if let Some(err) = &first_fail.error {
-                let mut err_short = err.replace('\n', " / ");
-                if err_short.len() > 120 {
-                    err_short.truncate(120);
-                    err_short.push_str("...");
-                }
-                recommendation = format!("{} | 失败示例: {} - {}", recommendation, first_fail.name, err_short);
+/// 连通性测试的请求超时（秒）
+const CONNECTIVITY_TEST_TIMEOUT_SECS: u64 = 15;
+
+/// 通过代理（或直连）请求任意 http(s) 地址，用于确认代理本身是否可用
+/// 未传入代理参数时使用已保存的代理配置（未启用则直连）
+#[tauri::command]
+pub async fn test_proxy_connectivity(
+    url: String,
+    proxy_host: Option<String>,
+    proxy_port: Option<u16>,
+    proxy_type: Option<String>,
+    proxy_username: Option<String>,
+    proxy_password: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<ProxyConnectivityResult, String> {
+    let target = validate_connectivity_target(&url)?;
+
+    let proxy = match proxy_host.filter(|h| !h.trim().is_empty()) {
+        Some(host) => Some(ProxySettings {
+            proxy_type: proxy_type.unwrap_or_else(|| "http".to_string()),
+            host,
+            port: proxy_port.unwrap_or(7890),
+            username: proxy_username,
+            password: proxy_password,
+        }),
+        None => {
+            let config = state.config.lock().map_err(|e| format!("获取配置失败: {}", e))?;
+            let mcp = &config.mcp_config;
+            if mcp.acemcp_proxy_enabled.unwrap_or(false) {
+                Some(ProxySettings {
+                    proxy_type: mcp.acemcp_proxy_type.clone().unwrap_or_else(|| "http".to_string()),
+                    host: mcp.acemcp_proxy_host.clone().unwrap_or_else(|| "127.0.0.1".to_string()),
+                    port: mcp.acemcp_proxy_port.unwrap_or(7890),
+                    username: mcp.acemcp_proxy_username.clone(),
+                    password: mcp.acemcp_proxy_password.clone(),
+                })
             } else {
-                recommendation = format!("{} | 存在失败项", recommendation);
+                None
             }
         }
+    };
+    let proxy_label = proxy
+        .as_ref()
+        .map(|p| format!("{}://{}:{}", p.proxy_type, p.host.trim(), p.port));
+
+    // 目标站点与 ACE 无关，不套用 ACE 的 mTLS/自定义 CA 与 HTTP/2 配置
+    let client = build_speed_test_client(
+        proxy.as_ref(),
+        CONNECTIVITY_TEST_TIMEOUT_SECS,
+        &AcemcpTlsOptions::default(),
+        &AcemcpHttpOptions::default(),
+        target.as_str(),
+    )?;
+
+    log::info!(
+        "🌐 [Connectivity] 测试连通性: url={}, proxy={}",
+        target,
+        proxy_label.as_deref().unwrap_or("直连")
+    );
+    let start = std::time::Instant::now();
+    let (status_code, error) = match client.get(target.clone()).send().await {
+        Ok(resp) => (Some(resp.status().as_u16()), None),
+        Err(e) => {
+            log::warn!("❌ [Connectivity] 请求失败: {}", e);
+            (None, Some(format!("请求失败: {}", e)))
+        }
+    };
+
+    Ok(ProxyConnectivityResult {
+        url: target.to_string(),
+        success: status_code.is_some(),
+        status_code,
+        latency_ms: status_code.map(|_| start.elapsed().as_millis() as u64),
+        via_proxy: proxy_label.is_some(),
+        proxy: proxy_label,
+        error,
+    })
+}
+
+/// 校验连通性测试目标：仅允许 http/https，拒绝本机/未指定/链路本地地址，避免被用来探测本机服务
+fn validate_connectivity_target(url: &str) -> Result<reqwest::Url, String> {
+    let parsed = reqwest::Url::parse(url.trim()).map_err(|e| format!("无效的 URL: {}", e))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(format!("仅支持 http/https 地址，当前为: {}", parsed.scheme()));
     }
-    
-    let result = ProxySpeedTestResult {
-        mode: test_mode,
-        proxy_info,
-        metrics,
-        timestamp: chrono::Utc::now().to_rfc3339(),
-        recommendation,
-        success: all_success,
+
+    let host = parsed
+        .host_str()
+        .ok_or_else(|| "URL 缺少主机名".to_string())?
+        .trim_start_matches('[')
+        .trim_end_matches(']');
+    let blocked = match host.parse::<std::net::IpAddr>() {
+        Ok(std::net::IpAddr::V4(ip)) => ip.is_loopback() || ip.is_unspecified() || ip.is_link_local(),
+        Ok(std::net::IpAddr::V6(ip)) => {
+            ip.is_loopback() || ip.is_unspecified() || (ip.segments()[0] & 0xffc0) == 0xfe80
+        }
+        Err(_) => {
+            let domain = host.trim_end_matches('.').to_ascii_lowercase();
+            domain == "localhost" || domain.ends_with(".localhost")
+        }
     };
-    
-    log::info!("✅ [SpeedTest] 代理测速完成: success={}, metrics={}, recommendation={}", 
-               all_success, result.metrics.len(), result.recommendation);
-    
-    // 输出每个指标的详细结果
-    for (i, m) in result.metrics.iter().enumerate() {
-        log::debug!("📈 [SpeedTest] 指标[{}] {}: proxy={}ms, direct={}ms, success={}",
-                   i, m.name,
-                   m.proxy_time_ms.map_or("-".to_string(), |v| v.to_string()),
-                   m.direct_time_ms.map_or("-".to_string(), |v| v.to_string()),
-                   m.success);
+    if blocked {
+        return Err("不允许测试本机或链路本地地址".to_string());
     }
-    
-    // 阶段5: 全部完成
-    let final_status = if all_success { SpeedTestStageStatus::Completed } else { SpeedTestStageStatus::Failed };
-    emit_progress(5, "生成报告", 100, final_status, 
-                  Some(&format!("成功率: {}/{}", ok, total)), None);
-    
-    Ok(result)
+    Ok(parsed)
 }
 
 /// 代理设置（用于测速等临时请求）
@@ -1655,10 +4818,21 @@ impl ProxySettings {
 struct UploadBlob {
     path: String,
     content: String,
+    /// 来源文件是否经过有损解码（内容可能有乱码；仅本地标记，不发送给服务端）
+    #[serde(skip_serializing)]
+    lossy: bool,
+}
+
+/// 测速读取的文件内容及解码信息
+struct DecodedFile {
+    content: String,
+    /// 是否无法按任何已知编码无损解码，只能降级为 UTF-8 (lossy)
+    lossy: bool,
 }
 
 /// 读取文件内容，支持多种编码检测（与 acemcp::mcp.rs 保持一致）
-fn read_file_with_encoding_for_speed_test(path: &std::path::Path) -> Result<String, String> {
+/// 带 BOM 的 UTF-16 文件会被 encoding_rs 自动识别；无 BOM 的 UTF-16 或混合编码文件会降级为有损解码
+fn read_file_with_encoding_for_speed_test(path: &std::path::Path) -> Result<DecodedFile, String> {
     use std::fs;
     use std::io::Read;
 
@@ -1671,32 +4845,34 @@ fn read_file_with_encoding_for_speed_test(path: &std::path::Path) -> Result<Stri
     // 尝试 utf-8
     let (decoded, _, had_errors) = UTF_8.decode(&buf);
     if !had_errors {
-        return Ok(decoded.into_owned());
+        return Ok(DecodedFile { content: decoded.into_owned(), lossy: false });
     }
 
     // 尝试 gbk
     let (decoded, _, had_errors) = GBK.decode(&buf);
     if !had_errors {
         log::debug!("测速读取文件：成功使用 GBK 编码: {:?}", path);
-        return Ok(decoded.into_owned());
+        return Ok(DecodedFile { content: decoded.into_owned(), lossy: false });
     }
 
     // 尝试 latin-1 (WINDOWS_1252 是 ISO-8859-1 的超集)
     let (decoded, _, had_errors) = WINDOWS_1252.decode(&buf);
     if !had_errors {
         log::debug!("测速读取文件：成功使用 WINDOWS_1252 编码: {:?}", path);
-        return Ok(decoded.into_owned());
+        return Ok(DecodedFile { content: decoded.into_owned(), lossy: false });
     }
 
     // 降级：utf-8 lossy
     let (decoded, _, _) = UTF_8.decode(&buf);
-    log::debug!("测速读取文件：使用 UTF-8 (lossy)，部分字符可能丢失: {:?}", path);
-    Ok(decoded.into_owned())
+    log::warn!("测速读取文件：使用 UTF-8 (lossy)，部分字符可能丢失: {:?}", path);
+    Ok(DecodedFile { content: decoded.into_owned(), lossy: true })
 }
 
 /// 分割文件内容为多个 blob（如果超过最大行数）
 /// 与 acemcp::mcp.rs 保持一致：chunk 索引从 1 开始
-fn split_content_for_speed_test(path: &str, content: &str, max_lines: usize) -> Vec<UploadBlob> {
+/// 有损解码的文件会在每个 blob 上标记 lossy，便于解释“分块内容不完整”的情况
+fn split_content_for_speed_test(path: &str, file: &DecodedFile, max_lines: usize) -> Vec<UploadBlob> {
+    let content = &file.content;
     let lines: Vec<&str> = content.split_inclusive('\n').collect();
     let total_lines = lines.len();
 
@@ -1704,6 +4880,7 @@ fn split_content_for_speed_test(path: &str, content: &str, max_lines: usize) ->
         return vec![UploadBlob {
             path: path.to_string(),
             content: content.to_string(),
+            lossy: file.lossy,
         }];
     }
 
@@ -1720,6 +4897,7 @@ fn split_content_for_speed_test(path: &str, content: &str, max_lines: usize) ->
         blobs.push(UploadBlob {
             path: chunk_path,
             content: chunk_content,
+            lossy: file.lossy,
         });
     }
 
@@ -1728,11 +4906,21 @@ fn split_content_for_speed_test(path: &str, content: &str, max_lines: usize) ->
 
 /// 构建测速用 HTTP Client（支持代理 + connect_timeout）
 /// 说明：测速过程中会多次请求，如果每次都 build client 会有额外开销
-fn build_speed_test_client(proxy: Option<&ProxySettings>, timeout_secs: u64) -> Result<reqwest::Client, String> {
+fn build_speed_test_client(
+    proxy: Option<&ProxySettings>,
+    timeout_secs: u64,
+    tls: &AcemcpTlsOptions,
+    http: &AcemcpHttpOptions,
+    target_url: &str,
+) -> Result<reqwest::Client, String> {
     let mut client_builder = reqwest::Client::builder()
         .connect_timeout(std::time::Duration::from_millis(crate::constants::network::CONNECTION_TIMEOUT_MS))
         .timeout(std::time::Duration::from_secs(timeout_secs));
 
+    client_builder = tls.apply(client_builder).map_err(|e| e.to_string())?;
+    let plain_http_proxy = super::mcp::is_plain_http_proxy(proxy.map(|p| p.proxy_type.as_str()), target_url);
+    client_builder = http.apply(client_builder, plain_http_proxy);
+
     if let Some(p) = proxy {
         client_builder = client_builder.proxy(p.to_reqwest_proxy()?);
     }
@@ -1755,46 +4943,34 @@ async fn upload_blobs_batch(
     }
 
     let payload = serde_json::json!({ "blobs": blobs });
+    let request_id = new_request_id();
     let start = std::time::Instant::now();
 
-    let resp = client
-        .post(upload_url)
-        .timeout(std::time::Duration::from_secs(timeout_secs))
-        .header(reqwest::header::AUTHORIZATION, format!("Bearer {}", token))
-        .header(reqwest::header::CONTENT_TYPE, "application/json")
-        .json(&payload)
-        .send()
+    let resp = send_with_rate_limit("测速上传", &RateLimitBudget::new(), || {
+        client
+            .post(upload_url)
+            .timeout(std::time::Duration::from_secs(timeout_secs))
+            .header(reqwest::header::AUTHORIZATION, format!("Bearer {}", token))
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(REQUEST_ID_HEADER, &request_id)
+            .json(&payload)
+            .send()
+    })
         .await
-        .map_err(|e| format!("上传请求失败: {}", e))?;
+        .map_err(|e| format!("上传请求失败: {} (request_id={})", e, request_id))?;
 
     let elapsed = start.elapsed().as_millis() as u64;
+    log::debug!("📤 [SpeedTest] 上传批次: blobs={}, status={}, elapsed={}ms, request_id={}", blobs.len(), resp.status(), elapsed, request_id);
 
     if !resp.status().is_success() {
         let status = resp.status();
         let body = resp.text().await.unwrap_or_default();
-        return Err(format!("HTTP {} {}", status, body));
+        return Err(format!("HTTP {} {} (request_id={})", status, body, request_id));
     }
 
     Ok(elapsed)
 }
 
-fn format_bytes(bytes: u64) -> String {
-    const KB: u64 = 1024;
-    const MB: u64 = 1024 * 1024;
-    const GB: u64 = 1024 * 1024 * 1024;
-
-    if bytes >= GB {
-        return format!("{:.2}GB", bytes as f64 / GB as f64);
-    }
-    if bytes >= MB {
-        return format!("{:.2}MB", bytes as f64 / MB as f64);
-    }
-    if bytes >= KB {
-        return format!("{:.2}KB", bytes as f64 / KB as f64);
-    }
-    format!("{}B", bytes)
-}
-
 fn append_error(dst: &mut Option<String>, msg: String) {
     match dst {
         Some(s) => {
@@ -1817,9 +4993,20 @@ struct ProjectUploadResult {
     total_bytes: u64,
     truncated: bool,
     first_error: Option<String>,
+    /// 读取或上传失败的文件明细
+    file_errors: FileErrorList,
+    /// 有损解码的文件数（混合编码/无 BOM 的 UTF-16 等，检索质量可能受影响）
+    lossy_files: usize,
+    /// 来自有损解码文件的 blob 数
+    lossy_blobs: usize,
+    /// 因内容与本次已上传的 blob 相同而跳过的 blob 数
+    dedup_blobs: usize,
+    /// 去重节省的上传字节数
+    dedup_bytes: u64,
 }
 
 /// 项目上传测速：按文件列表读取内容并批量上传 blobs
+/// - 每批最多 `batch_size` 个文件、`max_blobs_per_request` 个 blob，任一达到上限即发送
 /// - `max_files`: Some(n) 表示最多测试 n 个文件（采样），None 表示全量
 async fn upload_project_for_speed_test(
     client: &reqwest::Client,
@@ -1828,7 +5015,8 @@ async fn upload_project_for_speed_test(
     project_root_path: &str,
     project_files_status: &ProjectFilesStatus,
     batch_size: usize,
-    max_lines_per_blob: usize,
+    max_blobs_per_request: usize,
+    max_lines_per_blob: &super::mcp::MaxLinesPolicy,
     max_files: Option<usize>,
 ) -> Result<ProjectUploadResult, String> {
     use std::path::PathBuf;
@@ -1840,16 +5028,25 @@ async fn upload_project_for_speed_test(
     };
 
     let truncated = max_files.is_some() && total_files > files_to_test;
-    let upload_url = format!("{}/batch-upload", base_url);
+    let upload_url = super::mcp::join_ace_url(&base_url, "batch-upload");
 
     let start = std::time::Instant::now();
 
-    let mut batch: Vec<UploadBlob> = Vec::with_capacity(batch_size);
+    let mut pending: Vec<UploadBlob> = Vec::new();
     let mut tested_files = 0usize;
     let mut skipped_files = 0usize;
     let mut blob_count = 0usize;
     let mut total_bytes = 0u64;
     let mut first_error: Option<String> = None;
+    let mut file_errors = FileErrorList::default();
+    let mut uploaded_batches = 0usize;
+    let mut last_upload_error: Option<String> = None;
+    let mut lossy_files = 0usize;
+    let mut lossy_blobs = 0usize;
+    // 本次已上传内容的哈希：大段重复内容切出的相同分块只上传一次
+    let mut seen_contents: std::collections::HashSet<Vec<u8>> = std::collections::HashSet::new();
+    let mut dedup_blobs = 0usize;
+    let mut dedup_bytes = 0u64;
 
     for file in project_files_status.files.iter().take(files_to_test) {
         let abs_path = PathBuf::from(project_root_path).join(&file.path);
@@ -1866,26 +5063,50 @@ async fn upload_project_for_speed_test(
                 if first_error.is_none() {
                     first_error = Some(format!("读取文件失败: path={}, error={}", file.path, e));
                 }
+                file_errors.push(file.path.clone(), format!("读取失败: {}", e));
                 continue;
             }
         };
 
         tested_files += 1;
-        let blobs = split_content_for_speed_test(&file.path, &content, max_lines_per_blob);
+        let blobs = split_content_for_speed_test(&file.path, &content, max_lines_per_blob.for_path(&file.path));
         blob_count += blobs.len();
+        if content.lossy {
+            lossy_files += 1;
+            lossy_blobs += blobs.len();
+        }
+
 
         for b in blobs {
-            batch.push(b);
-            if batch.len() >= batch_size {
-                // 上传一批
-                let _ = upload_blobs_batch(client, &upload_url, token, &batch, 120).await?;
-                batch.clear();
+            let digest = ring::digest::digest(&ring::digest::SHA256, b.content.as_bytes());
+            if !seen_contents.insert(digest.as_ref().to_vec()) {
+                dedup_blobs += 1;
+                dedup_bytes += b.content.len() as u64;
+                log::debug!("♻️ [SpeedTest] 跳过重复内容 blob: path={}", b.path);
+                continue;
             }
+            pending.push(b);
         }
     }
 
-    if !batch.is_empty() {
-        let _ = upload_blobs_batch(client, &upload_url, token, &batch, 120).await?;
+    // 与正式索引使用同一套批次规划（文件数与 blob 数双上限）
+    let batch_ranges = super::mcp::plan_upload_batches(
+        pending.iter().map(|b| super::mcp::blob_file_key(&b.path)),
+        batch_size,
+        max_blobs_per_request,
+    );
+    for range in batch_ranges {
+        upload_speed_test_batch(
+            client, &upload_url, token, &pending[range],
+            &mut uploaded_batches, &mut last_upload_error, &mut file_errors,
+        ).await;
+    }
+
+    // 所有批次均失败时视为整体失败；部分失败时在 file_errors 中列出受影响的文件
+    if uploaded_batches == 0 {
+        if let Some(e) = last_upload_error {
+            return Err(e);
+        }
     }
 
     let elapsed_ms = start.elapsed().as_millis() as u64;
@@ -1900,13 +5121,43 @@ async fn upload_project_for_speed_test(
         total_bytes,
         truncated,
         first_error,
+        file_errors,
+        lossy_files,
+        lossy_blobs,
+        dedup_blobs,
+        dedup_bytes,
     })
 }
 
+/// 上传测速的一个批次；失败时将批次内涉及的文件逐个记入 file_errors，并继续后续批次
+async fn upload_speed_test_batch(
+    client: &reqwest::Client,
+    upload_url: &str,
+    token: &str,
+    batch: &[UploadBlob],
+    uploaded_batches: &mut usize,
+    last_error: &mut Option<String>,
+    file_errors: &mut FileErrorList,
+) {
+    match upload_blobs_batch(client, upload_url, token, batch, 120).await {
+        Ok(_) => *uploaded_batches += 1,
+        Err(e) => {
+            let mut seen = std::collections::HashSet::new();
+            for b in batch {
+                let path = super::mcp::blob_file_key(&b.path);
+                if seen.insert(path) {
+                    file_errors.push(path, format!("上传失败: {}", e));
+                }
+            }
+            *last_error = Some(e);
+        }
+    }
+}
+
 fn build_single_file_blobs_for_speed_test(
     project_root_path: &str,
     rel_path: &str,
-    max_lines_per_blob: usize,
+    max_lines_per_blob: &super::mcp::MaxLinesPolicy,
 ) -> Result<(Vec<UploadBlob>, u64), String> {
     use std::path::PathBuf;
 
@@ -1916,31 +5167,105 @@ fn build_single_file_blobs_for_speed_test(
     let content = read_file_with_encoding_for_speed_test(&abs_path)
         .map_err(|e| format!("读取文件失败: path={}, error={}", rel_path, e))?;
 
-    let blobs = split_content_for_speed_test(rel_path, &content, max_lines_per_blob);
+    let blobs = split_content_for_speed_test(rel_path, &content, max_lines_per_blob.for_path(rel_path));
     Ok((blobs, file_bytes))
 }
 
+/// 顺序模式的 Ping 轮数
+const PING_SEQUENTIAL_ROUNDS: usize = 3;
+/// 并发模式的 Ping 轮数（样本更多，P95 更有意义）
+const PING_CONCURRENT_ROUNDS: usize = 8;
+/// 并发模式下每轮发起前的最大随机抖动（毫秒）
+const PING_JITTER_MAX_MS: u64 = 50;
+
+/// 执行多轮 Ping，返回 (成功耗时列表, 最后一次错误, 总轮数)
+/// 并发模式：先发一次预热请求建立 keep-alive 连接（不计入统计），
+/// 再以随机抖动错开各轮发起时间并发执行，既避免串行累加，又尽量复用已建立的连接
+async fn run_ping_rounds(
+    client: &reqwest::Client,
+    url: &str,
+    token: &str,
+    concurrent: bool,
+) -> (Vec<u64>, Option<String>, usize) {
+    let mut ok: Vec<u64> = Vec::new();
+    let mut last_err: Option<String> = None;
+
+    if !concurrent {
+        for _ in 0..PING_SEQUENTIAL_ROUNDS {
+            match ping_endpoint(client, url, token).await {
+                Ok(ms) => ok.push(ms),
+                Err(e) => last_err = Some(e),
+            }
+        }
+        return (ok, last_err, PING_SEQUENTIAL_ROUNDS);
+    }
+
+    if let Err(e) = ping_endpoint(client, url, token).await {
+        log::debug!("🔗 [Ping] 预热请求失败（继续并发测试）: {}", e);
+    }
+
+    let mut join_set = tokio::task::JoinSet::new();
+    for _ in 0..PING_CONCURRENT_ROUNDS {
+        let client = client.clone();
+        let url = url.to_string();
+        let token = token.to_string();
+        let jitter_ms = fastrand::u64(0..=PING_JITTER_MAX_MS);
+        join_set.spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(jitter_ms)).await;
+            ping_endpoint(&client, &url, &token).await
+        });
+    }
+
+    while let Some(res) = join_set.join_next().await {
+        match res {
+            Ok(Ok(ms)) => ok.push(ms),
+            Ok(Err(e)) => last_err = Some(e),
+            Err(e) => last_err = Some(format!("Ping 任务异常: {}", e)),
+        }
+    }
+
+    (ok, last_err, PING_CONCURRENT_ROUNDS)
+}
+
+/// 计算延迟分布（min/avg/P95，P95 采用 nearest-rank）
+fn latency_stats(samples: &[u64]) -> Option<LatencyStats> {
+    if samples.is_empty() {
+        return None;
+    }
+    let mut sorted = samples.to_vec();
+    sorted.sort_unstable();
+    let rank = ((sorted.len() as f64) * 0.95).ceil() as usize;
+    Some(LatencyStats {
+        min_ms: sorted[0],
+        avg_ms: sorted.iter().sum::<u64>() / sorted.len() as u64,
+        p95_ms: sorted[rank.saturating_sub(1).min(sorted.len() - 1)],
+        samples: sorted.len(),
+    })
+}
+
 /// Ping 测试辅助函数
 /// 注意：使用 GET 方法而非 HEAD，因为部分 ACE 服务器的 /health 端点不支持 HEAD 方法（返回 405）
 async fn ping_endpoint(client: &reqwest::Client, url: &str, token: &str) -> Result<u64, String> {
-    log::debug!("🔗 [Ping] 开始请求: url={}", url);
+    let request_id = new_request_id();
+    log::debug!("🔗 [Ping] 开始请求: url={}, request_id={}", url, request_id);
     
     let start = std::time::Instant::now();
     let response = client
         .get(url)  // 使用 GET 方法代替 HEAD，解决 HTTP 405 Method Not Allowed 问题
         .timeout(std::time::Duration::from_secs(10))
         .header(reqwest::header::AUTHORIZATION, format!("Bearer {}", token))
+        .header(REQUEST_ID_HEADER, &request_id)
         .send()
         .await
         .map_err(|e| {
-            log::warn!("❌ [Ping] 请求失败: url={}, error={}", url, e);
+            log::warn!("❌ [Ping] 请求失败: url={}, request_id={}, error={}", url, request_id, e);
             format!("请求失败: {}", e)
         })?;
     
     let elapsed = start.elapsed().as_millis() as u64;
     let status = response.status();
     
-    log::debug!("✅ [Ping] 响应: url={}, status={}, elapsed={}ms", url, status, elapsed);
+    log::debug!("✅ [Ping] 响应: url={}, status={}, elapsed={}ms, request_id={}", url, status, elapsed, request_id);
     
     if status.is_success() || status.as_u16() == 404 {
         // 404 也算成功，因为只是测试连通性
@@ -1952,6 +5277,83 @@ async fn ping_endpoint(client: &reqwest::Client, url: &str, token: &str) -> Resu
     }
 }
 
+/// 测量 DNS 解析与 TCP 建连耗时（阻塞调用，需在阻塞线程池中执行）
+fn measure_dns_and_connect(host: &str, port: u16) -> Result<(u64, u64), String> {
+    use std::net::ToSocketAddrs;
+
+    let start = std::time::Instant::now();
+    let addr = (host, port)
+        .to_socket_addrs()
+        .map_err(|e| format!("DNS 解析失败: {}: {}", host, e))?
+        .next()
+        .ok_or_else(|| format!("DNS 解析无结果: {}", host))?;
+    let dns_ms = start.elapsed().as_millis() as u64;
+
+    let start = std::time::Instant::now();
+    std::net::TcpStream::connect_timeout(
+        &addr,
+        std::time::Duration::from_millis(crate::constants::network::CONNECTION_TIMEOUT_MS),
+    )
+    .map_err(|e| format!("TCP 连接失败: {}: {}", addr, e))?;
+    let connect_ms = start.elapsed().as_millis() as u64;
+
+    Ok((dns_ms, connect_ms))
+}
+
+/// 详细 Ping：分阶段测量 DNS / TCP / TLS / 首字节耗时
+/// reqwest 不暴露连接阶段计时，这里手动测量 DNS 与 TCP，再用新建客户端的首次请求（冷）
+/// 与复用连接的第二次请求（热）之差估算 TLS 握手；经代理时 DNS/TCP 只能测到代理服务器
+async fn detailed_ping(
+    proxy: Option<&ProxySettings>,
+    tls: &AcemcpTlsOptions,
+    http: &AcemcpHttpOptions,
+    base_url: &str,
+    health_url: &str,
+    token: &str,
+) -> Result<PingPhaseTimings, String> {
+    let target = reqwest::Url::parse(health_url).map_err(|e| format!("无效的 URL: {}", e))?;
+    let is_https = target.scheme() == "https";
+    let (host, port) = match proxy {
+        Some(p) => (p.host.trim().to_string(), p.port),
+        None => (
+            target.host_str().ok_or("URL 缺少主机名")?.trim_matches(|c| c == '[' || c == ']').to_string(),
+            target.port_or_known_default().ok_or("无法确定端口")?,
+        ),
+    };
+
+    let (dns_ms, connect_ms) = tokio::task::spawn_blocking(move || measure_dns_and_connect(&host, port))
+        .await
+        .map_err(|e| format!("连接测量任务异常: {}", e))??;
+
+    // 新客户端保证首次请求需要重新建连与握手
+    let client = build_speed_test_client(proxy, 30, tls, http, base_url)?;
+    let cold_ms = ping_endpoint(&client, health_url, token).await?;
+    let warm_ms = ping_endpoint(&client, health_url, token).await?;
+
+    let tls_ms = is_https.then(|| cold_ms.saturating_sub(warm_ms + dns_ms + connect_ms));
+    let note = match (proxy.is_some(), is_https) {
+        (true, true) => Some("经代理：DNS/TCP 为到代理服务器的耗时，TLS 为估算值且包含 CONNECT 隧道建立".to_string()),
+        (true, false) => Some("经代理：DNS/TCP 为到代理服务器的耗时".to_string()),
+        (false, true) => Some("TLS 为估算值（首次请求 - 复用连接请求 - DNS - TCP）".to_string()),
+        (false, false) => None,
+    };
+
+    log::info!(
+        "📡 [SpeedTest] 详细 Ping: via_proxy={}, dns={}ms, connect={}ms, tls={:?}ms, ttfb={}ms, total={}ms",
+        proxy.is_some(), dns_ms, connect_ms, tls_ms, warm_ms, cold_ms
+    );
+
+    Ok(PingPhaseTimings {
+        dns_ms: Some(dns_ms),
+        connect_ms: Some(connect_ms),
+        tls_ms,
+        ttfb_ms: Some(warm_ms),
+        total_ms: cold_ms,
+        via_proxy: proxy.is_some(),
+        note,
+    })
+}
+
 /// 搜索测试结果（包含耗时和结果预览）
 struct SearchEndpointResult {
     elapsed_ms: u64,
@@ -1961,21 +5363,26 @@ struct SearchEndpointResult {
 /// 搜索测试辅助函数
 /// 返回耗时和搜索结果预览（用于前端展示）
 async fn search_endpoint(client: &reqwest::Client, url: &str, token: &str, payload: &serde_json::Value) -> Result<SearchEndpointResult, String> {
+    let request_id = new_request_id();
     let start = std::time::Instant::now();
-    let response = client
-        .post(url)
-        .timeout(std::time::Duration::from_secs(30))
-        .header(reqwest::header::AUTHORIZATION, format!("Bearer {}", token))
-        .header(reqwest::header::CONTENT_TYPE, "application/json")
-        .json(payload)
-        .send()
+    let response = send_with_rate_limit("测速检索", &RateLimitBudget::new(), || {
+        client
+            .post(url)
+            .timeout(std::time::Duration::from_secs(30))
+            .header(reqwest::header::AUTHORIZATION, format!("Bearer {}", token))
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(REQUEST_ID_HEADER, &request_id)
+            .json(payload)
+            .send()
+    })
         .await
-        .map_err(|e| format!("请求失败: {}", e))?;
+        .map_err(|e| format!("请求失败: {} (request_id={})", e, request_id))?;
     
     let elapsed = start.elapsed().as_millis() as u64;
+    log::debug!("🔍 [SpeedTest] 检索响应: status={}, elapsed={}ms, request_id={}", response.status(), elapsed, request_id);
     
     if !response.status().is_success() {
-        return Err(format!("HTTP {}", response.status()));
+        return Err(format!("HTTP {} (request_id={})", response.status(), request_id));
     }
     
     // 解析响应内容，提取搜索结果预览
@@ -1997,6 +5404,132 @@ async fn search_endpoint(client: &reqwest::Client, url: &str, token: &str, paylo
     })
 }
 
+/// 以流式方式请求检索端点，测量首字节耗时与持续吞吐
+/// 服务端返回普通（非分块/非流式）响应时返回 Ok(None)，由调用方跳过该指标
+async fn stream_probe(
+    client: &reqwest::Client,
+    url: &str,
+    token: &str,
+    payload: &serde_json::Value,
+) -> Result<Option<StreamProbeStats>, String> {
+    let request_id = new_request_id();
+    let start = std::time::Instant::now();
+    let mut response = send_with_rate_limit("测速流式检索", &RateLimitBudget::new(), || {
+        client
+            .post(url)
+            .timeout(std::time::Duration::from_secs(60))
+            .header(reqwest::header::AUTHORIZATION, format!("Bearer {}", token))
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(reqwest::header::ACCEPT, "text/event-stream, application/x-ndjson, application/json")
+            .header(REQUEST_ID_HEADER, &request_id)
+            .json(payload)
+            .send()
+    })
+        .await
+        .map_err(|e| format!("请求失败: {} (request_id={})", e, request_id))?;
+    log::debug!("🔍 [SpeedTest] 流式检索响应: status={}, request_id={}", response.status(), request_id);
+
+    if !response.status().is_success() {
+        return Err(format!("HTTP {} (request_id={})", response.status(), request_id));
+    }
+
+    let header = |name: reqwest::header::HeaderName| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("")
+            .to_ascii_lowercase()
+    };
+    let content_type = header(reqwest::header::CONTENT_TYPE);
+    let transfer_encoding = header(reqwest::header::TRANSFER_ENCODING);
+    let streamed = content_type.contains("text/event-stream")
+        || content_type.contains("ndjson")
+        || transfer_encoding.contains("chunked");
+    if !streamed {
+        return Ok(None);
+    }
+
+    let mut first_byte_ms: Option<u64> = None;
+    let mut bytes = 0u64;
+    let mut chunk_count = 0usize;
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| format!("接收流式响应失败: {}", e))?
+    {
+        if first_byte_ms.is_none() {
+            first_byte_ms = Some(start.elapsed().as_millis() as u64);
+        }
+        bytes += chunk.len() as u64;
+        chunk_count += 1;
+    }
+    let total_ms = start.elapsed().as_millis() as u64;
+    let first_byte_ms = first_byte_ms.unwrap_or(total_ms);
+
+    // 吞吐只统计首字节之后的传输阶段，排除服务端处理耗时
+    let transfer_ms = total_ms.saturating_sub(first_byte_ms).max(1);
+    let throughput_kbps = (bytes as f64 / 1024.0) / (transfer_ms as f64 / 1000.0);
+
+    Ok(Some(StreamProbeStats {
+        first_byte_ms,
+        total_ms,
+        bytes,
+        chunk_count,
+        throughput_kbps,
+    }))
+}
+
+/// 将 ACE 的 formatted_retrieval 文本拆分为代码块（按结果顺序）
+fn split_retrieval_blocks(formatted: &str) -> Vec<&str> {
+    // 跳过 ACE 标题行（如 "The following code sections were retrieved:"）
+    let content = formatted
+        .strip_prefix("The following code sections were retrieved:")
+        .unwrap_or(formatted)
+        .trim();
+
+    log::debug!("🔍 处理后内容长度={}", content.len());
+
+    // ACE 格式通常是按 "---" 分隔的多个代码块
+    // 每个块包含文件路径和代码内容
+    let blocks: Vec<&str> = content
+        .split("\n---\n")
+        .filter(|b| !b.trim().is_empty() && b.len() > 10)
+        .collect();
+
+    // 如果没有 --- 分隔，尝试按双空行分隔
+    if blocks.len() <= 1 {
+        content
+            .split("\n\n")
+            .filter(|b| !b.trim().is_empty() && b.len() > 10)
+            .collect()
+    } else {
+        blocks
+    }
+}
+
+/// 尝试从代码块第一行提取文件路径
+/// ACE 格式可能是 "Path: xxx" 或 "File: xxx" 或直接是路径
+fn block_file_path(block: &str) -> Option<String> {
+    let first_line = block.lines().next().unwrap_or("");
+    first_line
+        .strip_prefix("Path: ")
+        .or_else(|| first_line.strip_prefix("File: "))
+        .or_else(|| first_line.strip_prefix("# "))
+        .or_else(|| first_line.strip_prefix("## "))
+        .or_else(|| {
+            // 如果第一行看起来是文件路径（包含 / 或 \ 或常见扩展名）
+            if first_line.contains('/') || first_line.contains('\\')
+               || first_line.ends_with(".rs") || first_line.ends_with(".ts")
+               || first_line.ends_with(".vue") || first_line.ends_with(".py") {
+                Some(first_line)
+            } else {
+                None
+            }
+        })
+        .map(|p| p.trim().to_string())
+}
+
 /// 解析搜索结果响应，提取预览片段
 fn parse_search_result_preview(body: &str) -> Option<super::types::SearchResultPreview> {
     use super::types::{SearchResultPreview, SearchResultSnippet};
@@ -2033,31 +5566,7 @@ fn parse_search_result_preview(body: &str) -> Option<super::types::SearchResultP
         log::debug!("🔍 [SpeedTest] 发现 formatted_retrieval 字段, 长度={}", formatted.len());
         
         if !formatted.is_empty() && formatted != "No relevant code context found for your query." {
-            // 跳过 ACE 标题行（如 "The following code sections were retrieved:"）
-            let content = formatted
-                .strip_prefix("The following code sections were retrieved:")
-                .or_else(|| formatted.strip_prefix("The following code sections were retrieved:\n"))
-                .unwrap_or(formatted)
-                .trim();
-            
-            log::debug!("🔍 [SpeedTest] 处理后内容长度={}", content.len());
-            
-            // ACE 格式通常是按 "---" 分隔的多个代码块
-            // 每个块包含文件路径和代码内容
-            let blocks: Vec<&str> = content
-                .split("\n---\n")
-                .filter(|b| !b.trim().is_empty() && b.len() > 10)
-                .collect();
-            
-            // 如果没有 --- 分隔，尝试按双空行分隔
-            let blocks = if blocks.len() <= 1 {
-                content
-                    .split("\n\n")
-                    .filter(|b| !b.trim().is_empty() && b.len() > 10)
-                    .collect()
-            } else {
-                blocks
-            };
+            let blocks = split_retrieval_blocks(formatted);
             
             total_matches = blocks.len().max(1); // 至少有一个匹配
             log::debug!("🔍 [SpeedTest] 分割出 {} 个代码块", blocks.len());
@@ -2068,27 +5577,7 @@ fn parse_search_result_preview(body: &str) -> Option<super::types::SearchResultP
                     continue;
                 }
                 
-                // 尝试从第一行提取文件路径
-                // ACE 格式可能是 "Path: xxx" 或 "File: xxx" 或直接是路径
-                let first_line = lines.first().unwrap_or(&"");
-                let file_path = first_line
-                    .strip_prefix("Path: ")
-                    .or_else(|| first_line.strip_prefix("File: "))
-                    .or_else(|| first_line.strip_prefix("# "))
-                    .or_else(|| first_line.strip_prefix("## "))
-                    .or_else(|| {
-                        // 如果第一行看起来是文件路径（包含 / 或 \ 或常见扩展名）
-                        if first_line.contains('/') || first_line.contains('\\') 
-                           || first_line.ends_with(".rs") || first_line.ends_with(".ts")
-                           || first_line.ends_with(".vue") || first_line.ends_with(".py") {
-                            Some(*first_line)
-                        } else {
-                            None
-                        }
-                    })
-                    .unwrap_or("代码片段")
-                    .trim()
-                    .to_string();
+                let file_path = block_file_path(block).unwrap_or_else(|| "代码片段".to_string());
                 
                 // 提取代码片段（去除路径行，取前20行）
                 let snippet: String = lines.iter()
@@ -2250,6 +5739,102 @@ fn parse_search_result_preview(body: &str) -> Option<super::types::SearchResultP
     })
 }
 
+/// 默认参与趋势统计的最近测速次数
+const PROXY_TREND_DEFAULT_RUNS: usize = 5;
+/// 平均差异低于该百分比时视为代理与直连相当
+const PROXY_TREND_NEUTRAL_PERCENT: f64 = 5.0;
+
+/// 保存一次对比测速的汇总（只统计代理与直连都成功的指标），失败仅记录日志
+fn record_speed_test_history(metrics: &[SpeedTestMetric], proxy: Option<&DetectedProxy>) {
+    let compared: Vec<(u64, u64)> = metrics
+        .iter()
+        .filter_map(|m| Some((m.proxy_time_ms?, m.direct_time_ms?)))
+        .collect();
+    if compared.is_empty() {
+        return;
+    }
+
+    let entry = SpeedTestHistoryEntry {
+        tested_at: chrono::Utc::now(),
+        proxy: proxy.map(|p| format!("{}://{}:{}", p.proxy_type, p.host, p.port)),
+        proxy_total_ms: compared.iter().map(|(p, _)| p).sum(),
+        direct_total_ms: compared.iter().map(|(_, d)| d).sum(),
+        compared_metrics: compared.len(),
+    };
+    if let Err(e) = super::history::record_speed_test(entry) {
+        log::warn!("保存测速历史失败: {}", e);
+    }
+}
+
+/// 根据最近多次对比测速给出代理推荐趋势，避免单次测速的波动影响判断
+#[tauri::command]
+pub fn get_proxy_recommendation_trend(limit: Option<usize>) -> ProxyRecommendationTrend {
+    let runs = limit.unwrap_or(PROXY_TREND_DEFAULT_RUNS).max(1);
+    let entries: Vec<SpeedTestHistoryEntry> = super::history::load_speed_tests()
+        .into_iter()
+        .take(runs)
+        .collect();
+    compute_proxy_trend(&entries)
+}
+
+fn compute_proxy_trend(entries: &[SpeedTestHistoryEntry]) -> ProxyRecommendationTrend {
+    // 每次测速的提速百分比：(直连 - 代理) / 直连
+    let changes: Vec<f64> = entries
+        .iter()
+        .filter(|e| e.direct_total_ms > 0)
+        .map(|e| (e.direct_total_ms as f64 - e.proxy_total_ms as f64) / e.direct_total_ms as f64 * 100.0)
+        .collect();
+    let samples = changes.len();
+    if samples == 0 {
+        return ProxyRecommendationTrend {
+            samples: 0,
+            proxy_faster_runs: 0,
+            direct_faster_runs: 0,
+            avg_change_percent: 0.0,
+            consistent: false,
+            confidence: "low".to_string(),
+            summary: "暂无对比测速记录，请先以对比模式运行测速".to_string(),
+        };
+    }
+
+    let proxy_faster_runs = changes.iter().filter(|c| **c > 0.0).count();
+    let direct_faster_runs = changes.iter().filter(|c| **c < 0.0).count();
+    let avg = changes.iter().sum::<f64>() / samples as f64;
+    let avg_change_percent = (avg * 10.0).round() / 10.0;
+    let consistent = proxy_faster_runs == samples || direct_faster_runs == samples;
+
+    let confidence = if samples < 3 {
+        "low"
+    } else if samples >= PROXY_TREND_DEFAULT_RUNS && consistent {
+        "high"
+    } else {
+        "medium"
+    };
+
+    let mut summary = if avg.abs() < PROXY_TREND_NEUTRAL_PERCENT {
+        format!("最近{}次: 代理与直连相当", samples)
+    } else if avg > 0.0 {
+        format!("最近{}次: 代理平均快 {:.0}%", samples, avg)
+    } else {
+        format!("最近{}次: 代理平均慢 {:.0}%", samples, -avg)
+    };
+    if confidence == "low" {
+        summary.push_str("（样本较少，仅供参考）");
+    } else if !consistent {
+        summary.push_str("（各次结果不一致）");
+    }
+
+    ProxyRecommendationTrend {
+        samples,
+        proxy_faster_runs,
+        direct_faster_runs,
+        avg_change_percent,
+        consistent,
+        confidence: confidence.to_string(),
+        summary,
+    }
+}
+
 /// 生成推荐建议
 fn generate_recommendation(metrics: &[SpeedTestMetric], mode: &str) -> String {
     if mode != "compare" {
@@ -2289,3 +5874,257 @@ fn generate_recommendation(metrics: &[SpeedTestMetric], mode: &str) -> String {
         "🟡 代理与直连性能相当".to_string()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::types::FileIndexStatus;
+
+    #[test]
+    fn test_summarize_index_status() {
+        let now = chrono::Utc::now();
+        let mut all_status = ProjectsIndexStatus::default();
+        for (root, status, files, failed_at, error) in [
+            ("/a", IndexStatus::Synced, 10, None, None),
+            ("/b", IndexStatus::Failed, 5, Some(now - chrono::Duration::hours(1)), Some("旧错误")),
+            ("/c", IndexStatus::Failed, 7, Some(now), Some("新错误")),
+        ] {
+            let project = ProjectIndexStatus {
+                project_root: root.to_string(),
+                status,
+                total_files: files,
+                last_failure_time: failed_at,
+                last_error: error.map(|e| e.to_string()),
+                ..Default::default()
+            };
+            all_status.projects.insert(root.to_string(), project);
+        }
+
+        let summary = summarize_index_status(&all_status);
+        assert_eq!(summary.project_count, 3);
+        assert_eq!(summary.counts.synced, 1);
+        assert_eq!(summary.counts.failed, 2);
+        assert_eq!(summary.total_files, 22);
+        assert_eq!(summary.last_failure_project.as_deref(), Some("/c"));
+        assert_eq!(summary.last_failure_error.as_deref(), Some("新错误"));
+    }
+
+    #[test]
+    fn test_build_directory_stats() {
+        let file = |path: &str, status| FileIndexStatus {
+            path: path.to_string(),
+            status,
+            included_by: Default::default(),
+            last_indexed: None,
+        };
+        let files_status = ProjectFilesStatus {
+            project_root: "/repo".to_string(),
+            files: vec![
+                file("README.md", FileIndexStatusKind::Indexed),
+                file("src/main.rs", FileIndexStatusKind::Indexed),
+                file("src\\ui\\app.rs", FileIndexStatusKind::Pending),
+                file("src/ui/bad.rs", FileIndexStatusKind::Pending),
+            ],
+        };
+        let failed: std::collections::HashSet<String> = ["src/ui/bad.rs".to_string()].into_iter().collect();
+
+        let tree = build_directory_stats(&files_status, &failed);
+        assert_eq!((tree.indexed, tree.pending, tree.failed), (2, 1, 1));
+        assert_eq!(tree.children.len(), 1);
+        let src = &tree.children[0];
+        assert_eq!((src.path.as_str(), src.indexed, src.pending, src.failed), ("src", 1, 1, 1));
+        let ui = &src.children[0];
+        assert_eq!((ui.name.as_str(), ui.path.as_str()), ("ui", "src/ui"));
+        assert_eq!((ui.indexed, ui.pending, ui.failed), (0, 1, 1));
+    }
+
+    #[test]
+    fn test_compute_index_coverage() {
+        let root = std::env::temp_dir().join(format!("sanshu-coverage-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).unwrap();
+        for name in ["a.rs", "b.rs", "c.rs"] {
+            std::fs::write(root.join(name), format!("fn {}() {{}}\n", &name[..1])).unwrap();
+        }
+
+        // 服务端记录中没有任何当前内容的 blob：三个文件均为待索引
+        let files = super::super::mcp::collect_file_statuses(
+            &root.to_string_lossy(),
+            &[".rs".to_string()],
+            &[],
+            &super::super::mcp::MaxLinesPolicy::new(800, None),
+            false,
+            &std::collections::HashMap::new(),
+            None,
+        )
+        .unwrap();
+        let files_status = ProjectFilesStatus { project_root: "/repo".to_string(), files };
+
+        // a.rs / b.rs 以前上传过（已修改），c.rs 从未上传（新增）
+        let indexed_paths: std::collections::HashSet<String> =
+            ["a.rs".to_string(), "b.rs".to_string()].into_iter().collect();
+        let coverage = compute_index_coverage(&files_status, &indexed_paths);
+        assert_eq!(coverage.total_files, 3);
+        assert_eq!(coverage.indexed_files, 0);
+        assert_eq!(coverage.pending_files, 3);
+        assert_eq!(coverage.new_files, 1);
+        assert_eq!(coverage.coverage_percent, 0.0);
+
+        let coverage = compute_index_coverage(&files_status, &Default::default());
+        assert_eq!(coverage.new_files, 3);
+        assert_eq!(compute_index_coverage(&ProjectFilesStatus::default(), &indexed_paths).coverage_percent, 0.0);
+
+        let mut partial = files_status.clone();
+        partial.files[0].status = FileIndexStatusKind::Indexed;
+        partial.files[1].status = FileIndexStatusKind::Indexed;
+        let coverage = compute_index_coverage(&partial, &indexed_paths);
+        assert_eq!((coverage.indexed_files, coverage.pending_files), (2, 1));
+        assert_eq!(coverage.coverage_percent, 66.7);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_validate_connectivity_target() {
+        assert!(validate_connectivity_target("https://www.google.com/generate_204").is_ok());
+        assert!(validate_connectivity_target("http://1.1.1.1").is_ok());
+        for url in [
+            "file:///etc/passwd",
+            "ftp://example.com",
+            "http://localhost:8080",
+            "http://api.localhost",
+            "http://127.0.0.1:7890",
+            "http://[::1]/",
+            "http://0.0.0.0",
+            "http://169.254.169.254/latest/meta-data",
+            "not a url",
+        ] {
+            assert!(validate_connectivity_target(url).is_err(), "{}", url);
+        }
+    }
+
+    #[test]
+    fn test_compute_proxy_trend() {
+        let entry = |proxy_ms: u64, direct_ms: u64| SpeedTestHistoryEntry {
+            tested_at: chrono::Utc::now(),
+            proxy: None,
+            proxy_total_ms: proxy_ms,
+            direct_total_ms: direct_ms,
+            compared_metrics: 3,
+        };
+
+        let trend = compute_proxy_trend(&[entry(80, 100), entry(70, 100), entry(80, 100), entry(78, 100), entry(77, 100)]);
+        assert_eq!(trend.samples, 5);
+        assert!(trend.consistent);
+        assert_eq!(trend.confidence, "high");
+        assert_eq!(trend.avg_change_percent, 23.0);
+        assert_eq!(trend.summary, "最近5次: 代理平均快 23%");
+
+        let trend = compute_proxy_trend(&[entry(150, 100), entry(90, 100)]);
+        assert_eq!(trend.confidence, "low");
+        assert!(!trend.consistent);
+        assert!(trend.summary.starts_with("最近2次: 代理平均慢 20%"));
+
+        assert_eq!(compute_proxy_trend(&[]).samples, 0);
+    }
+
+    #[tokio::test]
+    async fn test_retry_uses_injected_config() {
+        let mut mcp_config = crate::config::default_mcp_config();
+        mcp_config.acemcp_base_url = Some("ftp://ace.example.com".to_string());
+        let args = TestAcemcpArgs {
+            base_url: "https://ignored.example.com".to_string(),
+            token: "test-token".to_string(),
+            project_root_path: None,
+        };
+
+        // 已保存配置优先于测试参数
+        let conn_config = EffectiveAcemcpConnConfig::resolve(&args, &mcp_config);
+        assert_eq!(conn_config.base_url, "ftp://ace.example.com");
+        assert_eq!(conn_config.token, "test-token");
+
+        // 注入配置即可测试连接逻辑，URL 校验失败时不访问网络
+        let result = run_connection_test_with(&conn_config).await.unwrap();
+        assert!(!result.success);
+        assert!(result.message.contains("http://"));
+    }
+
+    #[test]
+    fn test_find_expected_path() {
+        let text = "The following code sections were retrieved:\nPath: src/a.rs\nfn a() {}\n---\nPath: src\\rust\\Config.rs\nfn b() {}";
+        let blocks = split_retrieval_blocks(text);
+        assert_eq!(blocks.len(), 2);
+        let (rank, path) = find_expected_path(&blocks, "rust/config.rs").unwrap();
+        assert_eq!(rank, 2);
+        assert_eq!(path, "src\\rust\\Config.rs");
+        assert!(find_expected_path(&blocks, "missing.rs").is_none());
+    }
+
+    #[test]
+    fn test_effective_config_is_redacted() {
+        let config = EffectiveAcemcpConnConfig {
+            base_url: "https://ace.example.com".to_string(),
+            token: "ace-secret-token".to_string(),
+            proxy_enabled: true,
+            proxy_host: "127.0.0.1".to_string(),
+            proxy_port: 7890,
+            proxy_type: "http".to_string(),
+            proxy_username: Some("dev".to_string()),
+            proxy_password: Some("proxy-secret".to_string()),
+            client_cert_path: None,
+            client_key_path: None,
+            extra_ca_cert_path: None,
+            accept_invalid_certs: false,
+            user_agent_suffix: None,
+        };
+        let json = serde_json::to_string(&config.redacted()).unwrap();
+        assert!(!json.contains("ace-secret-token"));
+        assert!(!json.contains("proxy-secret"));
+        assert!(json.contains(crate::utils::logger::REDACTED));
+
+        let mut by_reference = config.clone();
+        by_reference.token = "env:ACE_TOKEN".to_string();
+        assert_eq!(by_reference.redacted().token, "env:ACE_TOKEN");
+
+        // 连接测试缓存只对同一份配置有效
+        let result = TestConnectionResult { success: true, message: "ok".to_string(), effective_config: None };
+        remember_connection_test(&config, &result);
+        assert!(cached_connection_test(&config).is_some());
+        let mut other_proxy = config.clone();
+        other_proxy.proxy_port = 7891;
+        assert!(cached_connection_test(&other_proxy).is_none());
+        assert!(cached_connection_test(&by_reference).is_none());
+    }
+
+    #[test]
+    fn test_merge_imported_mcp_config() {
+        let mut current = crate::config::default_mcp_config();
+        current.acemcp_token = Some("secret".to_string());
+        current.acemcp_batch_size = Some(20);
+
+        let imported = serde_json::json!({
+            "mcp_config": {
+                "acemcp_token": "******",
+                "acemcp_batch_size": 5,
+                "acemcp_proxy_type": "socks5",
+                "acemcp_proxy_port": 1080,
+                "unknown_field": 1
+            }
+        });
+        let (mcp, result) = merge_imported_mcp_config(&current, imported.clone(), false).unwrap();
+        assert_eq!(mcp.acemcp_token.as_deref(), Some("secret"));
+        assert_eq!(mcp.acemcp_batch_size, Some(20));
+        assert_eq!(mcp.acemcp_proxy_port, Some(1080));
+        assert_eq!(result.kept, vec!["acemcp_batch_size".to_string()]);
+        assert!(result.ignored.contains(&"unknown_field".to_string()));
+
+        let (mcp, _) = merge_imported_mcp_config(&current, imported, true).unwrap();
+        assert_eq!(mcp.acemcp_batch_size, Some(5));
+        assert_eq!(mcp.acemcp_token.as_deref(), Some("secret"));
+
+        let bad_type = serde_json::json!({ "acemcp_proxy_type": "ftp" });
+        assert!(merge_imported_mcp_config(&current, bad_type, true).is_err());
+        let bad_port = serde_json::json!({ "acemcp_proxy_port": 70000 });
+        assert!(merge_imported_mcp_config(&current, bad_port, true).is_err());
+    }
+}
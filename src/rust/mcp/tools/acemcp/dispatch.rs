@@ -0,0 +1,218 @@
+// Acemcp 工具分发
+// `execute_acemcp_tool` 通过注册表查找工具：每个工具声明名称、说明、参数列表与处理函数，
+// 新增工具只需在 TOOLS 中登记，无需修改分发逻辑
+use std::future::Future;
+use std::pin::Pin;
+
+use super::types::{AcemcpRequest, AcemcpToolResponse};
+use super::AcemcpTool;
+
+/// 工具处理函数返回的 future（参数已通过校验）
+type ToolFuture = Pin<Box<dyn Future<Output = Result<serde_json::Value, String>> + Send>>;
+
+/// 参数取值约束
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ArgKind {
+    /// 非空字符串
+    Text,
+    /// 已存在的目录路径
+    ProjectDir,
+}
+
+/// 工具参数声明
+pub(crate) struct ToolArg {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub required: bool,
+    pub kind: ArgKind,
+}
+
+/// 已注册的工具
+pub(crate) struct AcemcpToolSpec {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub args: &'static [ToolArg],
+    handler: fn(serde_json::Value) -> ToolFuture,
+}
+
+const PROJECT_ROOT_ARG: ToolArg = ToolArg {
+    name: "project_root_path",
+    description: "项目根目录的绝对路径",
+    required: true,
+    kind: ArgKind::ProjectDir,
+};
+
+/// 工具注册表
+static TOOLS: &[AcemcpToolSpec] = &[
+    AcemcpToolSpec {
+        name: "search_context",
+        description: "在已索引的项目中执行语义代码搜索",
+        args: &[
+            PROJECT_ROOT_ARG,
+            ToolArg { name: "query", description: "自然语言搜索查询", required: true, kind: ArgKind::Text },
+        ],
+        handler: search_context_handler,
+    },
+    AcemcpToolSpec {
+        name: "get_index_status",
+        description: "获取项目的索引状态",
+        args: &[PROJECT_ROOT_ARG],
+        handler: get_index_status_handler,
+    },
+    AcemcpToolSpec {
+        name: "list_files",
+        description: "列出项目内可索引文件及其索引状态",
+        args: &[PROJECT_ROOT_ARG],
+        handler: list_files_handler,
+    },
+    AcemcpToolSpec {
+        name: "reindex",
+        description: "立即执行一次增量索引",
+        args: &[PROJECT_ROOT_ARG],
+        handler: reindex_handler,
+    },
+];
+
+/// 已注册的全部工具
+pub(crate) fn registered_tools() -> &'static [AcemcpToolSpec] {
+    TOOLS
+}
+
+/// 按名称分发工具调用，始终返回统一结构
+pub(crate) async fn execute_tool(tool_name: &str, arguments: serde_json::Value) -> AcemcpToolResponse {
+    let Some(spec) = TOOLS.iter().find(|t| t.name == tool_name) else {
+        let available: Vec<&str> = TOOLS.iter().map(|t| t.name).collect();
+        return AcemcpToolResponse::error(
+            AcemcpToolResponse::UNKNOWN_TOOL,
+            format!("未知的工具: {}（可用工具: {}）", tool_name, available.join(", ")),
+        );
+    };
+
+    let (errors, has_missing) = validate_arguments(spec, &arguments);
+    if !errors.is_empty() {
+        let code = if has_missing {
+            AcemcpToolResponse::MISSING_ARGUMENT
+        } else {
+            AcemcpToolResponse::INVALID_ARGUMENT
+        };
+        let mut response = AcemcpToolResponse::error(code, errors.join("；"));
+        response.validation_errors = errors;
+        return response;
+    }
+
+    match (spec.handler)(arguments).await {
+        Ok(result) => AcemcpToolResponse::success(result),
+        Err(e) => AcemcpToolResponse::error(AcemcpToolResponse::EXECUTION_FAILED, e),
+    }
+}
+
+/// 按工具声明校验参数，一次性返回全部错误，以及是否存在缺失的必填参数
+fn validate_arguments(spec: &AcemcpToolSpec, arguments: &serde_json::Value) -> (Vec<String>, bool) {
+    let mut errors = Vec::new();
+    let mut has_missing = false;
+    if !arguments.is_object() && !arguments.is_null() {
+        return (vec!["参数必须为 JSON 对象".to_string()], false);
+    }
+
+    for arg in spec.args {
+        let value = match arguments.get(arg.name).filter(|v| !v.is_null()) {
+            Some(v) => v,
+            None => {
+                if arg.required {
+                    has_missing = true;
+                    errors.push(format!("缺少{}参数", arg.name));
+                }
+                continue;
+            }
+        };
+        let text = match value.as_str().map(str::trim).filter(|s| !s.is_empty()) {
+            Some(s) => s,
+            None => {
+                errors.push(format!("{} 必须为非空字符串", arg.name));
+                continue;
+            }
+        };
+        if arg.kind == ArgKind::ProjectDir
+            && !super::commands::check_directory_exists(text.to_string()).unwrap_or(false)
+        {
+            errors.push(format!("{} 指向的目录不存在: {}", arg.name, text));
+        }
+    }
+    (errors, has_missing)
+}
+
+/// 读取字符串参数（已在分发时完成校验）
+fn str_arg(arguments: &serde_json::Value, name: &str) -> String {
+    arguments
+        .get(name)
+        .and_then(|v| v.as_str())
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn to_json<T: serde::Serialize>(value: &T) -> Result<serde_json::Value, String> {
+    serde_json::to_value(value).map_err(|e| format!("结果序列化失败: {}", e))
+}
+
+fn search_context_handler(arguments: serde_json::Value) -> ToolFuture {
+    Box::pin(async move {
+        let req = AcemcpRequest {
+            project_root_path: str_arg(&arguments, "project_root_path"),
+            query: str_arg(&arguments, "query"),
+        };
+        let result = AcemcpTool::search_context(req).await.map_err(|e| e.to_string())?;
+        to_json(&result)
+    })
+}
+
+fn get_index_status_handler(arguments: serde_json::Value) -> ToolFuture {
+    Box::pin(async move {
+        let status = AcemcpTool::get_index_status(str_arg(&arguments, "project_root_path"));
+        to_json(&status)
+    })
+}
+
+fn list_files_handler(arguments: serde_json::Value) -> ToolFuture {
+    Box::pin(async move {
+        let files = AcemcpTool::get_project_files_status(str_arg(&arguments, "project_root_path"))
+            .await
+            .map_err(|e| e.to_string())?;
+        to_json(&files)
+    })
+}
+
+fn reindex_handler(arguments: serde_json::Value) -> ToolFuture {
+    Box::pin(async move {
+        let message = AcemcpTool::trigger_index_update(str_arg(&arguments, "project_root_path"))
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(serde_json::Value::String(message))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_unknown_tool_and_missing_args() {
+        let resp = execute_tool("nope", serde_json::json!({})).await;
+        assert_eq!(resp.error_code.as_deref(), Some(AcemcpToolResponse::UNKNOWN_TOOL));
+        assert!(resp.error.unwrap().contains("search_context"));
+
+        let dir = std::env::temp_dir().to_string_lossy().to_string();
+        let resp = execute_tool("search_context", serde_json::json!({"project_root_path": dir})).await;
+        assert_eq!(resp.error_code.as_deref(), Some(AcemcpToolResponse::MISSING_ARGUMENT));
+        assert_eq!(resp.error.as_deref(), Some("缺少query参数"));
+
+        // 所有错误一次性返回
+        let resp = execute_tool(
+            "search_context",
+            serde_json::json!({"project_root_path": "/definitely/not/here", "query": "  "}),
+        )
+        .await;
+        assert_eq!(resp.error_code.as_deref(), Some(AcemcpToolResponse::INVALID_ARGUMENT));
+        assert_eq!(resp.validation_errors.len(), 2);
+        assert!(resp.validation_errors.contains(&"query 必须为非空字符串".to_string()));
+    }
+}
@@ -0,0 +1,204 @@
+// 调试搜索历史、查询模板与对比测速历史
+// 保存在 ~/.acemcp/data/ 下（debug_search_history.json / speed_test_history.json），与 projects.json 并列
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use super::mcp::{home_projects_file, normalize_project_path};
+use super::types::{DebugSearchHistoryEntry, DebugSearchTemplate, SpeedTestHistoryEntry};
+
+/// 每个项目保留的历史查询条数
+const DEBUG_SEARCH_HISTORY_PER_PROJECT: usize = 20;
+/// 保留的对比测速记录条数
+const SPEED_TEST_HISTORY_LIMIT: usize = 20;
+
+#[derive(Serialize, Deserialize, Default)]
+struct DebugSearchHistoryFile {
+    /// 历史记录（最新的在前）
+    #[serde(default)]
+    entries: Vec<DebugSearchHistoryEntry>,
+    #[serde(default)]
+    templates: Vec<DebugSearchTemplate>,
+}
+
+impl DebugSearchHistoryFile {
+    /// 记录一次查询：同一项目的相同查询只保留最新一条，并按项目截断到上限
+    fn record(&mut self, project_root: &str, query: &str) {
+        self.entries
+            .retain(|e| !(e.project_root == project_root && e.query == query));
+        self.entries.insert(0, DebugSearchHistoryEntry {
+            project_root: project_root.to_string(),
+            query: query.to_string(),
+            searched_at: chrono::Utc::now(),
+        });
+
+        let mut kept = 0usize;
+        self.entries.retain(|e| {
+            if e.project_root != project_root {
+                return true;
+            }
+            kept += 1;
+            kept <= DEBUG_SEARCH_HISTORY_PER_PROJECT
+        });
+    }
+}
+
+fn history_file_path() -> PathBuf {
+    home_projects_file().with_file_name("debug_search_history.json")
+}
+
+fn load_history_file() -> DebugSearchHistoryFile {
+    match fs::read_to_string(history_file_path()) {
+        Ok(data) => serde_json::from_str(&data).unwrap_or_default(),
+        Err(_) => DebugSearchHistoryFile::default(),
+    }
+}
+
+fn save_history_file(file: &DebugSearchHistoryFile) -> Result<()> {
+    let data = serde_json::to_string_pretty(file)?;
+    fs::write(history_file_path(), data)?;
+    Ok(())
+}
+
+/// 读取调试搜索历史（最新的在前），指定项目时只返回该项目的记录
+pub fn load_history(project_root_path: Option<&str>) -> Vec<DebugSearchHistoryEntry> {
+    let project = project_root_path.map(normalize_project_path);
+    load_history_file()
+        .entries
+        .into_iter()
+        .filter(|e| project.as_ref().map_or(true, |p| &e.project_root == p))
+        .collect()
+}
+
+/// 保存一次调试搜索查询
+pub fn record_query(project_root_path: &str, query: &str) -> Result<()> {
+    let query = query.trim();
+    if query.is_empty() {
+        anyhow::bail!("查询内容不能为空");
+    }
+    let mut file = load_history_file();
+    file.record(&normalize_project_path(project_root_path), query);
+    save_history_file(&file)
+}
+
+/// 清空调试搜索历史，指定项目时只清空该项目
+pub fn clear_history(project_root_path: Option<&str>) -> Result<usize> {
+    let project = project_root_path.map(normalize_project_path);
+    let mut file = load_history_file();
+    let before = file.entries.len();
+    file.entries
+        .retain(|e| project.as_ref().map_or(false, |p| &e.project_root != p));
+    save_history_file(&file)?;
+    Ok(before - file.entries.len())
+}
+
+/// 获取所有查询模板
+pub fn load_templates() -> Vec<DebugSearchTemplate> {
+    load_history_file().templates
+}
+
+/// 新增或覆盖同名查询模板
+pub fn save_template(name: &str, query: &str) -> Result<()> {
+    let name = name.trim();
+    if name.is_empty() || query.trim().is_empty() {
+        anyhow::bail!("模板名称和查询内容不能为空");
+    }
+    let mut file = load_history_file();
+    let template = DebugSearchTemplate { name: name.to_string(), query: query.to_string() };
+    match file.templates.iter_mut().find(|t| t.name == name) {
+        Some(existing) => *existing = template,
+        None => file.templates.push(template),
+    }
+    save_history_file(&file)
+}
+
+/// 删除查询模板，返回是否存在
+pub fn delete_template(name: &str) -> Result<bool> {
+    let mut file = load_history_file();
+    let before = file.templates.len();
+    file.templates.retain(|t| t.name != name);
+    let removed = file.templates.len() != before;
+    if removed {
+        save_history_file(&file)?;
+    }
+    Ok(removed)
+}
+
+/// 用给定的值替换模板中的 `{name}` 占位符，缺少值的占位符会报错
+pub fn render_template(query: &str, values: &HashMap<String, String>) -> Result<String> {
+    let mut out = String::with_capacity(query.len());
+    let mut rest = query;
+    while let Some(open) = rest.find('{') {
+        out.push_str(&rest[..open]);
+        let after = &rest[open + 1..];
+        let Some(close) = after.find('}') else {
+            out.push_str(&rest[open..]);
+            return Ok(out);
+        };
+        let key = after[..close].trim();
+        match values.get(key) {
+            Some(value) => out.push_str(value),
+            None => anyhow::bail!("模板占位符缺少取值: {}", key),
+        }
+        rest = &after[close + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+fn speed_test_history_path() -> PathBuf {
+    home_projects_file().with_file_name("speed_test_history.json")
+}
+
+/// 读取对比测速记录（最新的在前）
+pub fn load_speed_tests() -> Vec<SpeedTestHistoryEntry> {
+    match fs::read_to_string(speed_test_history_path()) {
+        Ok(data) => serde_json::from_str(&data).unwrap_or_default(),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// 追加一条对比测速记录，超出上限时丢弃最旧的
+pub fn record_speed_test(entry: SpeedTestHistoryEntry) -> Result<()> {
+    let mut entries = load_speed_tests();
+    entries.insert(0, entry);
+    entries.truncate(SPEED_TEST_HISTORY_LIMIT);
+    fs::write(speed_test_history_path(), serde_json::to_string_pretty(&entries)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_dedup_and_limit() {
+        let mut file = DebugSearchHistoryFile::default();
+        file.record("/other", "keep");
+        for i in 0..(DEBUG_SEARCH_HISTORY_PER_PROJECT + 5) {
+            file.record("/repo", &format!("q{}", i));
+        }
+        file.record("/repo", "q10");
+
+        let repo: Vec<_> = file.entries.iter().filter(|e| e.project_root == "/repo").collect();
+        assert_eq!(repo.len(), DEBUG_SEARCH_HISTORY_PER_PROJECT);
+        assert_eq!(repo[0].query, "q10");
+        assert_eq!(repo.iter().filter(|e| e.query == "q10").count(), 1);
+        assert!(file.entries.iter().any(|e| e.project_root == "/other"));
+    }
+
+    #[test]
+    fn test_render_template() {
+        let values: HashMap<String, String> =
+            [("module".to_string(), "watcher".to_string())].into_iter().collect();
+        assert_eq!(
+            render_template("where is { module } debounced?", &values).unwrap(),
+            "where is watcher debounced?"
+        );
+        assert!(render_template("find {missing}", &values).is_err());
+        assert_eq!(render_template("no close {brace", &values).unwrap(), "no close {brace");
+    }
+}
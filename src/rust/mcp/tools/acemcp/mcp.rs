@@ -5,44 +5,196 @@ use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::io::Read;
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
-use reqwest::header::{AUTHORIZATION, CONTENT_TYPE};
+use reqwest::header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE};
 use reqwest::Client;
 use ring::digest::{Context as ShaContext, SHA256};
 use ignore::gitignore::{Gitignore, GitignoreBuilder};
 use serde::{Deserialize, Serialize};
 use encoding_rs::{GBK, WINDOWS_1252, UTF_8};
-use globset::{Glob, GlobSet, GlobSetBuilder};
+use globset::Glob;
 
 use super::types::{
     AcemcpRequest,
+    AcemcpUsage,
+    IndexResumeResult,
     AcemcpConfig,
+    AcemcpProxyOverride,
     ProjectIndexStatus,
     ProjectsIndexStatus,
     IndexStatus,
     ProjectFilesStatus,
     FileIndexStatus,
+    FileErrorList,
     FileIndexStatusKind,
+    IncludeSource,
+    IndexEstimate,
+    IndexVerification,
+    SearchStreamStats,
+    ExclusionPreview,
+    ExclusionPatternStats,
+    FileChunkInfo,
+    FileChunkPreview,
+    ProxyFailoverEvent,
 };
 use crate::log_debug;
 use crate::log_important;
-// 代理模块（在 create_acemcp_client 中使用）
+use crate::constants::mcp::{
+    DEFAULT_ACEMCP_BATCH_SIZE, DEFAULT_ACEMCP_MAX_BLOBS_PER_REQUEST, DEFAULT_ACEMCP_MAX_LINES_PER_BLOB,
+    DEFAULT_ACEMCP_PROXY_FAILOVER_THRESHOLD,
+};
+use crate::network::proxy::{ProxyDetector, ProxyInfo, ProxyType};
+// 代理模块（在 create_acemcp_client 中使用，客户端经 shared_acemcp_client 复用）
 
 /// Acemcp工具实现
 pub struct AcemcpTool;
 
 impl AcemcpTool {
     /// 执行代码库搜索（仅搜索，不触发索引）
+    /// 搜索被 `cancel_search` 取消时返回带“已取消”说明的错误结果
     pub async fn search_context(request: AcemcpRequest) -> Result<CallToolResult, McpError> {
+        match Self::search_context_cancellable(request).await? {
+            Some(result) => Ok(result),
+            None => Ok(CallToolResult {
+                content: vec![Content::text(format!("Acemcp{}", SEARCH_CANCELLED))],
+                is_error: Some(true),
+                meta: None,
+                structured_content: None,
+            }),
+        }
+    }
+
+    /// 可取消的代码库搜索：被 `cancel_search` 取消时返回 None
+    /// 取消会直接丢弃进行中的请求 future（包括智能等待），不影响其他项目的搜索
+    pub async fn search_context_cancellable(request: AcemcpRequest) -> Result<Option<CallToolResult>, McpError> {
+        Self::search_context_cancellable_with_proxy(request, None).await
+    }
+
+    /// 可取消的代码库搜索，可指定仅本次生效的代理（不写入配置，也不替换共享客户端）
+    pub async fn search_context_cancellable_with_proxy(
+        request: AcemcpRequest,
+        proxy_override: Option<AcemcpProxyOverride>,
+    ) -> Result<Option<CallToolResult>, McpError> {
+        let project_root_path = request.project_root_path.clone();
+        run_cancellable_search(&project_root_path, Self::search_context_inner(request, proxy_override))
+            .await
+            .transpose()
+    }
+
+    async fn search_context_inner(
+        request: AcemcpRequest,
+        proxy_override: Option<AcemcpProxyOverride>,
+    ) -> Result<CallToolResult, McpError> {
         log_important!(info,
             "Acemcp搜索请求（仅搜索模式）: project_root_path={}, query={}",
             request.project_root_path, request.query
         );
 
-        // 读取配置
-        let mut acemcp_config = Self::get_acemcp_config()
+        let (mut acemcp_config, hint_message) = Self::prepare_search(&request).await?;
+        if let Some(proxy) = &proxy_override {
+            log_important!(info, "本次搜索使用临时代理: {}://{}:{}", proxy.proxy_type, proxy.host, proxy.port);
+            proxy.apply(&mut acemcp_config);
+        }
+
+        // 3. 执行搜索（不触发索引）
+        let search_result = match search_only(&acemcp_config, &request.project_root_path, &request.query).await {
+            Ok(text) => text,
+            Err(e) => {
+                return Ok(CallToolResult {
+                    content: vec![Content::text(format!("Acemcp搜索失败: {}", e))],
+                    is_error: Some(true),
+                    meta: None,
+                    structured_content: None,
+                });
+            }
+        };
+
+        // 4. 附加提示信息
+        let final_result = if hint_message.is_empty() {
+            search_result
+        } else {
+            format!("{}{}", search_result, hint_message)
+        };
+
+        Ok(CallToolResult { 
+            content: vec![Content::text(final_result)], 
+            is_error: None,
+            meta: None,
+            structured_content: None,
+        })
+    }
+
+    /// 流式搜索：服务端以分块/流式返回时，边接收边通过 `on_chunk` 推送增量文本
+    /// 服务端不支持流式时自动退化为缓冲模式（接收完成后一次性推送完整结果）
+    /// 被 `cancel_search` 取消时返回 None
+    pub async fn search_context_streaming<F>(
+        request: AcemcpRequest,
+        on_chunk: F,
+    ) -> Result<Option<(CallToolResult, SearchStreamStats)>, McpError>
+    where
+        F: FnMut(String) + Send,
+    {
+        let project_root_path = request.project_root_path.clone();
+        run_cancellable_search(&project_root_path, Self::search_context_streaming_inner(request, on_chunk))
+            .await
+            .transpose()
+    }
+
+    async fn search_context_streaming_inner<F>(
+        request: AcemcpRequest,
+        mut on_chunk: F,
+    ) -> Result<(CallToolResult, SearchStreamStats), McpError>
+    where
+        F: FnMut(String) + Send,
+    {
+        log_important!(info,
+            "Acemcp流式搜索请求: project_root_path={}, query={}",
+            request.project_root_path, request.query
+        );
+
+        let (acemcp_config, hint_message) = Self::prepare_search(&request).await?;
+
+        let (search_result, stats) = match search_only_streaming(
+            &acemcp_config,
+            &request.project_root_path,
+            &request.query,
+            &mut on_chunk,
+        ).await {
+            Ok(v) => v,
+            Err(e) => {
+                return Ok((CallToolResult {
+                    content: vec![Content::text(format!("Acemcp搜索失败: {}", e))],
+                    is_error: Some(true),
+                    meta: None,
+                    structured_content: None,
+                }, SearchStreamStats::default()));
+            }
+        };
+
+        // 提示信息作为最后一个片段推送，保证流式拼接结果与最终结果一致
+        let final_result = if hint_message.is_empty() {
+            search_result
+        } else {
+            on_chunk(hint_message.clone());
+            format!("{}{}", search_result, hint_message)
+        };
+
+        Ok((CallToolResult {
+            content: vec![Content::text(final_result)],
+            is_error: None,
+            meta: None,
+            structured_content: None,
+        }, stats))
+    }
+
+    /// 搜索前的准备工作：读取配置、启动文件监听、按索引状态决定是否后台索引/智能等待
+    /// 返回规范化后的配置以及需要附加到结果末尾的提示信息
+    async fn prepare_search(request: &AcemcpRequest) -> Result<(AcemcpConfig, String), McpError> {
+        // 读取配置（含项目级租户覆盖）
+        let mut acemcp_config = Self::get_project_acemcp_config(&request.project_root_path)
             .await
             .map_err(|e| McpError::internal_error(format!("获取acemcp配置失败: {}", e), None))?;
 
@@ -52,9 +204,12 @@ impl AcemcpTool {
             acemcp_config.base_url = Some(normalized);
         }
 
-        // 首次搜索时自动启动文件监听（如果尚未启动）
+        // 保活已启用时确保后台保活任务在运行
+        ensure_keepalive_started();
+
+        // 首次搜索时自动启动文件监听（如果尚未启动，安全模式下不启动）
         let watcher_manager = super::watcher::get_watcher_manager();
-        if !watcher_manager.is_watching(&request.project_root_path) {
+        if !crate::app::safe_mode::is_safe_mode() && !watcher_manager.is_watching(&request.project_root_path) {
             log_debug!("首次搜索，尝试启动文件监听");
             if let Err(e) = watcher_manager.start_watching(
                 request.project_root_path.clone(),
@@ -97,32 +252,7 @@ impl AcemcpTool {
             }
         }
 
-        // 3. 执行搜索（不触发索引）
-        let search_result = match search_only(&acemcp_config, &request.project_root_path, &request.query).await {
-            Ok(text) => text,
-            Err(e) => {
-                return Ok(CallToolResult {
-                    content: vec![Content::text(format!("Acemcp搜索失败: {}", e))],
-                    is_error: Some(true),
-                    meta: None,
-                    structured_content: None,
-                });
-            }
-        };
-
-        // 4. 附加提示信息
-        let final_result = if hint_message.is_empty() {
-            search_result
-        } else {
-            format!("{}{}", search_result, hint_message)
-        };
-
-        Ok(CallToolResult { 
-            content: vec![Content::text(final_result)], 
-            is_error: None,
-            meta: None,
-            structured_content: None,
-        })
+        Ok((acemcp_config, hint_message))
     }
 
     /// 执行索引更新（向后兼容的索引+搜索一体化接口）
@@ -133,7 +263,7 @@ impl AcemcpTool {
         );
 
         // 读取配置
-        let mut acemcp_config = Self::get_acemcp_config()
+        let mut acemcp_config = Self::get_project_acemcp_config(&request.project_root_path)
             .await
             .map_err(|e| McpError::internal_error(format!("获取acemcp配置失败: {}", e), None))?;
 
@@ -175,11 +305,33 @@ impl AcemcpTool {
     pub async fn trigger_index_update(project_root_path: String) -> Result<String> {
         log_important!(info, "手动触发索引更新: project_root_path={}", project_root_path);
 
-        let acemcp_config = Self::get_acemcp_config().await?;
+        let acemcp_config = Self::get_project_acemcp_config(&project_root_path).await?;
+
+        // 配置了每日预算时，按本次实际需要上传的新 blob 数提前提醒
+        let budget_warning = match acemcp_config.daily_blob_budget {
+            Some(_) => count_new_blobs(&acemcp_config, &project_root_path, false)
+                .await
+                .ok()
+                .and_then(|new_blobs| {
+                    daily_budget_warning(
+                        acemcp_config.daily_blob_budget,
+                        load_usage_today().blobs_uploaded,
+                        new_blobs,
+                    )
+                }),
+            None => None,
+        };
+        if let Some(warning) = &budget_warning {
+            log_important!(warn, "{}", warning);
+        }
 
         match update_index(&acemcp_config, &project_root_path).await {
             Ok(blob_names) => {
-                Ok(format!("索引更新成功，共 {} 个 blobs", blob_names.len()))
+                let message = format!("索引更新成功，共 {} 个 blobs", blob_names.len());
+                Ok(match budget_warning {
+                    Some(warning) => format!("{}\n{}", warning, message),
+                    None => message,
+                })
             }
             Err(e) => {
                 Err(anyhow::anyhow!("索引更新失败: {}", e))
@@ -187,6 +339,26 @@ impl AcemcpTool {
         }
     }
 
+    /// 从上次中断的检查点继续索引：已上传成功的 blob 不再重复上传
+    /// 没有检查点时等同于一次普通的增量索引
+    pub async fn resume_index(project_root_path: String) -> Result<IndexResumeResult> {
+        let normalized_root = normalize_project_path(&project_root_path);
+        let checkpoint_blobs = load_project_checkpoint(&normalized_root).len();
+        log_important!(info, "恢复索引: project_root={}, checkpoint_blobs={}", normalized_root, checkpoint_blobs);
+
+        let acemcp_config = Self::get_project_acemcp_config(&project_root_path).await?;
+        let blob_names = update_index(&acemcp_config, &project_root_path)
+            .await
+            .map_err(|e| anyhow::anyhow!("恢复索引失败: {}", e))?;
+
+        Ok(IndexResumeResult {
+            project_root: normalized_root,
+            checkpoint_blobs,
+            resumed_blobs: get_project_status(&project_root_path).resumed_blobs,
+            total_blobs: blob_names.len(),
+        })
+    }
+
     /// 获取项目索引状态（供 Tauri 命令调用）
     pub fn get_index_status(project_root_path: String) -> ProjectIndexStatus {
         get_project_status(&project_root_path)
@@ -197,49 +369,182 @@ impl AcemcpTool {
         load_projects_status()
     }
 
+    /// 将项目状态重置为 Idle（进度清零、清除错误信息），返回重置前的状态
+    /// 用于崩溃后项目一直显示“索引中”的恢复，不会删除已索引的 blob
+    pub fn reset_project_status(project_root_path: &str) -> Result<IndexStatus> {
+        let previous = get_project_status(project_root_path).status;
+        update_project_status(project_root_path, |status| {
+            status.status = IndexStatus::Idle;
+            status.progress = 0;
+            status.last_error = None;
+        })?;
+        log_important!(info,
+            "已重置项目索引状态: project_root_path={}, 原状态={:?}",
+            normalize_project_path(project_root_path), previous
+        );
+        Ok(previous)
+    }
+
+    /// 列出 projects.json / projects_status.json 中记录的所有项目（规范化 key，已排序去重）
+    pub fn list_known_projects() -> Vec<String> {
+        let mut roots: Vec<String> = load_projects_file().0.into_keys().collect();
+        roots.extend(load_projects_status().projects.into_keys());
+        roots.sort();
+        roots.dedup();
+        roots
+    }
+
+    /// 从 projects.json、blob_times.json、indexed_files.json、检查点和 projects_status.json 中批量移除项目记录
+    /// 返回实际存在记录并被移除的项目
+    pub fn remove_projects(project_roots: &[String]) -> Result<Vec<String>> {
+        let keys: std::collections::HashSet<String> =
+            project_roots.iter().map(|r| normalize_path_key(r)).collect();
+        let mut removed = std::collections::BTreeSet::new();
+
+        let mut projects = load_projects_file();
+        let before = projects.0.len();
+        projects.0.retain(|k, _| {
+            let hit = keys.contains(k);
+            if hit {
+                removed.insert(k.clone());
+            }
+            !hit
+        });
+        if projects.0.len() != before {
+            fs::write(home_projects_file(), serde_json::to_string_pretty(&projects)?)?;
+        }
+
+        remove_project_side_records(&keys)?;
+
+        let mut status = load_projects_status();
+        let before = status.projects.len();
+        status.projects.retain(|k, _| {
+            let hit = keys.contains(k);
+            if hit {
+                removed.insert(k.clone());
+            }
+            !hit
+        });
+        if status.projects.len() != before {
+            save_projects_status(&status)?;
+        }
+
+        Ok(removed.into_iter().collect())
+    }
+
     /// 获取项目内所有可索引文件的索引状态（供 Tauri 命令调用）
     pub async fn get_project_files_status(project_root_path: String) -> anyhow::Result<ProjectFilesStatus> {
-        // 读取 Acemcp 配置，主要用于获取扩展名、排除规则和分块行数
-        let acemcp_config = Self::get_acemcp_config().await?;
-        let max_lines = acemcp_config.max_lines_per_blob.unwrap_or(800) as usize;
+        // 读取项目级 Acemcp 配置，主要用于获取扩展名、排除规则（含运行时排除的子目录）和分块行数
+        let acemcp_config = Self::get_project_acemcp_config(&project_root_path).await?;
+        let max_lines = MaxLinesPolicy::from_config(&acemcp_config);
         let text_exts = acemcp_config.text_extensions.clone().unwrap_or_default();
         let exclude_patterns = acemcp_config.exclude_patterns.clone().unwrap_or_default();
+        let follow_symlinks = acemcp_config.follow_symlinks.unwrap_or(false);
 
-        // 读取 projects.json，获取已索引的 blob 名称集合
-        let projects_path = home_projects_file();
-        let projects: ProjectsFile = if projects_path.exists() {
-            let data = fs::read_to_string(&projects_path).unwrap_or_default();
-            serde_json::from_str(&data).unwrap_or_default()
-        } else {
-            ProjectsFile::default()
-        };
-
-        let normalized_root = PathBuf::from(&project_root_path)
-            .canonicalize()
-            .unwrap_or_else(|_| PathBuf::from(&project_root_path))
-            .to_string_lossy()
-            .replace('\\', "/");
-
-        let existing_blob_names: std::collections::HashSet<String> = projects
-            .0
-            .get(&normalized_root)
-            .cloned()
-            .unwrap_or_default()
-            .into_iter()
-            .collect();
+        // 读取 projects.json / blob_times.json，获取已索引的 blob 及其上传时间
+        let normalized_root = normalize_project_path(&project_root_path);
+        let existing_blob_names = load_project_blob_times(&normalized_root);
 
-        let files = collect_file_statuses(
+        let cancel_flag = register_files_status_cancel(&normalized_root);
+        let result = collect_file_statuses(
             &project_root_path,
             &text_exts,
             &exclude_patterns,
-            max_lines,
+            &max_lines,
+            follow_symlinks,
             &existing_blob_names,
-        )?;
+            Some(&cancel_flag),
+        );
+        unregister_files_status_cancel(&normalized_root, &cancel_flag);
 
         Ok(ProjectFilesStatus {
             project_root: normalized_root,
-            files,
+            files: result?,
+        })
+    }
+
+    /// 基于文件元数据估算索引规模（不读取文件内容）
+    /// 返回的 IndexEstimate 中吞吐量与耗时字段留空，由调用方结合测速结果补全
+    pub async fn estimate_index_size(project_root_path: String) -> anyhow::Result<IndexEstimate> {
+        let acemcp_config = Self::get_project_acemcp_config(&project_root_path).await?;
+        let max_lines = MaxLinesPolicy::from_config(&acemcp_config);
+        let text_exts = acemcp_config.text_extensions.clone().unwrap_or_default();
+        let exclude_patterns = acemcp_config.exclude_patterns.clone().unwrap_or_default();
+        let follow_symlinks = acemcp_config.follow_symlinks.unwrap_or(false);
+        let normalized_root = normalize_project_path(&project_root_path);
+
+        let root = project_root_path.clone();
+        let (file_count, total_bytes, estimated_blobs) = tokio::task::spawn_blocking(move || {
+            estimate_files(&root, &text_exts, &exclude_patterns, &max_lines, follow_symlinks)
+        })
+        .await
+        .map_err(|e| anyhow::anyhow!("索引规模估算任务异常: {}", e))??;
+
+        Ok(IndexEstimate {
+            project_root: normalized_root,
+            file_count,
+            total_bytes,
+            estimated_blobs,
+            throughput_bytes_per_sec: None,
+            estimated_secs: None,
+            summary: String::new(),
+        })
+    }
+
+    /// 分块流式获取项目文件索引状态（适用于大型仓库）
+    /// 每累计 chunk_size 个文件回调一次，不在内存中保留完整列表；
+    /// 可通过 cancel_files_status 按项目路径取消。返回已回调的文件总数
+    pub async fn stream_project_files_status<F>(
+        project_root_path: String,
+        chunk_size: usize,
+        mut on_chunk: F,
+    ) -> anyhow::Result<usize>
+    where
+        F: FnMut(Vec<FileIndexStatus>) + Send + 'static,
+    {
+        let acemcp_config = Self::get_project_acemcp_config(&project_root_path).await?;
+        let max_lines = MaxLinesPolicy::from_config(&acemcp_config);
+        let text_exts = acemcp_config.text_extensions.clone().unwrap_or_default();
+        let exclude_patterns = acemcp_config.exclude_patterns.clone().unwrap_or_default();
+        let follow_symlinks = acemcp_config.follow_symlinks.unwrap_or(false);
+
+        let normalized_root = normalize_project_path(&project_root_path);
+        let existing_blob_names = load_project_blob_times(&normalized_root);
+
+        let chunk_size = chunk_size.max(1);
+        let cancel_flag = register_files_status_cancel(&normalized_root);
+        let walk_flag = cancel_flag.clone();
+
+        // 目录遍历与文件读取均为阻塞操作，放到阻塞线程池执行
+        let result = tokio::task::spawn_blocking(move || {
+            let mut buffer: Vec<FileIndexStatus> = Vec::with_capacity(chunk_size);
+            let mut total = 0usize;
+            walk_file_statuses(
+                &project_root_path,
+                &text_exts,
+                &exclude_patterns,
+                &max_lines,
+                follow_symlinks,
+                &existing_blob_names,
+                Some(&walk_flag),
+                &mut |status| {
+                    buffer.push(status);
+                    if buffer.len() >= chunk_size {
+                        total += buffer.len();
+                        on_chunk(std::mem::replace(&mut buffer, Vec::with_capacity(chunk_size)));
+                    }
+                },
+            )?;
+            if !buffer.is_empty() {
+                total += buffer.len();
+                on_chunk(buffer);
+            }
+            Ok::<usize, anyhow::Error>(total)
         })
+        .await;
+        unregister_files_status_cancel(&normalized_root, &cancel_flag);
+
+        result.map_err(|e| anyhow::anyhow!("文件状态扫描任务异常: {}", e))?
     }
 
     /// 获取acemcp配置（公有方法，供 commands 模块调用）
@@ -248,15 +553,57 @@ impl AcemcpTool {
         let config = crate::config::load_standalone_config()
             .map_err(|e| anyhow::anyhow!("读取配置文件失败: {}", e))?;
 
-        Ok(AcemcpConfig {
+        Ok(Self::acemcp_config_from(config))
+    }
+
+    /// 获取指定项目的生效配置：存在项目级覆盖时替换 base_url/token，否则与全局配置一致
+    pub async fn get_project_acemcp_config(project_root_path: &str) -> Result<AcemcpConfig> {
+        let config = crate::config::load_standalone_config()
+            .map_err(|e| anyhow::anyhow!("读取配置文件失败: {}", e))?;
+        let project_override = find_project_override(&config.mcp_config, project_root_path);
+        let project_exclusions = find_project_watch_exclusions(&config.mcp_config, project_root_path);
+
+        let mut acemcp_config = Self::acemcp_config_from(config);
+        if !project_exclusions.is_empty() {
+            // 运行时排除的子目录同时作用于扫描与索引，转换为锚定在相对路径开头的正则排除模式
+            let patterns = acemcp_config.exclude_patterns.get_or_insert_with(Vec::new);
+            patterns.extend(project_exclusions.iter().map(|sub| exclusion_subpath_pattern(sub)));
+            acemcp_config.project_exclusions = project_exclusions;
+        }
+        if let Some(project_override) = project_override {
+            log_debug!("使用项目级租户覆盖: project_root={}", project_root_path);
+            if project_override.base_url.is_some() {
+                acemcp_config.base_url = project_override.base_url;
+            }
+            if let Some(token) = project_override.token.as_deref() {
+                acemcp_config.token = crate::config::resolve_token_opt(Some(token));
+            }
+        }
+        Ok(acemcp_config)
+    }
+
+    fn acemcp_config_from(config: crate::config::AppConfig) -> AcemcpConfig {
+        AcemcpConfig {
             base_url: config.mcp_config.acemcp_base_url,
-            token: config.mcp_config.acemcp_token,
+            token: crate::config::resolve_token_opt(config.mcp_config.acemcp_token.as_deref()),
             batch_size: config.mcp_config.acemcp_batch_size,
+            max_blobs_per_request: config.mcp_config.acemcp_max_blobs_per_request,
+            daily_blob_budget: config.mcp_config.acemcp_daily_blob_budget,
+            user_agent_suffix: config.mcp_config.acemcp_user_agent_suffix.clone(),
             max_lines_per_blob: config.mcp_config.acemcp_max_lines_per_blob,
-            text_extensions: config.mcp_config.acemcp_text_extensions,
+            max_lines_by_extension: config.mcp_config.acemcp_max_lines_by_extension,
+            text_extensions: config
+                .mcp_config
+                .acemcp_text_extensions
+                .map(|exts| normalize_text_extensions_lenient(&exts)),
             exclude_patterns: config.mcp_config.acemcp_exclude_patterns,
-            // 智能等待默认值：1-5 秒随机等待
-            smart_wait_range: Some((1, 5)),
+            follow_symlinks: config.mcp_config.acemcp_follow_symlinks,
+            project_exclusions: Vec::new(),
+            // 智能等待：默认 1-5 秒随机等待，0/0 表示禁用
+            smart_wait_range: smart_wait_range_from(
+                config.mcp_config.acemcp_smart_wait_min_secs,
+                config.mcp_config.acemcp_smart_wait_max_secs,
+            ),
             // 代理配置
             proxy_enabled: config.mcp_config.acemcp_proxy_enabled,
             proxy_host: config.mcp_config.acemcp_proxy_host,
@@ -264,7 +611,19 @@ impl AcemcpTool {
             proxy_type: config.mcp_config.acemcp_proxy_type,
             proxy_username: config.mcp_config.acemcp_proxy_username,
             proxy_password: config.mcp_config.acemcp_proxy_password,
-        })
+            client_cert_path: config.mcp_config.acemcp_client_cert_path,
+            client_key_path: config.mcp_config.acemcp_client_key_path,
+            extra_ca_cert_path: config.mcp_config.acemcp_extra_ca_cert_path,
+            accept_invalid_certs: config.mcp_config.acemcp_accept_invalid_certs,
+            keepalive_enabled: config.mcp_config.acemcp_keepalive_enabled,
+            keepalive_interval_secs: config.mcp_config.acemcp_keepalive_interval_secs,
+            max_file_bytes: config.mcp_config.acemcp_max_file_bytes,
+            proxy_failover_enabled: config.mcp_config.acemcp_proxy_failover_enabled,
+            failover_proxy: config.mcp_config.acemcp_last_good_proxy,
+            http2: config.mcp_config.acemcp_http2,
+            pool_max_idle_per_host: config.mcp_config.acemcp_pool_max_idle_per_host,
+            bypass_shared_client: false,
+        }
     }
 
 
@@ -374,6 +733,10 @@ struct BlobItem {
 #[derive(Serialize, Deserialize, Default)]
 pub(crate) struct ProjectsFile(pub HashMap<String, Vec<String>>);
 
+/// 项目 -> (blob 名称 -> 最近一次上传时间)，与 projects.json 并列保存
+#[derive(Serialize, Deserialize, Default)]
+pub(crate) struct BlobTimesFile(pub HashMap<String, HashMap<String, chrono::DateTime<chrono::Utc>>>);
+
 fn normalize_base_url(input: &str) -> String {
     let mut url = input.trim().to_string();
     if !(url.starts_with("http://") || url.starts_with("https://")) {
@@ -383,6 +746,104 @@ fn normalize_base_url(input: &str) -> String {
     url
 }
 
+/// 将 ACE 端点路径拼接到 base_url 之后，支持部署在网关子路径下的 base_url（如 `https://host/ace`）
+/// 拼接前去除 base_url 的 query / fragment 与末尾斜杠；无法解析为 URL 时退化为字符串拼接
+pub(crate) fn join_ace_url(base_url: &str, endpoint: &str) -> String {
+    let endpoint = endpoint.trim_start_matches('/');
+    match reqwest::Url::parse(base_url.trim()) {
+        Ok(mut url) if !url.cannot_be_a_base() => {
+            url.set_query(None);
+            url.set_fragment(None);
+            let path = format!("{}/{}", url.path().trim_end_matches('/'), endpoint);
+            url.set_path(&path);
+            url.to_string()
+        }
+        _ => format!("{}/{}", base_url.trim().trim_end_matches('/'), endpoint),
+    }
+}
+
+/// 将配置中的智能等待最小/最大秒数映射为 smart_wait_range（0/0 表示禁用）
+pub(crate) fn smart_wait_range_from(min_secs: Option<u64>, max_secs: Option<u64>) -> Option<(u64, u64)> {
+    use crate::constants::mcp::{DEFAULT_ACEMCP_SMART_WAIT_MAX_SECS, DEFAULT_ACEMCP_SMART_WAIT_MIN_SECS};
+
+    let min = min_secs.unwrap_or(DEFAULT_ACEMCP_SMART_WAIT_MIN_SECS);
+    let max = max_secs.unwrap_or(DEFAULT_ACEMCP_SMART_WAIT_MAX_SECS);
+    if min == 0 && max == 0 {
+        return None;
+    }
+    // 手工编辑配置可能写反，按区间处理以免 fastrand 范围 panic
+    Some((min.min(max), min.max(max)))
+}
+
+/// 查找项目级租户覆盖（按规范化路径匹配）
+pub(crate) fn find_project_override(
+    mcp_config: &crate::config::McpConfig,
+    project_root_path: &str,
+) -> Option<crate::config::AcemcpProjectOverride> {
+    let overrides = mcp_config.acemcp_project_overrides.as_ref()?;
+    if overrides.is_empty() {
+        return None;
+    }
+    overrides.get(&normalize_project_path(project_root_path)).cloned()
+}
+
+/// 读取项目运行时排除的子目录（未配置时为空）
+pub(crate) fn find_project_watch_exclusions(
+    mcp_config: &crate::config::McpConfig,
+    project_root_path: &str,
+) -> Vec<String> {
+    mcp_config
+        .acemcp_project_watch_exclusions
+        .as_ref()
+        .and_then(|m| m.get(&normalize_project_path(project_root_path)))
+        .cloned()
+        .unwrap_or_default()
+}
+
+/// 规范化运行时排除的子路径：统一正斜杠，去除首尾斜杠与 `./` 前缀
+/// 空路径、绝对路径或包含 `..` 的路径返回 None
+pub(crate) fn normalize_exclusion_subpath(subpath: &str) -> Option<String> {
+    let trimmed = subpath.trim().replace('\\', "/");
+    if trimmed.starts_with('/') || trimmed.contains(':') {
+        return None;
+    }
+    let parts: Vec<&str> = trimmed
+        .split('/')
+        .filter(|p| !p.is_empty() && *p != ".")
+        .collect();
+    if parts.is_empty() || parts.iter().any(|p| *p == "..") {
+        return None;
+    }
+    Some(parts.join("/"))
+}
+
+/// 子目录排除对应的正则排除模式（匹配该目录本身及其下所有路径）
+pub(crate) fn exclusion_subpath_pattern(subpath: &str) -> String {
+    format!("{}^{}(/|$)", EXCLUDE_REGEX_PREFIX, regex::escape(subpath))
+}
+
+/// 配置中的静态排除模式（去掉由运行时排除子目录转换而来的模式）
+/// 监听器只用它构建固定的匹配器，运行时排除子目录另行从共享列表实时判断，移除后无需重启即可生效
+pub(crate) fn static_exclude_patterns(exclude_patterns: &[String], project_exclusions: &[String]) -> Vec<String> {
+    let runtime: HashSet<String> = project_exclusions
+        .iter()
+        .map(|sub| exclusion_subpath_pattern(sub))
+        .collect();
+    exclude_patterns
+        .iter()
+        .filter(|p| !runtime.contains(*p))
+        .cloned()
+        .collect()
+}
+
+/// 相对路径（正斜杠分隔）是否位于任一排除子目录之下
+pub(crate) fn is_under_exclusion(rel_path: &str, exclusions: &[String]) -> bool {
+    exclusions.iter().any(|sub| {
+        rel_path == sub
+            || (rel_path.starts_with(sub.as_str()) && rel_path[sub.len()..].starts_with('/'))
+    })
+}
+
 async fn retry_request<F, Fut, T>(mut f: F, max_retries: usize, base_delay_secs: f64) -> anyhow::Result<T>
 where
     F: FnMut() -> Fut,
@@ -428,65 +889,522 @@ where
         .unwrap_or_else(|| anyhow::anyhow!("未知错误")))
 }
 
-pub(crate) fn home_projects_file() -> PathBuf {
-    let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
-    let data_dir = home.join(".acemcp").join("data");
-    let _ = fs::create_dir_all(&data_dir);
-    data_dir.join("projects.json")
+/// 同一请求（含 retry_request 的所有尝试）因 429 限流累计等待的上限（秒），避免长时间阻塞界面
+const RATE_LIMIT_MAX_WAIT_SECS: u64 = 30;
+/// 服务端未给出 Retry-After 时的默认等待（秒）
+const RATE_LIMIT_DEFAULT_WAIT_SECS: u64 = 2;
+
+/// 解析 Retry-After 头：支持秒数或 HTTP 日期两种格式
+pub(crate) fn parse_retry_after(value: &str, now: chrono::DateTime<chrono::Utc>) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let wait = at.with_timezone(&chrono::Utc) - now;
+    // 日期已过期时视为立即重试
+    Some(wait.to_std().unwrap_or(Duration::ZERO))
 }
 
-/// 获取项目索引状态文件路径
-fn home_projects_status_file() -> PathBuf {
+/// 一次逻辑请求共享的 429 等待预算
+///
+/// 在 retry_request 外创建，使多次尝试的限流等待合计不超过 RATE_LIMIT_MAX_WAIT_SECS
+pub(crate) struct RateLimitBudget {
+    remaining_ms: AtomicU64,
+}
+
+impl RateLimitBudget {
+    pub(crate) fn new() -> Self {
+        Self { remaining_ms: AtomicU64::new(RATE_LIMIT_MAX_WAIT_SECS * 1000) }
+    }
+
+    /// 从预算中扣除本次等待，返回实际可等待的时长（预算不足时截断）
+    fn take(&self, wanted: Duration) -> Duration {
+        let wanted_ms = wanted.as_millis() as u64;
+        let before = self
+            .remaining_ms
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |r| Some(r.saturating_sub(wanted_ms)))
+            .unwrap_or(0);
+        Duration::from_millis(wanted_ms.min(before))
+    }
+
+    fn is_exhausted(&self) -> bool {
+        self.remaining_ms.load(Ordering::SeqCst) == 0
+    }
+}
+
+/// 统一处理 ACE 的 429 限流：按 Retry-After 等待（受 budget 限制）后重试一次
+///
+/// 第二次的响应原样返回，仍为 429 时由调用方按普通失败处理；预算已耗尽时不再等待，直接返回 429 响应
+pub(crate) async fn send_with_rate_limit<F, Fut>(label: &str, budget: &RateLimitBudget, mut send: F) -> reqwest::Result<reqwest::Response>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = reqwest::Result<reqwest::Response>>,
+{
+    let resp = send().await?;
+    if resp.status() != reqwest::StatusCode::TOO_MANY_REQUESTS {
+        return Ok(resp);
+    }
+    if budget.is_exhausted() {
+        log_important!(warn, "{} 被 ACE 限流 (HTTP 429)，限流等待预算已用完，不再重试", label);
+        return Ok(resp);
+    }
+
+    let retry_after = resp
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| parse_retry_after(v, chrono::Utc::now()));
+    let wait = budget.take(retry_after.unwrap_or(Duration::from_secs(RATE_LIMIT_DEFAULT_WAIT_SECS)));
+    log_important!(warn,
+        "{} 被 ACE 限流 (HTTP 429)，Retry-After={:?}，等待 {}ms 后重试一次",
+        label, retry_after, wait.as_millis()
+    );
+    tokio::time::sleep(wait).await;
+    send().await
+}
+
+pub(crate) fn home_projects_file() -> PathBuf {
     let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
     let data_dir = home.join(".acemcp").join("data");
     let _ = fs::create_dir_all(&data_dir);
-    data_dir.join("projects_status.json")
+    data_dir.join("projects.json")
 }
 
-/// 读取所有项目的索引状态
-fn load_projects_status() -> ProjectsIndexStatus {
-    let status_path = home_projects_status_file();
-    log_debug!("📂 [load_projects_status] 状态文件路径: {:?}", status_path);
-    
-    if status_path.exists() {
-        let data = fs::read_to_string(&status_path).unwrap_or_default();
-        log_debug!("📄 [load_projects_status] 读取到状态文件，大小: {} 字节", data.len());
-        
-        match serde_json::from_str::<ProjectsIndexStatus>(&data) {
-            Ok(status) => {
-                log_debug!("✅ [load_projects_status] 解析成功，项目数: {}", status.projects.len());
-                status
-            }
-            Err(e) => {
-                log_debug!("⚠️ [load_projects_status] 解析失败: {}", e);
-                ProjectsIndexStatus::default()
+/// 读取 projects.json，并将历史 key 统一为规范化路径
+/// 说明：旧版本可能以 `//?/C:/...` 等形式保存 key，这里合并到同一个规范化 key 下
+pub(crate) fn load_projects_file() -> ProjectsFile {
+    let raw = load_raw_projects_file();
+
+    let mut projects = ProjectsFile::default();
+    for (key, blob_names) in raw.0 {
+        let entry = projects.0.entry(normalize_path_key(&key)).or_default();
+        for name in blob_names {
+            if !entry.contains(&name) {
+                entry.push(name);
             }
         }
-    } else {
-        log_debug!("📭 [load_projects_status] 状态文件不存在，返回空列表");
-        ProjectsIndexStatus::default()
     }
+    projects
 }
 
-/// 保存所有项目的索引状态
-fn save_projects_status(status: &ProjectsIndexStatus) -> Result<()> {
-    let status_path = home_projects_status_file();
-    let data = serde_json::to_string_pretty(status)?;
-    fs::write(status_path, data)?;
-    Ok(())
+/// 按原样读取 projects.json（不合并历史 key、不去重）
+fn load_raw_projects_file() -> ProjectsFile {
+    match fs::read_to_string(home_projects_file()) {
+        Ok(data) => serde_json::from_str(&data).unwrap_or_default(),
+        Err(_) => ProjectsFile::default(),
+    }
 }
 
-/// 更新指定项目的索引状态
-fn update_project_status<F>(project_root: &str, updater: F) -> Result<()>
-where
-    F: FnOnce(&mut ProjectIndexStatus),
+/// 统计项目在原始记录中的重复 blob 数（包括同一项目的多个历史 key 之间的重复）
+fn count_duplicate_records(raw: &ProjectsFile, normalized_root: &str) -> usize {
+    let mut seen = HashSet::new();
+    raw.0
+        .iter()
+        .filter(|(key, _)| normalize_path_key(key) == normalized_root)
+        .flat_map(|(_, names)| names)
+        .filter(|name| !seen.insert(name.as_str()))
+        .count()
+}
+
+const BLOB_TIMES_FILE: &str = "blob_times.json";
+const INDEXED_FILES_FILE: &str = "indexed_files.json";
+const INDEX_CHECKPOINT_DIR: &str = "index_checkpoints";
+
+fn home_blob_times_file() -> PathBuf {
+    home_projects_file().with_file_name(BLOB_TIMES_FILE)
+}
+
+/// 读取 blob_times.json（key 同样统一为规范化路径）
+pub(crate) fn load_blob_times_file() -> BlobTimesFile {
+    let raw: BlobTimesFile = match fs::read_to_string(home_blob_times_file()) {
+        Ok(data) => serde_json::from_str(&data).unwrap_or_default(),
+        Err(_) => BlobTimesFile::default(),
+    };
+
+    let mut times = BlobTimesFile::default();
+    for (key, entries) in raw.0 {
+        times.0.entry(normalize_path_key(&key)).or_default().extend(entries);
+    }
+    times
+}
+
+fn save_blob_times_file(times: &BlobTimesFile) -> Result<()> {
+    let data = serde_json::to_string_pretty(times)?;
+    fs::write(home_blob_times_file(), data)?;
+    Ok(())
+}
+
+/// 项目 -> 在 projects.json 中至少有一个 blob 的文件（相对路径）
+/// blob 名称是路径与内容的哈希，无法反推路径，因此与 projects.json 同步单独记录，
+/// 用于区分“新增文件”与“已修改文件”
+fn home_indexed_files_file() -> PathBuf {
+    home_projects_file().with_file_name(INDEXED_FILES_FILE)
+}
+
+fn load_indexed_files_file() -> HashMap<String, Vec<String>> {
+    let raw: HashMap<String, Vec<String>> = fs::read_to_string(home_indexed_files_file())
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default();
+    let mut files: HashMap<String, Vec<String>> = HashMap::new();
+    for (key, paths) in raw {
+        files.entry(normalize_path_key(&key)).or_default().extend(paths);
+    }
+    files
+}
+
+/// 读取项目已有 blob 记录的文件路径
+pub(crate) fn load_project_indexed_files(normalized_root: &str) -> HashSet<String> {
+    load_indexed_files_file()
+        .remove(normalized_root)
+        .unwrap_or_default()
+        .into_iter()
+        .collect()
+}
+
+/// 覆盖保存项目已有 blob 记录的文件路径（为空时移除该项目）
+fn save_project_indexed_files(normalized_root: &str, paths: &HashSet<String>) -> Result<()> {
+    let mut files = load_indexed_files_file();
+    if paths.is_empty() {
+        files.remove(normalized_root);
+    } else {
+        let mut sorted: Vec<String> = paths.iter().cloned().collect();
+        sorted.sort();
+        files.insert(normalized_root.to_string(), sorted);
+    }
+    fs::write(home_indexed_files_file(), serde_json::to_string_pretty(&files)?)?;
+    Ok(())
+}
+
+/// 上传进度检查点：本轮索引中已上传成功、但尚未写入 projects.json 的 blob
+/// 索引被中断（退出应用、断网）后，下次索引会将其视为已上传，从中断处继续。
+/// 每个项目一个文件、每行一个 blob 名称，每批成功后只追加本批，
+/// 不同项目（以及 GUI / MCP 服务两个进程）不会互相覆盖
+fn home_index_checkpoint_dir() -> PathBuf {
+    home_projects_file().with_file_name(INDEX_CHECKPOINT_DIR)
+}
+
+fn checkpoint_file_in(dir: &Path, normalized_root: &str) -> PathBuf {
+    let digest = ring::digest::digest(&SHA256, normalized_root.as_bytes());
+    dir.join(format!("{}.txt", &hex::encode(digest.as_ref())[..16]))
+}
+
+fn load_checkpoint_in(dir: &Path, normalized_root: &str) -> Vec<String> {
+    fs::read_to_string(checkpoint_file_in(dir, normalized_root))
+        .map(|data| data.lines().filter(|l| !l.is_empty()).map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+fn append_checkpoint_in(dir: &Path, normalized_root: &str, blob_names: &[String]) -> Result<()> {
+    use std::io::Write;
+    fs::create_dir_all(dir)?;
+    let mut data = String::new();
+    for name in blob_names {
+        data.push_str(name);
+        data.push('\n');
+    }
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(checkpoint_file_in(dir, normalized_root))?;
+    file.write_all(data.as_bytes())?;
+    Ok(())
+}
+
+fn clear_checkpoint_in(dir: &Path, normalized_root: &str) -> Result<()> {
+    match fs::remove_file(checkpoint_file_in(dir, normalized_root)) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// 读取项目的上传检查点（无检查点时为空）
+pub(crate) fn load_project_checkpoint(normalized_root: &str) -> Vec<String> {
+    load_checkpoint_in(&home_index_checkpoint_dir(), normalized_root)
+}
+
+/// 将一批上传成功的 blob 追加到项目的检查点
+fn append_project_checkpoint(normalized_root: &str, blob_names: &[String]) -> Result<()> {
+    append_checkpoint_in(&home_index_checkpoint_dir(), normalized_root, blob_names)
+}
+
+/// 删除项目的检查点（上传结果已写入 projects.json 或项目被移除）
+fn clear_project_checkpoint(normalized_root: &str) -> Result<()> {
+    clear_checkpoint_in(&home_index_checkpoint_dir(), normalized_root)
+}
+
+/// 从数据目录下的 blob_times.json、indexed_files.json 和检查点中移除项目，
+/// 与 projects.json 中的删除保持一致（历史 key 规范化后匹配的同样移除）
+fn remove_project_side_records_in(data_dir: &Path, keys: &HashSet<String>) -> Result<()> {
+    for name in [BLOB_TIMES_FILE, INDEXED_FILES_FILE] {
+        let path = data_dir.join(name);
+        let Ok(data) = fs::read_to_string(&path) else {
+            continue;
+        };
+        let mut map: serde_json::Map<String, serde_json::Value> = serde_json::from_str(&data).unwrap_or_default();
+        let before = map.len();
+        map.retain(|k, _| !keys.contains(&normalize_path_key(k)));
+        if map.len() != before {
+            fs::write(&path, serde_json::to_string_pretty(&map)?)?;
+        }
+    }
+    let checkpoint_dir = data_dir.join(INDEX_CHECKPOINT_DIR);
+    for key in keys {
+        clear_checkpoint_in(&checkpoint_dir, key)?;
+    }
+    Ok(())
+}
+
+/// 移除项目在 projects.json 之外的索引附属记录（keys 为规范化路径）
+pub(crate) fn remove_project_side_records(keys: &HashSet<String>) -> Result<()> {
+    let projects_file = home_projects_file();
+    let data_dir = projects_file.parent().unwrap_or_else(|| Path::new("."));
+    remove_project_side_records_in(data_dir, keys)
+}
+
+fn home_usage_file() -> PathBuf {
+    home_projects_file().with_file_name("usage.json")
+}
+
+/// 串行化用量文件的读改写：多个项目可能同时上传，GUI 与 MCP 服务进程也会同时记录，
+/// 因此使用文件锁而不是进程内的互斥锁
+fn lock_usage_file() -> std::io::Result<fs::File> {
+    use fs2::FileExt;
+    let lock = fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .open(home_usage_file().with_extension("json.lock"))?;
+    lock.lock_exclusive()?;
+    Ok(lock)
+}
+
+/// 读取当日上传用量，日期变化后从 0 开始
+pub(crate) fn load_usage_today() -> AcemcpUsage {
+    let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+    let usage: AcemcpUsage = fs::read_to_string(home_usage_file())
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default();
+    if usage.date == today {
+        usage
+    } else {
+        AcemcpUsage { date: today, ..Default::default() }
+    }
+}
+
+/// 记录一次成功的上传请求及其 blob 数
+fn record_upload_usage(blobs: usize) {
+    let result = (|| -> anyhow::Result<()> {
+        // 文件锁随 _lock 释放而解除
+        let _lock = lock_usage_file()?;
+        let mut usage = load_usage_today();
+        usage.blobs_uploaded += blobs as u64;
+        usage.upload_requests += 1;
+        // 先写临时文件再重命名，读取方不会看到写了一半的内容
+        let path = home_usage_file();
+        let tmp_path = path.with_extension(format!("json.{}.tmp", std::process::id()));
+        fs::write(&tmp_path, serde_json::to_string(&usage)?)?;
+        fs::rename(&tmp_path, &path)?;
+        Ok(())
+    })();
+    if let Err(e) = result {
+        log_debug!("保存上传用量失败（不影响索引）: {}", e);
+    }
+}
+
+/// 计划上传 planned_blobs 个 blob 时，若会超出每日预算则返回提醒文案（未配置预算时不提醒）
+pub(crate) fn daily_budget_warning(budget: Option<u32>, used_today: u64, planned_blobs: u64) -> Option<String> {
+    let budget = budget.filter(|b| *b > 0)? as u64;
+    if used_today + planned_blobs <= budget {
+        return None;
+    }
+    Some(format!(
+        "⚠️ 今日已上传 {} 个 blob，本次预计上传约 {} 个，将超出每日预算 {}，可能触发租户配额限制",
+        used_today, planned_blobs, budget
+    ))
+}
+
+/// 统计本次索引实际需要上传的 blob 数（未记录在 projects.json 与检查点中的 blob）
+/// 强制重建时全部 blob 都需要上传。用于在上传前按每日预算提醒
+pub(crate) async fn count_new_blobs(config: &AcemcpConfig, project_root_path: &str, force_full: bool) -> anyhow::Result<u64> {
+    let max_lines = MaxLinesPolicy::from_config(config);
+    let text_exts = config.text_extensions.clone().unwrap_or_default();
+    let exclude_patterns = config.exclude_patterns.clone().unwrap_or_default();
+    let follow_symlinks = config.follow_symlinks.unwrap_or(false);
+    let root = project_root_path.to_string();
+    let blobs = tokio::task::spawn_blocking(move || {
+        collect_blobs(&root, &text_exts, &exclude_patterns, &max_lines, follow_symlinks)
+    })
+    .await
+    .map_err(|e| anyhow::anyhow!("统计待上传 blob 任务异常: {}", e))??;
+
+    let hashes: HashSet<String> = blobs.iter().map(|b| sha256_hex(&b.path, &b.content)).collect();
+    if force_full {
+        return Ok(hashes.len() as u64);
+    }
+    let normalized_root = normalize_project_path(project_root_path);
+    let mut known: HashSet<String> = load_projects_file().0.remove(&normalized_root).unwrap_or_default().into_iter().collect();
+    known.extend(load_project_checkpoint(&normalized_root));
+    Ok(hashes.difference(&known).count() as u64)
+}
+
+/// 读取项目已记录的 blob 及其上传时间
+/// 早于 blob_times.json 引入的记录没有时间，对应值为 None
+fn load_project_blob_times(normalized_root: &str) -> HashMap<String, Option<chrono::DateTime<chrono::Utc>>> {
+    let names = load_projects_file().0.remove(normalized_root).unwrap_or_default();
+    let times = load_blob_times_file().0.remove(normalized_root).unwrap_or_default();
+    names
+        .into_iter()
+        .map(|name| {
+            let at = times.get(&name).copied();
+            (name, at)
+        })
+        .collect()
+}
+
+/// 规范化项目路径，作为 projects.json / projects_status.json / 文件监听的统一 key
+/// 优先使用 canonical 路径（路径不存在时退化为原始输入），再做字符串层面的统一
+pub fn normalize_project_path(path: &str) -> String {
+    let canonical = PathBuf::from(path)
+        .canonicalize()
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_else(|_| path.to_string());
+    normalize_path_key(&canonical)
+}
+
+/// 纯字符串层面的路径 key 规范化（不访问文件系统）
+/// - 统一使用正斜杠
+/// - 去除 Windows 扩展长度路径前缀（`\\?\` / `//?/`，`UNC/` 还原为 `//`）
+/// - 盘符统一为小写
+/// - 去除末尾斜杠（保留根路径）
+pub(crate) fn normalize_path_key(path: &str) -> String {
+    let mut normalized = path.trim().replace('\\', "/");
+
+    if let Some(rest) = normalized.strip_prefix("//?/") {
+        normalized = match rest.strip_prefix("UNC/") {
+            Some(unc) => format!("//{}", unc),
+            None => rest.to_string(),
+        };
+    }
+
+    let bytes = normalized.as_bytes();
+    if bytes.len() >= 2 && bytes[1] == b':' && bytes[0].is_ascii_alphabetic() {
+        normalized = format!("{}{}", (bytes[0] as char).to_ascii_lowercase(), &normalized[1..]);
+    }
+
+    while normalized.len() > 1 && normalized.ends_with('/') {
+        // 保留盘符根路径，如 "c:/"
+        if normalized.len() == 3 && normalized.as_bytes()[1] == b':' {
+            break;
+        }
+        normalized.pop();
+    }
+
+    normalized
+}
+
+/// 规范化文件扩展名列表：去除空白、补全前导点、统一小写并去重（保持原有顺序）
+/// 存在空条目（如 "" 或 "."）时返回错误
+pub(crate) fn normalize_text_extensions(exts: &[String]) -> std::result::Result<Vec<String>, String> {
+    let mut seen = std::collections::HashSet::new();
+    let mut normalized = Vec::with_capacity(exts.len());
+    for raw in exts {
+        let ext = raw.trim().trim_start_matches('.').to_lowercase();
+        if ext.is_empty() {
+            return Err(format!("文件扩展名不能为空: {:?}", raw));
+        }
+        let ext = format!(".{}", ext);
+        if seen.insert(ext.clone()) {
+            normalized.push(ext);
+        }
+    }
+    Ok(normalized)
+}
+
+/// 规范化按扩展名覆盖的最大行数：扩展名补全前导点并统一小写，行数必须为正数
+pub(crate) fn normalize_max_lines_overrides(
+    overrides: &HashMap<String, u32>,
+) -> std::result::Result<HashMap<String, u32>, String> {
+    let mut normalized = HashMap::with_capacity(overrides.len());
+    for (raw, lines) in overrides {
+        let ext = raw.trim().trim_start_matches('.').to_lowercase();
+        if ext.is_empty() {
+            return Err(format!("文件扩展名不能为空: {:?}", raw));
+        }
+        if *lines == 0 {
+            return Err(format!("扩展名 .{} 的最大行数必须大于 0", ext));
+        }
+        normalized.insert(format!(".{}", ext), *lines);
+    }
+    Ok(normalized)
+}
+
+/// 宽松版本：忽略空条目，用于读取历史配置
+pub(crate) fn normalize_text_extensions_lenient(exts: &[String]) -> Vec<String> {
+    let valid: Vec<String> = exts
+        .iter()
+        .filter(|e| !e.trim().trim_start_matches('.').is_empty())
+        .cloned()
+        .collect();
+    normalize_text_extensions(&valid).unwrap_or_default()
+}
+
+/// 获取项目索引状态文件路径
+fn home_projects_status_file() -> PathBuf {
+    let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
+    let data_dir = home.join(".acemcp").join("data");
+    let _ = fs::create_dir_all(&data_dir);
+    data_dir.join("projects_status.json")
+}
+
+/// 读取所有项目的索引状态
+fn load_projects_status() -> ProjectsIndexStatus {
+    let status_path = home_projects_status_file();
+    log_debug!("📂 [load_projects_status] 状态文件路径: {:?}", status_path);
+    
+    if status_path.exists() {
+        let data = fs::read_to_string(&status_path).unwrap_or_default();
+        log_debug!("📄 [load_projects_status] 读取到状态文件，大小: {} 字节", data.len());
+        
+        match serde_json::from_str::<ProjectsIndexStatus>(&data) {
+            Ok(mut status) => {
+                log_debug!("✅ [load_projects_status] 解析成功，项目数: {}", status.projects.len());
+                // 兼容旧版本写入的未规范化 key
+                let projects = std::mem::take(&mut status.projects);
+                for (key, mut project) in projects {
+                    let normalized = normalize_path_key(&key);
+                    project.project_root = normalized.clone();
+                    status.projects.entry(normalized).or_insert(project);
+                }
+                status
+            }
+            Err(e) => {
+                log_debug!("⚠️ [load_projects_status] 解析失败: {}", e);
+                ProjectsIndexStatus::default()
+            }
+        }
+    } else {
+        log_debug!("📭 [load_projects_status] 状态文件不存在，返回空列表");
+        ProjectsIndexStatus::default()
+    }
+}
+
+/// 保存所有项目的索引状态
+fn save_projects_status(status: &ProjectsIndexStatus) -> Result<()> {
+    let status_path = home_projects_status_file();
+    let data = serde_json::to_string_pretty(status)?;
+    fs::write(status_path, data)?;
+    Ok(())
+}
+
+/// 更新指定项目的索引状态
+fn update_project_status<F>(project_root: &str, updater: F) -> Result<()>
+where
+    F: FnOnce(&mut ProjectIndexStatus),
 {
     let mut all_status = load_projects_status();
-    let normalized_root = PathBuf::from(project_root)
-        .canonicalize()
-        .unwrap_or_else(|_| PathBuf::from(project_root))
-        .to_string_lossy()
-        .replace('\\', "/");
+    let normalized_root = normalize_project_path(project_root);
 
     let project_status = all_status.projects
         .entry(normalized_root.clone())
@@ -504,11 +1422,7 @@ where
 /// 获取指定项目的索引状态
 fn get_project_status(project_root: &str) -> ProjectIndexStatus {
     let all_status = load_projects_status();
-    let normalized_root = PathBuf::from(project_root)
-        .canonicalize()
-        .unwrap_or_else(|_| PathBuf::from(project_root))
-        .to_string_lossy()
-        .replace('\\', "/");
+    let normalized_root = normalize_project_path(project_root);
 
     all_status.projects.get(&normalized_root).cloned().unwrap_or_else(|| {
         let mut status = ProjectIndexStatus::default();
@@ -519,25 +1433,23 @@ fn get_project_status(project_root: &str) -> ProjectIndexStatus {
 
 /// 读取文件内容，支持多种编码检测
 /// 尝试的编码顺序：utf-8, gbk (包含 gb2312), windows-1252 (包含 latin-1)
-/// 如果都失败，则使用 utf-8 with errors='ignore'
-fn read_file_with_encoding(path: &Path) -> Option<String> {
-    let mut file = fs::File::open(path).ok()?;
+/// 如果都失败，则使用 utf-8 with errors='ignore'；仅在文件无法打开或读取时返回错误
+fn read_file_with_encoding(path: &Path) -> std::io::Result<String> {
+    let mut file = fs::File::open(path)?;
     let mut buf = Vec::new();
-    if file.read_to_end(&mut buf).is_err() {
-        return None;
-    }
+    file.read_to_end(&mut buf)?;
 
     // 尝试 utf-8
     let (decoded, _, had_errors) = UTF_8.decode(&buf);
     if !had_errors {
-        return Some(decoded.into_owned());
+        return Ok(decoded.into_owned());
     }
 
     // 尝试 gbk
     let (decoded, _, had_errors) = GBK.decode(&buf);
     if !had_errors {
         log_debug!("成功使用 GBK 编码读取文件: {:?}", path);
-        return Some(decoded.into_owned());
+        return Ok(decoded.into_owned());
     }
 
     // 尝试 gb2312 (GBK 是 GB2312 的超集，可以处理 GB2312 编码)
@@ -548,13 +1460,13 @@ fn read_file_with_encoding(path: &Path) -> Option<String> {
     let (decoded, _, had_errors) = WINDOWS_1252.decode(&buf);
     if !had_errors {
         log_debug!("成功使用 WINDOWS_1252 编码读取文件: {:?}", path);
-        return Some(decoded.into_owned());
+        return Ok(decoded.into_owned());
     }
 
     // 如果所有编码都失败，使用 utf-8 with errors='ignore' (lossy 解码)
     let (decoded, _, _) = UTF_8.decode(&buf);
     log_debug!("使用 UTF-8 (lossy) 读取文件，部分字符可能丢失: {:?}", path);
-    Some(decoded.into_owned())
+    Ok(decoded.into_owned())
 }
 
 fn sha256_hex(path: &str, content: &str) -> String {
@@ -566,6 +1478,108 @@ fn sha256_hex(path: &str, content: &str) -> String {
     hex::encode(digest.as_ref())
 }
 
+/// blob 所属文件的路径（去掉分块后缀 `#chunkNofM`）
+pub(crate) fn blob_file_key(blob_path: &str) -> &str {
+    let is_number = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+    match blob_path.rsplit_once("#chunk") {
+        Some((file, suffix))
+            if suffix
+                .split_once("of")
+                .map(|(n, m)| is_number(n) && is_number(m))
+                .unwrap_or(false) =>
+        {
+            file
+        }
+        _ => blob_path,
+    }
+}
+
+/// 规划上传批次：每批最多包含 `max_files` 个文件且最多 `max_blobs` 个 blob，任一达到上限即切分
+/// 单个文件的 blob 数超过上限时跨多个请求发送；返回各批次在 blob 列表中的下标区间
+pub(crate) fn plan_upload_batches<'a>(
+    file_keys: impl IntoIterator<Item = &'a str>,
+    max_files: usize,
+    max_blobs: usize,
+) -> Vec<std::ops::Range<usize>> {
+    let max_files = max_files.max(1);
+    let max_blobs = max_blobs.max(1);
+    let mut ranges = Vec::new();
+    let mut start = 0usize;
+    let mut files_in_batch = 0usize;
+    let mut last_file: Option<&str> = None;
+    let mut total = 0usize;
+
+    for (idx, key) in file_keys.into_iter().enumerate() {
+        let new_file = last_file != Some(key);
+        let blobs_in_batch = idx - start;
+        if blobs_in_batch > 0
+            && (blobs_in_batch >= max_blobs || (new_file && files_in_batch >= max_files))
+        {
+            ranges.push(start..idx);
+            start = idx;
+            files_in_batch = 0;
+        }
+        // 新文件或续传的超大文件分片都计入本批文件数
+        if new_file || idx == start {
+            files_in_batch += 1;
+        }
+        last_file = Some(key);
+        total = idx + 1;
+    }
+    if total > start {
+        ranges.push(start..total);
+    }
+    ranges
+}
+
+/// 分块行数策略：按扩展名覆盖全局的 max_lines_per_blob（Markdown 文档与压缩后的 JS 适合的粒度差别很大）
+#[derive(Debug, Clone)]
+pub(crate) struct MaxLinesPolicy {
+    default: usize,
+    /// 带点的小写扩展名（可为复合扩展名，如 .min.js）-> 最大行数
+    overrides: Vec<(String, usize)>,
+}
+
+impl MaxLinesPolicy {
+    /// 忽略为 0 或扩展名为空的覆盖项
+    pub(crate) fn new(default: usize, overrides: Option<&HashMap<String, u32>>) -> Self {
+        let mut overrides: Vec<(String, usize)> = overrides
+            .into_iter()
+            .flatten()
+            .filter(|(_, lines)| **lines > 0)
+            .filter_map(|(ext, lines)| {
+                let ext = ext.trim().trim_start_matches('.').to_lowercase();
+                (!ext.is_empty()).then(|| (format!(".{}", ext), *lines as usize))
+            })
+            .collect();
+        // 复合扩展名优先匹配（.min.js 先于 .js）
+        overrides.sort_by(|a, b| b.0.len().cmp(&a.0.len()).then_with(|| a.0.cmp(&b.0)));
+        Self { default: default.max(1), overrides }
+    }
+
+    pub(crate) fn from_config(config: &AcemcpConfig) -> Self {
+        Self::new(
+            config.max_lines_per_blob.unwrap_or(DEFAULT_ACEMCP_MAX_LINES_PER_BLOB) as usize,
+            config.max_lines_by_extension.as_ref(),
+        )
+    }
+
+    /// 全局最大行数
+    pub(crate) fn default_lines(&self) -> usize {
+        self.default
+    }
+
+    /// 文件使用的最大行数：匹配最长的扩展名覆盖，未覆盖时使用全局值
+    pub(crate) fn for_path(&self, path: &str) -> usize {
+        let name = path.rsplit(['/', '\\']).next().unwrap_or(path).to_lowercase();
+        self.overrides
+            .iter()
+            .find(|(ext, _)| name.ends_with(ext.as_str()) && name.len() > ext.len())
+            .map(|(_, lines)| *lines)
+            .unwrap_or(self.default)
+    }
+}
+
 /// 分割文件内容为多个 blob（如果超过最大行数）
 /// 与 Python 版本保持一致：chunk 索引从 1 开始
 fn split_content(path: &str, content: &str, max_lines: usize) -> Vec<BlobItem> {
@@ -596,53 +1610,141 @@ fn split_content(path: &str, content: &str, max_lines: usize) -> Vec<BlobItem> {
     blobs
 }
 
-/// 构建排除模式的 GlobSet
-fn build_exclude_globset(exclude_patterns: &[String]) -> Result<GlobSet> {
-    let mut builder = GlobSetBuilder::new();
-    for pattern in exclude_patterns {
-        // 尝试将模式转换为 Glob
-        if let Ok(glob) = Glob::new(pattern) {
-            builder.add(glob);
-        } else {
-            log_debug!("无效的排除模式，跳过: {}", pattern);
-        }
+/// 预览单个文件的分块结果（与索引阶段使用同一套读取与分割逻辑，不上传）
+pub(crate) fn preview_file_chunks(
+    root: &str,
+    rel_path: &str,
+    max_lines_per_blob: &MaxLinesPolicy,
+) -> anyhow::Result<FileChunkPreview> {
+    let root_path = PathBuf::from(root);
+    if !root_path.exists() {
+        anyhow::bail!("项目根目录不存在: {}", root);
     }
-    builder.build().map_err(|e| anyhow::anyhow!("构建排除模式失败: {}", e))
-}
 
-/// 检查路径是否应该被排除
-/// 使用 globset 进行完整的 fnmatch 模式匹配（与 Python 版本保持一致）
-/// Python 版本使用 fnmatch.fnmatch 检查路径的各个部分和完整路径
-fn should_exclude(path: &Path, root: &Path, exclude_globset: Option<&GlobSet>) -> bool {
-    if exclude_globset.is_none() {
-        return false;
+    // 仅允许项目内的相对路径，拒绝绝对路径与 `..`
+    let rel = rel_path.trim().replace('\\', "/");
+    let rel = rel.trim_start_matches("./");
+    let rel_as_path = Path::new(rel);
+    if rel.is_empty()
+        || !rel_as_path
+            .components()
+            .all(|c| matches!(c, std::path::Component::Normal(_)))
+    {
+        anyhow::bail!("无效的相对路径: {}", rel_path);
     }
-    let globset = exclude_globset.unwrap();
 
-    // 获取相对路径
-    let rel = match path.strip_prefix(root) {
-        Ok(rel) => rel,
-        Err(_) => path,
-    };
+    let abs_path = root_path.join(rel_as_path);
+    if !abs_path.is_file() {
+        anyhow::bail!("文件不存在: {}", rel);
+    }
 
-    // 转换为使用正斜杠的字符串（用于匹配）
-    let rel_forward = rel.to_string_lossy().replace('\\', "/");
-    
-    // 检查完整相对路径（与 Python 版本的 fnmatch(path_str, pattern) 一致）
-    if globset.is_match(&rel_forward) {
-        return true;
+    let content = read_file_with_encoding(&abs_path)
+        .map_err(|e| anyhow::anyhow!("读取文件失败: path={}, error={}", rel, e))?;
+    let max_lines = max_lines_per_blob.for_path(rel);
+    let blobs = split_content(rel, &content, max_lines);
+
+    let mut chunks = Vec::with_capacity(blobs.len());
+    let mut next_line = 1usize;
+    for blob in &blobs {
+        let line_count = blob.content.split_inclusive('\n').count();
+        chunks.push(FileChunkInfo {
+            chunk_path: blob.path.clone(),
+            start_line: next_line,
+            end_line: (next_line + line_count).saturating_sub(1),
+            byte_size: blob.content.len(),
+            blob_name: sha256_hex(&blob.path, &blob.content),
+        });
+        next_line += line_count;
     }
 
-    // 检查路径的各个部分（与 Python 版本的 fnmatch(part, pattern) 一致）
-    for part in rel.iter() {
-        if let Some(part_str) = part.to_str() {
-            if globset.is_match(part_str) {
-                return true;
+    Ok(FileChunkPreview {
+        project_root: normalize_project_path(root),
+        rel_path: rel.to_string(),
+        total_lines: next_line - 1,
+        total_bytes: content.len(),
+        max_lines_per_blob: max_lines,
+        chunks,
+    })
+}
+
+/// 正则排除模式前缀，例如 `re:.*\.generated\..*`
+const EXCLUDE_REGEX_PREFIX: &str = "re:";
+
+/// 单条排除规则
+enum ExcludeRule {
+    /// glob 模式（不含通配符的普通字符串按路径片段精确匹配）
+    Glob(globset::GlobMatcher),
+    /// `re:` 前缀的正则，匹配完整相对路径（未锚定，可匹配任意位置）
+    Regex(regex::Regex),
+}
+
+/// 排除模式匹配器
+///
+/// 支持三种写法：
+/// - 普通字符串（如 `node_modules`）：与路径中任一片段完全相同时排除，不会误伤 `my_node_modules`
+/// - glob（如 `*.min.js`、`docs/**/*.pdf`）：匹配完整相对路径或任一路径片段
+/// - `re:` 前缀的正则：匹配使用正斜杠的完整相对路径
+///
+/// 多个模式同时命中时，以配置中靠前的模式为准（用于排除预览的统计归属）
+pub(crate) struct ExcludeMatcher {
+    rules: Vec<ExcludeRule>,
+    /// 有效模式（与 rules 下标一一对应）
+    patterns: Vec<String>,
+    /// 无法解析的模式（已跳过）
+    invalid: Vec<String>,
+}
+
+impl ExcludeMatcher {
+    pub(crate) fn new(exclude_patterns: &[String]) -> Self {
+        let mut matcher = Self { rules: Vec::new(), patterns: Vec::new(), invalid: Vec::new() };
+        for pattern in exclude_patterns {
+            let rule = match pattern.strip_prefix(EXCLUDE_REGEX_PREFIX) {
+                Some(re) => regex::Regex::new(re).ok().map(ExcludeRule::Regex),
+                None => Glob::new(pattern).ok().map(|g| ExcludeRule::Glob(g.compile_matcher())),
+            };
+            match rule {
+                Some(rule) => {
+                    matcher.rules.push(rule);
+                    matcher.patterns.push(pattern.clone());
+                }
+                None => {
+                    log_debug!("无效的排除模式，跳过: {}", pattern);
+                    matcher.invalid.push(pattern.clone());
+                }
             }
         }
+        matcher
     }
 
-    false
+    /// 返回命中的排除模式在有效模式中的下标（未命中返回 None）
+    /// 匹配顺序：先匹配完整相对路径，再逐个匹配路径的各个部分（仅 glob）
+    fn match_index(&self, path: &Path, root: &Path) -> Option<usize> {
+        let rel = path.strip_prefix(root).unwrap_or(path);
+        // 转换为使用正斜杠的字符串（用于匹配）
+        let rel_forward = rel.to_string_lossy().replace('\\', "/");
+
+        // 检查完整相对路径（与 Python 版本的 fnmatch(path_str, pattern) 一致）
+        let full_match = self.rules.iter().position(|rule| match rule {
+            ExcludeRule::Glob(glob) => glob.is_match(&rel_forward),
+            ExcludeRule::Regex(re) => re.is_match(&rel_forward),
+        });
+        if full_match.is_some() {
+            return full_match;
+        }
+
+        // 检查路径的各个部分（与 Python 版本的 fnmatch(part, pattern) 一致）
+        for part in rel.iter().filter_map(|p| p.to_str()) {
+            let part_match = self.rules.iter().position(|rule| match rule {
+                ExcludeRule::Glob(glob) => glob.is_match(part),
+                ExcludeRule::Regex(_) => false,
+            });
+            if part_match.is_some() {
+                return part_match;
+            }
+        }
+
+        None
+    }
 }
 
 fn build_gitignore(root: &Path) -> Option<Gitignore> {
@@ -655,217 +1757,902 @@ fn build_gitignore(root: &Path) -> Option<Gitignore> {
     None
 }
 
-fn collect_blobs(root: &str, text_exts: &[String], exclude_patterns: &[String], max_lines_per_blob: usize) -> anyhow::Result<Vec<BlobItem>> {
-    let root_path = PathBuf::from(root);
-    if !root_path.exists() { anyhow::bail!("项目根目录不存在: {}", root); }
-    
-    log_important!(info, "开始收集代码文件: 根目录={}, 扩展名={:?}, 排除模式={:?}", root, text_exts, exclude_patterns);
-    
-    // 构建排除模式的 GlobSet
-    let exclude_globset = if exclude_patterns.is_empty() {
-        None
-    } else {
-        match build_exclude_globset(exclude_patterns) {
-            Ok(gs) => Some(gs),
-            Err(e) => {
-                log_debug!("构建排除模式失败，将使用简单匹配: {}", e);
+/// 项目本地排除规则文件（gitignore 语法）
+pub(crate) const LOCAL_IGNORE_FILE: &str = ".acemcpignore";
+/// 项目本地包含规则文件（gitignore 语法，命中即强制纳入索引）
+pub(crate) const LOCAL_INCLUDE_FILE: &str = ".acemcpinclude";
+
+/// 从项目根目录下的规则文件构建匹配器（文件不存在或解析失败返回 None）
+fn build_local_rules(root: &Path, file_name: &str) -> Option<Gitignore> {
+    let path = root.join(file_name);
+    if !path.exists() {
+        return None;
+    }
+    let mut builder = GitignoreBuilder::new(root);
+    if let Some(e) = builder.add(&path) {
+        log_debug!("解析 {} 失败，已忽略: {}", file_name, e);
+        return None;
+    }
+    builder.build().ok()
+}
+
+/// 提取包含规则中的字面目录前缀（用于判断是否需要进入被排除的目录）
+/// 例如 `vendor/ourlib/**` -> `vendor/ourlib`；不含 `/` 的模式返回 None
+fn include_dir_prefixes(root: &Path) -> Vec<String> {
+    let content = match fs::read_to_string(root.join(LOCAL_INCLUDE_FILE)) {
+        Ok(c) => c,
+        Err(_) => return Vec::new(),
+    };
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#') && !l.starts_with('!'))
+        .filter_map(|l| {
+            let pattern = l.trim_start_matches('/').trim_end_matches('/');
+            let literal_end = pattern.find(['*', '?', '[', '{']).unwrap_or(pattern.len());
+            let literal = &pattern[..literal_end];
+            // 只有字面部分包含目录层级时才有意义
+            let dir = if literal_end == pattern.len() { literal } else { literal.rsplit_once('/')?.0 };
+            if dir.is_empty() || !pattern.contains('/') {
                 None
+            } else {
+                Some(dir.to_string())
             }
+        })
+        .collect()
+}
+
+/// 路径被排除的原因（排除规则预览按此归因）
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ExcludeSource {
+    /// `.acemcpignore` 命中
+    LocalIgnore,
+    /// `.gitignore` 命中
+    Gitignore,
+    /// 全局排除模式命中（有效模式中的下标）
+    Pattern(usize),
+}
+
+/// 路径判定结果
+enum PathDecision {
+    /// 排除（不索引），并记录由哪一层规则决定
+    Excluded(ExcludeSource),
+    /// 纳入索引，并记录由哪一层规则决定
+    Included(IncludeSource),
+}
+
+/// 分层的文件过滤规则
+///
+/// 优先级（从高到低）：
+/// 1. `.acemcpinclude` 命中：强制纳入（可覆盖下面所有排除规则）
+/// 2. `.acemcpignore` 命中：排除
+/// 3. `.gitignore` 命中：排除
+/// 4. 全局 `exclude_patterns` 命中：排除
+/// 5. 其余路径按全局规则纳入
+///
+/// 被排除的目录默认不再深入遍历；仅当 `.acemcpinclude` 中存在以该目录为前缀的
+/// 带路径模式（如 `vendor/ourlib/`）时才继续进入，以便找回其中被包含的文件。
+pub(crate) struct PathRules {
+    root: PathBuf,
+    gitignore: Option<Gitignore>,
+    excludes: ExcludeMatcher,
+    local_ignore: Option<Gitignore>,
+    local_include: Option<Gitignore>,
+    include_prefixes: Vec<String>,
+}
+
+impl PathRules {
+    pub(crate) fn new(root: &Path, exclude_patterns: &[String]) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            gitignore: build_gitignore(root),
+            excludes: ExcludeMatcher::new(exclude_patterns),
+            local_ignore: build_local_rules(root, LOCAL_IGNORE_FILE),
+            local_include: build_local_rules(root, LOCAL_INCLUDE_FILE),
+            include_prefixes: include_dir_prefixes(root),
         }
-    };
-    
-    let mut out = Vec::new();
-    let gitignore = build_gitignore(&root_path);
-    let mut dirs_stack = vec![root_path.clone()];
-    let mut scanned_files = 0;
-    let mut indexed_files = 0;
-    let mut excluded_count = 0;
-    
-    while let Some(dir) = dirs_stack.pop() {
-        let entries = match fs::read_dir(&dir) { Ok(e) => e, Err(_) => continue };
-        for entry in entries.flatten() {
-            let p = entry.path();
-            
-            // 检查 .gitignore
-            if let Some(gi) = &gitignore {
-                if gi.matched_path_or_any_parents(&p, p.is_dir()).is_ignore() { continue; }
+    }
+
+    /// 按优先级判定路径是否纳入索引
+    fn decide(&self, path: &Path, is_dir: bool) -> PathDecision {
+        if let Some(include) = &self.local_include {
+            if include.matched_path_or_any_parents(path, is_dir).is_ignore() {
+                return PathDecision::Included(IncludeSource::Local);
             }
-            
-            // 检查排除模式
-            if p.is_dir() {
-                if should_exclude(&p, &root_path, exclude_globset.as_ref()) {
-                    excluded_count += 1;
-                    continue;
-                }
-                dirs_stack.push(p);
-                continue;
+        }
+        if let Some(ignore) = &self.local_ignore {
+            if ignore.matched_path_or_any_parents(path, is_dir).is_ignore() {
+                return PathDecision::Excluded(ExcludeSource::LocalIgnore);
             }
-            
-            scanned_files += 1;
-            if should_exclude(&p, &root_path, exclude_globset.as_ref()) {
-                excluded_count += 1;
-                log_debug!("排除文件: {:?}", p);
+        }
+        if let Some(gi) = &self.gitignore {
+            if gi.matched_path_or_any_parents(path, is_dir).is_ignore() {
+                return PathDecision::Excluded(ExcludeSource::Gitignore);
+            }
+        }
+        if let Some(idx) = self.excludes.match_index(path, &self.root) {
+            return PathDecision::Excluded(ExcludeSource::Pattern(idx));
+        }
+        PathDecision::Included(IncludeSource::Global)
+    }
+
+    /// 路径是否被任一层规则排除（供文件监听过滤事件使用）
+    pub(crate) fn is_excluded(&self, path: &Path, is_dir: bool) -> bool {
+        matches!(self.decide(path, is_dir), PathDecision::Excluded(_))
+    }
+
+    /// 被排除的目录是否仍需深入（其下存在 `.acemcpinclude` 指定的路径）
+    fn has_included_descendants(&self, dir: &Path) -> bool {
+        if self.include_prefixes.is_empty() {
+            return false;
+        }
+        let rel = dir.strip_prefix(&self.root).unwrap_or(dir).to_string_lossy().replace('\\', "/");
+        let rel_with_slash = format!("{}/", rel);
+        self.include_prefixes.iter().any(|prefix| prefix.starts_with(&rel_with_slash))
+    }
+}
+
+/// 项目目录遍历栈：统一处理符号链接目录
+///
+/// - 不跟随（默认）：符号链接指向的目录直接跳过，避免扫描到项目之外
+/// - 跟随：按规范化路径记录已访问目录，链接成环或多条链接指向同一目录时只遍历一次
+struct DirWalker {
+    stack: Vec<PathBuf>,
+    follow_symlinks: bool,
+    visited: HashSet<PathBuf>,
+}
+
+impl DirWalker {
+    fn new(root: &Path, follow_symlinks: bool) -> Self {
+        let mut visited = HashSet::new();
+        if follow_symlinks {
+            if let Ok(canonical) = fs::canonicalize(root) {
+                visited.insert(canonical);
+            }
+        }
+        Self { stack: vec![root.to_path_buf()], follow_symlinks, visited }
+    }
+
+    fn next_dir(&mut self) -> Option<PathBuf> {
+        self.stack.pop()
+    }
+
+    /// 将子目录加入待遍历栈（符号链接目录或已访问目录会被跳过）
+    fn push_dir(&mut self, dir: PathBuf) {
+        if !self.follow_symlinks {
+            let is_link = fs::symlink_metadata(&dir).map(|m| m.file_type().is_symlink()).unwrap_or(false);
+            if is_link {
+                log_debug!("跳过符号链接目录: {:?}", dir);
+            } else {
+                self.stack.push(dir);
+            }
+            return;
+        }
+
+        match fs::canonicalize(&dir) {
+            Ok(canonical) if self.visited.insert(canonical) => self.stack.push(dir),
+            Ok(_) => log_debug!("跳过已遍历的目录（符号链接循环或重复指向）: {:?}", dir),
+            Err(e) => log_debug!("无法解析目录真实路径，已跳过: {:?}, error={}", dir, e),
+        }
+    }
+}
+
+/// 项目遍历中回调的路径
+enum WalkEntry<'a> {
+    /// 被排除且不再深入的目录
+    ExcludedDir(&'a Path, ExcludeSource),
+    /// 文件及其判定结果
+    File(&'a fs::DirEntry, &'a PathDecision),
+}
+
+/// 按分层过滤规则遍历项目（collect_blobs、estimate_files、文件状态扫描、排除预览共用）
+///
+/// 纳入的目录会继续深入；被排除的目录仅在其下存在 `.acemcpinclude` 路径时深入，
+/// 否则作为 `ExcludedDir` 回调一次。回调返回错误时立即停止遍历（用于取消）。
+fn walk_project(
+    root_path: &Path,
+    rules: &PathRules,
+    follow_symlinks: bool,
+    on_entry: &mut dyn FnMut(WalkEntry<'_>) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    let mut walker = DirWalker::new(root_path, follow_symlinks);
+    while let Some(dir) = walker.next_dir() {
+        let entries = match fs::read_dir(&dir) {
+            Ok(e) => e,
+            Err(_) => continue,
+        };
+        for entry in entries.flatten() {
+            let p = entry.path();
+            let is_dir = p.is_dir();
+            let decision = rules.decide(&p, is_dir);
+            if is_dir {
+                match decision {
+                    PathDecision::Included(_) => walker.push_dir(p),
+                    PathDecision::Excluded(_) if rules.has_included_descendants(&p) => walker.push_dir(p),
+                    PathDecision::Excluded(source) => on_entry(WalkEntry::ExcludedDir(&p, source))?,
+                }
                 continue;
             }
-            
-            // 检查文件扩展名
-            let ext_ok = p.extension().and_then(|s| s.to_str()).map(|e| {
-                let dot = format!(".{}", e).to_lowercase();
-                text_exts.iter().any(|te| te.eq_ignore_ascii_case(&dot))
-            }).unwrap_or(false);
-            if !ext_ok { continue; }
-            
-            // 读取文件内容（使用多编码支持）
-            let rel = p.strip_prefix(&root_path).unwrap_or(&p).to_string_lossy().replace('\\', "/");
-            if let Some(content) = read_file_with_encoding(&p) {
-                let parts = split_content(&rel, &content, max_lines_per_blob);
+            on_entry(WalkEntry::File(&entry, &decision))?;
+        }
+    }
+    Ok(())
+}
+
+/// 文件扩展名是否在可索引的文本扩展名列表中（扩展名带点，不区分大小写）
+pub(crate) fn has_text_extension(path: &Path, text_exts: &[String]) -> bool {
+    path.extension()
+        .and_then(|s| s.to_str())
+        .map(|e| {
+            let dot = format!(".{}", e);
+            text_exts.iter().any(|te| te.eq_ignore_ascii_case(&dot))
+        })
+        .unwrap_or(false)
+}
+
+fn collect_blobs(root: &str, text_exts: &[String], exclude_patterns: &[String], max_lines_per_blob: &MaxLinesPolicy, follow_symlinks: bool) -> anyhow::Result<Vec<BlobItem>> {
+    collect_blobs_with_errors(root, text_exts, exclude_patterns, max_lines_per_blob, follow_symlinks).map(|(blobs, _)| blobs)
+}
+
+/// 收集 blob，同时返回无法读取的文件明细
+fn collect_blobs_with_errors(root: &str, text_exts: &[String], exclude_patterns: &[String], max_lines_per_blob: &MaxLinesPolicy, follow_symlinks: bool) -> anyhow::Result<(Vec<BlobItem>, FileErrorList)> {
+    let root_path = PathBuf::from(root);
+    if !root_path.exists() { anyhow::bail!("项目根目录不存在: {}", root); }
+    
+    log_important!(info, "开始收集代码文件: 根目录={}, 扩展名={:?}, 排除模式={:?}", root, text_exts, exclude_patterns);
+    
+    // 构建分层过滤规则（.acemcpinclude > .acemcpignore > .gitignore > 全局排除模式）
+    let rules = PathRules::new(&root_path, exclude_patterns);
+    
+    let mut out = Vec::new();
+    let mut read_errors = FileErrorList::default();
+    let mut scanned_files = 0;
+    let mut indexed_files = 0;
+    let mut excluded_count = 0;
+    
+    walk_project(&root_path, &rules, follow_symlinks, &mut |walk_entry| {
+        let entry = match walk_entry {
+            WalkEntry::ExcludedDir(..) => {
+                excluded_count += 1;
+                return Ok(());
+            }
+            WalkEntry::File(entry, decision) => {
+                scanned_files += 1;
+                if let PathDecision::Excluded(_) = decision {
+                    excluded_count += 1;
+                    log_debug!("排除文件: {:?}", entry.path());
+                    return Ok(());
+                }
+                entry
+            }
+        };
+        
+        // 检查文件扩展名
+        let p = entry.path();
+        if !has_text_extension(&p, text_exts) { return Ok(()); }
+        
+        // 读取文件内容（使用多编码支持）
+        let rel = p.strip_prefix(&root_path).unwrap_or(&p).to_string_lossy().replace('\\', "/");
+        match read_file_with_encoding(&p) {
+            Ok(content) => {
+                let parts = split_content(&rel, &content, max_lines_per_blob.for_path(&rel));
                 let blob_count = parts.len();
                 indexed_files += 1;
                 out.extend(parts);
                 log_important!(info, "索引文件: path={}, content_length={}, blobs={}", rel, content.len(), blob_count);
-            } else {
-                log_debug!("无法读取文件: {:?}", p);
+            }
+            Err(e) => {
+                log_debug!("无法读取文件: {:?}, error={}", p, e);
+                read_errors.push(rel, format!("读取失败: {}", e));
             }
         }
-    }
+        Ok(())
+    })?;
     
-    log_important!(info, "文件收集完成: 扫描文件数={}, 索引文件数={}, 生成blobs数={}, 排除文件/目录数={}", scanned_files, indexed_files, out.len(), excluded_count);
-    Ok(out)
+    log_important!(info, "文件收集完成: 扫描文件数={}, 索引文件数={}, 生成blobs数={}, 排除文件/目录数={}, 读取失败数={}", scanned_files, indexed_files, out.len(), excluded_count, read_errors.total);
+    Ok((out, read_errors))
 }
 
-/// 收集项目内所有可索引文件的索引状态
-///
-/// 为避免引入新的持久化结构，这里通过重新扫描文件并复用与索引阶段相同的
-/// 路径规范化与分块逻辑，基于现有的 blob 哈希集合判断文件是否“已完全索引”。
-fn collect_file_statuses(
+/// 估算时假定的平均每行字节数（用于不读内容时推算分块数量）
+const ESTIMATED_BYTES_PER_LINE: u64 = 40;
+
+/// 按与 collect_blobs 相同的过滤规则遍历项目，仅读取文件元数据
+/// 返回 (文件数, 总字节数, 预估 blob 数)
+fn estimate_files(
     root: &str,
     text_exts: &[String],
     exclude_patterns: &[String],
-    max_lines_per_blob: usize,
-    existing_blob_names: &HashSet<String>,
-) -> anyhow::Result<Vec<FileIndexStatus>> {
+    max_lines_per_blob: &MaxLinesPolicy,
+    follow_symlinks: bool,
+) -> anyhow::Result<(usize, u64, usize)> {
     let root_path = PathBuf::from(root);
     if !root_path.exists() {
         anyhow::bail!("项目根目录不存在: {}", root);
     }
 
-    // 构建排除模式的 GlobSet
-    let exclude_globset = if exclude_patterns.is_empty() {
-        None
-    } else {
-        match build_exclude_globset(exclude_patterns) {
-            Ok(gs) => Some(gs),
-            Err(e) => {
-                log_debug!("构建排除模式失败，将使用简单匹配: {}", e);
-                None
-            }
+    let rules = PathRules::new(&root_path, exclude_patterns);
+    let mut file_count = 0usize;
+    let mut total_bytes = 0u64;
+    let mut estimated_blobs = 0usize;
+
+    walk_project(&root_path, &rules, follow_symlinks, &mut |walk_entry| {
+        let entry = match walk_entry {
+            WalkEntry::File(entry, PathDecision::Included(_)) => entry,
+            _ => return Ok(()),
+        };
+        let p = entry.path();
+        if !has_text_extension(&p, text_exts) {
+            return Ok(());
         }
-    };
 
-    let gitignore = build_gitignore(&root_path);
-    let mut dirs_stack = vec![root_path.clone()];
-    let mut files_status = Vec::new();
+        let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+        let bytes_per_blob = (max_lines_per_blob.for_path(&p.to_string_lossy()) as u64) * ESTIMATED_BYTES_PER_LINE;
+        file_count += 1;
+        total_bytes += size;
+        estimated_blobs += size.div_ceil(bytes_per_blob).max(1) as usize;
+        Ok(())
+    })?;
 
-    while let Some(dir) = dirs_stack.pop() {
-        let entries = match fs::read_dir(&dir) {
-            Ok(e) => e,
-            Err(_) => continue,
-        };
+    Ok((file_count, total_bytes, estimated_blobs))
+}
 
-        for entry in entries.flatten() {
-            let p = entry.path();
+/// 排除预览中每个模式最多返回的示例路径数
+const EXCLUSION_SAMPLE_LIMIT: usize = 20;
 
-            // .gitignore 过滤
-            if let Some(gi) = &gitignore {
-                if gi.matched_path_or_any_parents(&p, p.is_dir()).is_ignore() {
-                    continue;
-                }
+/// 预览排除模式对项目的实际效果
+///
+/// 与 collect_blobs 共用同一套分层规则与遍历：`.acemcpinclude` 找回的路径不计为排除，
+/// `.gitignore` 忽略的路径不计入任何统计，`.acemcpignore` 排除的文件单独计数；
+/// 被排除的目录会继续统计其下的文件数量，以便展示“某模式排除了多少文件”。
+pub(crate) fn preview_exclusions(root: &str, exclude_patterns: &[String], follow_symlinks: bool) -> anyhow::Result<ExclusionPreview> {
+    let root_path = PathBuf::from(root);
+    if !root_path.exists() {
+        anyhow::bail!("项目根目录不存在: {}", root);
+    }
+
+    // 无效模式会被跳过，统计下标与 excludes.patterns 一一对应
+    let rules = PathRules::new(&root_path, exclude_patterns);
+
+    let mut pattern_stats: Vec<ExclusionPatternStats> = rules
+        .excludes
+        .patterns
+        .iter()
+        .map(|p| ExclusionPatternStats {
+            pattern: p.clone(),
+            excluded_files: 0,
+            excluded_dirs: 0,
+            sample_paths: Vec::new(),
+        })
+        .collect();
+
+    let mut total_files = 0usize;
+    let mut excluded_files = 0usize;
+    let mut local_ignored_files = 0usize;
+
+    walk_project(&root_path, &rules, follow_symlinks, &mut |walk_entry| {
+        let (path, is_dir, source) = match walk_entry {
+            WalkEntry::ExcludedDir(p, source) => (p.to_path_buf(), true, source),
+            WalkEntry::File(entry, PathDecision::Excluded(source)) => (entry.path(), false, *source),
+            WalkEntry::File(_, PathDecision::Included(_)) => {
+                total_files += 1;
+                return Ok(());
             }
+        };
+        if source == ExcludeSource::Gitignore {
+            return Ok(());
+        }
 
-            if p.is_dir() {
-                if should_exclude(&p, &root_path, exclude_globset.as_ref()) {
-                    continue;
+        let files = if is_dir { count_files_recursive(&path) } else { 1 };
+        total_files += files;
+        match source {
+            ExcludeSource::Pattern(idx) => {
+                let rel = path.strip_prefix(&root_path).unwrap_or(&path).to_string_lossy().replace('\\', "/");
+                let stats = &mut pattern_stats[idx];
+                if is_dir {
+                    stats.excluded_dirs += 1;
                 }
-                dirs_stack.push(p);
-                continue;
+                stats.excluded_files += files;
+                if stats.sample_paths.len() < EXCLUSION_SAMPLE_LIMIT {
+                    stats.sample_paths.push(if is_dir { format!("{}/", rel) } else { rel });
+                }
+                excluded_files += files;
             }
+            _ => local_ignored_files += files,
+        }
+        Ok(())
+    })?;
+
+    Ok(ExclusionPreview {
+        project_root: normalize_project_path(root),
+        total_files,
+        excluded_files,
+        local_ignored_files,
+        patterns: pattern_stats,
+        invalid_patterns: rules.excludes.invalid,
+    })
+}
 
-            if should_exclude(&p, &root_path, exclude_globset.as_ref()) {
-                continue;
+/// 统计目录下的文件数量（不跟随符号链接，避免循环）
+fn count_files_recursive(dir: &Path) -> usize {
+    let mut count = 0usize;
+    let mut stack = vec![dir.to_path_buf()];
+    while let Some(d) = stack.pop() {
+        let entries = match fs::read_dir(&d) {
+            Ok(e) => e,
+            Err(_) => continue,
+        };
+        for entry in entries.flatten() {
+            match entry.file_type() {
+                Ok(ft) if ft.is_dir() => stack.push(entry.path()),
+                Ok(_) => count += 1,
+                Err(_) => {}
             }
+        }
+    }
+    count
+}
 
-            // 扩展名过滤
-            let ext_ok = p
-                .extension()
-                .and_then(|s| s.to_str())
-                .map(|e| {
-                    let dot = format!(".{}", e).to_lowercase();
-                    text_exts.iter().any(|te| te.eq_ignore_ascii_case(&dot))
-                })
-                .unwrap_or(false);
+/// 文件状态遍历被取消时的错误信息
+pub(crate) const FILES_STATUS_CANCELLED: &str = "文件状态扫描已取消";
 
-            if !ext_ok {
-                continue;
+/// 文件状态扫描的取消令牌（项目路径 -> 取消标记）
+static FILES_STATUS_CANCEL_TOKENS: once_cell::sync::Lazy<Mutex<HashMap<String, Arc<AtomicBool>>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// 为项目注册新的文件状态扫描取消令牌
+/// 同一项目已有扫描在进行时，旧扫描会被取消（只保留最新一次请求）
+fn register_files_status_cancel(normalized_root: &str) -> Arc<AtomicBool> {
+    let flag = Arc::new(AtomicBool::new(false));
+    let mut tokens = FILES_STATUS_CANCEL_TOKENS.lock().unwrap();
+    if let Some(previous) = tokens.insert(normalized_root.to_string(), flag.clone()) {
+        previous.store(true, Ordering::SeqCst);
+    }
+    flag
+}
+
+/// 扫描结束后移除取消令牌（仅当令牌仍是本次注册的那一个时）
+fn unregister_files_status_cancel(normalized_root: &str, flag: &Arc<AtomicBool>) {
+    let mut tokens = FILES_STATUS_CANCEL_TOKENS.lock().unwrap();
+    if tokens.get(normalized_root).map(|f| Arc::ptr_eq(f, flag)).unwrap_or(false) {
+        tokens.remove(normalized_root);
+    }
+}
+
+/// 取消指定项目正在进行的文件状态扫描，返回是否存在进行中的扫描
+pub fn cancel_files_status(project_root_path: &str) -> bool {
+    let normalized_root = normalize_project_path(project_root_path);
+    let tokens = FILES_STATUS_CANCEL_TOKENS.lock().unwrap();
+    match tokens.get(&normalized_root) {
+        Some(flag) => {
+            flag.store(true, Ordering::SeqCst);
+            log_important!(info, "已请求取消文件状态扫描: {}", normalized_root);
+            true
+        }
+        None => false,
+    }
+}
+
+/// 搜索被取消时的提示
+pub(crate) const SEARCH_CANCELLED: &str = "搜索已取消";
+
+/// 进行中搜索的取消信号（项目路径 -> [(搜索序号, 取消信号发送端)]）
+/// 同一项目可能同时有多个搜索（MCP 调用与调试面板），取消时一并取消
+static SEARCH_CANCEL_SENDERS: once_cell::sync::Lazy<Mutex<HashMap<String, Vec<(u64, tokio::sync::oneshot::Sender<()>)>>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(HashMap::new()));
+
+static NEXT_SEARCH_ID: AtomicU64 = AtomicU64::new(0);
+
+/// 搜索结束（完成、失败或被取消）时移除本次注册的取消信号
+struct SearchCancelGuard {
+    normalized_root: String,
+    id: u64,
+}
+
+impl Drop for SearchCancelGuard {
+    fn drop(&mut self) {
+        let mut senders = SEARCH_CANCEL_SENDERS.lock().unwrap();
+        if let Some(list) = senders.get_mut(&self.normalized_root) {
+            list.retain(|(id, _)| *id != self.id);
+            if list.is_empty() {
+                senders.remove(&self.normalized_root);
             }
+        }
+    }
+}
 
-            let rel = p
-                .strip_prefix(&root_path)
-                .unwrap_or(&p)
-                .to_string_lossy()
-                .replace('\\', "/");
+/// 以可取消方式执行搜索 future：收到取消信号时直接丢弃 future（进行中的 HTTP 请求随之中止）并返回 None
+async fn run_cancellable_search<T>(
+    project_root_path: &str,
+    search: impl std::future::Future<Output = T>,
+) -> Option<T> {
+    let normalized_root = normalize_project_path(project_root_path);
+    let (cancel_tx, cancel_rx) = tokio::sync::oneshot::channel::<()>();
+    let id = NEXT_SEARCH_ID.fetch_add(1, Ordering::SeqCst);
+    SEARCH_CANCEL_SENDERS
+        .lock()
+        .unwrap()
+        .entry(normalized_root.clone())
+        .or_default()
+        .push((id, cancel_tx));
+    let _guard = SearchCancelGuard { normalized_root: normalized_root.clone(), id };
+
+    tokio::select! {
+        result = search => Some(result),
+        Ok(()) = cancel_rx => {
+            log_important!(info, "搜索已取消: {}", normalized_root);
+            None
+        }
+    }
+}
 
-            // 读取文件内容并根据分块结果计算 blob 哈希
-            if let Some(content) = read_file_with_encoding(&p) {
-                let blobs = split_content(&rel, &content, max_lines_per_blob);
-                if blobs.is_empty() {
-                    continue;
-                }
+/// 取消指定项目所有进行中的搜索，返回被取消的搜索数量（其他项目不受影响）
+pub fn cancel_search(project_root_path: &str) -> usize {
+    let normalized_root = normalize_project_path(project_root_path);
+    let senders = SEARCH_CANCEL_SENDERS
+        .lock()
+        .unwrap()
+        .remove(&normalized_root)
+        .unwrap_or_default();
+
+    let mut cancelled = 0;
+    for (_, sender) in senders {
+        if sender.send(()).is_ok() {
+            cancelled += 1;
+        }
+    }
+    if cancelled > 0 {
+        log_important!(info, "已请求取消 {} 个进行中的搜索: {}", cancelled, normalized_root);
+    }
+    cancelled
+}
 
-                let mut all_indexed = true;
-                for blob in &blobs {
-                    let hash = sha256_hex(&blob.path, &blob.content);
-                    if !existing_blob_names.contains(&hash) {
+/// 收集项目内所有可索引文件的索引状态
+///
+/// 为避免引入新的持久化结构，这里通过重新扫描文件并复用与索引阶段相同的
+/// 路径规范化与分块逻辑，基于现有的 blob 哈希集合判断文件是否“已完全索引”。
+pub(crate) fn collect_file_statuses(
+    root: &str,
+    text_exts: &[String],
+    exclude_patterns: &[String],
+    max_lines_per_blob: &MaxLinesPolicy,
+    follow_symlinks: bool,
+    existing_blob_names: &HashMap<String, Option<chrono::DateTime<chrono::Utc>>>,
+    cancel_flag: Option<&AtomicBool>,
+) -> anyhow::Result<Vec<FileIndexStatus>> {
+    let mut files_status = Vec::new();
+    walk_file_statuses(
+        root,
+        text_exts,
+        exclude_patterns,
+        max_lines_per_blob,
+        follow_symlinks,
+        existing_blob_names,
+        cancel_flag,
+        &mut |status| files_status.push(status),
+    )?;
+    Ok(files_status)
+}
+
+/// 逐个遍历项目内可索引文件并回调其索引状态（不在内存中累积完整列表）
+/// cancel_flag 被置位时在处理下一个目录项前立即停止，返回“已取消”错误
+fn walk_file_statuses(
+    root: &str,
+    text_exts: &[String],
+    exclude_patterns: &[String],
+    max_lines_per_blob: &MaxLinesPolicy,
+    follow_symlinks: bool,
+    existing_blob_names: &HashMap<String, Option<chrono::DateTime<chrono::Utc>>>,
+    cancel_flag: Option<&AtomicBool>,
+    on_file: &mut dyn FnMut(FileIndexStatus),
+) -> anyhow::Result<()> {
+    let root_path = PathBuf::from(root);
+    if !root_path.exists() {
+        anyhow::bail!("项目根目录不存在: {}", root);
+    }
+
+    // 构建分层过滤规则（与 collect_blobs 保持一致）
+    let rules = PathRules::new(&root_path, exclude_patterns);
+
+    walk_project(&root_path, &rules, follow_symlinks, &mut |walk_entry| {
+        if cancel_flag.map(|f| f.load(Ordering::SeqCst)).unwrap_or(false) {
+            anyhow::bail!("{}", FILES_STATUS_CANCELLED);
+        }
+
+        let (entry, included_by) = match walk_entry {
+            WalkEntry::File(entry, PathDecision::Included(source)) => (entry, *source),
+            _ => return Ok(()),
+        };
+
+        // 扩展名过滤
+        let p = entry.path();
+        if !has_text_extension(&p, text_exts) {
+            return Ok(());
+        }
+
+        let rel = p
+            .strip_prefix(&root_path)
+            .unwrap_or(&p)
+            .to_string_lossy()
+            .replace('\\', "/");
+
+        // 读取文件内容并根据分块结果计算 blob 哈希
+        if let Ok(content) = read_file_with_encoding(&p) {
+            let blobs = split_content(&rel, &content, max_lines_per_blob.for_path(&rel));
+            if blobs.is_empty() {
+                return Ok(());
+            }
+
+            let mut all_indexed = true;
+            let mut last_indexed: Option<chrono::DateTime<chrono::Utc>> = None;
+            for blob in &blobs {
+                let hash = sha256_hex(&blob.path, &blob.content);
+                match existing_blob_names.get(&hash) {
+                    Some(at) => last_indexed = last_indexed.max(*at),
+                    None => {
                         all_indexed = false;
                         break;
                     }
                 }
+            }
 
-                let status = if all_indexed {
-                    FileIndexStatusKind::Indexed
-                } else {
-                    FileIndexStatusKind::Pending
-                };
-
-                files_status.push(FileIndexStatus {
-                    path: rel.clone(),
-                    status,
-                });
+            let (status, last_indexed) = if all_indexed {
+                (FileIndexStatusKind::Indexed, last_indexed)
             } else {
-                // 无法读取内容时，保守地标记为 Pending，避免静默丢失
-                files_status.push(FileIndexStatus {
-                    path: rel.clone(),
-                    status: FileIndexStatusKind::Pending,
-                });
+                (FileIndexStatusKind::Pending, None)
+            };
+
+            on_file(FileIndexStatus {
+                path: rel,
+                status,
+                included_by,
+                last_indexed,
+            });
+        } else {
+            // 无法读取内容时，保守地标记为 Pending，避免静默丢失
+            on_file(FileIndexStatus {
+                path: rel,
+                status: FileIndexStatusKind::Pending,
+                included_by,
+                last_indexed: None,
+            });
+        }
+        Ok(())
+    })
+}
+
+/// find-missing 接口每次提交的 blob 名称数量
+const VERIFY_BATCH_SIZE: usize = 1000;
+
+/// 校验项目索引：对比 projects.json 中记录的 blob、当前工作区重新扫描的 blob，
+/// 以及服务端（find-missing 接口可用时）实际保存的 blob
+pub(crate) async fn verify_index(config: &AcemcpConfig, project_root_path: &str) -> anyhow::Result<IndexVerification> {
+    let normalized_root = normalize_project_path(project_root_path);
+    let max_lines = MaxLinesPolicy::from_config(config);
+    let text_exts = config.text_extensions.clone().unwrap_or_default();
+    let exclude_patterns = config.exclude_patterns.clone().unwrap_or_default();
+    let follow_symlinks = config.follow_symlinks.unwrap_or(false);
+
+    // 本地记录：load_projects_file 已合并去重，重复项需从原始文件统计
+    let recorded_list = load_projects_file().0.get(&normalized_root).cloned().unwrap_or_default();
+    let recorded: HashSet<String> = recorded_list.iter().cloned().collect();
+    let duplicate_recorded = count_duplicate_records(&load_raw_projects_file(), &normalized_root);
+
+    // 重新扫描当前工作区，计算应有的 blob 哈希
+    let root = project_root_path.to_string();
+    let blobs = tokio::task::spawn_blocking(move || collect_blobs(&root, &text_exts, &exclude_patterns, &max_lines, follow_symlinks))
+        .await
+        .map_err(|e| anyhow::anyhow!("扫描任务异常: {}", e))??;
+    let current: HashSet<String> = blobs.iter().map(|b| sha256_hex(&b.path, &b.content)).collect();
+
+    let not_uploaded = current.difference(&recorded).count();
+    let stale = recorded.difference(&current).count();
+
+    // 服务端校验：接口不可用时退化为仅本地一致性检查
+    let (server_checked, server_missing, server_error) = match find_missing_on_server(config, &recorded_list).await {
+        Ok(missing) => (true, Some(missing), None),
+        Err(e) => {
+            log_important!(warn, "服务端索引校验不可用，仅执行本地检查: {}", e);
+            (false, None, Some(e.to_string()))
+        }
+    };
+
+    let mut problems = Vec::new();
+    if not_uploaded > 0 {
+        problems.push(format!("{} 个 blob 尚未上传（文件新增或已修改）", not_uploaded));
+    }
+    if let Some(missing) = server_missing.filter(|m| *m > 0) {
+        problems.push(format!("服务端缺失 {} 个已记录的 blob", missing));
+    }
+    if stale > 0 {
+        problems.push(format!("{} 个已记录的 blob 在本地已不存在（文件删除或已修改）", stale));
+    }
+    if duplicate_recorded > 0 {
+        problems.push(format!("本地记录中有 {} 个重复项", duplicate_recorded));
+    }
+
+    let needs_repair = !problems.is_empty();
+    let mut summary = if problems.is_empty() {
+        format!("索引一致：本地记录 {} 个 blob，与当前文件相符", recorded.len())
+    } else {
+        format!("发现问题：{}。建议执行“修复索引”重新同步", problems.join("；"))
+    };
+    if !server_checked {
+        summary.push_str("（服务端校验不可用，仅完成本地检查）");
+    }
+
+    Ok(IndexVerification {
+        project_root: normalized_root,
+        recorded_blobs: recorded.len(),
+        current_blobs: current.len(),
+        not_uploaded,
+        stale,
+        duplicate_recorded,
+        server_checked,
+        server_missing,
+        server_error,
+        needs_repair,
+        summary,
+    })
+}
+
+/// 调用 find-missing 接口，返回服务端不认识的 blob 数量
+async fn find_missing_on_server(config: &AcemcpConfig, blob_names: &[String]) -> anyhow::Result<usize> {
+    let base_url = config.base_url.clone().ok_or_else(|| anyhow::anyhow!("未配置 base_url"))?;
+    let token = config.token.clone().ok_or_else(|| anyhow::anyhow!("未配置 token"))?;
+    let client = shared_acemcp_client(config)?;
+    let url = join_ace_url(&base_url, "find-missing");
+
+    let mut missing = 0usize;
+    for chunk in blob_names.chunks(VERIFY_BATCH_SIZE) {
+        let payload = serde_json::json!({ "mem_object_names": chunk });
+        let resp = send_with_rate_limit("索引校验", &RateLimitBudget::new(), || {
+            client
+                .post(&url)
+                .header(AUTHORIZATION, format!("Bearer {}", token))
+                .header(CONTENT_TYPE, "application/json")
+                .json(&payload)
+                .send()
+        })
+            .await
+            .map_err(|e| anyhow::anyhow!("请求 find-missing 失败: {}", e))?;
+
+        if !resp.status().is_success() {
+            anyhow::bail!("find-missing 返回 HTTP {}", resp.status());
+        }
+
+        let v: serde_json::Value = resp
+            .json()
+            .await
+            .map_err(|e| anyhow::anyhow!("解析 find-missing 响应失败: {}", e))?;
+        let unknown = v
+            .get("unknown_memory_names")
+            .and_then(|x| x.as_array())
+            .ok_or_else(|| anyhow::anyhow!("find-missing 响应缺少 unknown_memory_names 字段"))?;
+        missing += unknown.len();
+    }
+
+    Ok(missing)
+}
+
+// ============ 应用退出时的优雅关闭 ============
+
+/// 应用退出时中断索引的说明（写入项目状态）
+pub(crate) const INDEX_INTERRUPTED_BY_SHUTDOWN: &str = "应用退出，索引已中断（下次启动时自动续传）";
+
+/// 应用是否正在退出（置位后不再开始新的索引，进行中的索引在当前批次完成后停止）
+static SHUTTING_DOWN: AtomicBool = AtomicBool::new(false);
+/// 本进程中进行中的 update_index：规范化项目路径 -> 并发次数
+/// 说明：状态文件由 GUI 与 MCP 服务进程共享，退出时只能重置本进程自己正在索引的项目
+static ACTIVE_INDEX_ROOTS: once_cell::sync::Lazy<Mutex<HashMap<String, usize>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn is_shutting_down() -> bool {
+    SHUTTING_DOWN.load(Ordering::SeqCst)
+}
+
+fn active_index_run_count() -> usize {
+    ACTIVE_INDEX_ROOTS.lock().unwrap().values().sum()
+}
+
+/// 标记本进程对某项目的一次进行中索引，离开作用域（含提前返回）时自动移除
+struct IndexRunGuard {
+    root: String,
+}
+
+impl IndexRunGuard {
+    fn enter(project_root: &str) -> Self {
+        let root = normalize_project_path(project_root);
+        *ACTIVE_INDEX_ROOTS.lock().unwrap().entry(root.clone()).or_insert(0) += 1;
+        Self { root }
+    }
+}
+
+impl Drop for IndexRunGuard {
+    fn drop(&mut self) {
+        let mut roots = ACTIVE_INDEX_ROOTS.lock().unwrap();
+        if let Some(count) = roots.get_mut(&self.root) {
+            *count -= 1;
+            if *count == 0 {
+                roots.remove(&self.root);
             }
         }
     }
+}
+
+/// 退出前的优雅关闭：停止所有文件监听，等待进行中的上传批次完成（最多 grace），
+/// 超时仍未结束的项目将“索引中”状态落盘为空闲，避免下次启动时卡在索引中。
+/// 只处理本进程发起的索引，另一进程（GUI / MCP 服务）正在索引的项目保持不变。
+/// 已上传批次由检查点记录，下次索引时自动续传。
+pub async fn shutdown(grace: Duration) {
+    SHUTTING_DOWN.store(true, Ordering::SeqCst);
+    super::watcher::get_watcher_manager().stop_all();
+
+    let deadline = tokio::time::Instant::now() + grace;
+    while active_index_run_count() > 0 && tokio::time::Instant::now() < deadline {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
 
-    Ok(files_status)
+    let remaining: HashSet<String> = ACTIVE_INDEX_ROOTS.lock().unwrap().keys().cloned().collect();
+    if !remaining.is_empty() {
+        log_important!(warn, "等待上传批次完成超时，仍有 {} 个项目在索引中", remaining.len());
+    }
+
+    // 本进程进行中的索引来不及写完状态时，落盘为中断状态
+    let mut all_status = load_projects_status();
+    let mut reset = 0usize;
+    for (root, status) in all_status.projects.iter_mut() {
+        if remaining.contains(root) && status.status == IndexStatus::Indexing {
+            status.status = IndexStatus::Idle;
+            status.last_error = Some(INDEX_INTERRUPTED_BY_SHUTDOWN.to_string());
+            reset += 1;
+        }
+    }
+    if reset > 0 {
+        if let Err(e) = save_projects_status(&all_status) {
+            log_important!(warn, "退出时保存索引状态失败: {}", e);
+        }
+    }
+    log_important!(info, "Acemcp 已完成退出清理: 中断的索引项目={}", reset);
 }
 
+/// 索引缓存（projects.json + blob_times.json）中每个 blob 大约占用的字节数
+const INDEX_CACHE_BYTES_PER_BLOB: u64 = 256;
+/// 索引缓存空间检查的额外余量
+const INDEX_CACHE_SPACE_MARGIN: u64 = 16 * 1024 * 1024;
+
 /// 只执行索引更新，不进行搜索
 /// 返回值：成功上传的 blob 名称列表
 pub(crate) async fn update_index(config: &AcemcpConfig, project_root_path: &str) -> anyhow::Result<Vec<String>> {
+    run_update_index(config, project_root_path, false).await
+}
+
+/// 强制全量重建索引：忽略已记录的 blob 重新上传全部文件
+/// 上传全部成功后才替换 projects.json 中的记录，期间旧记录保持可用（检索不受影响）；
+/// 有批次失败或被中断时保留旧记录，已上传的批次写入检查点
+pub(crate) async fn force_reindex(config: &AcemcpConfig, project_root_path: &str) -> anyhow::Result<Vec<String>> {
+    run_update_index(config, project_root_path, true).await
+}
+
+async fn run_update_index(config: &AcemcpConfig, project_root_path: &str, force_full: bool) -> anyhow::Result<Vec<String>> {
     let base_url = config.base_url.clone().ok_or_else(|| anyhow::anyhow!("未配置 base_url"))?;
     // 严格校验 base_url
     let has_scheme = base_url.starts_with("http://") || base_url.starts_with("https://");
     let has_host = base_url.trim().len() > "https://".len();
     if !has_scheme || !has_host { anyhow::bail!("无效的 base_url，请填写完整的 http(s)://host[:port] 格式"); }
     let token = config.token.clone().ok_or_else(|| anyhow::anyhow!("未配置 token"))?;
-    let batch_size = config.batch_size.unwrap_or(10) as usize;
-    let max_lines = config.max_lines_per_blob.unwrap_or(800) as usize;
+    let batch_size = config.batch_size.unwrap_or(DEFAULT_ACEMCP_BATCH_SIZE) as usize;
+    let max_blobs_per_request = config
+        .max_blobs_per_request
+        .unwrap_or(DEFAULT_ACEMCP_MAX_BLOBS_PER_REQUEST) as usize;
+    let max_lines = MaxLinesPolicy::from_config(config);
     let text_exts = config.text_extensions.clone().unwrap_or_default();
     let exclude_patterns = config.exclude_patterns.clone().unwrap_or_default();
+    let follow_symlinks = config.follow_symlinks.unwrap_or(false);
+
+    if is_shutting_down() {
+        anyhow::bail!("应用正在退出，跳过索引");
+    }
+    let _run_guard = IndexRunGuard::enter(project_root_path);
 
     // 更新状态：开始索引
     let _ = update_project_status(project_root_path, |status| {
@@ -881,7 +2668,7 @@ pub(crate) async fn update_index(config: &AcemcpConfig, project_root_path: &str)
         "Acemcp配置: base_url={}, batch_size={}, max_lines_per_blob={}, text_exts数量={}, exclude_patterns数量={}",
         base_url,
         batch_size,
-        max_lines,
+        max_lines.default_lines(),
         text_exts.len(),
         exclude_patterns.len()
     );
@@ -891,7 +2678,10 @@ pub(crate) async fn update_index(config: &AcemcpConfig, project_root_path: &str)
 
     // 收集 blob（根据扩展名与排除规则，简化版 .gitignore 支持）
     log_important!(info, "开始收集代码文件...");
-    let blobs = collect_blobs(project_root_path, &text_exts, &exclude_patterns, max_lines)?;
+    let (blobs, mut file_errors) = collect_blobs_with_errors(project_root_path, &text_exts, &exclude_patterns, &max_lines, follow_symlinks)?;
+    let _ = update_project_status(project_root_path, |status| {
+        status.file_errors = file_errors.clone();
+    });
     if blobs.is_empty() {
         // 更新状态：失败
         let _ = update_project_status(project_root_path, |status| {
@@ -910,13 +2700,14 @@ pub(crate) async fn update_index(config: &AcemcpConfig, project_root_path: &str)
 
     // 加载 projects.json
     let projects_path = home_projects_file();
-    let mut projects: ProjectsFile = if projects_path.exists() {
-        let data = fs::read_to_string(&projects_path).unwrap_or_default();
-        serde_json::from_str(&data).unwrap_or_default()
-    } else { ProjectsFile::default() };
-
-    let normalized_root = PathBuf::from(project_root_path).canonicalize().unwrap_or_else(|_| PathBuf::from(project_root_path)).to_string_lossy().replace('\\', "/");
-    let existing_blob_names: std::collections::HashSet<String> = projects.0.get(&normalized_root).cloned().unwrap_or_default().into_iter().collect();
+    let mut projects = load_projects_file();
+    let normalized_root = normalize_project_path(project_root_path);
+    // 强制重建时不复用已记录的 blob 与旧检查点，全部重新上传
+    let mut existing_blob_names: std::collections::HashSet<String> = if force_full {
+        HashSet::new()
+    } else {
+        projects.0.get(&normalized_root).cloned().unwrap_or_default().into_iter().collect()
+    };
 
     // 计算所有 blob 的哈希值，建立哈希到 blob 的映射
     let mut blob_hash_map: std::collections::HashMap<String, BlobItem> = std::collections::HashMap::new();
@@ -925,8 +2716,29 @@ pub(crate) async fn update_index(config: &AcemcpConfig, project_root_path: &str)
         blob_hash_map.insert(hash.clone(), blob.clone());
     }
 
-    // 分离已存在和新增加的 blob（与 Python 版本保持一致）
+    // 上次索引中断时已上传成功的 blob 视为既有 blob，从中断处继续（内容已变化的 blob 哈希不同，不会被跳过）
     let all_blob_hashes: std::collections::HashSet<String> = blob_hash_map.keys().cloned().collect();
+    let previous_checkpoint = if force_full {
+        // 强制重建不沿用旧检查点，本轮从空检查点开始追加
+        let _ = clear_project_checkpoint(&normalized_root);
+        Vec::new()
+    } else {
+        load_project_checkpoint(&normalized_root)
+    };
+    let checkpoint: Vec<String> = previous_checkpoint
+        .into_iter()
+        .filter(|h| all_blob_hashes.contains(h) && !existing_blob_names.contains(h))
+        .collect();
+    let resumed_blobs = checkpoint.len();
+    if resumed_blobs > 0 {
+        log_important!(info, "从上次中断的索引检查点恢复: 跳过 {} 个已上传的 blob", resumed_blobs);
+        existing_blob_names.extend(checkpoint.iter().cloned());
+    }
+    let _ = update_project_status(project_root_path, |status| {
+        status.resumed_blobs = resumed_blobs;
+    });
+
+    // 分离已存在和新增加的 blob（与 Python 版本保持一致）
     let existing_hashes: std::collections::HashSet<String> = all_blob_hashes.intersection(&existing_blob_names).cloned().collect();
     let new_hashes: std::collections::HashSet<String> = all_blob_hashes.difference(&existing_blob_names).cloned().collect();
 
@@ -944,23 +2756,63 @@ pub(crate) async fn update_index(config: &AcemcpConfig, project_root_path: &str)
         new_blobs.len()
     );
 
+    if let Some(warning) = daily_budget_warning(
+        config.daily_blob_budget,
+        load_usage_today().blobs_uploaded,
+        new_blobs.len() as u64,
+    ) {
+        log_important!(warn, "{}: project_root={}", warning, normalized_root);
+    }
+
+    // 上传完成后需要重写 projects.json / blob_times.json，提前确认数据目录空间足够，避免写到一半失败
+    if !new_blobs.is_empty() {
+        let required = (blobs.len() as u64).saturating_mul(INDEX_CACHE_BYTES_PER_BLOB) + INDEX_CACHE_SPACE_MARGIN;
+        if let Err(e) = crate::utils::disk::check_free_space(&projects_path, required) {
+            let _ = update_project_status(project_root_path, |status| {
+                status.status = IndexStatus::Failed;
+                status.last_error = Some(e.clone());
+                status.last_failure_time = Some(chrono::Utc::now());
+            });
+            anyhow::bail!("{}", e);
+        }
+    }
+
     // 创建 HTTP 客户端（支持代理）
-    let client = create_acemcp_client(config)?;
+    let mut client = shared_acemcp_client(config)?;
 
-    // 批量上传新增 blobs
+    // 批量上传新增 blobs（每批成功后追加到检查点，中断后可恢复）
     let mut uploaded_names: Vec<String> = Vec::new();
     let mut failed_batches: Vec<usize> = Vec::new();
+    // 失败批次的错误原因（批次序号 -> 原因），用于生成逐文件的错误明细
+    let mut batch_errors: HashMap<usize, String> = HashMap::new();
+    // 因应用退出而未上传的批次数
+    let mut interrupted_batches = 0usize;
     
     if !new_blobs.is_empty() {
-        let total_batches = (new_blobs.len() + batch_size - 1) / batch_size;
+        let mut current_route = proxy_route_label(config);
+        let _ = update_project_status(project_root_path, |status| {
+            status.proxies_used = vec![current_route.clone()];
+            status.proxy_failovers.clear();
+        });
+        // 连续失败的批次数（用于触发代理故障转移，每次索引最多切换一次）
+        let mut consecutive_failures = 0usize;
+        let mut failed_over = false;
+
+        let batch_ranges = plan_upload_batches(
+            new_blobs.iter().map(|b| blob_file_key(&b.path)),
+            batch_size,
+            max_blobs_per_request,
+        );
+        let total_batches = batch_ranges.len();
         log_important!(info,
             "=== 开始批量上传代码索引 ==="
         );
         log_important!(info,
-            "目标端点: {}/batch-upload, 总批次: {}, 每批上限: {}, 总blobs: {}",
+            "目标端点: {}/batch-upload, 总批次: {}, 每批文件上限: {}, 每批 blob 上限: {}, 总blobs: {}",
             base_url,
             total_batches,
             batch_size,
+            max_blobs_per_request,
             new_blobs.len()
         );
 
@@ -968,11 +2820,17 @@ pub(crate) async fn update_index(config: &AcemcpConfig, project_root_path: &str)
             "=== 批量上传代码索引 ==="
         );
 
-        for i in 0..total_batches {
-            let start = i * batch_size;
-            let end = usize::min(start + batch_size, new_blobs.len());
-            let batch = &new_blobs[start..end];
-            let url = format!("{}/batch-upload", base_url);
+        let mut i = 0usize;
+        while i < total_batches {
+            // 应用退出：当前批次已完成，不再发送后续批次
+            if is_shutting_down() {
+                interrupted_batches = total_batches - i;
+                log_important!(warn, "应用退出，停止上传剩余 {} 个批次", interrupted_batches);
+                break;
+            }
+            let batch = &new_blobs[batch_ranges[i].clone()];
+            let url = join_ace_url(&base_url, "batch-upload");
+            let failed_before = failed_batches.len();
             
             log_important!(info,
                 "上传批次 {}/{}: url={}, blobs={}",
@@ -1003,17 +2861,21 @@ pub(crate) async fn update_index(config: &AcemcpConfig, project_root_path: &str)
                 .sum();
             log_debug!("批次载荷概要: blobs={}, approx_chars={}", batch.len(), approx_chars);
             
+            let request_id = new_request_id();
+            let rate_limit_budget = RateLimitBudget::new();
             match retry_request(|| async {
-                let r = client
-                    .post(&url)
-                    .header(AUTHORIZATION, format!("Bearer {}", token))
-                    .header(CONTENT_TYPE, "application/json")
-                    .json(&payload)
-                    .send()
-                    .await?;
+                let r = send_with_rate_limit("上传批次", &rate_limit_budget, || {
+                    client
+                        .post(&url)
+                        .header(AUTHORIZATION, format!("Bearer {}", token))
+                        .header(CONTENT_TYPE, "application/json")
+                        .header(REQUEST_ID_HEADER, &request_id)
+                        .json(&payload)
+                        .send()
+                }).await?;
                 
                 let status = r.status();
-                log_important!(info, "HTTP响应状态: {}", status);
+                log_important!(info, "HTTP响应状态: {}, request_id={}", status, request_id);
                 
                 if !status.is_success() {
                     let body = r.text().await.unwrap_or_default();
@@ -1046,8 +2908,13 @@ pub(crate) async fn update_index(config: &AcemcpConfig, project_root_path: &str)
                         if batch_names.is_empty() {
                             log_important!(info, "批次 {} 返回了空的blob名称列表", i + 1);
                             failed_batches.push(i + 1);
+                            batch_errors.insert(i + 1, "服务端返回了空的 blob 名称列表".to_string());
                         } else {
                             uploaded_names.extend(batch_names.clone());
+                            record_upload_usage(batch_names.len());
+                            if let Err(e) = append_project_checkpoint(&normalized_root, &batch_names) {
+                                log_debug!("保存索引检查点失败（不影响索引）: {}", e);
+                            }
                             log_important!(info, "批次 {} 上传成功，获得 {} 个blob名称", i + 1, batch_names.len());
                             // 详细记录每个上传成功的 blob 名称
                             for (idx, name) in batch_names.iter().enumerate() {
@@ -1058,13 +2925,40 @@ pub(crate) async fn update_index(config: &AcemcpConfig, project_root_path: &str)
                     } else {
                         log_important!(info, "批次 {} 响应中缺少blob_names字段", i + 1);
                         failed_batches.push(i + 1);
+                        batch_errors.insert(i + 1, "响应中缺少 blob_names 字段".to_string());
                     }
                 }
                 Err(e) => {
                     log_important!(info, "批次 {} 上传失败: {}", i + 1, e);
                     failed_batches.push(i + 1);
+                    batch_errors.insert(i + 1, e.to_string());
+                }
+            }
+
+            if failed_batches.len() > failed_before {
+                consecutive_failures += 1;
+            } else {
+                consecutive_failures = 0;
+            }
+
+            if !failed_over && consecutive_failures >= DEFAULT_ACEMCP_PROXY_FAILOVER_THRESHOLD as usize {
+                if let Some((alt_client, event)) = try_proxy_failover(config, &current_route, i + 1).await {
+                    failed_over = true;
+                    client = alt_client;
+                    current_route = event.to.clone();
+                    // 从连续失败的第一个批次开始用备用代理重传
+                    let retry_from = i + 1 - consecutive_failures;
+                    failed_batches.retain(|b| *b <= retry_from);
+                    consecutive_failures = 0;
+                    let _ = update_project_status(project_root_path, |status| {
+                        status.proxies_used.push(event.to.clone());
+                        status.proxy_failovers.push(event.clone());
+                    });
+                    i = retry_from;
+                    continue;
                 }
             }
+            i += 1;
         }
         
         // 上传结果总结
@@ -1073,6 +2967,19 @@ pub(crate) async fn update_index(config: &AcemcpConfig, project_root_path: &str)
         );
         if !failed_batches.is_empty() {
             log_important!(info, "上传完成，但有失败的批次: {:?}, 成功上传blobs: {}", failed_batches, uploaded_names.len());
+            for batch_no in &failed_batches {
+                let reason = batch_errors.get(batch_no).cloned().unwrap_or_default();
+                let mut seen = HashSet::new();
+                for blob in &new_blobs[batch_ranges[batch_no - 1].clone()] {
+                    let path = blob_file_key(&blob.path);
+                    if seen.insert(path) {
+                        file_errors.push(path, format!("上传失败（批次 {}）: {}", batch_no, reason));
+                    }
+                }
+            }
+            let _ = update_project_status(project_root_path, |status| {
+                status.file_errors = file_errors.clone();
+            });
         } else {
             log_important!(info, "所有批次上传成功，共上传 {} 个blobs", uploaded_names.len());
         }
@@ -1080,11 +2987,69 @@ pub(crate) async fn update_index(config: &AcemcpConfig, project_root_path: &str)
         log_important!(info, "没有新的blob需要上传，使用已有索引");
     }
 
+    // 强制重建未全部成功：保留旧记录与本次检查点，不替换 projects.json
+    if force_full && (!failed_batches.is_empty() || interrupted_batches > 0) {
+        let message = if interrupted_batches > 0 {
+            INDEX_INTERRUPTED_BY_SHUTDOWN.to_string()
+        } else {
+            format!("强制重建索引未完成（失败批次: {:?}），已保留原有索引记录", failed_batches)
+        };
+        let _ = update_project_status(project_root_path, |status| {
+            status.status = if interrupted_batches > 0 { IndexStatus::Idle } else { IndexStatus::Failed };
+            status.last_error = Some(message.clone());
+            if interrupted_batches == 0 {
+                status.last_failure_time = Some(chrono::Utc::now());
+            }
+        });
+        anyhow::bail!("{}", message);
+    }
+
     // 合并并保存 projects.json（与 Python 版本保持一致）
     // 只保留当前项目中仍然存在的 blob 的哈希值（自动删除已删除的 blob）
+    let uploaded_at = chrono::Utc::now();
+    let mut blob_times = load_blob_times_file();
+    let previous_times = blob_times.0.remove(&normalized_root).unwrap_or_default();
+    let mut project_times: HashMap<String, chrono::DateTime<chrono::Utc>> = existing_hashes
+        .iter()
+        .filter_map(|h| previous_times.get(h).map(|at| (h.clone(), *at)))
+        .collect();
+    project_times.extend(checkpoint.iter().chain(uploaded_names.iter()).map(|h| (h.clone(), uploaded_at)));
+
     let all_blob_names: Vec<String> = existing_hashes.into_iter().chain(uploaded_names.into_iter()).collect();
     projects.0.insert(normalized_root.clone(), all_blob_names.clone());
-    if let Ok(s) = serde_json::to_string_pretty(&projects) { let _ = fs::write(projects_path, s); }
+    let projects_saved = serde_json::to_string_pretty(&projects)
+        .map_err(anyhow::Error::from)
+        .and_then(|s| fs::write(&projects_path, s).map_err(anyhow::Error::from));
+    // blob_times.json / indexed_files.json 只在 projects.json 写入成功后更新，避免三者记录不一致
+    match projects_saved {
+        Ok(()) => {
+            blob_times.0.insert(normalized_root.clone(), project_times);
+            if let Err(e) = save_blob_times_file(&blob_times) {
+                log_debug!("保存 blob_times.json 失败（不影响索引）: {}", e);
+            }
+            let indexed_files: HashSet<String> = all_blob_names
+                .iter()
+                .filter_map(|h| blob_hash_map.get(h))
+                .map(|blob| blob_file_key(&blob.path).to_string())
+                .collect();
+            if let Err(e) = save_project_indexed_files(&normalized_root, &indexed_files) {
+                log_debug!("保存 indexed_files.json 失败（不影响索引）: {}", e);
+            }
+        }
+        Err(e) => log_important!(warn, "保存 projects.json 失败: {}", e),
+    }
+    // 上传结果已写入 projects.json，检查点不再需要
+    if let Err(e) = clear_project_checkpoint(&normalized_root) {
+        log_debug!("清理索引检查点失败: {}", e);
+    }
+
+    if interrupted_batches > 0 {
+        let _ = update_project_status(project_root_path, |status| {
+            status.status = IndexStatus::Idle;
+            status.last_error = Some(INDEX_INTERRUPTED_BY_SHUTDOWN.to_string());
+        });
+        anyhow::bail!("{}", INDEX_INTERRUPTED_BY_SHUTDOWN);
+    }
 
     // 使用合并后的 blob_names（与 Python 版本保持一致）
     let blob_names = all_blob_names;
@@ -1141,8 +3106,8 @@ fn write_index_memory_to_ji(project_root_path: &str, config: &AcemcpConfig) {
     // 构建记忆内容
     let text_exts = config.text_extensions.clone().unwrap_or_default();
     let exclude_patterns = config.exclude_patterns.clone().unwrap_or_default();
-    let batch_size = config.batch_size.unwrap_or(10);
-    let max_lines = config.max_lines_per_blob.unwrap_or(800);
+    let batch_size = config.batch_size.unwrap_or(DEFAULT_ACEMCP_BATCH_SIZE);
+    let max_lines = config.max_lines_per_blob.unwrap_or(DEFAULT_ACEMCP_MAX_LINES_PER_BLOB);
 
     let memory_content = format!(
         "acemcp 代码索引已启用 - 配置摘要: 文件扩展名={:?}, 排除模式={:?}, 批次大小={}, 最大行数/块={}",
@@ -1166,56 +3131,37 @@ async fn search_only(config: &AcemcpConfig, project_root_path: &str, query: &str
     let base_url = config.base_url.clone().ok_or_else(|| anyhow::anyhow!("未配置 base_url"))?;
     let token = config.token.clone().ok_or_else(|| anyhow::anyhow!("未配置 token"))?;
 
-    // 从 projects.json 读取已有的 blob 名称
-    let projects_path = home_projects_file();
-    let projects: ProjectsFile = if projects_path.exists() {
-        let data = fs::read_to_string(&projects_path).unwrap_or_default();
-        serde_json::from_str(&data).unwrap_or_default()
-    } else {
-        ProjectsFile::default()
-    };
-
-    let normalized_root = PathBuf::from(project_root_path)
-        .canonicalize()
-        .unwrap_or_else(|_| PathBuf::from(project_root_path))
-        .to_string_lossy()
-        .replace('\\', "/");
-
-    let blob_names = projects.0.get(&normalized_root).cloned().unwrap_or_default();
-
-    if blob_names.is_empty() {
-        anyhow::bail!("项目尚未索引或索引为空，请先执行索引操作");
-    }
+    let blob_names = load_search_blob_names(project_root_path)?;
 
     // 发起检索
     log_important!(info,
         "=== 开始代码检索（仅搜索模式） ==="
     );
-    let search_url = format!("{}/agents/codebase-retrieval", base_url);
+    let search_url = join_ace_url(&base_url, "agents/codebase-retrieval");
     log_important!(info, "检索请求: url={}, 使用blobs数量={}, 查询内容={}", search_url, blob_names.len(), query);
 
-    let payload = serde_json::json!({
-        "information_request": query,
-        "blobs": {"checkpoint_id": serde_json::Value::Null, "added_blobs": blob_names, "deleted_blobs": []},
-        "dialog": [],
-        "max_output_length": 0,
-        "disable_codebase_retrieval": false,
-        "enable_commit_retrieval": false,
-    });
+    let payload = build_search_payload(query, blob_names);
 
     // 创建 HTTP 客户端（支持代理）
-    let client = create_acemcp_client(config)?;
+    let client = shared_acemcp_client(config)?;
+    let request_id = new_request_id();
+    let rate_limit_budget = RateLimitBudget::new();
+    let idle = mark_ace_activity();
+    let start = std::time::Instant::now();
     let value: serde_json::Value = retry_request(|| async {
-        let r = client
-            .post(&search_url)
-            .header(AUTHORIZATION, format!("Bearer {}", token))
-            .header(CONTENT_TYPE, "application/json")
-            .json(&payload)
-            .send()
-            .await?;
+        let r = send_with_rate_limit("代码检索", &rate_limit_budget, || {
+            client
+                .post(&search_url)
+                .header(AUTHORIZATION, format!("Bearer {}", token))
+                .header(CONTENT_TYPE, "application/json")
+                .header(REQUEST_ID_HEADER, &request_id)
+                .json(&payload)
+                .send()
+        }).await?;
+        log_search_latency(config, idle, start.elapsed().as_millis());
 
         let status = r.status();
-        log_important!(info, "检索请求HTTP响应状态: {}", status);
+        log_important!(info, "检索请求HTTP响应状态: {}, request_id={}", status, request_id);
 
         if !status.is_success() {
             let body = r.text().await.unwrap_or_default();
@@ -1252,11 +3198,596 @@ async fn search_only(config: &AcemcpConfig, project_root_path: &str, query: &str
     }
 }
 
+/// 从 projects.json 读取项目已索引的 blob 名称（搜索时使用）
+fn load_search_blob_names(project_root_path: &str) -> anyhow::Result<Vec<String>> {
+    let projects = load_projects_file();
+    let normalized_root = normalize_project_path(project_root_path);
+
+    let blob_names = projects.0.get(&normalized_root).cloned().unwrap_or_default();
+
+    if blob_names.is_empty() {
+        anyhow::bail!("项目尚未索引或索引为空，请先执行索引操作");
+    }
+    Ok(blob_names)
+}
+
+/// 构造 codebase-retrieval 请求体
+fn build_search_payload(query: &str, blob_names: Vec<String>) -> serde_json::Value {
+    serde_json::json!({
+        "information_request": query,
+        "blobs": {"checkpoint_id": serde_json::Value::Null, "added_blobs": blob_names, "deleted_blobs": []},
+        "dialog": [],
+        "max_output_length": 0,
+        "disable_codebase_retrieval": false,
+        "enable_commit_retrieval": false,
+    })
+}
+
+/// 只执行搜索（流式版本）
+/// 响应为 `text/event-stream` 或 NDJSON 时按行解析并实时回调增量文本；
+/// 否则退化为缓冲模式，读取完整 JSON 后一次性回调 formatted_retrieval
+async fn search_only_streaming<F>(
+    config: &AcemcpConfig,
+    project_root_path: &str,
+    query: &str,
+    on_chunk: &mut F,
+) -> anyhow::Result<(String, SearchStreamStats)>
+where
+    F: FnMut(String) + Send,
+{
+    let base_url = config.base_url.clone().ok_or_else(|| anyhow::anyhow!("未配置 base_url"))?;
+    let token = config.token.clone().ok_or_else(|| anyhow::anyhow!("未配置 token"))?;
+
+    let blob_names = load_search_blob_names(project_root_path)?;
+
+    log_important!(info,
+        "=== 开始代码检索（流式模式） ==="
+    );
+    let search_url = join_ace_url(&base_url, "agents/codebase-retrieval");
+    log_important!(info, "检索请求: url={}, 使用blobs数量={}, 查询内容={}", search_url, blob_names.len(), query);
+
+    let payload = build_search_payload(query, blob_names);
+
+    let client = shared_acemcp_client(config)?;
+    let idle = mark_ace_activity();
+    let start = std::time::Instant::now();
+    let request_id = new_request_id();
+    let rate_limit_budget = RateLimitBudget::new();
+    // 仅对“建立连接 + 响应头”阶段重试；开始接收响应体后不再重试，避免重复推送片段
+    let mut response = retry_request(|| async {
+        let r = send_with_rate_limit("流式检索", &rate_limit_budget, || {
+            client
+                .post(&search_url)
+                .header(AUTHORIZATION, format!("Bearer {}", token))
+                .header(CONTENT_TYPE, "application/json")
+                .header(ACCEPT, "text/event-stream, application/x-ndjson, application/json")
+                .header(REQUEST_ID_HEADER, &request_id)
+                .json(&payload)
+                .send()
+        }).await?;
+        log_search_latency(config, idle, start.elapsed().as_millis());
+
+        let status = r.status();
+        log_important!(info, "检索请求HTTP响应状态: {}, request_id={}", status, request_id);
+
+        if !status.is_success() {
+            let body = r.text().await.unwrap_or_default();
+            anyhow::bail!("HTTP {} {}", status, body);
+        }
+        Ok(r)
+    }, 3, 2.0).await?;
+
+    let content_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("")
+        .to_ascii_lowercase();
+    let streamed = content_type.contains("text/event-stream") || content_type.contains("ndjson");
+    log_important!(info, "检索响应 Content-Type: {}, 流式模式: {}", content_type, streamed);
+
+    let mut stats = SearchStreamStats { streamed, ..Default::default() };
+    let mut buffer: Vec<u8> = Vec::new();
+    let mut text = String::new();
+
+    while let Some(chunk) = response.chunk().await? {
+        if stats.first_byte_ms.is_none() {
+            stats.first_byte_ms = Some(start.elapsed().as_millis() as u64);
+        }
+        buffer.extend_from_slice(&chunk);
+
+        if streamed {
+            // 按行切分，未以换行结束的尾部留到下一次处理（避免截断多字节字符）
+            while let Some(pos) = buffer.iter().position(|b| *b == b'\n') {
+                let line: Vec<u8> = buffer.drain(..=pos).collect();
+                if let Some(delta) = parse_stream_line(&String::from_utf8_lossy(&line)) {
+                    text.push_str(&delta);
+                    stats.chunk_count += 1;
+                    on_chunk(delta);
+                }
+            }
+        }
+    }
+    stats.last_byte_ms = Some(start.elapsed().as_millis() as u64);
+
+    if streamed {
+        if !buffer.is_empty() {
+            if let Some(delta) = parse_stream_line(&String::from_utf8_lossy(&buffer)) {
+                text.push_str(&delta);
+                stats.chunk_count += 1;
+                on_chunk(delta);
+            }
+        }
+    } else {
+        let value: serde_json::Value = serde_json::from_slice(&buffer)
+            .map_err(|e| anyhow::anyhow!("解析检索响应失败: {}", e))?;
+        text = value
+            .get("formatted_retrieval")
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_string();
+        if !text.is_empty() {
+            stats.chunk_count = 1;
+            on_chunk(text.clone());
+        }
+    }
+
+    log_important!(info,
+        "流式检索完成: 首字节 {:?}ms, 末字节 {:?}ms, 片段数 {}, 文本长度 {}",
+        stats.first_byte_ms, stats.last_byte_ms, stats.chunk_count, text.len()
+    );
+
+    if text.is_empty() {
+        log_important!(info, "搜索返回空结果");
+        let empty = "No relevant code context found for your query.".to_string();
+        on_chunk(empty.clone());
+        Ok((empty, stats))
+    } else {
+        Ok((text, stats))
+    }
+}
+
+/// 解析流式响应中的一行（兼容 SSE 的 `data:` 前缀与 NDJSON），返回其中的增量文本
+fn parse_stream_line(line: &str) -> Option<String> {
+    let line = line.trim_end_matches(['\r', '\n']);
+    // SSE 注释/事件名/ID 行不包含内容
+    if line.starts_with(':') || line.starts_with("event:") || line.starts_with("id:") {
+        return None;
+    }
+    let data = line.strip_prefix("data:").map(|s| s.trim_start()).unwrap_or(line);
+    if data.trim().is_empty() || data.trim() == "[DONE]" {
+        return None;
+    }
+
+    match serde_json::from_str::<serde_json::Value>(data) {
+        Ok(v) => ["formatted_retrieval", "text", "delta"]
+            .iter()
+            .find_map(|k| v.get(*k).and_then(|x| x.as_str()))
+            .filter(|s| !s.is_empty())
+            .map(|s| s.to_string()),
+        // 非 JSON 行按纯文本处理，保留换行
+        Err(_) => Some(format!("{}\n", data)),
+    }
+}
+
+/// ACE 请求的 TLS 选项（真实索引/检索客户端与测速客户端共用）
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct AcemcpTlsOptions {
+    /// mTLS 客户端证书路径（PEM）
+    pub client_cert_path: Option<String>,
+    /// mTLS 客户端私钥路径（PKCS#8 PEM）
+    pub client_key_path: Option<String>,
+    /// 额外信任的根证书路径（PEM，可包含多张证书）
+    pub extra_ca_cert_path: Option<String>,
+    /// 跳过证书校验（危险）
+    pub accept_invalid_certs: bool,
+}
+
+impl AcemcpTlsOptions {
+    pub(crate) fn from_mcp_config(config: &crate::config::McpConfig) -> Self {
+        Self {
+            client_cert_path: config.acemcp_client_cert_path.clone(),
+            client_key_path: config.acemcp_client_key_path.clone(),
+            extra_ca_cert_path: config.acemcp_extra_ca_cert_path.clone(),
+            accept_invalid_certs: config.acemcp_accept_invalid_certs.unwrap_or(false),
+        }
+    }
+
+    fn from_acemcp_config(config: &AcemcpConfig) -> Self {
+        Self {
+            client_cert_path: config.client_cert_path.clone(),
+            client_key_path: config.client_key_path.clone(),
+            extra_ca_cert_path: config.extra_ca_cert_path.clone(),
+            accept_invalid_certs: config.accept_invalid_certs.unwrap_or(false),
+        }
+    }
+
+    /// 将 TLS 选项应用到 ClientBuilder；未配置任何选项时原样返回
+    pub(crate) fn apply(&self, mut builder: reqwest::ClientBuilder) -> anyhow::Result<reqwest::ClientBuilder> {
+        let cert_path = self.client_cert_path.as_deref().map(str::trim).filter(|p| !p.is_empty());
+        let key_path = self.client_key_path.as_deref().map(str::trim).filter(|p| !p.is_empty());
+
+        match (cert_path, key_path) {
+            (None, None) => {}
+            (Some(cert_path), Some(key_path)) => {
+                let cert = fs::read(cert_path)
+                    .map_err(|e| anyhow::anyhow!("读取客户端证书失败: {} (路径: {})", e, cert_path))?;
+                let key = fs::read(key_path)
+                    .map_err(|e| anyhow::anyhow!("读取客户端私钥失败: {} (路径: {})", e, key_path))?;
+                let identity = reqwest::Identity::from_pkcs8_pem(&cert, &key).map_err(|e| {
+                    anyhow::anyhow!("客户端证书或私钥格式无效（证书需为 PEM，私钥需为 PKCS#8 PEM）: {}", e)
+                })?;
+                log_important!(info, "🔐 已加载 mTLS 客户端证书: {}", cert_path);
+                builder = builder.identity(identity);
+            }
+            (Some(_), None) => anyhow::bail!("已配置客户端证书，但未配置客户端私钥路径"),
+            (None, Some(_)) => anyhow::bail!("已配置客户端私钥，但未配置客户端证书路径"),
+        }
+
+        // 额外信任的根证书（自签名 ACE 部署）
+        if let Some(ca_path) = self.extra_ca_cert_path.as_deref().map(str::trim).filter(|p| !p.is_empty()) {
+            let pem = fs::read(ca_path)
+                .map_err(|e| anyhow::anyhow!("读取根证书失败: {} (路径: {})", e, ca_path))?;
+            let certs = reqwest::Certificate::from_pem_bundle(&pem)
+                .map_err(|e| anyhow::anyhow!("根证书格式无效（需为 PEM）: {}", e))?;
+            if certs.is_empty() {
+                anyhow::bail!("根证书文件中没有找到证书: {}", ca_path);
+            }
+            log_important!(info, "🔐 已加载额外信任的根证书: {} ({} 张)", ca_path, certs.len());
+            for cert in certs {
+                builder = builder.add_root_certificate(cert);
+            }
+        }
+
+        // 跳过证书校验：每次构建客户端都提示，避免被长期遗忘
+        if self.accept_invalid_certs {
+            log_important!(warn, "⚠️ 已启用 acemcp_accept_invalid_certs：ACE 请求将跳过证书校验，存在中间人攻击风险，仅建议在受信任的内网调试时使用");
+            builder = builder.danger_accept_invalid_certs(true);
+        }
+
+        Ok(builder)
+    }
+}
+
+/// ACE 请求的 HTTP 协议与连接池选项（真实索引/检索客户端与测速客户端共用）
+/// 默认值与未配置时的行为一致：HTTP/1.1、reqwest 默认连接池
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct AcemcpHttpOptions {
+    /// 强制 HTTP/2（prior knowledge，跳过协商，需网关支持）
+    pub http2: bool,
+    /// 每个主机保留的最大空闲连接数（None 表示使用 reqwest 默认值）
+    pub pool_max_idle_per_host: Option<usize>,
+    /// User-Agent 附加标识（便于网关运营方识别企业流量）
+    pub user_agent_suffix: Option<String>,
+}
+
+impl AcemcpHttpOptions {
+    pub(crate) fn from_mcp_config(config: &crate::config::McpConfig) -> Self {
+        Self {
+            http2: config.acemcp_http2.unwrap_or(false),
+            pool_max_idle_per_host: config.acemcp_pool_max_idle_per_host.map(|n| n as usize),
+            user_agent_suffix: config.acemcp_user_agent_suffix.clone(),
+        }
+    }
+
+    fn from_acemcp_config(config: &AcemcpConfig) -> Self {
+        Self {
+            http2: config.http2.unwrap_or(false),
+            pool_max_idle_per_host: config.pool_max_idle_per_host.map(|n| n as usize),
+            user_agent_suffix: config.user_agent_suffix.clone(),
+        }
+    }
+
+    /// 将协议/连接池选项应用到 ClientBuilder
+    /// `plain_http_proxy` 表示请求经 HTTP 代理转发明文 http 地址：此时代理需要解析请求本身，
+    /// 只会说 HTTP/1.1 的代理无法处理 HTTP/2 帧，因此回退到 HTTP/1.1（https 经 CONNECT 隧道或 SOCKS5 不受影响）
+    pub(crate) fn apply(&self, mut builder: reqwest::ClientBuilder, plain_http_proxy: bool) -> reqwest::ClientBuilder {
+        builder = builder.user_agent(acemcp_user_agent(self.user_agent_suffix.as_deref()));
+        if let Some(max_idle) = self.pool_max_idle_per_host {
+            builder = builder.pool_max_idle_per_host(max_idle);
+        }
+        if self.http2 {
+            if plain_http_proxy {
+                log_important!(warn, "⚠️ 已配置 HTTP/2，但 http 地址需经 HTTP 代理明文转发，回退到 HTTP/1.1");
+            } else {
+                log_debug!("使用 HTTP/2（prior knowledge + 自适应窗口）");
+                builder = builder.http2_prior_knowledge().http2_adaptive_window(true);
+            }
+        }
+        builder
+    }
+}
+
+/// ACE 请求的 User-Agent：`sanshu/<版本>`，配置了附加标识时追加在其后
+pub(crate) fn acemcp_user_agent(suffix: Option<&str>) -> String {
+    match suffix.map(str::trim).filter(|s| !s.is_empty()) {
+        Some(suffix) => format!("{} {}", crate::constants::app::USER_AGENT, suffix),
+        None => crate::constants::app::USER_AGENT.to_string(),
+    }
+}
+
+/// 请求关联 ID 的请求头，服务端日志可据此与客户端日志对应
+pub(crate) const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+/// 生成新的请求关联 ID（同一逻辑请求的重试复用同一个 ID）
+pub(crate) fn new_request_id() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
+
+/// 代理是否需要解析明文请求（HTTP/HTTPS 代理 + http 目标地址）
+pub(crate) fn is_plain_http_proxy(proxy_type: Option<&str>, base_url: &str) -> bool {
+    matches!(proxy_type, Some("http") | Some("https"))
+        && base_url.trim().to_ascii_lowercase().starts_with("http://")
+}
+
+/// 共享客户端的缓存键：代理、TLS、HTTP 协议配置完全一致时复用同一个 Client（连接池）
+#[derive(PartialEq, Eq)]
+struct AcemcpClientKey {
+    proxy_enabled: bool,
+    proxy_host: Option<String>,
+    proxy_port: Option<u16>,
+    proxy_type: Option<String>,
+    proxy_username: Option<String>,
+    proxy_password: Option<String>,
+    tls: AcemcpTlsOptions,
+    http: AcemcpHttpOptions,
+    base_url: Option<String>,
+}
+
+impl AcemcpClientKey {
+    fn from_config(config: &AcemcpConfig) -> Self {
+        Self {
+            proxy_enabled: config.proxy_enabled.unwrap_or(false),
+            proxy_host: config.proxy_host.clone(),
+            proxy_port: config.proxy_port,
+            proxy_type: config.proxy_type.clone(),
+            proxy_username: config.proxy_username.clone(),
+            proxy_password: config.proxy_password.clone(),
+            tls: AcemcpTlsOptions::from_acemcp_config(config),
+            http: AcemcpHttpOptions::from_acemcp_config(config),
+            base_url: config.base_url.clone(),
+        }
+    }
+}
+
+/// 进程内共享的 ACE 客户端（reqwest::Client 内部为 Arc，clone 开销很小且共享连接池）
+static SHARED_ACEMCP_CLIENT: once_cell::sync::Lazy<Mutex<Option<(AcemcpClientKey, Client)>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(None));
+
+/// 获取共享的 ACE 客户端
+/// 代理、TLS 或 HTTP 协议配置变化时自动重建，避免每次检索/上传都重新建立连接与 TLS 握手
+fn shared_acemcp_client(config: &AcemcpConfig) -> anyhow::Result<Client> {
+    if config.bypass_shared_client {
+        return create_acemcp_client(config);
+    }
+    let key = AcemcpClientKey::from_config(config);
+    let mut guard = SHARED_ACEMCP_CLIENT
+        .lock()
+        .map_err(|e| anyhow::anyhow!("获取共享客户端失败: {}", e))?;
+
+    if let Some((cached_key, client)) = guard.as_ref() {
+        if *cached_key == key {
+            return Ok(client.clone());
+        }
+        log_debug!("ACE 代理/TLS 配置已变化，重建共享客户端");
+    }
+
+    let client = create_acemcp_client(config)?;
+    *guard = Some((key, client.clone()));
+    Ok(client)
+}
+
+/// 丢弃共享客户端（保存配置后调用，确保证书文件内容等外部变化也能生效）
+pub(crate) fn reset_shared_acemcp_client() {
+    if let Ok(mut guard) = SHARED_ACEMCP_CLIENT.lock() {
+        if guard.take().is_some() {
+            log_debug!("已重置共享 ACE 客户端");
+        }
+    }
+}
+
+/// 保活 Ping 默认间隔（秒），小于 reqwest 连接池默认的 90 秒空闲回收时间
+pub(crate) const KEEPALIVE_DEFAULT_INTERVAL_SECS: u64 = 45;
+/// 保活 Ping 最小间隔（秒），避免对 ACE 产生无意义的请求压力
+pub(crate) const KEEPALIVE_MIN_INTERVAL_SECS: u64 = 10;
+
+/// 保活任务是否正在运行（同一进程内最多一个）
+static KEEPALIVE_RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// 本进程最近一次与 ACE 通信（检索或保活 Ping）的时间
+static LAST_ACE_ACTIVITY: once_cell::sync::Lazy<Mutex<Option<std::time::Instant>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(None));
+
+/// 记录一次与 ACE 的通信，返回距上次通信的空闲时长（本进程首次通信为 None）
+fn mark_ace_activity() -> Option<Duration> {
+    let mut guard = LAST_ACE_ACTIVITY.lock().ok()?;
+    let idle = guard.map(|at| at.elapsed());
+    *guard = Some(std::time::Instant::now());
+    idle
+}
+
+/// 记录检索的响应头延迟及距上次 ACE 通信的空闲时长，用于对比开启/关闭保活时空闲后首次检索的延迟
+fn log_search_latency(config: &AcemcpConfig, idle: Option<Duration>, header_ms: u128) {
+    let idle = idle
+        .map(|d| format!("{}s", d.as_secs()))
+        .unwrap_or_else(|| "首次".to_string());
+    log_important!(info,
+        "检索延迟: 响应头耗时={}ms, 距上次 ACE 通信={}, 保活={}",
+        header_ms, idle, config.keepalive_enabled.unwrap_or(false)
+    );
+}
+
+/// MCP 服务进程内定期检查保活配置：在界面中开启保活后无需等到下一次检索即可开始预热
+pub(crate) fn start_keepalive_supervisor() {
+    let Ok(handle) = tokio::runtime::Handle::try_current() else {
+        return;
+    };
+    handle.spawn(async {
+        loop {
+            ensure_keepalive_started();
+            tokio::time::sleep(Duration::from_secs(KEEPALIVE_DEFAULT_INTERVAL_SECS)).await;
+        }
+    });
+}
+
+/// 按需启动后台保活任务：定期经共享客户端 Ping `{base_url}/health`，
+/// 让连接池中的连接（含代理隧道与 TLS 会话）保持预热，降低空闲后首次检索的延迟
+///
+/// 每轮都重新读取配置：保活被关闭或 base_url/token 不完整时任务退出，之后的检索会再次尝试启动
+pub(crate) fn ensure_keepalive_started() {
+    if KEEPALIVE_RUNNING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let handle = match tokio::runtime::Handle::try_current() {
+        Ok(handle) => handle,
+        Err(_) => {
+            KEEPALIVE_RUNNING.store(false, Ordering::SeqCst);
+            return;
+        }
+    };
+
+    handle.spawn(async move {
+        loop {
+            let config = match AcemcpTool::get_acemcp_config().await {
+                Ok(config) => config,
+                Err(e) => {
+                    log_debug!("读取配置失败，停止保活任务: {}", e);
+                    break;
+                }
+            };
+            if !config.keepalive_enabled.unwrap_or(false) {
+                log_debug!("保活未启用，停止保活任务");
+                break;
+            }
+            let base_url = config.base_url.clone().unwrap_or_default();
+            let token = config.token.clone().unwrap_or_default();
+            if base_url.trim().is_empty() || token.trim().is_empty() {
+                log_important!(info, "ACE 配置不完整，停止保活任务");
+                break;
+            }
+
+            keepalive_ping(&config, &base_url, &token).await;
+
+            let interval = config
+                .keepalive_interval_secs
+                .unwrap_or(KEEPALIVE_DEFAULT_INTERVAL_SECS)
+                .max(KEEPALIVE_MIN_INTERVAL_SECS);
+            tokio::time::sleep(Duration::from_secs(interval)).await;
+        }
+        KEEPALIVE_RUNNING.store(false, Ordering::SeqCst);
+    });
+}
+
+/// 单次保活 Ping（复用共享客户端，因此自动遵循当前的代理/直连选择）
+async fn keepalive_ping(config: &AcemcpConfig, base_url: &str, token: &str) {
+    let client = match shared_acemcp_client(config) {
+        Ok(client) => client,
+        Err(e) => {
+            log_debug!("保活 Ping 获取客户端失败: {}", e);
+            return;
+        }
+    };
+
+    let url = join_ace_url(&normalize_base_url(base_url), "health");
+    let request_id = new_request_id();
+    mark_ace_activity();
+    let start = std::time::Instant::now();
+    match client
+        .get(&url)
+        .timeout(Duration::from_secs(10))
+        .header(AUTHORIZATION, format!("Bearer {}", token))
+        .header(REQUEST_ID_HEADER, &request_id)
+        .send()
+        .await
+    {
+        Ok(resp) => log_debug!("保活 Ping: status={}, 耗时={}ms, request_id={}", resp.status(), start.elapsed().as_millis(), request_id),
+        Err(e) => log_debug!("保活 Ping 失败: {}, request_id={}", e, request_id),
+    }
+}
+
+/// 当前配置对应的网络路径描述（代理地址或 "直连"）
+fn proxy_route_label(config: &AcemcpConfig) -> String {
+    if !config.proxy_enabled.unwrap_or(false) {
+        return "直连".to_string();
+    }
+    format!(
+        "{}://{}:{}",
+        config.proxy_type.clone().unwrap_or_else(|| "http".to_string()),
+        config.proxy_host.clone().unwrap_or_else(|| "127.0.0.1".to_string()),
+        config.proxy_port.unwrap_or(7890)
+    )
+}
+
+/// 上传连续失败时尝试切换到备用代理（代理检测缓存中最近验证可用的代理）
+/// 仅在启用故障转移、当前正在使用代理、且备用代理与当前不同并通过复检时返回新客户端
+/// 新客户端不写入共享缓存，只作用于本次索引
+async fn try_proxy_failover(
+    config: &AcemcpConfig,
+    current_route: &str,
+    batch: usize,
+) -> Option<(Client, ProxyFailoverEvent)> {
+    if !config.proxy_failover_enabled.unwrap_or(false) || !config.proxy_enabled.unwrap_or(false) {
+        return None;
+    }
+    let Some(alternate) = config.failover_proxy.as_ref() else {
+        log_important!(warn, "上传连续失败，但没有可用的备用代理，无法故障转移");
+        return None;
+    };
+    let target = format!("{}://{}:{}", alternate.proxy_type, alternate.host, alternate.port);
+    if target == current_route {
+        log_important!(warn, "上传连续失败，备用代理与当前代理相同（{}），跳过故障转移", target);
+        return None;
+    }
+
+    let proxy_type = if alternate.proxy_type == "socks5" { ProxyType::Socks5 } else { ProxyType::Http };
+    let probe = ProxyInfo::new(proxy_type, alternate.host.clone(), alternate.port);
+    if !ProxyDetector::check_proxy(&probe).await {
+        log_important!(warn, "备用代理 {} 复检不可用，放弃故障转移", target);
+        return None;
+    }
+
+    let mut alt_config = config.clone();
+    alt_config.proxy_enabled = Some(true);
+    alt_config.proxy_host = Some(alternate.host.clone());
+    alt_config.proxy_port = Some(alternate.port);
+    alt_config.proxy_type = Some(alternate.proxy_type.clone());
+    // 检测到的本地代理不需要认证，避免把原代理的凭据发给新代理
+    alt_config.proxy_username = None;
+    alt_config.proxy_password = None;
+
+    match create_acemcp_client(&alt_config) {
+        Ok(client) => {
+            log_important!(warn, "上传连续失败，代理故障转移: {} -> {}（批次 {}）", current_route, target, batch);
+            Some((client, ProxyFailoverEvent {
+                from: current_route.to_string(),
+                to: target,
+                batch,
+                at: chrono::Utc::now(),
+            }))
+        }
+        Err(e) => {
+            log_important!(warn, "创建备用代理客户端失败，放弃故障转移: {}", e);
+            None
+        }
+    }
+}
+
 /// 创建支持代理的 HTTP 客户端
 /// 根据配置决定是否使用代理
 fn create_acemcp_client(config: &AcemcpConfig) -> anyhow::Result<Client> {
     let mut client_builder = Client::builder()
         .timeout(Duration::from_secs(60));
+
+    // TLS 选项（mTLS 客户端证书等，未配置时不影响默认行为）
+    client_builder = AcemcpTlsOptions::from_acemcp_config(config).apply(client_builder)?;
+
+    // HTTP/2 与连接池选项（未配置时保持 HTTP/1.1 与默认连接池）
+    let proxy_type = config
+        .proxy_enabled
+        .unwrap_or(false)
+        .then(|| config.proxy_type.clone().unwrap_or_else(|| "http".to_string()));
+    let plain_http_proxy = is_plain_http_proxy(proxy_type.as_deref(), config.base_url.as_deref().unwrap_or(""));
+    client_builder = AcemcpHttpOptions::from_acemcp_config(config).apply(client_builder, plain_http_proxy);
     
     // 检查是否启用代理
     if config.proxy_enabled.unwrap_or(false) {
@@ -1307,3 +3838,324 @@ fn create_acemcp_client(config: &AcemcpConfig) -> anyhow::Result<Client> {
     client_builder.build()
         .map_err(|e| anyhow::anyhow!("构建 HTTP 客户端失败: {}", e))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exclusion_subpath() {
+        assert_eq!(normalize_exclusion_subpath("./build/out/").as_deref(), Some("build/out"));
+        assert_eq!(normalize_exclusion_subpath("src\\gen").as_deref(), Some("src/gen"));
+        assert_eq!(normalize_exclusion_subpath("../x"), None);
+        assert_eq!(normalize_exclusion_subpath(" / "), None);
+
+        let subs = vec!["build/out".to_string()];
+        assert!(is_under_exclusion("build/out", &subs));
+        assert!(is_under_exclusion("build/out/a.rs", &subs));
+        assert!(!is_under_exclusion("build/output/a.rs", &subs));
+
+        let matcher = ExcludeMatcher::new(&[exclusion_subpath_pattern("build/out")]);
+        assert!(matcher.match_index(Path::new("/p/build/out/a.rs"), Path::new("/p")).is_some());
+        assert!(matcher.match_index(Path::new("/p/build/output/a.rs"), Path::new("/p")).is_none());
+
+        // 运行时排除子目录不进入监听器的静态匹配器
+        let patterns = vec!["node_modules".to_string(), exclusion_subpath_pattern("build/out")];
+        assert_eq!(static_exclude_patterns(&patterns, &subs), vec!["node_modules".to_string()]);
+    }
+
+    #[test]
+    fn test_index_run_guard_tracks_own_roots() {
+        let a1 = IndexRunGuard::enter("/tmp/guard-proj-a");
+        let a2 = IndexRunGuard::enter("/tmp/guard-proj-a/");
+        let b = IndexRunGuard::enter("/tmp/guard-proj-b");
+        let key_a = normalize_project_path("/tmp/guard-proj-a");
+        let key_b = normalize_project_path("/tmp/guard-proj-b");
+        assert_eq!(ACTIVE_INDEX_ROOTS.lock().unwrap().get(&key_a), Some(&2));
+
+        drop(a1);
+        drop(b);
+        assert!(ACTIVE_INDEX_ROOTS.lock().unwrap().contains_key(&key_a));
+        assert!(!ACTIVE_INDEX_ROOTS.lock().unwrap().contains_key(&key_b));
+        drop(a2);
+        assert!(!ACTIVE_INDEX_ROOTS.lock().unwrap().contains_key(&key_a));
+    }
+
+    #[test]
+    fn test_checkpoint_resume_per_project() {
+        let dir = std::env::temp_dir().join(format!("sanshu-checkpoints-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let names = |v: &[&str]| v.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        // 中断前上传成功的两个批次依次追加，另一项目的检查点互不影响
+        append_checkpoint_in(&dir, "/repo/a", &names(&["h1", "h2"])).unwrap();
+        append_checkpoint_in(&dir, "/repo/b", &names(&["x1"])).unwrap();
+        append_checkpoint_in(&dir, "/repo/a", &names(&["h3"])).unwrap();
+        assert_eq!(load_checkpoint_in(&dir, "/repo/a"), names(&["h1", "h2", "h3"]));
+        assert_eq!(load_checkpoint_in(&dir, "/repo/b"), names(&["x1"]));
+
+        // 索引完成后清除，下次从空检查点开始
+        clear_checkpoint_in(&dir, "/repo/a").unwrap();
+        clear_checkpoint_in(&dir, "/repo/a").unwrap();
+        assert!(load_checkpoint_in(&dir, "/repo/a").is_empty());
+        assert_eq!(load_checkpoint_in(&dir, "/repo/b"), names(&["x1"]));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_remove_project_side_records() {
+        let dir = std::env::temp_dir().join(format!("sanshu-side-records-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        // 历史 key（末尾斜杠）规范化后与待移除项目相同
+        let times = serde_json::json!({
+            "/repo/a/": { "h1": "2024-01-01T00:00:00Z" },
+            "/repo/b": { "x1": "2024-01-01T00:00:00Z" }
+        });
+        let files = serde_json::json!({ "/repo/a": ["src/a.rs"], "/repo/b": ["src/b.rs"] });
+        fs::write(dir.join(BLOB_TIMES_FILE), times.to_string()).unwrap();
+        fs::write(dir.join(INDEXED_FILES_FILE), files.to_string()).unwrap();
+        let checkpoint_dir = dir.join(INDEX_CHECKPOINT_DIR);
+        append_checkpoint_in(&checkpoint_dir, "/repo/a", &["h2".to_string()]).unwrap();
+
+        let keys: HashSet<String> = ["/repo/a".to_string()].into_iter().collect();
+        remove_project_side_records_in(&dir, &keys).unwrap();
+
+        let read = |name: &str| -> serde_json::Value {
+            serde_json::from_str(&fs::read_to_string(dir.join(name)).unwrap()).unwrap()
+        };
+        let times = read(BLOB_TIMES_FILE);
+        let times = times.as_object().unwrap();
+        assert!(times.contains_key("/repo/b") && times.len() == 1);
+        let files = read(INDEXED_FILES_FILE);
+        let files = files.as_object().unwrap();
+        assert!(files.contains_key("/repo/b") && files.len() == 1);
+        assert!(load_checkpoint_in(&checkpoint_dir, "/repo/a").is_empty());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_preview_exclusions_uses_local_rules() {
+        let root = std::env::temp_dir().join(format!("sanshu-preview-excl-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        for dir in ["src", "build", "notes"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        for file in ["src/a.rs", "build/out.js", "build/keep.rs", "notes/x.md"] {
+            fs::write(root.join(file), "x\n").unwrap();
+        }
+        fs::write(root.join(LOCAL_INCLUDE_FILE), "build/keep.rs\n").unwrap();
+        fs::write(root.join(LOCAL_IGNORE_FILE), "notes/\n").unwrap();
+
+        let preview = preview_exclusions(&root.to_string_lossy(), &["build".to_string()], false).unwrap();
+        // build/keep.rs 被 .acemcpinclude 找回，不计为排除；notes/ 由 .acemcpignore 排除，单独计数
+        assert_eq!(preview.patterns[0].excluded_files, 1);
+        assert_eq!(preview.patterns[0].excluded_dirs, 0);
+        assert_eq!(preview.patterns[0].sample_paths, vec!["build/out.js".to_string()]);
+        assert_eq!(preview.excluded_files, 1);
+        assert_eq!(preview.local_ignored_files, 1);
+        assert_eq!(preview.total_files, 6);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_daily_budget_warning() {
+        assert!(daily_budget_warning(None, 900, 500).is_none());
+        assert!(daily_budget_warning(Some(0), 900, 500).is_none());
+        assert!(daily_budget_warning(Some(1000), 400, 500).is_none());
+        assert!(daily_budget_warning(Some(1000), 600, 500).is_some());
+    }
+
+    #[test]
+    fn test_join_ace_url() {
+        assert_eq!(join_ace_url("https://host", "health"), "https://host/health");
+        assert_eq!(join_ace_url("https://host/", "health"), "https://host/health");
+        assert_eq!(join_ace_url("https://host/ace", "agents/codebase-retrieval"), "https://host/ace/agents/codebase-retrieval");
+        assert_eq!(join_ace_url("https://host/ace/", "/batch-upload"), "https://host/ace/batch-upload");
+        // query / fragment 不应出现在拼接结果中
+        assert_eq!(join_ace_url("https://host/ace?tenant=a#x", "health"), "https://host/ace/health");
+        assert_eq!(join_ace_url("not a url/", "health"), "not a url/health");
+    }
+
+    #[test]
+    fn test_acemcp_user_agent() {
+        let base = crate::constants::app::USER_AGENT;
+        assert!(base.starts_with("sanshu/"));
+        assert_eq!(acemcp_user_agent(None), base);
+        assert_eq!(acemcp_user_agent(Some("  ")), base);
+        assert_eq!(acemcp_user_agent(Some(" acme-corp ")), format!("{} acme-corp", base));
+    }
+
+    #[test]
+    fn test_count_duplicate_records() {
+        let mut raw = ProjectsFile::default();
+        raw.0.insert("/work/app".to_string(), vec!["a".to_string(), "b".to_string(), "a".to_string()]);
+        // 历史 key 与规范化 key 指向同一项目，其间的重复同样计入
+        raw.0.insert("/work/app/".to_string(), vec!["b".to_string(), "c".to_string()]);
+        raw.0.insert("/work/other".to_string(), vec!["a".to_string()]);
+        assert_eq!(count_duplicate_records(&raw, "/work/app"), 2);
+        assert_eq!(count_duplicate_records(&raw, "/work/other"), 0);
+        assert_eq!(count_duplicate_records(&raw, "/work/missing"), 0);
+    }
+
+    #[test]
+    fn test_plan_upload_batches() {
+        assert_eq!(blob_file_key("src/a.rs#chunk2of3"), "src/a.rs");
+        assert_eq!(blob_file_key("docs/#chunk.md"), "docs/#chunk.md");
+
+        // 文件数上限：3 个单 blob 文件，每批 2 个文件
+        assert_eq!(plan_upload_batches(["a", "b", "c"], 2, 50), vec![0..2, 2..3]);
+        // blob 数上限：大文件 big 有 5 个分块，每请求最多 2 个 blob，跨多个请求发送
+        let keys = ["a", "big", "big", "big", "big", "big", "c"];
+        assert_eq!(plan_upload_batches(keys, 10, 2), vec![0..2, 2..4, 4..6, 6..7]);
+        // 两个上限同时生效
+        assert_eq!(plan_upload_batches(["a", "a", "b", "c"], 2, 3), vec![0..3, 3..4]);
+        assert!(plan_upload_batches(Vec::<&str>::new(), 10, 50).is_empty());
+    }
+
+    #[test]
+    fn test_normalize_path_key_extended_length() {
+        assert_eq!(normalize_path_key(r"\\?\C:\Users\dev\project"), "c:/Users/dev/project");
+        assert_eq!(normalize_path_key("//?/D:/work/repo/"), "d:/work/repo");
+        assert_eq!(normalize_path_key(r"\\?\UNC\server\share\repo"), "//server/share/repo");
+    }
+
+    #[test]
+    fn test_normalize_path_key_mixed_separators() {
+        assert_eq!(normalize_path_key(r"C:\Users/dev\project\"), "c:/Users/dev/project");
+        assert_eq!(normalize_path_key("/home/dev/project/"), "/home/dev/project");
+        // 根路径保留末尾斜杠
+        assert_eq!(normalize_path_key(r"C:\"), "c:/");
+        assert_eq!(normalize_path_key("/"), "/");
+    }
+
+    #[test]
+    fn test_exclude_matcher_patterns() {
+        let root = Path::new("/repo");
+        let patterns: Vec<String> = vec!["*.min.js".into(), r"re:.*\.generated\..*".into(), "node_modules".into(), "re:(".into()];
+        let matcher = ExcludeMatcher::new(&patterns);
+        assert_eq!(matcher.invalid, vec!["re:(".to_string()]);
+
+        // glob：匹配任一路径片段
+        assert_eq!(matcher.match_index(Path::new("/repo/static/app.min.js"), root), Some(0));
+        assert_eq!(matcher.match_index(Path::new("/repo/static/app.js"), root), None);
+        // 正则：匹配完整相对路径
+        assert_eq!(matcher.match_index(Path::new("/repo/src/api.generated.ts"), root), Some(1));
+        assert_eq!(matcher.match_index(Path::new("/repo/src/generated.ts"), root), None);
+        // 普通字符串：按片段精确匹配，不误伤相似名称
+        assert_eq!(matcher.match_index(Path::new("/repo/web/node_modules/lib/index.js"), root), Some(2));
+        assert_eq!(matcher.match_index(Path::new("/repo/my_node_modules_dir/index.js"), root), None);
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        let now = chrono::DateTime::parse_from_rfc3339("2015-10-21T07:28:00Z").unwrap().with_timezone(&chrono::Utc);
+        assert_eq!(parse_retry_after(" 5 ", now), Some(Duration::from_secs(5)));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:30 GMT", now), Some(Duration::from_secs(30)));
+        // 过去的时间点视为立即重试
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:27:00 GMT", now), Some(Duration::ZERO));
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+
+    #[test]
+    fn test_rate_limit_budget_shared_across_retries() {
+        let budget = RateLimitBudget::new();
+        assert_eq!(budget.take(Duration::from_secs(20)), Duration::from_secs(20));
+        // 第二次尝试只能用掉剩余的 10 秒
+        assert_eq!(budget.take(Duration::from_secs(20)), Duration::from_secs(10));
+        assert!(budget.is_exhausted());
+        assert_eq!(budget.take(Duration::from_secs(2)), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_normalize_text_extensions() {
+        let input = vec!["py".to_string(), ".PY".to_string(), " .Rs ".to_string(), ".py".to_string()];
+        assert_eq!(normalize_text_extensions(&input).unwrap(), vec![".py", ".rs"]);
+
+        let with_empty = vec![".py".to_string(), " ".to_string()];
+        assert!(normalize_text_extensions(&with_empty).is_err());
+        assert_eq!(normalize_text_extensions_lenient(&with_empty), vec![".py"]);
+    }
+
+    #[test]
+    fn test_normalize_project_path_is_idempotent() {
+        let once = normalize_project_path(r"\\?\C:\not\exists\repo");
+        assert_eq!(normalize_project_path(&once), once);
+    }
+
+    #[test]
+    fn test_file_error_list_is_capped() {
+        let mut errors = FileErrorList::default();
+        for i in 0..FileErrorList::MAX_ITEMS + 7 {
+            errors.push(format!("src/{}.rs", i), "读取失败");
+        }
+        assert_eq!(errors.total, FileErrorList::MAX_ITEMS + 7);
+        assert_eq!(errors.items.len(), FileErrorList::MAX_ITEMS);
+        assert_eq!(errors.omitted(), 7);
+        assert_eq!(errors.items[0].path, "src/0.rs");
+    }
+
+    #[test]
+    fn test_max_lines_override_precedence() {
+        let overrides: HashMap<String, u32> =
+            [("MD".to_string(), 2000), (".js".to_string(), 300), (".min.js".to_string(), 20), (".rs".to_string(), 0)]
+                .into_iter()
+                .collect();
+        let policy = MaxLinesPolicy::new(800, Some(&overrides));
+        assert_eq!(policy.for_path("docs/README.md"), 2000);
+        assert_eq!(policy.for_path("src/app.js"), 300);
+        // 复合扩展名优先于单一扩展名
+        assert_eq!(policy.for_path("dist/app.min.js"), 20);
+        // 0 视为无效覆盖，回退到全局值
+        assert_eq!(policy.for_path("src/main.rs"), 800);
+        assert_eq!(policy.for_path("src/lib.py"), 800);
+        assert_eq!(MaxLinesPolicy::new(800, None).for_path("a.md"), 800);
+
+        assert!(normalize_max_lines_overrides(&overrides).is_err());
+        let valid: HashMap<String, u32> = [("MD".to_string(), 10)].into_iter().collect();
+        assert_eq!(normalize_max_lines_overrides(&valid).unwrap().get(".md"), Some(&10));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_self_referential_symlink_does_not_loop() {
+        let root = std::env::temp_dir().join(format!("sanshu_symlink_test_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("src/main.rs"), "fn main() {}\n").unwrap();
+        std::os::unix::fs::symlink(&root, root.join("src/loop")).unwrap();
+
+        let exts = vec![".rs".to_string()];
+        let policy = MaxLinesPolicy::new(800, None);
+        for follow in [false, true] {
+            let blobs = collect_blobs(root.to_str().unwrap(), &exts, &[], &policy, follow).unwrap();
+            assert_eq!(blobs.len(), 1, "follow_symlinks={}", follow);
+            assert_eq!(blobs[0].path, "src/main.rs");
+        }
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_preview_file_chunks_boundaries() {
+        let root = std::env::temp_dir().join(format!("sanshu_chunk_preview_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("src/a.rs"), "1\n2\n3\n4\n5\n").unwrap();
+
+        let policy = MaxLinesPolicy::new(2, None);
+        let preview = preview_file_chunks(root.to_str().unwrap(), "./src/a.rs", &policy).unwrap();
+        assert_eq!(preview.total_lines, 5);
+        let bounds: Vec<(usize, usize)> = preview.chunks.iter().map(|c| (c.start_line, c.end_line)).collect();
+        assert_eq!(bounds, vec![(1, 2), (3, 4), (5, 5)]);
+        assert_eq!(preview.chunks[1].chunk_path, "src/a.rs#chunk2of3");
+        assert_eq!(preview.chunks[2].byte_size, 2);
+
+        assert!(preview_file_chunks(root.to_str().unwrap(), "../a.rs", &policy).is_err());
+
+        let _ = fs::remove_dir_all(&root);
+    }
+}
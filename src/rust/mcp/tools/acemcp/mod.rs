@@ -5,7 +5,9 @@ pub mod mcp;
 pub mod types;
 pub mod commands;
 pub mod watcher;
+pub mod history;
+pub mod dispatch;
 
 // 重新导出工具以便访问
-pub use mcp::AcemcpTool;
+pub use mcp::{AcemcpTool, normalize_project_path};
 pub use watcher::get_watcher_manager;
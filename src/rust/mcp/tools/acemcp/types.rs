@@ -12,7 +12,8 @@ pub struct AcemcpRequest {
 }
 
 /// Acemcp配置
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Debug 输出会隐藏 token 与代理密码，避免误打日志泄露
+#[derive(Clone, Serialize, Deserialize)]
 pub struct AcemcpConfig {
     /// API端点URL
     pub base_url: Option<String>,
@@ -20,12 +21,27 @@ pub struct AcemcpConfig {
     pub token: Option<String>,
     /// 每批上传的文件数量
     pub batch_size: Option<u32>,
+    /// 单次上传请求的最大 blob 数
+    #[serde(default)]
+    pub max_blobs_per_request: Option<u32>,
+    /// 每日上传 blob 预算（超出时仅提醒）
+    #[serde(default)]
+    pub daily_blob_budget: Option<u32>,
     /// 大文件分割前的最大行数
     pub max_lines_per_blob: Option<u32>,
+    /// 按扩展名覆盖的最大行数（键为带点的小写扩展名）
+    #[serde(default)]
+    pub max_lines_by_extension: Option<HashMap<String, u32>>,
     /// 要索引的文件扩展名列表
     pub text_extensions: Option<Vec<String>>,
     /// 要排除的模式列表
     pub exclude_patterns: Option<Vec<String>>,
+    /// 扫描时是否跟随符号链接目录（跟随时按真实路径去重以防循环）
+    #[serde(default)]
+    pub follow_symlinks: Option<bool>,
+    /// 项目运行时排除的子目录（相对项目根目录，仅项目级配置填充）
+    #[serde(default)]
+    pub project_exclusions: Vec<String>,
     /// 搜索时的智能等待配置（秒）
     /// 当检测到索引正在进行时，随机等待 [min, max] 秒后再执行搜索
     /// 默认值：Some((1, 5))，设为 None 则禁用智能等待
@@ -43,6 +59,108 @@ pub struct AcemcpConfig {
     pub proxy_username: Option<String>,
     /// 代理密码（可选）
     pub proxy_password: Option<String>,
+    /// mTLS 客户端证书路径（PEM）
+    #[serde(default)]
+    pub client_cert_path: Option<String>,
+    /// mTLS 客户端私钥路径（PKCS#8 PEM）
+    #[serde(default)]
+    pub client_key_path: Option<String>,
+    /// 额外信任的根证书路径（PEM）
+    #[serde(default)]
+    pub extra_ca_cert_path: Option<String>,
+    /// 是否跳过证书校验（危险，仅限内网自签名调试）
+    #[serde(default)]
+    pub accept_invalid_certs: Option<bool>,
+    /// 是否启用后台保活 Ping
+    #[serde(default)]
+    pub keepalive_enabled: Option<bool>,
+    /// 保活 Ping 间隔（秒）
+    #[serde(default)]
+    pub keepalive_interval_secs: Option<u64>,
+    /// 文件监听忽略超过该大小的文件变更（字节，None 表示不限制）
+    #[serde(default)]
+    pub max_file_bytes: Option<u64>,
+    /// 上传连续失败时是否自动切换到备用代理
+    #[serde(default)]
+    pub proxy_failover_enabled: Option<bool>,
+    /// 故障转移使用的备用代理（来自代理检测缓存）
+    #[serde(default)]
+    pub failover_proxy: Option<crate::config::LastKnownProxy>,
+    /// 是否强制使用 HTTP/2（prior knowledge）
+    #[serde(default)]
+    pub http2: Option<bool>,
+    /// 每个主机保留的最大空闲连接数
+    #[serde(default)]
+    pub pool_max_idle_per_host: Option<u32>,
+    /// User-Agent 附加标识
+    #[serde(default)]
+    pub user_agent_suffix: Option<String>,
+    /// 临时配置（如调试搜索的代理覆盖）：每次单独建立客户端，不替换进程内共享客户端
+    #[serde(skip)]
+    pub bypass_shared_client: bool,
+}
+
+/// 单次请求的临时代理覆盖（不写入配置）
+#[derive(Debug, Clone)]
+pub struct AcemcpProxyOverride {
+    pub host: String,
+    pub port: u16,
+    pub proxy_type: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+impl AcemcpProxyOverride {
+    /// 替换配置中的代理设置并关闭故障转移，保证请求确实经过指定代理
+    pub fn apply(&self, config: &mut AcemcpConfig) {
+        config.proxy_enabled = Some(true);
+        config.proxy_host = Some(self.host.clone());
+        config.proxy_port = Some(self.port);
+        config.proxy_type = Some(self.proxy_type.clone());
+        config.proxy_username = self.username.clone();
+        config.proxy_password = self.password.clone();
+        config.proxy_failover_enabled = Some(false);
+        config.bypass_shared_client = true;
+    }
+}
+
+impl std::fmt::Debug for AcemcpConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mask = |v: &Option<String>| v.as_ref().map(|_| crate::utils::logger::REDACTED);
+        f.debug_struct("AcemcpConfig")
+            .field("base_url", &self.base_url)
+            .field("token", &mask(&self.token))
+            .field("batch_size", &self.batch_size)
+            .field("max_blobs_per_request", &self.max_blobs_per_request)
+            .field("daily_blob_budget", &self.daily_blob_budget)
+            .field("user_agent_suffix", &self.user_agent_suffix)
+            .field("max_lines_per_blob", &self.max_lines_per_blob)
+            .field("max_lines_by_extension", &self.max_lines_by_extension)
+            .field("text_extensions", &self.text_extensions)
+            .field("exclude_patterns", &self.exclude_patterns)
+            .field("follow_symlinks", &self.follow_symlinks)
+            .field("project_exclusions", &self.project_exclusions)
+            .field("smart_wait_range", &self.smart_wait_range)
+            .field("proxy_enabled", &self.proxy_enabled)
+            .field("proxy_host", &self.proxy_host)
+            .field("proxy_port", &self.proxy_port)
+            .field("proxy_type", &self.proxy_type)
+            .field("proxy_username", &self.proxy_username)
+            .field("proxy_password", &mask(&self.proxy_password))
+            .field("client_cert_path", &self.client_cert_path)
+            .field("client_key_path", &self.client_key_path)
+            .field("extra_ca_cert_path", &self.extra_ca_cert_path)
+            .field("accept_invalid_certs", &self.accept_invalid_certs)
+            .field("keepalive_enabled", &self.keepalive_enabled)
+            .field("keepalive_interval_secs", &self.keepalive_interval_secs)
+            .field("max_file_bytes", &self.max_file_bytes)
+            .field("proxy_failover_enabled", &self.proxy_failover_enabled)
+            .field("failover_proxy", &self.failover_proxy)
+            .field("http2", &self.http2)
+            .field("pool_max_idle_per_host", &self.pool_max_idle_per_host)
+            .field("bypass_shared_client", &self.bypass_shared_client)
+            .finish()
+    }
 }
 
 
@@ -85,6 +203,70 @@ pub struct ProjectIndexStatus {
     pub last_error: Option<String>,
     /// 按目录聚合的统计信息（目录路径 -> (已索引, 待处理)）
     pub directory_stats: HashMap<String, (usize, usize)>,
+    /// 最近一次上传使用过的代理（按使用顺序，"直连" 表示未使用代理）
+    #[serde(default)]
+    pub proxies_used: Vec<String>,
+    /// 最近一次上传中发生的代理故障转移
+    #[serde(default)]
+    pub proxy_failovers: Vec<ProxyFailoverEvent>,
+    /// 最近一次索引从中断检查点恢复、跳过上传的 blob 数
+    #[serde(default)]
+    pub resumed_blobs: usize,
+    /// 最近一次索引中读取或上传失败的文件
+    #[serde(default)]
+    pub file_errors: FileErrorList,
+}
+
+/// 单个文件的读取/上传错误
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileError {
+    /// 相对项目根目录的文件路径
+    pub path: String,
+    /// 失败原因
+    pub reason: String,
+}
+
+/// 有上限的文件错误列表：最多保留 `MAX_ITEMS` 条明细，`total` 记录全部错误数
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FileErrorList {
+    /// 错误总数（可能大于 items 数量）
+    pub total: usize,
+    /// 错误明细（最多 MAX_ITEMS 条）
+    pub items: Vec<FileError>,
+}
+
+impl FileErrorList {
+    /// 明细条数上限，避免返回给前端的数据过大
+    pub const MAX_ITEMS: usize = 50;
+
+    pub fn push(&mut self, path: impl Into<String>, reason: impl Into<String>) {
+        self.total += 1;
+        if self.items.len() < Self::MAX_ITEMS {
+            self.items.push(FileError { path: path.into(), reason: reason.into() });
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.total == 0
+    }
+
+    /// 超出上限而未列出的错误数
+    pub fn omitted(&self) -> usize {
+        self.total - self.items.len()
+    }
+}
+
+/// 上传过程中的一次代理故障转移
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProxyFailoverEvent {
+    /// 切换前的代理
+    pub from: String,
+    /// 切换后的代理
+    pub to: String,
+    /// 触发切换的批次序号（从 1 开始）
+    pub batch: usize,
+    /// 切换时间
+    pub at: DateTime<Utc>,
 }
 
 impl Default for ProjectIndexStatus {
@@ -101,10 +283,44 @@ impl Default for ProjectIndexStatus {
             last_failure_time: None,
             last_error: None,
             directory_stats: HashMap::new(),
+            proxies_used: Vec::new(),
+            proxy_failovers: Vec::new(),
+            resumed_blobs: 0,
+            file_errors: FileErrorList::default(),
         }
     }
 }
 
+/// 当日上传用量（本地统计，按本地日期重置）
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct AcemcpUsage {
+    /// 统计日期（YYYY-MM-DD，本地时区）
+    pub date: String,
+    /// 当日已上传的 blob 数
+    pub blobs_uploaded: u64,
+    /// 当日上传请求数
+    pub upload_requests: u64,
+    /// 配置的每日 blob 预算（未配置时为空）
+    #[serde(default)]
+    pub daily_blob_budget: Option<u32>,
+    /// 剩余预算（未配置预算时为空）
+    #[serde(default)]
+    pub remaining_blobs: Option<u64>,
+}
+
+/// 恢复中断索引的结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexResumeResult {
+    /// 项目根路径（规范化后）
+    pub project_root: String,
+    /// 恢复前检查点中记录的已上传 blob 数
+    pub checkpoint_blobs: usize,
+    /// 实际从检查点恢复（跳过上传）的 blob 数
+    pub resumed_blobs: usize,
+    /// 索引完成后的 blob 总数
+    pub total_blobs: usize,
+}
+
 /// 所有项目的索引状态集合
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct ProjectsIndexStatus {
@@ -122,6 +338,17 @@ pub enum FileIndexStatusKind {
     Pending,
 }
 
+/// 文件被纳入索引的规则来源
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum IncludeSource {
+    /// 全局配置（text_extensions / exclude_patterns / .gitignore）
+    #[default]
+    Global,
+    /// 项目本地 `.acemcpinclude` 规则
+    Local,
+}
+
 /// 文件索引状态信息
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileIndexStatus {
@@ -129,6 +356,12 @@ pub struct FileIndexStatus {
     pub path: String,
     /// 文件索引状态
     pub status: FileIndexStatusKind,
+    /// 纳入索引的规则来源（用于排查本地覆盖规则）
+    #[serde(default)]
+    pub included_by: IncludeSource,
+    /// 最近一次上传时间（取该文件所有分块中最新的一次），未索引时为空
+    #[serde(default)]
+    pub last_indexed: Option<DateTime<Utc>>,
 }
 
 /// 项目内所有可索引文件的状态集合（用于前端构建项目结构树）
@@ -140,6 +373,23 @@ pub struct ProjectFilesStatus {
     pub files: Vec<FileIndexStatus>,
 }
 
+/// 分块推送的项目文件索引状态（通过 `acemcp_project_files_chunk` 事件推送）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectFilesStatusChunk {
+    /// 项目根路径（规范化后）
+    pub project_root: String,
+    /// 分块序号（从 0 开始）
+    pub seq: usize,
+    /// 本块包含的文件状态
+    pub files: Vec<FileIndexStatus>,
+    /// 是否为最后一块（扫描完成、取消或失败时发送，files 为空）
+    pub done: bool,
+    /// 扫描是否被取消
+    pub cancelled: bool,
+    /// 扫描失败时的错误信息
+    pub error: Option<String>,
+}
+
 // ============ 代理测速相关类型 ============
 
 /// 检测到的代理信息
@@ -155,6 +405,40 @@ pub struct DetectedProxy {
     pub response_time_ms: Option<u64>,
 }
 
+/// 一键自动配置代理的结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AutoProxyConfigResult {
+    /// 是否已写入并启用代理配置
+    pub configured: bool,
+    /// 选中的代理（未找到可用代理时为空）
+    pub proxy: Option<DetectedProxy>,
+    /// 经该代理访问 ACE /health 的耗时（毫秒）
+    pub ace_latency_ms: Option<u64>,
+    /// 本地检测到的候选代理数量
+    pub candidates: usize,
+    /// 结果说明
+    pub message: String,
+}
+
+/// 任意 URL 连通性测试结果（用于确认代理本身是否可用）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProxyConnectivityResult {
+    /// 测试的 URL
+    pub url: String,
+    /// 是否收到 HTTP 响应（任意状态码均视为连通）
+    pub success: bool,
+    /// HTTP 状态码
+    pub status_code: Option<u16>,
+    /// 从发起请求到收到响应头的耗时（毫秒）
+    pub latency_ms: Option<u64>,
+    /// 请求是否经过代理
+    pub via_proxy: bool,
+    /// 使用的代理地址（直连时为空）
+    pub proxy: Option<String>,
+    /// 失败原因
+    pub error: Option<String>,
+}
+
 /// 代理测速结果
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProxySpeedTestResult {
@@ -199,7 +483,7 @@ pub struct SearchResultPreview {
 pub struct SpeedTestMetric {
     /// 指标名称（如 "网络延迟"、"单文件上传"、"语义搜索"）
     pub name: String,
-    /// 指标类型: "ping" | "upload_single" | "search"
+    /// 指标类型: "ping" | "upload_single" | "search" | "stream"
     pub metric_type: String,
     /// 代理模式耗时（毫秒）
     pub proxy_time_ms: Option<u64>,
@@ -212,6 +496,154 @@ pub struct SpeedTestMetric {
     /// 搜索结果预览（仅 search 类型有值）
     #[serde(skip_serializing_if = "Option::is_none")]
     pub search_result_preview: Option<SearchResultPreview>,
+    /// 代理模式延迟分布（仅 ping 类型有值）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy_latency: Option<LatencyStats>,
+    /// 直连模式延迟分布（仅 ping 类型有值）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub direct_latency: Option<LatencyStats>,
+    /// 代理模式流式接收统计（仅 stream 类型有值）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy_stream: Option<StreamProbeStats>,
+    /// 直连模式流式接收统计（仅 stream 类型有值）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub direct_stream: Option<StreamProbeStats>,
+    /// 代理模式分阶段耗时（仅 ping 类型且开启详细 Ping 时有值）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy_phases: Option<PingPhaseTimings>,
+    /// 直连模式分阶段耗时（仅 ping 类型且开启详细 Ping 时有值）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub direct_phases: Option<PingPhaseTimings>,
+    /// 读取或上传失败的文件明细（仅 upload_project 类型有值）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_errors: Option<FileErrorList>,
+}
+
+/// 详细 Ping 的分阶段耗时（毫秒），用于区分 TLS 握手慢（如 MITM 代理）与服务端延迟
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PingPhaseTimings {
+    /// DNS 解析耗时（经代理时为解析代理主机）
+    pub dns_ms: Option<u64>,
+    /// TCP 建连耗时（经代理时为连接代理服务器）
+    pub connect_ms: Option<u64>,
+    /// TLS 握手耗时（估算值；经代理时包含 CONNECT 隧道建立；http 地址为空）
+    pub tls_ms: Option<u64>,
+    /// 复用连接后的首字节耗时（近似服务端处理 + 往返）
+    pub ttfb_ms: Option<u64>,
+    /// 新连接上首次请求的总耗时
+    pub total_ms: u64,
+    /// 是否经过代理
+    pub via_proxy: bool,
+    /// 测量说明
+    pub note: Option<String>,
+}
+
+/// 一次对比测速的汇总记录（只统计代理与直连都成功的指标）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpeedTestHistoryEntry {
+    /// 测速时间
+    pub tested_at: DateTime<Utc>,
+    /// 测试使用的代理地址
+    pub proxy: Option<String>,
+    /// 代理模式下各指标耗时之和（毫秒）
+    pub proxy_total_ms: u64,
+    /// 直连模式下各指标耗时之和（毫秒）
+    pub direct_total_ms: u64,
+    /// 参与对比的指标数
+    pub compared_metrics: usize,
+}
+
+/// 基于最近多次对比测速的代理推荐趋势
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProxyRecommendationTrend {
+    /// 参与统计的测速次数
+    pub samples: usize,
+    /// 代理更快的次数
+    pub proxy_faster_runs: usize,
+    /// 直连更快的次数
+    pub direct_faster_runs: usize,
+    /// 代理相对直连的平均提速百分比（正数表示代理更快，负数表示更慢）
+    pub avg_change_percent: f64,
+    /// 各次结论是否一致（全部代理更快或全部直连更快）
+    pub consistent: bool,
+    /// 置信度: "low" | "medium" | "high"
+    pub confidence: String,
+    /// 可直接展示的摘要，如 "最近5次: 代理平均快 23%"
+    pub summary: String,
+}
+
+/// 流式请求的接收统计（用于判断代理是否缓冲了分块响应）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreamProbeStats {
+    /// 首字节耗时（毫秒）
+    pub first_byte_ms: u64,
+    /// 接收完成总耗时（毫秒）
+    pub total_ms: u64,
+    /// 接收的字节数
+    pub bytes: u64,
+    /// 收到的分块数量（代理缓冲时通常只有 1 块）
+    pub chunk_count: usize,
+    /// 首字节之后的持续吞吐（KB/s）
+    pub throughput_kbps: f64,
+}
+
+/// 多轮 Ping 的延迟分布
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LatencyStats {
+    /// 最小延迟（毫秒）
+    pub min_ms: u64,
+    /// 平均延迟（毫秒）
+    pub avg_ms: u64,
+    /// P95 延迟（毫秒）
+    pub p95_ms: u64,
+    /// 成功的样本数
+    pub samples: usize,
+}
+
+/// 带宽测量结果（单条链路）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BandwidthMeasurement {
+    /// 链路: "proxy" | "direct"
+    pub route: String,
+    /// 单个合成负载的大小（字节）
+    pub payload_bytes: u64,
+    /// 可压缩负载上传耗时（毫秒）
+    pub compressible_ms: Option<u64>,
+    /// 可压缩负载上传速率（Mbps）
+    pub compressible_mbps: Option<f64>,
+    /// 随机负载上传耗时（毫秒）
+    pub random_ms: Option<u64>,
+    /// 随机负载上传速率（Mbps）
+    pub random_mbps: Option<f64>,
+    /// 是否全部成功
+    pub success: bool,
+    /// 错误信息
+    pub error: Option<String>,
+}
+
+/// 本地文件读取吞吐测试结果（不含任何网络 I/O，用于与项目上传耗时对比定位瓶颈）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocalReadBenchmark {
+    /// 项目根路径
+    pub project_root: String,
+    /// 计划读取的文件数
+    pub planned_files: usize,
+    /// 成功读取并解码的文件数
+    pub read_files: usize,
+    /// 读取失败的文件数
+    pub failed_files: usize,
+    /// 使用有损解码的文件数
+    pub lossy_files: usize,
+    /// 读取的总字节数
+    pub total_bytes: u64,
+    /// 读取 + 解码总耗时（毫秒）
+    pub elapsed_ms: u64,
+    /// 吞吐（MB/s）
+    pub mb_per_sec: f64,
+    /// 吞吐（文件/秒）
+    pub files_per_sec: f64,
+    /// 第一个读取错误
+    pub first_error: Option<String>,
 }
 
 // ============== 测速进度反馈 ==============
@@ -246,3 +678,421 @@ pub struct SpeedTestProgress {
     pub sub_step: Option<String>,
 }
 
+
+// ============ 流式搜索相关类型 ============
+
+/// 流式搜索的增量片段（通过 `acemcp_search_chunk` 事件推送给前端）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchStreamChunk {
+    /// 项目路径
+    pub project_path: String,
+    /// 查询语句
+    pub query: String,
+    /// 片段序号（从 0 开始）
+    pub seq: usize,
+    /// 增量文本
+    pub text: String,
+    /// 是否为最后一个片段
+    pub done: bool,
+}
+
+/// 流式搜索的传输统计
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SearchStreamStats {
+    /// 服务端是否以流式返回（false 表示已退化为缓冲模式）
+    pub streamed: bool,
+    /// 从发起请求到收到首字节的耗时（毫秒）
+    pub first_byte_ms: Option<u64>,
+    /// 从发起请求到收到末字节的耗时（毫秒）
+    pub last_byte_ms: Option<u64>,
+    /// 推送的片段数量
+    pub chunk_count: usize,
+}
+
+// ============ 文件监听统计相关类型 ============
+
+/// 文件监听管理器运行统计
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatcherStats {
+    /// 正在监听的项目数
+    pub watching_count: usize,
+    /// 允许同时上传的最大项目数
+    pub max_concurrent_uploads: usize,
+    /// 正在上传的项目数
+    pub active_uploads: usize,
+    /// 排队等待上传的项目数
+    pub queued_uploads: usize,
+    /// 被监听过滤器忽略的文件变更事件数（命中排除模式或超过大小上限）
+    pub ignored_events: usize,
+    /// 被合并为单次修改的"原子保存"次数（临时文件改名覆盖、删除后重建等）
+    pub coalesced_renames: usize,
+    /// 因项目关闭自动索引而跳过的索引触发次数
+    #[serde(default)]
+    pub skipped_auto_index: usize,
+}
+
+/// 项目自动索引开关状态
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectAutoIndexState {
+    /// 项目根路径（规范化后）
+    pub project_root: String,
+    /// 全局自动索引开关
+    pub global_enabled: bool,
+    /// 项目级自动索引开关
+    pub project_enabled: bool,
+    /// 实际生效状态（全局且项目均启用）
+    pub effective: bool,
+}
+
+// ============ 排除规则预览相关类型 ============
+
+/// 单个排除模式的命中统计
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExclusionPatternStats {
+    /// 排除模式
+    pub pattern: String,
+    /// 被该模式排除的文件数（含被排除目录下的所有文件）
+    pub excluded_files: usize,
+    /// 被该模式直接命中的目录数
+    pub excluded_dirs: usize,
+    /// 命中路径示例（相对路径，目录以 `/` 结尾，数量有上限）
+    pub sample_paths: Vec<String>,
+}
+
+/// 排除规则预览结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExclusionPreview {
+    /// 项目根路径（规范化后）
+    pub project_root: String,
+    /// 扫描到的文件总数（不含 .gitignore 忽略的文件）
+    pub total_files: usize,
+    /// 被排除模式排除的文件总数
+    pub excluded_files: usize,
+    /// 被项目 `.acemcpignore` 排除的文件数（不计入各模式统计）
+    #[serde(default)]
+    pub local_ignored_files: usize,
+    /// 各模式的命中统计（与配置顺序一致）
+    pub patterns: Vec<ExclusionPatternStats>,
+    /// 无法解析的模式（索引时同样会被忽略）
+    pub invalid_patterns: Vec<String>,
+}
+
+// ============ 文件分块预览相关类型 ============
+
+/// 单个分块的边界信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileChunkInfo {
+    /// 分块路径（与搜索结果一致，如 `src/main.rs#chunk2of5`；未分块时为原路径）
+    pub chunk_path: String,
+    /// 起始行号（从 1 开始）
+    pub start_line: usize,
+    /// 结束行号（含）
+    pub end_line: usize,
+    /// 分块内容字节数
+    pub byte_size: usize,
+    /// 分块对应的 blob 名称（上传时使用的哈希）
+    pub blob_name: String,
+}
+
+/// 文件分块预览结果（仅本地计算，不上传）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileChunkPreview {
+    /// 项目根路径（规范化后）
+    pub project_root: String,
+    /// 文件相对路径（使用正斜杠）
+    pub rel_path: String,
+    /// 文件总行数
+    pub total_lines: usize,
+    /// 解码后的文件总字节数
+    pub total_bytes: usize,
+    /// 对该文件生效的每块最大行数（已应用按扩展名覆盖）
+    pub max_lines_per_blob: usize,
+    /// 各分块边界
+    pub chunks: Vec<FileChunkInfo>,
+}
+
+// ============ 批量重建索引相关类型 ============
+
+/// 单个项目的重建索引结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectReindexResult {
+    /// 是否成功
+    pub success: bool,
+    /// 结果说明（成功时为 blob 数量，失败时为错误信息）
+    pub message: String,
+}
+
+/// 批量重建索引进度（通过 `acemcp_reindex_progress` 事件推送）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReindexProgress {
+    /// 项目总数
+    pub total: usize,
+    /// 已完成的项目数
+    pub completed: usize,
+    /// 刚完成的项目路径
+    pub project_root: String,
+    /// 该项目是否成功
+    pub success: bool,
+}
+
+// ============ 日志实时推送相关类型 ============
+
+/// 实时日志行（通过 `acemcp_log_line` 事件推送）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AcemcpLogLine {
+    /// 原始日志行
+    pub line: String,
+    /// 日志级别（无法解析时为空，如多行日志的续行）
+    pub level: Option<String>,
+    /// 关联的项目路径
+    pub project: Option<String>,
+}
+
+// ============ 健康概览相关类型 ============
+
+/// 连接测试结果快照
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectionTestSnapshot {
+    /// 是否连接成功
+    pub success: bool,
+    /// 测试结果说明
+    pub message: String,
+    /// 测试时间
+    pub tested_at: DateTime<Utc>,
+}
+
+/// 各索引状态的项目数量
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IndexStatusCounts {
+    pub idle: usize,
+    pub indexing: usize,
+    pub synced: usize,
+    pub failed: usize,
+}
+
+impl IndexStatusCounts {
+    /// 计入一个项目的状态
+    pub fn record(&mut self, status: &IndexStatus) {
+        match status {
+            IndexStatus::Idle => self.idle += 1,
+            IndexStatus::Indexing => self.indexing += 1,
+            IndexStatus::Synced => self.synced += 1,
+            IndexStatus::Failed => self.failed += 1,
+        }
+    }
+}
+
+/// 所有项目索引状态的汇总（供状态徽标轮询，避免每次传输完整的项目状态表）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IndexSummary {
+    /// 项目总数
+    pub project_count: usize,
+    /// 各索引状态的项目数量
+    pub counts: IndexStatusCounts,
+    /// 所有项目的文件总数
+    pub total_files: usize,
+    /// 所有项目的已索引文件数
+    pub indexed_files: usize,
+    /// 最近一次失败时间（所有项目中最新的一次）
+    pub last_failure_time: Option<DateTime<Utc>>,
+    /// 最近一次失败的错误信息
+    pub last_failure_error: Option<String>,
+    /// 最近一次失败的项目
+    pub last_failure_project: Option<String>,
+}
+
+/// 单个项目的实时索引完成度（基于当前磁盘扫描，而非缓存的项目状态）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IndexCoverage {
+    /// 项目根路径（规范化后）
+    pub project_root: String,
+    /// 完成度百分比（0-100，保留一位小数；没有可索引文件时为 0）
+    pub coverage_percent: f64,
+    /// 可索引文件总数
+    pub total_files: usize,
+    /// 已完成索引的文件数
+    pub indexed_files: usize,
+    /// 待索引的文件数（含新增与已修改）
+    pub pending_files: usize,
+    /// 磁盘上存在但从未上传过的文件数（新增文件）
+    pub new_files: usize,
+}
+
+/// 目录级索引状态树节点（计数包含所有子目录中的文件）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DirectoryIndexStats {
+    /// 目录名（根节点为空）
+    pub name: String,
+    /// 相对于项目根目录的路径，使用正斜杠(/)分隔（根节点为空）
+    pub path: String,
+    /// 已完成索引的文件数
+    pub indexed: usize,
+    /// 待索引的文件数
+    pub pending: usize,
+    /// 最近一次索引中读取或上传失败的文件数
+    pub failed: usize,
+    /// 子目录（按名称排序）
+    pub children: Vec<DirectoryIndexStats>,
+}
+
+// ============ 调试搜索历史相关类型 ============
+
+/// 一条调试搜索历史记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DebugSearchHistoryEntry {
+    /// 项目根路径（规范化后）
+    pub project_root: String,
+    /// 搜索查询
+    pub query: String,
+    /// 最近一次执行时间
+    pub searched_at: DateTime<Utc>,
+}
+
+/// 调试搜索查询模板，query 中的 `{name}` 为占位符
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DebugSearchTemplate {
+    /// 模板名称（唯一）
+    pub name: String,
+    /// 带占位符的查询内容
+    pub query: String,
+}
+
+/// ACE 健康概览（供状态面板使用）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AcemcpHealth {
+    /// 配置是否完整（base_url 与 token 均已设置）
+    pub config_complete: bool,
+    /// 最近一次连接测试结果（从未测试过时为空）
+    pub connection: Option<ConnectionTestSnapshot>,
+    /// 各索引状态的项目数量
+    pub index_counts: IndexStatusCounts,
+    /// 正在监听的项目数
+    pub active_watchers: usize,
+    /// 当前使用的代理地址（未启用代理时为空）
+    pub proxy_in_use: Option<String>,
+}
+
+/// ACE 配置是否就绪（仅返回是否存在，不包含 token 等敏感值）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AcemcpConfiguredStatus {
+    /// 是否已设置 base_url
+    pub has_base_url: bool,
+    /// 是否已设置 token
+    pub has_token: bool,
+    /// base_url 与 token 均已设置
+    pub configured: bool,
+}
+
+// ============ 索引规模估算相关类型 ============
+
+/// 索引前的上传规模与耗时估算（仅基于文件元数据）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexEstimate {
+    /// 项目根路径（规范化后）
+    pub project_root: String,
+    /// 将要索引的文件数
+    pub file_count: usize,
+    /// 文件总字节数
+    pub total_bytes: u64,
+    /// 预估 blob 数（按 max_lines_per_blob 与平均行长推算）
+    pub estimated_blobs: usize,
+    /// 参考的上传吞吐量（字节/秒，来自最近一次测速；未测速时为空）
+    pub throughput_bytes_per_sec: Option<f64>,
+    /// 预计耗时（秒，未测速时为空）
+    pub estimated_secs: Option<u64>,
+    /// 可直接展示的摘要，如 "将上传约 8,300 文件 / 240MB, 预计 3 分钟"
+    pub summary: String,
+}
+
+// ============ 索引校验相关类型 ============
+
+/// 项目索引校验结果（本地记录 vs 当前文件 vs 服务端）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexVerification {
+    /// 项目根路径（规范化后）
+    pub project_root: String,
+    /// projects.json 中记录的 blob 数（去重后）
+    pub recorded_blobs: usize,
+    /// 按当前文件重新计算的 blob 数
+    pub current_blobs: usize,
+    /// 当前文件中尚未上传的 blob 数
+    pub not_uploaded: usize,
+    /// 已记录但本地已不存在的 blob 数
+    pub stale: usize,
+    /// 本地记录中的重复项数量
+    pub duplicate_recorded: usize,
+    /// 是否完成了服务端校验
+    pub server_checked: bool,
+    /// 服务端缺失的已记录 blob 数（未完成服务端校验时为空）
+    pub server_missing: Option<usize>,
+    /// 服务端校验失败原因
+    pub server_error: Option<String>,
+    /// 是否建议执行修复（重新同步索引）
+    pub needs_repair: bool,
+    /// 可直接展示的结论
+    pub summary: String,
+}
+
+// ============ 工具调用相关类型 ============
+
+/// `execute_acemcp_tool` 的统一返回结构：成功与失败均以该结构返回，前端只需处理一种形态
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AcemcpToolResponse {
+    /// "success" | "error"
+    pub status: String,
+    /// 工具执行结果（仅成功时存在）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<serde_json::Value>,
+    /// 错误信息（仅失败时存在）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// 错误类型："missing_argument" | "invalid_argument" | "execution_failed" | "unknown_tool"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<String>,
+    /// 参数校验失败时的全部错误（一次性返回，而非仅第一条）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub validation_errors: Vec<String>,
+}
+
+/// 可调用工具的参数声明
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AcemcpToolArgInfo {
+    pub name: String,
+    pub description: String,
+    pub required: bool,
+}
+
+/// 可调用工具说明（供前端展示与参数表单生成）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AcemcpToolInfo {
+    pub name: String,
+    pub description: String,
+    pub args: Vec<AcemcpToolArgInfo>,
+}
+
+impl AcemcpToolResponse {
+    pub const MISSING_ARGUMENT: &'static str = "missing_argument";
+    pub const INVALID_ARGUMENT: &'static str = "invalid_argument";
+    pub const EXECUTION_FAILED: &'static str = "execution_failed";
+    pub const UNKNOWN_TOOL: &'static str = "unknown_tool";
+
+    pub fn success(result: serde_json::Value) -> Self {
+        Self {
+            status: "success".to_string(),
+            result: Some(result),
+            error: None,
+            error_code: None,
+            validation_errors: Vec::new(),
+        }
+    }
+
+    pub fn error(error_code: &str, error: impl Into<String>) -> Self {
+        Self {
+            status: "error".to_string(),
+            result: None,
+            error: Some(error.into()),
+            error_code: Some(error_code.to_string()),
+            validation_errors: Vec::new(),
+        }
+    }
+}
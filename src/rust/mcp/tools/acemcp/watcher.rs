@@ -1,20 +1,258 @@
 use anyhow::Result;
 use notify_debouncer_full::{
     new_debouncer,
-    notify::{RecommendedWatcher, RecursiveMode, Watcher},
+    notify::{
+        event::{ModifyKind, RenameMode},
+        Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher,
+    },
     DebounceEventResult, Debouncer, FileIdMap,
 };
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
-use tokio::sync::mpsc;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, oneshot, Semaphore};
 
-use super::types::AcemcpConfig;
-use super::mcp::update_index;
+use super::types::{AcemcpConfig, WatcherStats};
+use super::mcp::{force_reindex, update_index};
+use crate::constants::mcp::DEFAULT_ACEMCP_WATCH_DEBOUNCE_MS;
 use crate::log_important;
 use crate::log_debug;
 
+/// 默认允许同时上传的项目数
+const DEFAULT_MAX_CONCURRENT_UPLOADS: usize = 2;
+
+/// 单次自动索引持有上传许可的最长时间（秒）
+/// 说明：单个 HTTP 请求已有 60 秒超时，这里兜底整个 update_index，避免异常卡住导致许可永久占用
+const UPLOAD_PERMIT_TIMEOUT_SECS: u64 = 600;
+
+/// 底层 debouncer 的最长合并窗口（毫秒），用于合并同一次保存产生的多个原始事件
+/// 说明：完整的防抖窗口由后台任务计时，以便支持"立即同步"提前触发
+const RAW_EVENT_DEBOUNCE_MS: u64 = 2_000;
+
+/// 从配置文件重新读取运行时排除子目录的最短间隔（秒）
+/// 说明：GUI 与 MCP 服务进程各有一份监听器，另一进程修改的排除列表只能通过配置文件同步
+const EXCLUSIONS_REFRESH_SECS: u64 = 5;
+
+/// 发往项目后台索引任务的信号
+enum WatchSignal {
+    /// 检测到相关文件变更，重置防抖计时
+    Changed,
+    /// 立即处理积压的变更，回复是否存在积压
+    Flush(oneshot::Sender<bool>),
+}
+
+/// 上传并发限制器
+/// 所有自动/批量索引共用，保证同一时刻最多 N 个项目在上传
+struct UploadLimiter {
+    /// 限制同时上传的项目数，其余项目排队等待
+    semaphore: Arc<Semaphore>,
+    /// 当前允许同时上传的最大项目数
+    max_concurrent: Mutex<usize>,
+    /// 正在上传的项目数
+    active: AtomicUsize,
+    /// 排队等待上传许可的项目数
+    queued: AtomicUsize,
+}
+
+impl UploadLimiter {
+    fn new(max_concurrent: usize) -> Self {
+        Self {
+            semaphore: Arc::new(Semaphore::new(max_concurrent)),
+            max_concurrent: Mutex::new(max_concurrent),
+            active: AtomicUsize::new(0),
+            queued: AtomicUsize::new(0),
+        }
+    }
+
+    /// 排队获取上传许可后执行索引更新，超时后放弃并释放许可
+    /// force_full 为 true 时在许可内全量重建（见 `force_reindex`）
+    async fn run_index(&self, config: &AcemcpConfig, project_root: &str, force_full: bool) -> Result<Vec<String>> {
+        self.queued.fetch_add(1, Ordering::SeqCst);
+        let permit = self.semaphore.clone().acquire_owned().await;
+        self.queued.fetch_sub(1, Ordering::SeqCst);
+        let _permit = permit.map_err(|e| anyhow::anyhow!("获取上传许可失败: {}", e))?;
+
+        self.active.fetch_add(1, Ordering::SeqCst);
+        let result = if force_full {
+            tokio::time::timeout(
+                Duration::from_secs(UPLOAD_PERMIT_TIMEOUT_SECS),
+                force_reindex(config, project_root),
+            ).await
+        } else {
+            tokio::time::timeout(
+                Duration::from_secs(UPLOAD_PERMIT_TIMEOUT_SECS),
+                update_index(config, project_root),
+            ).await
+        };
+        self.active.fetch_sub(1, Ordering::SeqCst);
+
+        match result {
+            Ok(r) => r,
+            Err(_) => Err(anyhow::anyhow!("索引更新超时（{}秒），已释放上传许可", UPLOAD_PERMIT_TIMEOUT_SECS)),
+        }
+    }
+}
+
+/// 监听事件过滤器：在触发自动索引前丢弃无关的文件变更
+/// 与索引扫描使用同一套分层规则（.acemcpinclude / .acemcpignore / .gitignore / 排除模式）及扩展名过滤
+struct WatchEventFilter {
+    root: PathBuf,
+    rules: super::mcp::PathRules,
+    /// 构建 rules 使用的静态排除模式（规则文件变化时重建）
+    exclude_patterns: Vec<String>,
+    text_exts: Vec<String>,
+    max_file_bytes: Option<u64>,
+    /// 项目 key（规范化路径），用于查找运行时排除的子目录
+    project_key: String,
+    /// 各项目运行时排除的子目录（与 WatcherManager 共享，修改后立即生效）
+    exclusions: Arc<Mutex<HashMap<String, Vec<String>>>>,
+    /// 上次从配置文件同步排除子目录的时间
+    last_refresh: Option<Instant>,
+}
+
+impl WatchEventFilter {
+    /// 定期从配置文件同步本项目的运行时排除子目录（覆盖另一进程所做的修改）
+    fn refresh_exclusions(&mut self) {
+        if self
+            .last_refresh
+            .is_some_and(|at| at.elapsed() < Duration::from_secs(EXCLUSIONS_REFRESH_SECS))
+        {
+            return;
+        }
+        self.last_refresh = Some(Instant::now());
+        if let Ok(config) = crate::config::load_standalone_config() {
+            let latest = super::mcp::find_project_watch_exclusions(&config.mcp_config, &self.project_key);
+            self.exclusions.lock().unwrap().insert(self.project_key.clone(), latest);
+        }
+    }
+
+    /// 规则文件（.gitignore / .acemcpignore / .acemcpinclude）本身变化时重建过滤规则
+    fn reload_rules_if_changed(&mut self, changes: &[Vec<PathBuf>]) {
+        let is_rule_file = |path: &PathBuf| {
+            path.parent() == Some(self.root.as_path())
+                && path.file_name().and_then(|n| n.to_str()).is_some_and(|name| {
+                    matches!(name, ".gitignore" | super::mcp::LOCAL_IGNORE_FILE | super::mcp::LOCAL_INCLUDE_FILE)
+                })
+        };
+        if changes.iter().flatten().any(is_rule_file) {
+            log_debug!("检测到过滤规则文件变化，重建监听过滤规则: {:?}", self.root);
+            self.rules = super::mcp::PathRules::new(&self.root, &self.exclude_patterns);
+        }
+    }
+
+    /// 事件涉及的所有路径都被排除或超过大小上限时忽略该事件
+    fn should_ignore(&self, paths: &[PathBuf]) -> bool {
+        !paths.is_empty() && paths.iter().all(|path| self.is_ignored_path(path))
+    }
+
+    fn is_ignored_path(&self, path: &Path) -> bool {
+        let is_dir = path.is_dir();
+        if self.rules.is_excluded(path, is_dir) || self.is_runtime_excluded(path) {
+            return true;
+        }
+        // 非文本扩展名的文件不会被索引；已删除/改名的路径无扩展名时可能是目录，保留事件
+        let may_be_file = path.is_file() || (!is_dir && path.extension().is_some());
+        if may_be_file && !super::mcp::has_text_extension(path, &self.text_exts) {
+            return true;
+        }
+        // 已删除的文件无法获取大小，保留事件以便从索引中移除
+        match self.max_file_bytes {
+            Some(max) => std::fs::metadata(path)
+                .map(|m| m.is_file() && m.len() > max)
+                .unwrap_or(false),
+            None => false,
+        }
+    }
+
+    fn is_runtime_excluded(&self, path: &Path) -> bool {
+        let exclusions = self.exclusions.lock().unwrap();
+        match exclusions.get(&self.project_key) {
+            Some(subs) if !subs.is_empty() => {
+                let rel = path.strip_prefix(&self.root).unwrap_or(path).to_string_lossy().replace('\\', "/");
+                super::mcp::is_under_exclusion(&rel, subs)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// 将编辑器"原子保存"产生的多个事件合并为目标文件的一次修改，返回 (各变更涉及的路径, 合并次数)
+///
+/// 识别的模式：
+/// - 写临时文件后改名覆盖目标（temp 先创建再改名为 target）：视为 target 修改，丢弃 temp 的事件
+/// - 先把原文件改名为备份再写入同名新文件（vim 备份）：视为原文件修改，丢弃备份文件的事件
+/// - 同一批次内先删除再创建同一路径：视为该路径修改
+fn coalesce_atomic_saves(events: &[&Event]) -> (Vec<Vec<PathBuf>>, usize) {
+    let has_create = |path: &PathBuf, pred: &dyn Fn(usize) -> bool| {
+        events
+            .iter()
+            .enumerate()
+            .any(|(j, e)| pred(j) && matches!(e.kind, EventKind::Create(_)) && e.paths.contains(path))
+    };
+
+    // 只在保存过程中短暂存在的临时/备份文件
+    let mut transient: HashSet<PathBuf> = HashSet::new();
+    // 合并后视为"修改"的目标文件
+    let mut modified: Vec<PathBuf> = Vec::new();
+    // 已被合并消费的事件下标
+    let mut consumed: HashSet<usize> = HashSet::new();
+    let mut coalesced = 0usize;
+
+    for (i, e) in events.iter().enumerate() {
+        if !matches!(e.kind, EventKind::Modify(ModifyKind::Name(RenameMode::Both))) || e.paths.len() != 2 {
+            continue;
+        }
+        let (from, to) = (&e.paths[0], &e.paths[1]);
+        if has_create(from, &|j| j < i) {
+            transient.insert(from.clone());
+            modified.push(to.clone());
+        } else if has_create(from, &|j| j > i) {
+            transient.insert(to.clone());
+            modified.push(from.clone());
+        } else {
+            continue;
+        }
+        consumed.insert(i);
+        coalesced += 1;
+    }
+
+    for (i, e) in events.iter().enumerate() {
+        if !matches!(e.kind, EventKind::Remove(_)) || e.paths.len() != 1 {
+            continue;
+        }
+        let path = &e.paths[0];
+        if !transient.contains(path) && !modified.contains(path) && has_create(path, &|j| j > i) {
+            modified.push(path.clone());
+            consumed.insert(i);
+            coalesced += 1;
+        }
+    }
+
+    let mut changes: Vec<Vec<PathBuf>> = modified.iter().map(|p| vec![p.clone()]).collect();
+    for (i, e) in events.iter().enumerate() {
+        if consumed.contains(&i) {
+            continue;
+        }
+        if e.paths.is_empty() {
+            // 无路径事件（如需要重新扫描）原样保留
+            changes.push(Vec::new());
+            continue;
+        }
+        let paths: Vec<PathBuf> = e
+            .paths
+            .iter()
+            .filter(|p| !transient.contains(*p) && !modified.contains(*p))
+            .cloned()
+            .collect();
+        if !paths.is_empty() {
+            changes.push(paths);
+        }
+    }
+    (changes, coalesced)
+}
+
 /// 文件监听器管理器
 /// 负责管理多个项目的文件监听器
 pub struct WatcherManager {
@@ -22,6 +260,20 @@ pub struct WatcherManager {
     watchers: Arc<Mutex<HashMap<String, Debouncer<RecommendedWatcher, FileIdMap>>>>,
     /// 是否启用自动索引（全局开关）
     auto_index_enabled: Arc<Mutex<bool>>,
+    /// 上传并发限制器
+    upload_limiter: Arc<UploadLimiter>,
+    /// 被监听过滤器忽略的文件变更事件数
+    ignored_events: Arc<AtomicUsize>,
+    /// 被合并为单次修改的原子保存次数
+    coalesced_renames: Arc<AtomicUsize>,
+    /// 项目路径 -> 运行时排除的子目录
+    project_exclusions: Arc<Mutex<HashMap<String, Vec<String>>>>,
+    /// 项目路径 -> 后台索引任务的信号发送端（用于立即同步）
+    signals: Arc<Mutex<HashMap<String, mpsc::Sender<WatchSignal>>>>,
+    /// 关闭自动索引的项目（仍监听文件变更，但不触发上传）
+    auto_index_disabled: Arc<Mutex<HashSet<String>>>,
+    /// 因项目关闭自动索引而跳过的索引触发次数
+    skipped_auto_index: Arc<AtomicUsize>,
 }
 
 impl WatcherManager {
@@ -35,12 +287,79 @@ impl WatcherManager {
             .unwrap_or(true);
         log_debug!("初始化自动索引开关: {}", enabled_from_config);
 
+        let max_uploads = crate::config::load_standalone_config()
+            .ok()
+            .and_then(|c| c.mcp_config.acemcp_max_concurrent_uploads)
+            .map(|n| (n as usize).max(1))
+            .unwrap_or(DEFAULT_MAX_CONCURRENT_UPLOADS);
+        log_debug!("初始化最大并发上传项目数: {}", max_uploads);
+
+        let auto_index_disabled: HashSet<String> = crate::config::load_standalone_config()
+            .ok()
+            .and_then(|c| c.mcp_config.acemcp_project_auto_index)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|(_, enabled)| !enabled)
+            .map(|(root, _)| super::mcp::normalize_project_path(&root))
+            .collect();
+
         Self {
             watchers: Arc::new(Mutex::new(HashMap::new())),
             auto_index_enabled: Arc::new(Mutex::new(enabled_from_config)),
+            upload_limiter: Arc::new(UploadLimiter::new(max_uploads)),
+            ignored_events: Arc::new(AtomicUsize::new(0)),
+            coalesced_renames: Arc::new(AtomicUsize::new(0)),
+            project_exclusions: Arc::new(Mutex::new(HashMap::new())),
+            signals: Arc::new(Mutex::new(HashMap::new())),
+            auto_index_disabled: Arc::new(Mutex::new(auto_index_disabled)),
+            skipped_auto_index: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// 调整同时上传的最大项目数（至少为 1）
+    /// 调小时不会打断正在进行的上传，多余的许可在归还后被回收
+    pub fn set_max_concurrent_uploads(&self, max: usize) {
+        let max = max.max(1);
+        let limiter = &self.upload_limiter;
+        let mut current = limiter.max_concurrent.lock().unwrap();
+        if max > *current {
+            limiter.semaphore.add_permits(max - *current);
+        } else if max < *current {
+            let semaphore = limiter.semaphore.clone();
+            let shrink = (*current - max) as u32;
+            tokio::spawn(async move {
+                if let Ok(permits) = semaphore.acquire_many_owned(shrink).await {
+                    permits.forget();
+                }
+            });
+        }
+        *current = max;
+        log_important!(info, "自动索引最大并发上传项目数已设置为 {}", max);
+    }
+
+    /// 获取监听与上传队列的统计信息
+    pub fn get_stats(&self) -> WatcherStats {
+        WatcherStats {
+            watching_count: self.watchers.lock().unwrap().len(),
+            max_concurrent_uploads: *self.upload_limiter.max_concurrent.lock().unwrap(),
+            active_uploads: self.upload_limiter.active.load(Ordering::SeqCst),
+            queued_uploads: self.upload_limiter.queued.load(Ordering::SeqCst),
+            ignored_events: self.ignored_events.load(Ordering::SeqCst),
+            coalesced_renames: self.coalesced_renames.load(Ordering::SeqCst),
+            skipped_auto_index: self.skipped_auto_index.load(Ordering::SeqCst),
         }
     }
 
+    /// 在上传并发限制内执行一次索引更新（与自动索引共享排队）
+    pub async fn index_with_limit(&self, config: &AcemcpConfig, project_root: &str) -> Result<Vec<String>> {
+        self.upload_limiter.run_index(config, project_root, false).await
+    }
+
+    /// 在上传并发限制内强制全量重建索引（取得许可后才开始，排队期间原索引仍可检索）
+    pub async fn force_reindex_with_limit(&self, config: &AcemcpConfig, project_root: &str) -> Result<Vec<String>> {
+        self.upload_limiter.run_index(config, project_root, true).await
+    }
+
     /// 获取全局自动索引开关状态
     pub fn is_auto_index_enabled(&self) -> bool {
         *self.auto_index_enabled.lock().unwrap()
@@ -52,6 +371,24 @@ impl WatcherManager {
         log_important!(info, "全局自动索引开关已{}",  if enabled { "启用" } else { "禁用" });
     }
 
+    /// 获取项目级自动索引开关（未单独关闭的项目视为启用）
+    pub fn is_project_auto_index_enabled(&self, project_root: &str) -> bool {
+        let normalized_root = super::mcp::normalize_project_path(project_root);
+        !self.auto_index_disabled.lock().unwrap().contains(&normalized_root)
+    }
+
+    /// 设置项目级自动索引开关，正在运行的监听任务在下次触发时生效
+    pub fn set_project_auto_index(&self, project_root: &str, enabled: bool) {
+        let normalized_root = super::mcp::normalize_project_path(project_root);
+        let mut disabled = self.auto_index_disabled.lock().unwrap();
+        if enabled {
+            disabled.remove(&normalized_root);
+        } else {
+            disabled.insert(normalized_root.clone());
+        }
+        log_important!(info, "项目自动索引开关已{}: {}", if enabled { "启用" } else { "禁用" }, normalized_root);
+    }
+
     /// 为指定项目启动文件监听
     /// 如果已经在监听，则不重复启动
     /// debounce_ms: 防抖延迟（毫秒），默认为 180000 (3分钟)
@@ -67,9 +404,7 @@ impl WatcherManager {
         let watch_path = PathBuf::from(&project_root)
             .canonicalize()
             .unwrap_or_else(|_| PathBuf::from(&project_root));
-        let normalized_root = watch_path
-            .to_string_lossy()
-            .replace('\\', "/");
+        let normalized_root = super::mcp::normalize_project_path(&project_root);
 
         // 检查是否已经在监听
         {
@@ -82,22 +417,63 @@ impl WatcherManager {
 
         log_important!(info, "启动文件监听: project_root={}", normalized_root);
 
-        // 创建异步通道用于接收文件变更事件
-        let (tx, mut rx) = mpsc::channel::<()>(100);
+        // 创建异步通道用于接收文件变更事件与立即同步请求
+        let (tx, mut rx) = mpsc::channel::<WatchSignal>(100);
+        let flush_tx = tx.clone();
 
-        // 创建 debouncer（使用配置的防抖延迟，默认 3 分钟）
-        let delay_ms = debounce_ms.unwrap_or(180_000);
+        // 防抖延迟（默认 3 分钟）由后台任务计时，底层 debouncer 仅做短窗口合并
+        let delay_ms = debounce_ms.unwrap_or(DEFAULT_ACEMCP_WATCH_DEBOUNCE_MS);
         log_important!(info, "文件监听防抖延迟: {}ms", delay_ms);
+        // 事件过滤：命中排除模式或超过大小上限的文件变更不触发索引（如持续写入的日志、缓存）
+        // 运行时排除子目录不进入静态匹配器，只按共享列表判断，移除后立即恢复监听
+        let static_patterns = super::mcp::static_exclude_patterns(
+            config.exclude_patterns.as_deref().unwrap_or_default(),
+            &config.project_exclusions,
+        );
+        let mut event_filter = WatchEventFilter {
+            root: watch_path.clone(),
+            rules: super::mcp::PathRules::new(&watch_path, &static_patterns),
+            exclude_patterns: static_patterns,
+            text_exts: config.text_extensions.clone().unwrap_or_default(),
+            max_file_bytes: config.max_file_bytes,
+            project_key: normalized_root.clone(),
+            exclusions: self.project_exclusions.clone(),
+            last_refresh: Some(Instant::now()),
+        };
+        self.project_exclusions
+            .lock()
+            .unwrap()
+            .insert(normalized_root.clone(), config.project_exclusions.clone());
+        let ignored_events = self.ignored_events.clone();
+        let coalesced_renames = self.coalesced_renames.clone();
         let mut debouncer = new_debouncer(
-            Duration::from_millis(delay_ms),
+            Duration::from_millis(delay_ms.min(RAW_EVENT_DEBOUNCE_MS)),
             None,
             move |result: DebounceEventResult| {
                 match result {
                     Ok(events) => {
-                        if !events.is_empty() {
-                            log_debug!("检测到文件变更事件，共 {} 个", events.len());
-                            // 发送信号触发索引更新
-                            let _ = tx.try_send(());
+                        let raw: Vec<&Event> = events.iter().map(|e| &e.event).collect();
+                        let (changes, coalesced) = coalesce_atomic_saves(&raw);
+                        if coalesced > 0 {
+                            coalesced_renames.fetch_add(coalesced, Ordering::SeqCst);
+                            log_debug!("合并 {} 次原子保存（改名/删除后重建）为文件修改", coalesced);
+                        }
+                        event_filter.refresh_exclusions();
+                        event_filter.reload_rules_if_changed(&changes);
+                        let total = changes.len();
+                        let relevant = changes
+                            .iter()
+                            .filter(|paths| !event_filter.should_ignore(paths))
+                            .count();
+                        let ignored = total - relevant;
+                        if ignored > 0 {
+                            ignored_events.fetch_add(ignored, Ordering::SeqCst);
+                            log_debug!("忽略 {} 个被过滤的文件变更事件", ignored);
+                        }
+                        if relevant > 0 {
+                            log_debug!("检测到文件变更事件，共 {} 个", relevant);
+                            // 发送信号，由后台任务在防抖窗口结束后触发索引更新
+                            let _ = tx.try_send(WatchSignal::Changed);
                         }
                     }
                     Err(errors) => {
@@ -119,16 +495,53 @@ impl WatcherManager {
             let mut watchers = self.watchers.lock().unwrap();
             watchers.insert(normalized_root.clone(), debouncer);
         }
+        self.signals.lock().unwrap().insert(normalized_root.clone(), flush_tx);
 
         // 启动后台任务处理索引更新
         let project_root_clone = normalized_root.clone();
         let config_fallback = config.clone();
+        let upload_limiter = self.upload_limiter.clone();
+        let auto_index_disabled = self.auto_index_disabled.clone();
+        let skipped_auto_index = self.skipped_auto_index.clone();
+        let window = Duration::from_millis(delay_ms);
         tokio::spawn(async move {
-            while let Some(_) = rx.recv().await {
+            // 存在积压变更时为防抖截止时间，每次新变更都会顺延
+            let mut deadline: Option<tokio::time::Instant> = None;
+            loop {
+                let signal = match deadline {
+                    Some(at) => match tokio::time::timeout_at(at, rx.recv()).await {
+                        Ok(signal) => signal,
+                        // 防抖窗口结束，按立即同步处理积压变更（无需回复）
+                        Err(_) => Some(WatchSignal::Flush(oneshot::channel().0)),
+                    },
+                    None => rx.recv().await,
+                };
+                match signal {
+                    None => break,
+                    Some(WatchSignal::Changed) => {
+                        deadline = Some(tokio::time::Instant::now() + window);
+                        continue;
+                    }
+                    Some(WatchSignal::Flush(reply)) => {
+                        let pending = deadline.take().is_some();
+                        let _ = reply.send(pending);
+                        if !pending {
+                            continue;
+                        }
+                    }
+                }
+
+                // 项目关闭自动索引时仅记录统计，不触发上传
+                if auto_index_disabled.lock().unwrap().contains(&project_root_clone) {
+                    skipped_auto_index.fetch_add(1, Ordering::SeqCst);
+                    log_debug!("项目已关闭自动索引，跳过本次索引更新: project_root={}", project_root_clone);
+                    continue;
+                }
+
                 log_important!(info, "触发自动索引更新: project_root={}", project_root_clone);
                 
                 // 每次触发时读取最新配置，避免“用户修改配置但监听仍沿用旧配置”的情况
-                let latest_config = match super::mcp::AcemcpTool::get_acemcp_config().await {
+                let latest_config = match super::mcp::AcemcpTool::get_project_acemcp_config(&project_root_clone).await {
                     Ok(c) => c,
                     Err(e) => {
                         log_debug!("获取最新 acemcp 配置失败，将使用启动监听时的配置（不影响索引）: {}", e);
@@ -136,7 +549,8 @@ impl WatcherManager {
                     }
                 };
 
-                match update_index(&latest_config, &project_root_clone).await {
+                // 排队等待上传许可，避免大量项目同时上传占满带宽/触发 ACE 限流
+                match upload_limiter.run_index(&latest_config, &project_root_clone, false).await {
                     Ok(blob_names) => {
                         log_important!(info, "自动索引更新成功: project_root={}, blobs={}", project_root_clone, blob_names.len());
                     }
@@ -150,14 +564,43 @@ impl WatcherManager {
         Ok(())
     }
 
+    /// 更新项目运行时排除的子目录，正在运行的监听器立即按新列表过滤事件
+    pub fn set_project_exclusions(&self, project_root: &str, exclusions: Vec<String>) {
+        let normalized_root = super::mcp::normalize_project_path(project_root);
+        log_important!(info, "更新监听排除子目录: project_root={}, exclusions={:?}", normalized_root, exclusions);
+        self.project_exclusions.lock().unwrap().insert(normalized_root, exclusions);
+    }
+
+    /// 立即处理项目积压的文件变更（跳过剩余防抖时间）
+    /// 返回是否存在积压变更；项目未在监听时返回错误
+    pub async fn flush_pending(&self, project_root: &str) -> Result<bool> {
+        let normalized_root = super::mcp::normalize_project_path(project_root);
+        let sender = self
+            .signals
+            .lock()
+            .unwrap()
+            .get(&normalized_root)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("项目未在监听中: {}", normalized_root))?;
+
+        let (reply_tx, reply_rx) = oneshot::channel();
+        sender
+            .send(WatchSignal::Flush(reply_tx))
+            .await
+            .map_err(|_| anyhow::anyhow!("监听任务已退出: {}", normalized_root))?;
+        let pending = reply_rx
+            .await
+            .map_err(|_| anyhow::anyhow!("监听任务未响应: {}", normalized_root))?;
+        log_important!(info, "立即同步: project_root={}, pending={}", normalized_root, pending);
+        Ok(pending)
+    }
+
     /// 停止监听指定项目
     pub fn stop_watching(&self, project_root: &str) -> Result<()> {
-        let normalized_root = PathBuf::from(project_root)
-            .canonicalize()
-            .unwrap_or_else(|_| PathBuf::from(project_root))
-            .to_string_lossy()
-            .replace('\\', "/");
+        let normalized_root = super::mcp::normalize_project_path(project_root);
 
+        // 移除信号发送端，debouncer 释放后后台任务随通道关闭退出
+        self.signals.lock().unwrap().remove(&normalized_root);
         let mut watchers = self.watchers.lock().unwrap();
         if watchers.remove(&normalized_root).is_some() {
             log_important!(info, "已停止文件监听: {}", normalized_root);
@@ -173,6 +616,7 @@ impl WatcherManager {
         let mut watchers = self.watchers.lock().unwrap();
         let count = watchers.len();
         watchers.clear();
+        self.signals.lock().unwrap().clear();
         log_important!(info, "已停止所有文件监听，共 {} 个项目", count);
     }
 
@@ -184,11 +628,7 @@ impl WatcherManager {
 
     /// 检查指定项目是否正在监听
     pub fn is_watching(&self, project_root: &str) -> bool {
-        let normalized_root = PathBuf::from(project_root)
-            .canonicalize()
-            .unwrap_or_else(|_| PathBuf::from(project_root))
-            .to_string_lossy()
-            .replace('\\', "/");
+        let normalized_root = super::mcp::normalize_project_path(project_root);
 
         let watchers = self.watchers.lock().unwrap();
         watchers.contains_key(&normalized_root)
@@ -204,3 +644,86 @@ pub fn get_watcher_manager() -> &'static WatcherManager {
     &WATCHER_MANAGER
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify_debouncer_full::notify::event::{CreateKind, RemoveKind};
+
+    fn event(kind: EventKind, paths: &[&str]) -> Event {
+        paths
+            .iter()
+            .fold(Event::new(kind), |e, p| e.add_path(PathBuf::from(p)))
+    }
+
+    #[test]
+    fn test_watch_event_filter_uses_index_rules() {
+        let root = std::env::temp_dir().join(format!("sanshu-watch-filter-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::create_dir_all(root.join("gen")).unwrap();
+        std::fs::write(root.join(".acemcpignore"), "gen/\n").unwrap();
+        for file in ["src/main.rs", "src/logo.png", "gen/api.rs"] {
+            std::fs::write(root.join(file), "x").unwrap();
+        }
+
+        let mut filter = WatchEventFilter {
+            root: root.clone(),
+            rules: super::super::mcp::PathRules::new(&root, &[]),
+            exclude_patterns: Vec::new(),
+            text_exts: vec![".rs".to_string()],
+            max_file_bytes: None,
+            project_key: "test".to_string(),
+            exclusions: Arc::new(Mutex::new(HashMap::new())),
+            last_refresh: Some(Instant::now()),
+        };
+        assert!(!filter.should_ignore(&[root.join("src/main.rs")]));
+        assert!(filter.should_ignore(&[root.join("src/logo.png")]));
+        assert!(filter.should_ignore(&[root.join("gen/api.rs")]));
+        // 已删除且无扩展名的路径可能是目录，保留事件
+        assert!(!filter.should_ignore(&[root.join("src/removed_dir")]));
+
+        // .acemcpignore 修改后立即按新规则过滤
+        std::fs::write(root.join(".acemcpignore"), "").unwrap();
+        filter.reload_rules_if_changed(&[vec![root.join(".acemcpignore")]]);
+        assert!(!filter.should_ignore(&[root.join("gen/api.rs")]));
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_coalesce_atomic_saves() {
+        let rename = EventKind::Modify(ModifyKind::Name(RenameMode::Both));
+        let create = EventKind::Create(CreateKind::File);
+        let remove = EventKind::Remove(RemoveKind::File);
+
+        // 写临时文件后改名覆盖
+        let events = [
+            event(create, &["/p/.main.rs.tmp"]),
+            event(rename, &["/p/.main.rs.tmp", "/p/main.rs"]),
+        ];
+        let (changes, coalesced) = coalesce_atomic_saves(&events.iter().collect::<Vec<_>>());
+        assert_eq!(coalesced, 1);
+        assert_eq!(changes, vec![vec![PathBuf::from("/p/main.rs")]]);
+
+        // vim 备份：原文件改名为备份后写入同名新文件
+        let events = [
+            event(rename, &["/p/lib.rs", "/p/lib.rs~"]),
+            event(create, &["/p/lib.rs"]),
+            event(remove, &["/p/lib.rs~"]),
+        ];
+        let (changes, coalesced) = coalesce_atomic_saves(&events.iter().collect::<Vec<_>>());
+        assert_eq!(coalesced, 1);
+        assert_eq!(changes, vec![vec![PathBuf::from("/p/lib.rs")]]);
+
+        // 删除后重建；普通改名保持不变
+        let events = [
+            event(remove, &["/p/a.rs"]),
+            event(create, &["/p/a.rs"]),
+            event(rename, &["/p/b.rs", "/p/c.rs"]),
+        ];
+        let (changes, coalesced) = coalesce_atomic_saves(&events.iter().collect::<Vec<_>>());
+        assert_eq!(coalesced, 1);
+        assert_eq!(changes.len(), 2);
+        assert!(changes.contains(&vec![PathBuf::from("/p/b.rs"), PathBuf::from("/p/c.rs")]));
+    }
+}
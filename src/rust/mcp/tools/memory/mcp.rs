@@ -86,7 +86,7 @@ async fn try_trigger_background_index(project_root: &str) -> Result<()> {
     use super::super::acemcp::mcp::{get_initial_index_state, ensure_initial_index_background, InitialIndexState};
 
     // 获取 acemcp 配置：复用工具内部读取逻辑，避免字段新增/演进导致此处漏填
-    let acemcp_config = super::super::acemcp::mcp::AcemcpTool::get_acemcp_config().await?;
+    let acemcp_config = super::super::acemcp::mcp::AcemcpTool::get_project_acemcp_config(project_root).await?;
 
     // 检查索引状态
     let initial_state = get_initial_index_state(project_root);
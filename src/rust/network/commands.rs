@@ -2,6 +2,7 @@
 use tauri::{AppHandle, State};
 use crate::config::{AppState, ProxyConfig, save_config};
 use super::{ProxyDetector, ProxyInfo, proxy::ProxyType};
+use super::proxy_dns::{ProxyCredentials, ProxyDnsTestResult};
 
 /// 获取代理配置
 #[tauri::command]
@@ -64,9 +65,46 @@ pub async fn test_proxy_connection(
     Ok(is_available)
 }
 
+/// 通过代理解析并连接目标主机，区分"DNS 失败"与"连接被拒"
+/// SOCKS5（socks5/socks5h）由代理端解析域名；HTTP 代理通过 CONNECT 隧道测试
+#[tauri::command]
+pub async fn test_proxy_dns(
+    hostname: String,
+    port: Option<u16>,
+    proxy_type: String,
+    proxy_host: String,
+    proxy_port: u16,
+    proxy_username: Option<String>,
+    proxy_password: Option<String>,
+) -> Result<ProxyDnsTestResult, String> {
+    let hostname = hostname.trim().to_string();
+    if hostname.is_empty() {
+        return Err("主机名不能为空".to_string());
+    }
+
+    let proxy_type_enum = match proxy_type.as_str() {
+        "socks5" | "socks5h" => ProxyType::Socks5,
+        _ => ProxyType::Http,
+    };
+    let proxy_info = ProxyInfo::new(proxy_type_enum, proxy_host, proxy_port);
+    let credentials = proxy_username
+        .filter(|u| !u.is_empty())
+        .map(|username| ProxyCredentials {
+            username,
+            password: proxy_password.unwrap_or_default(),
+        });
+
+    Ok(super::proxy_dns::test_proxy_dns(&proxy_info, credentials.as_ref(), &hostname, port.unwrap_or(443)).await)
+}
+
 /// 自动检测可用代理
 #[tauri::command]
 pub async fn detect_available_proxy() -> Result<Option<ProxyInfo>, String> {
+    if crate::app::safe_mode::is_safe_mode() {
+        log::warn!("🛟 安全模式下跳过代理检测");
+        return Ok(None);
+    }
+
     log::info!("🔍 开始自动检测可用代理");
     
     let proxy_info = ProxyDetector::detect_available_proxy().await;
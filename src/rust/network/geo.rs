@@ -1,5 +1,48 @@
 // IP地理位置检测模块
+use once_cell::sync::Lazy;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// 地理位置检测的默认总超时（秒）
+pub const DEFAULT_GEO_TIMEOUT_SECS: u64 = 5;
+
+/// 所有服务商均网络不可达后的重试次数
+const GEO_RETRY_COUNT: u32 = 1;
+/// 重试前的基础退避时间（毫秒）
+const GEO_RETRY_BACKOFF_MS: u64 = 300;
+/// 退避时间的最大随机抖动（毫秒）
+const GEO_RETRY_JITTER_MS: u64 = 200;
+/// 退避后至少还需剩余的时间（毫秒），不足时不再重试，保证总耗时不超过超时设置
+const GEO_RETRY_MIN_BUDGET_MS: u64 = 500;
+
+/// 地理位置检测失败的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GeoFailureKind {
+    /// 请求失败、被拒绝或超时（网络不通或被拦截）
+    NetworkBlocked,
+    /// 服务商有响应，但无法从中解析出国家代码
+    ParseFailed,
+}
+
+impl GeoFailureKind {
+    /// 面向用户的原因说明
+    pub fn reason(self) -> &'static str {
+        match self {
+            GeoFailureKind::NetworkBlocked => "网络不可达（请求失败、被拦截或超时）",
+            GeoFailureKind::ParseFailed => "服务商有响应，但无法解析出国家代码",
+        }
+    }
+}
+
+/// 地理位置检测失败结果
+#[derive(Debug, Clone)]
+pub struct GeoFailure {
+    pub kind: GeoFailureKind,
+    /// 只拿到 IP/城市等部分字段时的结果（不含国家代码）
+    pub partial: Option<GeoLocation>,
+}
 
 /// IP地理位置信息
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -14,59 +57,305 @@ pub struct GeoLocation {
     pub timezone: Option<String>,
 }
 
-/// 检测当前IP的地理位置
-/// 
-/// 使用 ipinfo.io API 检测IP地理位置
-/// 返回国家代码（如 "CN", "US" 等）
-/// 
-/// # 错误处理
-/// - 网络请求失败时返回 "UNKNOWN"
-/// - 解析失败时返回 "UNKNOWN"
-/// - 超时设置为 5 秒
-pub async fn detect_geo_location() -> String {
-    log::info!("🌍 开始检测IP地理位置");
-    
-    // 创建HTTP客户端，设置较短的超时时间
-    let client = match reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(5))
-        .build()
-    {
-        Ok(c) => c,
-        Err(e) => {
-            log::warn!("⚠️ 创建HTTP客户端失败: {}", e);
-            return "UNKNOWN".to_string();
+impl GeoLocation {
+    /// 检测失败时使用的占位结果
+    pub fn unknown() -> Self {
+        Self {
+            ip: "unknown".to_string(),
+            city: None,
+            region: None,
+            country: "UNKNOWN".to_string(),
+            loc: None,
+            org: None,
+            postal: None,
+            timezone: None,
         }
-    };
-    
-    // 请求 ipinfo.io API
-    match client
-        .get("https://ipinfo.io/json")
-        .send()
-        .await
-    {
-        Ok(response) => {
-            if !response.status().is_success() {
-                log::warn!("⚠️ IP地理位置检测请求失败: HTTP {}", response.status());
-                return "UNKNOWN".to_string();
-            }
-            
-            // 解析JSON响应
-            match response.json::<GeoLocation>().await {
+    }
+
+    /// 是否包含国家代码（仅有 IP/城市等部分字段时为 false）
+    pub fn has_country(&self) -> bool {
+        self.country != "UNKNOWN"
+    }
+}
+
+/// 响应不是合法 JSON 时，逐个提取 `"key": "value"` 形式的字符串字段
+static JSON_STRING_FIELD_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#""([A-Za-z_]+)"\s*:\s*"([^"\\]*)""#).expect("字段提取正则无效")
+});
+
+/// 宽松解析响应体：优先按 JSON 解析，失败时尽量挽救其中的字符串字段
+fn parse_body_lenient(body: &str) -> serde_json::Value {
+    if let Ok(value) = serde_json::from_str::<serde_json::Value>(body) {
+        return value;
+    }
+    let fields: serde_json::Map<String, serde_json::Value> = JSON_STRING_FIELD_RE
+        .captures_iter(body)
+        .map(|c| (c[1].to_string(), serde_json::Value::String(c[2].to_string())))
+        .collect();
+    serde_json::Value::Object(fields)
+}
+
+/// 地理位置服务商
+#[derive(Debug, Clone, Copy)]
+enum GeoProvider {
+    IpInfo,
+    IpApiCo,
+    IpSb,
+}
+
+impl GeoProvider {
+    const ALL: [GeoProvider; 3] = [GeoProvider::IpInfo, GeoProvider::IpApiCo, GeoProvider::IpSb];
+
+    fn name(self) -> &'static str {
+        match self {
+            GeoProvider::IpInfo => "ipinfo.io",
+            GeoProvider::IpApiCo => "ipapi.co",
+            GeoProvider::IpSb => "ip.sb",
+        }
+    }
+
+    fn url(self) -> &'static str {
+        match self {
+            GeoProvider::IpInfo => "https://ipinfo.io/json",
+            GeoProvider::IpApiCo => "https://ipapi.co/json/",
+            GeoProvider::IpSb => "https://api.ip.sb/geoip",
+        }
+    }
+
+    /// 将各服务商的响应统一转换为 GeoLocation
+    /// 逐字段提取，个别字段缺失或类型错误时只丢弃该字段；ip/country/city 都不可用时视为失败
+    fn parse(self, value: &serde_json::Value) -> Option<GeoLocation> {
+        let text = |key: &str| {
+            value
+                .get(key)
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_string())
+                .filter(|s| !s.is_empty())
+        };
+        let coords = || {
+            let lat = value.get("latitude").and_then(|v| v.as_f64())?;
+            let lon = value.get("longitude").and_then(|v| v.as_f64())?;
+            Some(format!("{},{}", lat, lon))
+        };
+
+        let (country_key, org_key, postal_key) = match self {
+            GeoProvider::IpInfo | GeoProvider::IpApiCo => ("country", "org", "postal"),
+            GeoProvider::IpSb => ("country_code", "organization", "postal_code"),
+        };
+        let loc = match self {
+            GeoProvider::IpInfo => text("loc"),
+            GeoProvider::IpApiCo | GeoProvider::IpSb => coords(),
+        };
+
+        let ip = text("ip");
+        let country = text(country_key).map(|c| c.trim().to_uppercase()).filter(|c| !c.is_empty());
+        let city = text("city");
+        if ip.is_none() && country.is_none() && city.is_none() {
+            return None;
+        }
+
+        Some(GeoLocation {
+            ip: ip.unwrap_or_else(|| "unknown".to_string()),
+            city,
+            region: text("region"),
+            country: country.unwrap_or_else(|| "UNKNOWN".to_string()),
+            loc,
+            org: text(org_key),
+            postal: text(postal_key),
+            timezone: text("timezone"),
+        })
+    }
+
+    async fn fetch(self, client: &reqwest::Client) -> Result<GeoLocation, (GeoFailureKind, String)> {
+        let blocked = |msg: String| (GeoFailureKind::NetworkBlocked, msg);
+        let response = client
+            .get(self.url())
+            .send()
+            .await
+            .map_err(|e| blocked(format!("网络请求失败: {}", e)))?;
+        if !response.status().is_success() {
+            return Err(blocked(format!("HTTP {}", response.status())));
+        }
+        let body = response
+            .text()
+            .await
+            .map_err(|e| blocked(format!("读取响应失败: {}", e)))?;
+        self.parse(&parse_body_lenient(&body)).ok_or_else(|| {
+            (GeoFailureKind::ParseFailed, "响应中没有可用的地理位置字段".to_string())
+        })
+    }
+}
+
+/// 一轮并发查询所有服务商的结果
+struct GeoRound {
+    /// 最先得到的含国家代码的结果
+    location: Option<GeoLocation>,
+    /// 最早得到的部分结果（不含国家代码）
+    partial: Option<GeoLocation>,
+    /// 是否有服务商响应但解析失败
+    parse_failed: bool,
+    /// 本轮是否因超时结束
+    timed_out: bool,
+}
+
+impl GeoRound {
+    /// 本轮未得到国家代码时的失败原因：有服务商响应（解析失败或只有部分字段）即为解析失败
+    fn failure_kind(&self) -> GeoFailureKind {
+        if self.parse_failed || self.partial.is_some() {
+            GeoFailureKind::ParseFailed
+        } else {
+            GeoFailureKind::NetworkBlocked
+        }
+    }
+
+    /// 只有所有服务商都网络不可达且未超时才值得重试；服务商已响应时重试也拿不到国家代码
+    fn should_retry(&self) -> bool {
+        self.location.is_none() && !self.timed_out && self.failure_kind() == GeoFailureKind::NetworkBlocked
+    }
+}
+
+/// 退避后剩余时间仍足够完成一轮查询时才重试
+fn retry_fits_budget(remaining: Duration, backoff: Duration) -> bool {
+    remaining >= backoff + Duration::from_millis(GEO_RETRY_MIN_BUDGET_MS)
+}
+
+/// 并发请求所有服务商，返回最先得到国家代码的结果；本轮耗时不超过 budget，
+/// 慢的服务商不会拖慢快的服务商
+async fn query_providers_once(client: &reqwest::Client, budget: Duration) -> GeoRound {
+    let (tx, mut rx) = tokio::sync::mpsc::channel(GeoProvider::ALL.len());
+    let handles: Vec<_> = GeoProvider::ALL
+        .into_iter()
+        .map(|provider| {
+            let client = client.clone();
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                let result = provider.fetch(&client).await;
+                let _ = tx.send((provider, result)).await;
+            })
+        })
+        .collect();
+    drop(tx);
+
+    let mut partial: Option<GeoLocation> = None;
+    let mut parse_failed = false;
+    let first_success = tokio::time::timeout(budget, async {
+        while let Some((provider, result)) = rx.recv().await {
+            match result {
+                Ok(geo) if geo.has_country() => {
+                    log::info!("✅ [{}] 检测到地理位置: {} ({})", provider.name(), geo.country, geo.city.as_deref().unwrap_or("未知城市"));
+                    return Some(geo);
+                }
                 Ok(geo) => {
-                    log::info!("✅ 检测到地理位置: {} ({})", geo.country, geo.city.unwrap_or_default());
-                    geo.country
+                    log::warn!("⚠️ [{}] 响应缺少国家代码，仅获得部分信息: ip={}", provider.name(), geo.ip);
+                    partial.get_or_insert(geo);
                 }
-                Err(e) => {
-                    log::warn!("⚠️ 解析地理位置信息失败: {}", e);
-                    "UNKNOWN".to_string()
+                Err((kind, e)) => {
+                    parse_failed |= kind == GeoFailureKind::ParseFailed;
+                    log::warn!("⚠️ [{}] IP地理位置检测失败: {}", provider.name(), e);
                 }
             }
         }
+        None
+    })
+    .await;
+
+    // 已得到结果或已超时，其余请求不再需要
+    for handle in handles {
+        handle.abort();
+    }
+
+    let (location, timed_out) = match first_success {
+        Ok(location) => (location, false),
+        Err(_) => (None, true),
+    };
+    GeoRound { location, partial, parse_failed, timed_out }
+}
+
+/// 检测当前IP的地理位置，并区分失败原因
+///
+/// 所有服务商都网络不可达时，经短暂退避（带随机抖动）后再重试一轮；
+/// 有服务商响应但解析失败时不重试。重试只在剩余时间充足时进行，总耗时不超过 timeout。
+/// 只拿到部分字段（无国家代码）视为解析失败，部分结果随失败原因一并返回
+pub async fn detect_geo_location_detailed(timeout: Duration) -> Result<GeoLocation, GeoFailure> {
+    let client = match reqwest::Client::builder().timeout(timeout).build() {
+        Ok(c) => c,
         Err(e) => {
-            log::warn!("⚠️ IP地理位置检测网络请求失败: {}", e);
-            "UNKNOWN".to_string()
+            log::warn!("⚠️ 创建HTTP客户端失败: {}", e);
+            return Err(GeoFailure { kind: GeoFailureKind::NetworkBlocked, partial: None });
+        }
+    };
+
+    let deadline = tokio::time::Instant::now() + timeout;
+    let mut partial: Option<GeoLocation> = None;
+    let mut kind = GeoFailureKind::NetworkBlocked;
+
+    for attempt in 0..=GEO_RETRY_COUNT {
+        if attempt > 0 {
+            let backoff = Duration::from_millis(GEO_RETRY_BACKOFF_MS + fastrand::u64(0..=GEO_RETRY_JITTER_MS));
+            let remaining = deadline.saturating_duration_since(tokio::time::Instant::now());
+            if !retry_fits_budget(remaining, backoff) {
+                log::debug!("剩余时间不足，不再重试地理位置检测: remaining={:?}", remaining);
+                break;
+            }
+            log::info!("🔁 地理位置检测重试（第 {} 次），退避 {}ms", attempt, backoff.as_millis());
+            tokio::time::sleep(backoff).await;
+        }
+
+        let budget = deadline.saturating_duration_since(tokio::time::Instant::now());
+        let round = query_providers_once(&client, budget).await;
+        if let Some(geo) = round.location {
+            return Ok(geo);
+        }
+        kind = round.failure_kind();
+        let retry = round.should_retry();
+        if round.timed_out {
+            log::warn!("⚠️ IP地理位置检测超时（{}秒）", timeout.as_secs());
+        }
+        if partial.is_none() {
+            partial = round.partial;
+        }
+        if !retry {
+            break;
         }
     }
+
+    log::warn!("⚠️ IP地理位置检测失败: {}", kind.reason());
+    Err(GeoFailure { kind, partial })
+}
+
+/// 检测当前IP的完整地理位置信息
+///
+/// 并发请求多个服务商，返回最先得到国家代码的结果（失败时重试一轮，见
+/// `detect_geo_location_detailed`）；总耗时不超过 timeout。最终没有完整结果则
+/// 返回最早的部分结果；全部失败或超时且没有部分结果时返回 None
+pub async fn detect_geo_location_with_timeout(timeout: Duration) -> Option<GeoLocation> {
+    match detect_geo_location_detailed(timeout).await {
+        Ok(geo) => Some(geo),
+        Err(failure) => failure.partial,
+    }
+}
+
+/// 检测当前IP的完整地理位置信息，失败时返回 country 为 "UNKNOWN" 的占位结果
+pub async fn detect_geo_location_full(timeout: Duration) -> GeoLocation {
+    log::info!("🌍 开始检测完整地理位置信息");
+    detect_geo_location_with_timeout(timeout)
+        .await
+        .unwrap_or_else(GeoLocation::unknown)
+}
+
+/// 检测当前IP的地理位置
+/// 
+/// 返回国家代码（如 "CN", "US" 等）
+/// 
+/// # 错误处理
+/// - 所有服务商请求失败、解析失败或超时时返回 "UNKNOWN"
+/// - 使用默认超时 DEFAULT_GEO_TIMEOUT_SECS
+pub async fn detect_geo_location() -> String {
+    log::info!("🌍 开始检测IP地理位置");
+    detect_geo_location_with_timeout(Duration::from_secs(DEFAULT_GEO_TIMEOUT_SECS))
+        .await
+        .map(|geo| geo.country)
+        .unwrap_or_else(|| "UNKNOWN".to_string())
 }
 
 #[cfg(test)]
@@ -80,5 +369,79 @@ mod tests {
         // 注意：这个测试依赖网络，可能会失败
         assert!(!country.is_empty());
     }
-}
 
+    #[test]
+    fn test_parse_provider_responses() {
+        let ipapi = serde_json::json!({
+            "ip": "1.2.3.4", "city": "Shanghai", "region": "Shanghai", "country": "CN",
+            "latitude": 31.2, "longitude": 121.4, "org": "AS4812", "timezone": "Asia/Shanghai"
+        });
+        let geo = GeoProvider::IpApiCo.parse(&ipapi).unwrap();
+        assert_eq!(geo.country, "CN");
+        assert_eq!(geo.loc.as_deref(), Some("31.2,121.4"));
+
+        let ipsb = serde_json::json!({ "ip": "5.6.7.8", "country_code": "US", "organization": "Example" });
+        let geo = GeoProvider::IpSb.parse(&ipsb).unwrap();
+        assert_eq!(geo.country, "US");
+        assert_eq!(geo.org.as_deref(), Some("Example"));
+
+        // 缺少国家代码时保留部分信息
+        let geo = GeoProvider::IpSb.parse(&serde_json::json!({ "ip": "5.6.7.8" })).unwrap();
+        assert_eq!(geo.ip, "5.6.7.8");
+        assert!(!geo.has_country());
+        // 没有任何可用字段才视为失败
+        assert!(GeoProvider::IpSb.parse(&serde_json::json!({ "error": "rate limited" })).is_none());
+    }
+
+    #[test]
+    fn test_parse_partial_payload() {
+        // 字段类型被代理改写（loc 变成数字）时，其余字段仍可用
+        let ipinfo = serde_json::json!({ "ip": "1.2.3.4", "country": "jp", "loc": 123, "city": null });
+        let geo = GeoProvider::IpInfo.parse(&ipinfo).unwrap();
+        assert_eq!(geo.country, "JP");
+        assert_eq!(geo.loc, None);
+        assert_eq!(geo.city, None);
+
+        // 响应被截断、不是合法 JSON 时逐字段挽救
+        let body = r#"{"ip": "1.2.3.4", "city": "Berlin", "country": "DE", "org": "AS3320 Deutsche"#;
+        let geo = GeoProvider::IpInfo.parse(&parse_body_lenient(body)).unwrap();
+        assert_eq!(geo.ip, "1.2.3.4");
+        assert_eq!(geo.country, "DE");
+        assert_eq!(geo.city.as_deref(), Some("Berlin"));
+        assert_eq!(geo.org, None);
+    }
+
+    fn round(partial: Option<GeoLocation>, parse_failed: bool, timed_out: bool) -> GeoRound {
+        GeoRound { location: None, partial, parse_failed, timed_out }
+    }
+
+    #[test]
+    fn test_round_failure_kind_and_retry() {
+        // 全部网络不可达：重试
+        let blocked = round(None, false, false);
+        assert_eq!(blocked.failure_kind(), GeoFailureKind::NetworkBlocked);
+        assert!(blocked.should_retry());
+
+        // 有服务商响应但解析失败：不重试
+        let parse_failed = round(None, true, false);
+        assert_eq!(parse_failed.failure_kind(), GeoFailureKind::ParseFailed);
+        assert!(!parse_failed.should_retry());
+
+        // 只拿到部分字段同样视为解析失败
+        let partial = round(Some(GeoLocation::unknown()), false, false);
+        assert_eq!(partial.failure_kind(), GeoFailureKind::ParseFailed);
+        assert!(!partial.should_retry());
+
+        // 超时后没有剩余时间，不重试
+        assert!(!round(None, false, true).should_retry());
+    }
+
+    #[test]
+    fn test_retry_fits_budget() {
+        let backoff = Duration::from_millis(GEO_RETRY_BACKOFF_MS);
+        assert!(retry_fits_budget(Duration::from_secs(5), backoff));
+        assert!(retry_fits_budget(backoff + Duration::from_millis(GEO_RETRY_MIN_BUDGET_MS), backoff));
+        assert!(!retry_fits_budget(Duration::from_millis(600), backoff));
+        assert!(!retry_fits_budget(Duration::ZERO, backoff));
+    }
+}
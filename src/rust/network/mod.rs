@@ -5,6 +5,7 @@ pub mod geo;
 pub mod proxy;
 pub mod client;
 pub mod commands;
+pub mod proxy_dns;
 
 pub use geo::detect_geo_location;
 pub use proxy::{ProxyDetector, ProxyInfo};
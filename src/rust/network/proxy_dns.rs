@@ -0,0 +1,323 @@
+// 代理 DNS 解析测试模块
+// 通过代理（SOCKS5 远程解析 / HTTP CONNECT）连接目标主机，
+// 区分"代理端 DNS 解析失败"与"解析成功但连接被拒"等情况
+use std::io;
+use std::time::{Duration, Instant};
+
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use tokio::net::TcpStream;
+
+use super::proxy::{ProxyInfo, ProxyType};
+
+/// 整个探测流程的超时
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+/// HTTP CONNECT 响应头的最大读取长度
+const MAX_RESPONSE_BYTES: usize = 8 * 1024;
+
+/// 代理 DNS 测试结论
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProxyDnsOutcome {
+    /// 解析成功并已建立连接
+    Success,
+    /// 代理端无法解析目标主机名
+    DnsFailed,
+    /// 解析成功，但目标拒绝连接
+    ConnectionRefused,
+    /// 连接目标超时
+    Timeout,
+    /// 代理要求认证或认证失败
+    AuthFailed,
+    /// 无法连接到代理本身
+    ProxyUnreachable,
+    /// 其他失败（详见 message）
+    Other,
+}
+
+/// 代理 DNS 测试结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProxyDnsTestResult {
+    /// 测试的目标主机名
+    pub hostname: String,
+    /// 目标端口
+    pub port: u16,
+    /// 使用的代理（不含认证信息）
+    pub proxy: String,
+    /// 是否连上了代理
+    pub proxy_reachable: bool,
+    /// 代理端是否解析成功（无法判断时为 None）
+    pub dns_resolved: Option<bool>,
+    /// 是否已通过代理连上目标
+    pub connected: bool,
+    pub outcome: ProxyDnsOutcome,
+    /// 面向用户的结论，如 "DNS 失败" / "连接被拒"
+    pub message: String,
+    pub elapsed_ms: u64,
+}
+
+/// 代理认证信息
+pub struct ProxyCredentials {
+    pub username: String,
+    pub password: String,
+}
+
+/// 单步探测结论：(结论, 是否解析成功, 详细说明)
+type Verdict = (ProxyDnsOutcome, Option<bool>, String);
+
+/// 通过代理连接 `hostname:port`，判断代理端的 DNS 解析与目标连接是否成功
+/// SOCKS5 代理使用域名地址类型（由代理解析，即 socks5h 语义）
+pub async fn test_proxy_dns(
+    proxy: &ProxyInfo,
+    credentials: Option<&ProxyCredentials>,
+    hostname: &str,
+    port: u16,
+) -> ProxyDnsTestResult {
+    let start = Instant::now();
+    let proxy_addr = format!("{}:{}", proxy.host, proxy.port);
+
+    let mut proxy_reachable = false;
+    let verdict = match tokio::time::timeout(PROBE_TIMEOUT, TcpStream::connect(&proxy_addr)).await {
+        Err(_) => (ProxyDnsOutcome::ProxyUnreachable, None, format!("连接代理 {} 超时", proxy_addr)),
+        Ok(Err(e)) => (ProxyDnsOutcome::ProxyUnreachable, None, format!("无法连接代理 {}: {}", proxy_addr, e)),
+        Ok(Ok(stream)) => {
+            proxy_reachable = true;
+            let probe = async {
+                match proxy.proxy_type {
+                    ProxyType::Socks5 => socks5_connect(&stream, credentials, hostname, port).await,
+                    ProxyType::Http => http_connect(&stream, credentials, hostname, port).await,
+                }
+            };
+            match tokio::time::timeout(PROBE_TIMEOUT, probe).await {
+                Ok(Ok(v)) => v,
+                Ok(Err(e)) => (ProxyDnsOutcome::Other, None, format!("与代理通信失败: {}", e)),
+                Err(_) => (ProxyDnsOutcome::Timeout, None, "等待代理响应超时".to_string()),
+            }
+        }
+    };
+
+    let (outcome, dns_resolved, detail) = verdict;
+    let message = match outcome {
+        ProxyDnsOutcome::Success => format!("DNS 解析成功，已通过代理连接 {}:{}", hostname, port),
+        ProxyDnsOutcome::DnsFailed => format!("DNS 失败：代理无法解析 {}（{}）", hostname, detail),
+        ProxyDnsOutcome::ConnectionRefused => format!("连接被拒：{} 已解析，但目标拒绝连接（{}）", hostname, detail),
+        _ => detail,
+    };
+    log::info!(
+        "🔍 代理 DNS 测试: proxy={}, target={}:{}, outcome={:?}, {}",
+        proxy.to_url(),
+        hostname,
+        port,
+        outcome,
+        message
+    );
+
+    ProxyDnsTestResult {
+        hostname: hostname.to_string(),
+        port,
+        proxy: proxy.to_url(),
+        proxy_reachable,
+        dns_resolved,
+        connected: outcome == ProxyDnsOutcome::Success,
+        outcome,
+        message,
+        elapsed_ms: start.elapsed().as_millis() as u64,
+    }
+}
+
+/// SOCKS5 握手 + CONNECT（ATYP=域名，由代理解析）
+async fn socks5_connect(
+    stream: &TcpStream,
+    credentials: Option<&ProxyCredentials>,
+    hostname: &str,
+    port: u16,
+) -> io::Result<Verdict> {
+    if hostname.is_empty() || hostname.len() > 255 {
+        return Ok((ProxyDnsOutcome::Other, None, "主机名长度无效".to_string()));
+    }
+
+    // 协商认证方式：0x00 无认证，0x02 用户名/密码
+    let greeting: &[u8] = if credentials.is_some() { &[5, 2, 0, 2] } else { &[5, 1, 0] };
+    write_all(stream, greeting).await?;
+    let mut reply = [0u8; 2];
+    read_exact(stream, &mut reply).await?;
+    if reply[0] != 5 {
+        return Ok((ProxyDnsOutcome::Other, None, "代理不是 SOCKS5 代理".to_string()));
+    }
+    match (reply[1], credentials) {
+        (0, _) => {}
+        (2, Some(c)) => {
+            let mut auth = vec![1, c.username.len().min(255) as u8];
+            auth.extend(c.username.as_bytes().iter().take(255));
+            auth.push(c.password.len().min(255) as u8);
+            auth.extend(c.password.as_bytes().iter().take(255));
+            write_all(stream, &auth).await?;
+            read_exact(stream, &mut reply).await?;
+            if reply[1] != 0 {
+                return Ok((ProxyDnsOutcome::AuthFailed, None, "代理认证失败：用户名或密码错误".to_string()));
+            }
+        }
+        _ => {
+            return Ok((ProxyDnsOutcome::AuthFailed, None, "代理要求认证或不接受当前认证方式".to_string()));
+        }
+    }
+
+    let mut request = vec![5, 1, 0, 3, hostname.len() as u8];
+    request.extend(hostname.as_bytes());
+    request.extend(port.to_be_bytes());
+    write_all(stream, &request).await?;
+    let mut header = [0u8; 4];
+    read_exact(stream, &mut header).await?;
+    Ok(socks5_reply_verdict(header[1]))
+}
+
+/// 按 SOCKS5 应答码（RFC 1928）判断结果
+fn socks5_reply_verdict(rep: u8) -> Verdict {
+    match rep {
+        0x00 => (ProxyDnsOutcome::Success, Some(true), String::new()),
+        0x04 => (ProxyDnsOutcome::DnsFailed, Some(false), "SOCKS5 应答: 主机不可达".to_string()),
+        0x05 => (ProxyDnsOutcome::ConnectionRefused, Some(true), "SOCKS5 应答: 连接被拒绝".to_string()),
+        0x06 => (ProxyDnsOutcome::Timeout, Some(true), "SOCKS5 应答: 连接超时".to_string()),
+        0x02 => (ProxyDnsOutcome::Other, None, "SOCKS5 应答: 代理规则不允许该连接".to_string()),
+        0x03 => (ProxyDnsOutcome::Other, None, "SOCKS5 应答: 网络不可达".to_string()),
+        0x07 | 0x08 => (ProxyDnsOutcome::Other, None, "SOCKS5 应答: 代理不支持该请求".to_string()),
+        other => (ProxyDnsOutcome::Other, None, format!("SOCKS5 应答: 代理一般性失败 (0x{:02x})", other)),
+    }
+}
+
+/// HTTP CONNECT 隧道请求
+async fn http_connect(
+    stream: &TcpStream,
+    credentials: Option<&ProxyCredentials>,
+    hostname: &str,
+    port: u16,
+) -> io::Result<Verdict> {
+    let target = format!("{}:{}", hostname, port);
+    let mut request = format!("CONNECT {} HTTP/1.1\r\nHost: {}\r\n", target, target);
+    if let Some(c) = credentials {
+        let token = base64::engine::general_purpose::STANDARD.encode(format!("{}:{}", c.username, c.password));
+        request.push_str(&format!("Proxy-Authorization: Basic {}\r\n", token));
+    }
+    request.push_str("\r\n");
+    write_all(stream, request.as_bytes()).await?;
+
+    let mut response = Vec::new();
+    let mut buf = [0u8; 2048];
+    while !contains_header_end(&response) && response.len() < MAX_RESPONSE_BYTES {
+        let n = read_some(stream, &mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        response.extend_from_slice(&buf[..n]);
+    }
+    // 错误响应的原因通常在响应体里，短暂等待读取一部分
+    if !response.starts_with(b"HTTP/1.1 200") && !response.starts_with(b"HTTP/1.0 200") {
+        if let Ok(Ok(n)) = tokio::time::timeout(Duration::from_millis(500), read_some(stream, &mut buf)).await {
+            response.extend_from_slice(&buf[..n]);
+        }
+    }
+    Ok(classify_http_connect_response(&String::from_utf8_lossy(&response)))
+}
+
+fn contains_header_end(data: &[u8]) -> bool {
+    data.windows(4).any(|w| w == b"\r\n\r\n")
+}
+
+/// 根据 CONNECT 响应的状态码与内容判断失败原因
+/// 不同代理对 DNS 失败的状态码不统一（502/503/504 均有），因此同时检查响应头和响应体中的关键字
+fn classify_http_connect_response(response: &str) -> Verdict {
+    let status = response
+        .lines()
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|code| code.parse::<u16>().ok());
+    let Some(status) = status else {
+        return (ProxyDnsOutcome::Other, None, "代理返回了无法识别的响应".to_string());
+    };
+    if (200..300).contains(&status) {
+        return (ProxyDnsOutcome::Success, Some(true), String::new());
+    }
+    if status == 407 {
+        return (ProxyDnsOutcome::AuthFailed, None, "代理要求认证 (HTTP 407)".to_string());
+    }
+
+    let lower = response.to_lowercase();
+    const DNS_HINTS: &[&str] = &[
+        "dns",
+        "resolve",
+        "no such host",
+        "name or service not known",
+        "unknown host",
+        "nodename nor servname",
+        "getaddrinfo",
+    ];
+    if DNS_HINTS.iter().any(|hint| lower.contains(hint)) {
+        return (ProxyDnsOutcome::DnsFailed, Some(false), format!("HTTP {}", status));
+    }
+    if lower.contains("refused") {
+        return (ProxyDnsOutcome::ConnectionRefused, Some(true), format!("HTTP {}", status));
+    }
+    if status == 504 || lower.contains("timed out") || lower.contains("timeout") {
+        return (ProxyDnsOutcome::Timeout, None, format!("通过代理连接目标超时 (HTTP {})", status));
+    }
+    (ProxyDnsOutcome::Other, None, format!("代理拒绝建立隧道 (HTTP {})", status))
+}
+
+async fn write_all(stream: &TcpStream, mut buf: &[u8]) -> io::Result<()> {
+    while !buf.is_empty() {
+        stream.writable().await?;
+        match stream.try_write(buf) {
+            Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+            Ok(n) => buf = &buf[n..],
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+async fn read_some(stream: &TcpStream, buf: &mut [u8]) -> io::Result<usize> {
+    loop {
+        stream.readable().await?;
+        match stream.try_read(buf) {
+            Ok(n) => return Ok(n),
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+async fn read_exact(stream: &TcpStream, buf: &mut [u8]) -> io::Result<()> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        let n = read_some(stream, &mut buf[filled..]).await?;
+        if n == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        filled += n;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_http_connect_response() {
+        let outcome = |r: &str| classify_http_connect_response(r).0;
+        assert_eq!(outcome("HTTP/1.1 200 Connection established\r\n\r\n"), ProxyDnsOutcome::Success);
+        assert_eq!(outcome("HTTP/1.1 407 Proxy Authentication Required\r\n\r\n"), ProxyDnsOutcome::AuthFailed);
+        assert_eq!(
+            outcome("HTTP/1.1 503 Service Unavailable\r\nX-Squid-Error: ERR_DNS_FAIL 0\r\n\r\n"),
+            ProxyDnsOutcome::DnsFailed
+        );
+        assert_eq!(
+            outcome("HTTP/1.1 502 Bad Gateway\r\n\r\ndial tcp 1.2.3.4:443: connect: connection refused"),
+            ProxyDnsOutcome::ConnectionRefused
+        );
+        assert_eq!(outcome("garbage"), ProxyDnsOutcome::Other);
+        assert_eq!(socks5_reply_verdict(0x04).0, ProxyDnsOutcome::DnsFailed);
+        assert_eq!(socks5_reply_verdict(0x05).1, Some(true));
+    }
+}
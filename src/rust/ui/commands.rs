@@ -23,6 +23,15 @@ pub async fn set_always_on_top(
     enabled: bool,
     state: State<'_, AppState>,
     app: tauri::AppHandle,
+) -> Result<(), String> {
+    persist_and_apply_always_on_top(enabled, &state, &app).await
+}
+
+/// 保存置顶状态并应用到主窗口（set_always_on_top / set_window_always_on_top 共用）
+pub(crate) async fn persist_and_apply_always_on_top(
+    enabled: bool,
+    state: &State<'_, AppState>,
+    app: &tauri::AppHandle,
 ) -> Result<(), String> {
     {
         let mut config = state
@@ -33,7 +42,7 @@ pub async fn set_always_on_top(
     }
 
     // 保存配置到文件
-    save_config(&state, &app)
+    save_config(state, app)
         .await
         .map_err(|e| format!("保存配置失败: {}", e))?;
 
@@ -280,7 +289,10 @@ pub async fn get_current_window_size(app: tauri::AppHandle) -> Result<serde_json
             let height = logical_size.height.round() as u32;
 
             // 验证并调整尺寸到有效范围
-            let (clamped_width, clamped_height) = crate::constants::window::clamp_window_size(width as f64, height as f64);
+            let (clamped_width, clamped_height) = match app.state::<AppState>().config.lock() {
+                Ok(config) => config.ui_config.window_config.clamp_size(width as f64, height as f64),
+                Err(_) => crate::constants::window::clamp_window_size(width as f64, height as f64),
+            };
             let final_width = clamped_width as u32;
             let final_height = clamped_height as u32;
 
@@ -789,6 +801,50 @@ pub async fn get_config_file_path(app: AppHandle) -> Result<String, String> {
     Ok(normalized_path)
 }
 
+/// 配置文件信息（路径、大小、最后修改时间），用于确认配置是否正常写入
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ConfigFileInfo {
+    /// 配置文件绝对路径（save_config 的写入目标）
+    pub path: String,
+    /// 文件是否存在（首次启动且从未保存时不存在）
+    pub exists: bool,
+    /// 文件大小（字节）
+    pub size_bytes: Option<u64>,
+    /// 最后修改时间（RFC3339）
+    pub modified_at: Option<String>,
+    /// 滚动备份文件路径（存在时返回）
+    pub backup_path: Option<String>,
+}
+
+/// 获取配置文件的路径、大小与最后修改时间
+#[tauri::command]
+pub async fn get_config_file_info(app: AppHandle) -> Result<ConfigFileInfo, String> {
+    let config_path = crate::config::get_config_path(&app)
+        .map_err(|e| format!("获取配置文件路径失败: {}", e))?;
+    let absolute_path = if config_path.is_absolute() {
+        config_path
+    } else {
+        std::env::current_dir()
+            .map_err(|e| format!("获取配置文件路径失败: {}", e))?
+            .join(&config_path)
+    };
+
+    let metadata = std::fs::metadata(&absolute_path).ok();
+    let modified_at = metadata
+        .as_ref()
+        .and_then(|m| m.modified().ok())
+        .map(|t| chrono::DateTime::<chrono::Local>::from(t).to_rfc3339());
+    let backup = crate::config::backup_path(&absolute_path);
+
+    Ok(ConfigFileInfo {
+        path: normalize_path_display(&absolute_path),
+        exists: metadata.is_some(),
+        size_bytes: metadata.as_ref().map(|m| m.len()),
+        modified_at,
+        backup_path: backup.exists().then(|| normalize_path_display(&backup)),
+    })
+}
+
 /// 跨平台路径显示规范化
 fn normalize_path_display(path: &std::path::Path) -> String {
     // 如果文件存在，尝试获取规范路径
@@ -907,3 +963,9 @@ pub async fn reset_shortcuts_to_default(
 
     Ok(())
 }
+
+/// 检查路径所在磁盘是否有足够空间（更新下载、重建索引前由前端预检）
+#[tauri::command]
+pub async fn check_disk_space(path: String, required_bytes: u64) -> Result<(), String> {
+    crate::utils::disk::check_free_space(std::path::Path::new(&path), required_bytes)
+}
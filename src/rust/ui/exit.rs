@@ -137,6 +137,9 @@ pub async fn handle_system_exit_request(
 
 /// 执行实际的退出操作
 async fn perform_exit(app: AppHandle) -> Result<(), String> {
+    // 停止监听与上传，落盘索引状态
+    crate::ui::exit_handler::run_graceful_shutdown().await;
+
     // 关闭所有窗口
     if let Some(window) = app.get_webview_window("main") {
         let _ = window.close();
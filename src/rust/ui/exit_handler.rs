@@ -1,7 +1,13 @@
 use crate::config::AppState;
+use crate::constants::app::SHUTDOWN_GRACE_SECS;
 use crate::log_important;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
 use tauri::{AppHandle, Manager};
 
+/// 退出前是否执行优雅关闭（由 setup_exit_handlers 启用）
+static GRACEFUL_SHUTDOWN_ENABLED: AtomicBool = AtomicBool::new(false);
+
 /// 处理应用退出请求（从前端快捷键调用）
 pub async fn handle_exit_request_internal(app_handle: AppHandle) -> Result<bool, String> {
     let state = app_handle.state::<AppState>();
@@ -15,13 +21,25 @@ pub async fn handle_exit_request_internal(app_handle: AppHandle) -> Result<bool,
     ).await
 }
 
-/// 设置应用退出处理器（保留向后兼容性）
+/// 设置应用退出处理器：启用退出前的优雅关闭
 pub fn setup_exit_handlers(_app_handle: &AppHandle) -> Result<(), String> {
-    log_important!(info, "✅ 应用退出处理器已设置（前端快捷键处理）");
+    GRACEFUL_SHUTDOWN_ENABLED.store(true, Ordering::SeqCst);
+    log_important!(info, "✅ 应用退出处理器已设置（前端快捷键处理，退出前优雅关闭）");
     Ok(())
 }
 
-/// 清理退出处理器（空操作，保持接口一致性）
+/// 清理退出处理器
 pub fn cleanup_exit_handlers(_app_handle: &AppHandle) {
-    log_important!(info, "应用退出处理器无需清理");
+    GRACEFUL_SHUTDOWN_ENABLED.store(false, Ordering::SeqCst);
+    log_important!(info, "应用退出处理器已清理");
+}
+
+/// 退出前的优雅关闭：停止文件监听、等待当前上传批次完成并落盘索引状态
+/// 只执行一次，重复调用（如连续触发退出）直接返回
+pub async fn run_graceful_shutdown() {
+    if !GRACEFUL_SHUTDOWN_ENABLED.swap(false, Ordering::SeqCst) {
+        return;
+    }
+    log_important!(info, "🛑 退出前清理：停止文件监听并等待上传完成（最多 {} 秒）", SHUTDOWN_GRACE_SECS);
+    crate::mcp::tools::acemcp::mcp::shutdown(Duration::from_secs(SHUTDOWN_GRACE_SECS)).await;
 } 
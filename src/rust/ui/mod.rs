@@ -7,6 +7,7 @@ pub mod updater;
 pub mod exit;
 pub mod window_events;
 pub mod exit_handler;
+pub mod tray;
 
 pub use commands::*;
 pub use window::*;
@@ -16,3 +17,4 @@ pub use updater::*;
 pub use exit::*;
 pub use window_events::*;
 pub use exit_handler::*;
+pub use tray::*;
@@ -0,0 +1,147 @@
+use crate::config::{AppState, save_config};
+use crate::constants::validation;
+use crate::log_important;
+use tauri::menu::{Menu, MenuItem};
+use tauri::tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent};
+use tauri::{AppHandle, Manager, State};
+
+/// 系统托盘图标 ID
+const TRAY_ID: &str = "sanshu-tray";
+const TRAY_MENU_SHOW: &str = "tray_show";
+const TRAY_MENU_QUIT: &str = "tray_quit";
+
+/// 确保系统托盘图标已创建（首次隐藏到托盘时按需创建）
+pub fn ensure_tray(app: &AppHandle) -> Result<(), String> {
+    if app.tray_by_id(TRAY_ID).is_some() {
+        return Ok(());
+    }
+
+    let show_item = MenuItem::with_id(app, TRAY_MENU_SHOW, "显示主窗口", true, None::<&str>)
+        .map_err(|e| format!("创建托盘菜单失败: {}", e))?;
+    let quit_item = MenuItem::with_id(app, TRAY_MENU_QUIT, "退出", true, None::<&str>)
+        .map_err(|e| format!("创建托盘菜单失败: {}", e))?;
+    let menu = Menu::with_items(app, &[&show_item, &quit_item])
+        .map_err(|e| format!("创建托盘菜单失败: {}", e))?;
+
+    let mut builder = TrayIconBuilder::with_id(TRAY_ID)
+        .tooltip("三术")
+        .menu(&menu)
+        .show_menu_on_left_click(false)
+        .on_menu_event(|app, event| match event.id.as_ref() {
+            TRAY_MENU_SHOW => restore_from_tray(app),
+            TRAY_MENU_QUIT => {
+                // 显式退出：沿用原有退出流程
+                let app = app.clone();
+                tauri::async_runtime::spawn(async move {
+                    if let Err(e) = crate::ui::exit::force_exit_app(app).await {
+                        log_important!(error, "从托盘退出失败: {}", e);
+                    }
+                });
+            }
+            _ => {}
+        })
+        .on_tray_icon_event(|tray, event| {
+            if let TrayIconEvent::Click {
+                button: MouseButton::Left,
+                button_state: MouseButtonState::Up,
+                ..
+            } = event
+            {
+                restore_from_tray(tray.app_handle());
+            }
+        });
+
+    if let Some(icon) = app.default_window_icon() {
+        builder = builder.icon(icon.clone());
+    }
+
+    builder
+        .build(app)
+        .map_err(|e| format!("创建系统托盘失败: {}", e))?;
+    Ok(())
+}
+
+/// 隐藏主窗口到系统托盘
+/// 隐藏前先记录当前位置和尺寸，保证之后从托盘退出时窗口状态依然被保存
+pub async fn hide_to_tray(app: &AppHandle) -> Result<(), String> {
+    let window = app
+        .get_webview_window("main")
+        .ok_or_else(|| "主窗口不存在".to_string())?;
+
+    let state = app.state::<AppState>();
+    {
+        let mut config = state
+            .config
+            .lock()
+            .map_err(|e| format!("获取配置失败: {}", e))?;
+
+        if let Ok(position) = window.outer_position() {
+            if validation::is_valid_window_position(position.x, position.y) {
+                config.ui_config.window_config.position_x = Some(position.x);
+                config.ui_config.window_config.position_y = Some(position.y);
+            }
+        }
+
+        if let Ok(size) = window.inner_size() {
+            let scale_factor = window.scale_factor().unwrap_or(1.0);
+            let (width, height) = config.ui_config.window_config.clamp_size(
+                size.width as f64 / scale_factor,
+                size.height as f64 / scale_factor,
+            );
+            config.ui_config.window_config.update_current_size(width, height);
+        }
+    }
+    save_config(&state, app)
+        .await
+        .map_err(|e| format!("保存配置失败: {}", e))?;
+
+    ensure_tray(app)?;
+    window.hide().map_err(|e| format!("隐藏窗口失败: {}", e))?;
+    log_important!(info, "主窗口已隐藏到系统托盘");
+    Ok(())
+}
+
+/// 从系统托盘恢复主窗口
+pub fn restore_from_tray(app: &AppHandle) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
+
+#[tauri::command]
+pub async fn get_close_to_tray(state: State<'_, AppState>) -> Result<bool, String> {
+    let config = state
+        .config
+        .lock()
+        .map_err(|e| format!("获取配置失败: {}", e))?;
+    Ok(config.ui_config.close_to_tray)
+}
+
+#[tauri::command]
+pub async fn set_close_to_tray(
+    enabled: bool,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    {
+        let mut config = state
+            .config
+            .lock()
+            .map_err(|e| format!("获取配置失败: {}", e))?;
+        config.ui_config.close_to_tray = enabled;
+    }
+
+    save_config(&state, &app)
+        .await
+        .map_err(|e| format!("保存配置失败: {}", e))?;
+
+    // 提前创建托盘图标，方便用户确认托盘入口存在
+    if enabled {
+        ensure_tray(&app)?;
+    }
+
+    log::info!("用户切换关闭到托盘为: {} (已保存配置)", enabled);
+    Ok(())
+}
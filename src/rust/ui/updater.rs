@@ -1,9 +1,10 @@
 use tauri::{AppHandle, Emitter, State};
 use serde::{Deserialize, Serialize};
-use std::{fs, io::{Read, Write}, path::PathBuf, process::Command};
-use crate::config::AppState;
-use crate::network::{detect_geo_location, ProxyDetector, ProxyInfo, create_update_client, create_download_client};
-use crate::network::geo::GeoLocation;
+use std::{fs, io::{Read, Write}, path::PathBuf, process::Command, time::Duration};
+use crate::config::{AppState, save_config};
+use crate::utils::disk::check_free_space;
+use crate::network::{ProxyDetector, ProxyInfo, create_update_client, create_download_client};
+use crate::network::geo::{GeoFailureKind, detect_geo_location_detailed, detect_geo_location_full};
 
 // 关闭更新检测/下载安装：用于自定义构建，避免与官方版本耦合
 const UPDATES_ENABLED: bool = false;
@@ -52,6 +53,64 @@ pub struct UpdateInfo {
     pub download_url: String,
     /// 网络状态信息（新增）
     pub network_status: NetworkStatus,
+    /// 是否为节流期内返回的缓存结果（未实际发起网络检查）
+    #[serde(default)]
+    pub cached: bool,
+    /// 最新版本已被用户跳过（此时 available 为 false）
+    #[serde(default)]
+    pub skipped: bool,
+}
+
+/// 两次实际网络检查之间的最短间隔
+const UPDATE_CHECK_MIN_INTERVAL: Duration = Duration::from_secs(30);
+/// 连续快速重复检查时节流间隔的上限
+const UPDATE_CHECK_MAX_INTERVAL: Duration = Duration::from_secs(8 * 60);
+
+/// 最近一次实际执行的更新检查结果（失败同样缓存，避免失败后被频繁重试）
+struct UpdateCheckCache {
+    result: Result<UpdateInfo, String>,
+    checked_at: std::time::Instant,
+    /// 当前节流间隔：节流期内每次重复请求翻倍，实际检查后重置
+    interval: Duration,
+}
+
+static UPDATE_CHECK_CACHE: once_cell::sync::Lazy<std::sync::Mutex<Option<UpdateCheckCache>>> =
+    once_cell::sync::Lazy::new(|| std::sync::Mutex::new(None));
+
+/// 节流期内的重复请求使间隔翻倍（不超过上限）
+fn next_throttle_interval(current: Duration) -> Duration {
+    (current * 2).min(UPDATE_CHECK_MAX_INTERVAL)
+}
+
+/// 节流期内返回缓存结果（成功或失败），并按指数退避延长节流间隔
+fn throttled_update_info() -> Option<Result<UpdateInfo, String>> {
+    let mut guard = UPDATE_CHECK_CACHE.lock().ok()?;
+    let cache = guard.as_mut()?;
+    let elapsed = cache.checked_at.elapsed();
+    if elapsed >= cache.interval {
+        return None;
+    }
+    cache.interval = next_throttle_interval(cache.interval);
+    log::info!(
+        "⏳ 更新检查过于频繁，返回 {}s 前的结果（节流间隔已延长至 {}s）",
+        elapsed.as_secs(),
+        cache.interval.as_secs()
+    );
+    Some(cache.result.clone().map(|mut info| {
+        info.cached = true;
+        info
+    }))
+}
+
+/// 记录一次实际检查的结果，并重置节流间隔
+fn cache_update_result(result: &Result<UpdateInfo, String>) {
+    if let Ok(mut guard) = UPDATE_CHECK_CACHE.lock() {
+        *guard = Some(UpdateCheckCache {
+            result: result.clone(),
+            checked_at: std::time::Instant::now(),
+            interval: UPDATE_CHECK_MIN_INTERVAL,
+        });
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -60,12 +119,145 @@ pub struct UpdateProgress {
     pub content_length: Option<u64>,
     pub downloaded: u64,
     pub percentage: f64,
+    /// 最近一段时间内的下载速度（字节/秒）
+    pub bytes_per_sec: f64,
+    /// 预计剩余时间（秒），总大小未知或速度为 0 时为 None
+    pub eta_secs: Option<u64>,
+    /// 下载是否已暂停
+    #[serde(default)]
+    pub paused: bool,
+}
+
+/// 更新下载的暂停/继续控制（同一时间只有一个更新下载）
+struct DownloadControl {
+    active: std::sync::atomic::AtomicBool,
+    paused: std::sync::atomic::AtomicBool,
+    resumed: tokio::sync::Notify,
+}
+
+impl DownloadControl {
+    /// 等待下载被继续（未暂停时立即返回）
+    async fn wait_resumed(&self) {
+        loop {
+            // 先注册通知再检查状态，避免错过检查与等待之间的 resume
+            let notified = self.resumed.notified();
+            if !self.paused.load(std::sync::atomic::Ordering::SeqCst) {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+static DOWNLOAD_CONTROL: once_cell::sync::Lazy<DownloadControl> = once_cell::sync::Lazy::new(|| DownloadControl {
+    active: std::sync::atomic::AtomicBool::new(false),
+    paused: std::sync::atomic::AtomicBool::new(false),
+    resumed: tokio::sync::Notify::new(),
+});
+
+/// 标记下载进行中，结束（含出错）时自动清除暂停状态
+struct ActiveDownloadGuard;
+
+impl ActiveDownloadGuard {
+    fn start() -> Self {
+        DOWNLOAD_CONTROL.paused.store(false, std::sync::atomic::Ordering::SeqCst);
+        DOWNLOAD_CONTROL.active.store(true, std::sync::atomic::Ordering::SeqCst);
+        Self
+    }
+}
+
+impl Drop for ActiveDownloadGuard {
+    fn drop(&mut self) {
+        DOWNLOAD_CONTROL.active.store(false, std::sync::atomic::Ordering::SeqCst);
+        DOWNLOAD_CONTROL.paused.store(false, std::sync::atomic::Ordering::SeqCst);
+    }
+}
+
+/// 暂停正在进行的更新下载（保留已下载的部分文件）
+#[tauri::command]
+pub fn pause_update_download() -> Result<(), String> {
+    if !DOWNLOAD_CONTROL.active.load(std::sync::atomic::Ordering::SeqCst) {
+        return Err("当前没有正在进行的更新下载".to_string());
+    }
+    DOWNLOAD_CONTROL.paused.store(true, std::sync::atomic::Ordering::SeqCst);
+    log::info!("⏸️ 已请求暂停更新下载");
+    Ok(())
+}
+
+/// 继续已暂停的更新下载（通过 Range 请求从断点续传）
+#[tauri::command]
+pub fn resume_update_download() -> Result<(), String> {
+    if !DOWNLOAD_CONTROL.active.load(std::sync::atomic::Ordering::SeqCst) {
+        return Err("当前没有正在进行的更新下载".to_string());
+    }
+    DOWNLOAD_CONTROL.paused.store(false, std::sync::atomic::Ordering::SeqCst);
+    DOWNLOAD_CONTROL.resumed.notify_waiters();
+    log::info!("▶️ 已请求继续更新下载");
+    Ok(())
+}
+
+/// 下载速度统计的滑动窗口长度
+const SPEED_WINDOW: std::time::Duration = std::time::Duration::from_secs(3);
+
+/// 基于滑动窗口的下载速度计算器
+/// 只统计最近 SPEED_WINDOW 内的数据块，避免下载初期或网络波动后速度长期失真
+struct DownloadSpeedMeter {
+    samples: std::collections::VecDeque<(std::time::Instant, u64)>,
+    window_bytes: u64,
+}
+
+impl DownloadSpeedMeter {
+    fn new() -> Self {
+        Self {
+            samples: std::collections::VecDeque::new(),
+            window_bytes: 0,
+        }
+    }
+
+    /// 记录一个数据块并返回当前速度（字节/秒）
+    fn record(&mut self, bytes: u64) -> f64 {
+        let now = std::time::Instant::now();
+        self.samples.push_back((now, bytes));
+        self.window_bytes += bytes;
+
+        // 保留至少两个样本，保证窗口有时间跨度
+        while self.samples.len() > 2 {
+            match self.samples.front() {
+                Some((t, b)) if now.duration_since(*t) > SPEED_WINDOW => {
+                    self.window_bytes -= *b;
+                    self.samples.pop_front();
+                }
+                _ => break,
+            }
+        }
+
+        let (first_time, first_bytes) = match self.samples.front() {
+            Some(s) => *s,
+            None => return 0.0,
+        };
+        let elapsed = now.duration_since(first_time).as_secs_f64();
+        if elapsed <= 0.0 {
+            return 0.0;
+        }
+        // 第一个样本的字节在窗口起点之前已到达，不计入速度
+        (self.window_bytes - first_bytes) as f64 / elapsed
+    }
 }
 
 /// 检查是否有可用更新
+///
+/// 距上次实际检查不足节流间隔时直接返回缓存结果（包括失败），force=true 时跳过节流
 #[tauri::command]
-pub async fn check_for_updates(app: AppHandle, state: State<'_, AppState>) -> Result<UpdateInfo, String> {
-    if !UPDATES_ENABLED {
+pub async fn check_for_updates(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    force: Option<bool>,
+) -> Result<UpdateInfo, String> {
+    let safe_mode = crate::app::safe_mode::is_safe_mode();
+    if safe_mode {
+        log::warn!("🛟 安全模式下跳过更新检查");
+    }
+    if !UPDATES_ENABLED || safe_mode {
         let current_version = app.package_info().version.to_string();
         return Ok(UpdateInfo {
             available: false,
@@ -74,18 +266,42 @@ pub async fn check_for_updates(app: AppHandle, state: State<'_, AppState>) -> Re
             release_notes: String::new(),
             download_url: String::new(),
             network_status: NetworkStatus::default(),
+            cached: false,
+            skipped: false,
         });
     }
 
+    let skipped_version = state
+        .config
+        .lock()
+        .ok()
+        .and_then(|config| config.ui_config.skipped_update_version.clone());
+
+    if !force.unwrap_or(false) {
+        if let Some(result) = throttled_update_info() {
+            return result.map(|info| apply_skipped_version(info, skipped_version.as_deref()));
+        }
+    }
+
+    let result = fetch_update_info(&app, &state).await;
+    cache_update_result(&result);
+
+    let update_info = apply_skipped_version(result?, skipped_version.as_deref());
+    log::info!("✅ 更新检查完成: {:?}", update_info);
+    Ok(update_info)
+}
+
+/// 实际发起网络请求检查最新版本
+async fn fetch_update_info(app: &AppHandle, state: &State<'_, AppState>) -> Result<UpdateInfo, String> {
     log::info!("🔍 开始检查更新");
 
     // 第一步：检测地理位置（用于网络状态展示）
-    let geo_info = detect_geo_location_full().await;
+    let geo_info = detect_geo_location_full(geo_timeout(state)).await;
     log::info!("🌍 地理位置检测完成: country={}, city={:?}",
         geo_info.country, geo_info.city);
 
     // 第二步：智能代理检测和配置
-    let proxy_info = detect_and_configure_proxy(&state).await;
+    let proxy_info = detect_and_configure_proxy(state).await;
 
     // 构建网络状态信息
     let mut network_status = NetworkStatus {
@@ -126,7 +342,21 @@ pub async fn check_for_updates(app: AppHandle, state: State<'_, AppState>) -> Re
 
     if !response.status().is_success() {
         let status = response.status();
-        let error_msg = if status == 403 {
+        let header = |name: &str| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.to_string())
+        };
+        let rate_limit_msg = github_rate_limit_message(
+            status.as_u16(),
+            header("x-ratelimit-remaining").as_deref(),
+            header("x-ratelimit-reset").as_deref(),
+        );
+        let error_msg = if let Some(msg) = rate_limit_msg {
+            msg
+        } else if status == 403 {
             "网络请求受限，请手动下载最新版本".to_string()
         } else if status == 404 {
             "网络连接异常，请检查网络后重试".to_string()
@@ -178,19 +408,105 @@ pub async fn check_for_updates(app: AppHandle, state: State<'_, AppState>) -> Re
     log::info!("🔄 版本比较结果 - 有更新: {}", has_update);
 
     // 获取实际的下载URL（从assets中找到对应平台的文件）
-    let download_url = get_platform_download_url(&release)?;
-
-    let update_info = UpdateInfo {
+    let prefer_installer = state
+        .config
+        .lock()
+        .map(|config| config.ui_config.prefer_installer_update)
+        .unwrap_or(false);
+    let download_url = get_platform_download_url(&release, prefer_installer)?;
+
+    Ok(UpdateInfo {
         available: has_update,
         current_version,
         latest_version,
         release_notes: release["body"].as_str().unwrap_or("").to_string(),
         download_url,
         network_status,
-    };
+        cached: false,
+        skipped: false,
+    })
+}
 
-    log::info!("✅ 更新检查完成: {:?}", update_info);
-    Ok(update_info)
+/// 最新版本恰好是用户跳过的版本时不再提示更新，但仍保留真实的 latest_version；
+/// 比跳过版本更新的版本照常提示
+fn apply_skipped_version(mut info: UpdateInfo, skipped: Option<&str>) -> UpdateInfo {
+    let is_skipped = skipped
+        .map(|v| v.trim().trim_start_matches('v'))
+        .filter(|v| !v.is_empty())
+        .map(|v| !compare_versions(&info.latest_version, v) && !compare_versions(v, &info.latest_version))
+        .unwrap_or(false);
+    if info.available && is_skipped {
+        log::info!("⏭️ 最新版本 {} 已被跳过，不提示更新", info.latest_version);
+        info.available = false;
+        info.skipped = true;
+    }
+    info
+}
+
+/// 跳过指定的更新版本
+#[tauri::command]
+pub async fn skip_update_version(
+    version: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    let version = version.trim().trim_start_matches('v').to_string();
+    if version.is_empty() {
+        return Err("版本号不能为空".to_string());
+    }
+    {
+        let mut config = state
+            .config
+            .lock()
+            .map_err(|e| format!("获取配置失败: {}", e))?;
+        config.ui_config.skipped_update_version = Some(version.clone());
+    }
+
+    save_config(&state, &app)
+        .await
+        .map_err(|e| format!("保存配置失败: {}", e))?;
+
+    log::info!("⏭️ 已跳过更新版本: {}", version);
+    Ok(())
+}
+
+/// 清除已跳过的更新版本，恢复正常提示
+#[tauri::command]
+pub async fn clear_skipped_update(state: State<'_, AppState>, app: AppHandle) -> Result<(), String> {
+    {
+        let mut config = state
+            .config
+            .lock()
+            .map_err(|e| format!("获取配置失败: {}", e))?;
+        config.ui_config.skipped_update_version = None;
+    }
+
+    save_config(&state, &app)
+        .await
+        .map_err(|e| format!("保存配置失败: {}", e))?;
+
+    log::info!("已清除跳过的更新版本");
+    Ok(())
+}
+
+/// 识别 GitHub API 限流（未认证请求每小时 60 次）
+/// 403/429 且剩余次数为 0 时返回带重置时间的提示；其他 403（如被拦截）返回 None，沿用原有提示
+fn github_rate_limit_message(status: u16, remaining: Option<&str>, reset: Option<&str>) -> Option<String> {
+    if status != 403 && status != 429 {
+        return None;
+    }
+    let exhausted = remaining.map(|r| r.trim() == "0").unwrap_or(status == 429);
+    if !exhausted {
+        return None;
+    }
+    let reset_at = reset
+        .and_then(|r| r.trim().parse::<i64>().ok())
+        .and_then(|ts| chrono::DateTime::from_timestamp(ts, 0))
+        .map(|t| t.with_timezone(&chrono::Local).format("%H:%M").to_string());
+    Some(match reset_at {
+        Some(at) => format!("GitHub 限流，请于 {} 后重试", at),
+        None => "GitHub 限流，请稍后重试".to_string(),
+    })
 }
 
 /// 简单的版本比较函数
@@ -220,16 +536,19 @@ pub async fn download_and_install_update(app: AppHandle, state: State<'_, AppSta
     if !UPDATES_ENABLED {
         return Err("更新已禁用".to_string());
     }
+    if crate::app::safe_mode::is_safe_mode() {
+        return Err("安全模式下已禁用更新".to_string());
+    }
 
     log::info!("🚀 开始下载和安装更新");
 
     // 首先检查更新信息
     log::info!("🔍 重新检查更新信息");
-    let update_info = check_for_updates(app.clone(), state.clone()).await?;
+    let update_info = check_for_updates(app.clone(), state.clone(), None).await?;
 
     log::info!("📊 更新信息: {:?}", update_info);
 
-    if !update_info.available {
+    if !update_info.available && !update_info.skipped {
         let error_msg = "没有可用的更新".to_string();
         log::warn!("⚠️ {}", error_msg);
         return Err(error_msg);
@@ -271,6 +590,75 @@ pub async fn get_current_version(app: AppHandle) -> Result<String, String> {
     Ok(app.package_info().version.to_string())
 }
 
+/// 自动更新能力检查结果
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SelfUpdateCapability {
+    /// 是否可以自动更新（否则前端应引导手动下载）
+    pub can_update: bool,
+    /// 更新需要写入的安装目录
+    pub install_dir: Option<String>,
+    /// 不可自动更新的原因
+    pub reason: Option<String>,
+}
+
+/// 自动更新需要写入的目录：macOS 为 .app 所在目录（替换整个应用包），其他平台为可执行文件所在目录
+fn self_update_target_dir() -> Result<PathBuf, String> {
+    let current_exe = std::env::current_exe()
+        .map_err(|e| format!("无法获取当前可执行文件路径: {}", e))?;
+    if cfg!(target_os = "macos") {
+        if let Ok(bundle) = resolve_macos_target_bundle(&current_exe) {
+            if let Some(parent) = bundle.parent() {
+                return Ok(parent.to_path_buf());
+            }
+        }
+    }
+    current_exe
+        .parent()
+        .map(|p| p.to_path_buf())
+        .ok_or_else(|| "无法获取应用程序目录".to_string())
+}
+
+/// 通过创建并删除临时文件探测目录是否可写（只读挂载、权限不足时返回原因）
+fn probe_dir_writable(dir: &std::path::Path) -> Result<(), String> {
+    let probe = dir.join(format!(".sanshu-write-test-{}", std::process::id()));
+    match fs::OpenOptions::new().write(true).create_new(true).open(&probe) {
+        Ok(_) => {
+            let _ = fs::remove_file(&probe);
+            Ok(())
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+            Err("安装目录不可写，需要管理员权限".to_string())
+        }
+        Err(e) => Err(format!("安装目录不可写: {}", e)),
+    }
+}
+
+/// 检查当前安装位置能否自动更新，供前端决定显示"自动更新"还是"手动下载"
+#[tauri::command]
+pub fn can_self_update() -> Result<SelfUpdateCapability, String> {
+    let unavailable = |install_dir: Option<String>, reason: &str| SelfUpdateCapability {
+        can_update: false,
+        install_dir,
+        reason: Some(reason.to_string()),
+    };
+    if !UPDATES_ENABLED {
+        return Ok(unavailable(None, "更新已禁用"));
+    }
+    if crate::app::safe_mode::is_safe_mode() {
+        return Ok(unavailable(None, "安全模式下已禁用更新"));
+    }
+
+    let dir = self_update_target_dir()?;
+    let install_dir = Some(dir.to_string_lossy().to_string());
+    match probe_dir_writable(&dir) {
+        Ok(()) => Ok(SelfUpdateCapability { can_update: true, install_dir, reason: None }),
+        Err(reason) => {
+            log::warn!("🔒 无法自动更新: dir={}, reason={}", dir.display(), reason);
+            Ok(unavailable(install_dir, &reason))
+        }
+    }
+}
+
 /// 重启应用以完成更新
 #[tauri::command]
 pub async fn restart_app(app: AppHandle) -> Result<(), String> {
@@ -278,7 +666,8 @@ pub async fn restart_app(app: AppHandle) -> Result<(), String> {
 }
 
 /// 获取当前平台对应的下载URL
-fn get_platform_download_url(release: &serde_json::Value) -> Result<String, String> {
+/// prefer_installer 为 true 时优先安装包（msi/exe/dmg/deb/rpm），否则优先压缩包（zip/tar.gz）
+fn get_platform_download_url(release: &serde_json::Value, prefer_installer: bool) -> Result<String, String> {
     let assets = release["assets"].as_array()
         .ok_or_else(|| "无法获取release assets".to_string())?;
 
@@ -307,7 +696,8 @@ fn get_platform_download_url(release: &serde_json::Value) -> Result<String, Stri
         return Err("不支持的平台".to_string());
     };
 
-    log::info!("🔍 查找平台 {} 的下载文件", platform);
+    let extensions = platform_asset_extensions(prefer_installer);
+    log::info!("🔍 查找平台 {} 的下载文件（按优先级接受扩展名: {:?}）", platform, extensions);
 
     // 列出所有可用的 assets
     for (i, asset) in assets.iter().enumerate() {
@@ -316,18 +706,10 @@ fn get_platform_download_url(release: &serde_json::Value) -> Result<String, Stri
         }
     }
 
-    // 查找对应平台的文件
-    for asset in assets {
-        if let Some(name) = asset["name"].as_str() {
-            log::info!("🔍 检查文件: {} (是否包含 '{}')", name, platform);
-            if name.contains(platform) {
-                if let Some(download_url) = asset["browser_download_url"].as_str() {
-                    log::info!("✅ 找到匹配的下载文件: {}", name);
-                    log::info!("🔗 下载URL: {}", download_url);
-                    return Ok(download_url.to_string());
-                }
-            }
-        }
+    if let Some((name, download_url)) = select_platform_asset(assets, platform, &extensions) {
+        log::info!("✅ 找到匹配的下载文件: {}", name);
+        log::info!("🔗 下载URL: {}", download_url);
+        return Ok(download_url.to_string());
     }
 
     // 如果找不到对应平台的文件，返回release页面URL作为fallback
@@ -336,6 +718,57 @@ fn get_platform_download_url(release: &serde_json::Value) -> Result<String, Stri
     Ok(release["html_url"].as_str().unwrap_or("").to_string())
 }
 
+/// 当前平台可自动安装的资源扩展名（按优先级排列）
+fn platform_asset_extensions(prefer_installer: bool) -> Vec<&'static str> {
+    let (archives, installers): (&[&str], &[&str]) = if cfg!(target_os = "macos") {
+        (&[".tar.gz"], &[".dmg"])
+    } else if cfg!(target_os = "windows") {
+        (&[".zip"], &[".msi", ".exe"])
+    } else {
+        (&[".tar.gz"], &[".deb", ".rpm"])
+    };
+
+    if prefer_installer {
+        installers.iter().chain(archives).copied().collect()
+    } else {
+        archives.iter().chain(installers).copied().collect()
+    }
+}
+
+/// 文件名中是否以完整片段的形式包含平台标识（前后不能紧跟字母或数字）
+/// 避免 `linux-x86_64` 误匹配 `linux-x86_64abc` 之类的名称
+fn contains_platform_segment(name: &str, platform: &str) -> bool {
+    let is_boundary = |c: Option<char>| c.map(|c| !c.is_ascii_alphanumeric()).unwrap_or(true);
+    name.match_indices(platform).any(|(start, matched)| {
+        let before = name[..start].chars().next_back();
+        let after = name[start + matched.len()..].chars().next();
+        is_boundary(before) && is_boundary(after)
+    })
+}
+
+/// 从 assets 中挑选当前平台的下载文件，返回 (文件名, 下载URL)
+/// 只接受 extensions 中的扩展名；多个匹配时按扩展名优先级、再按文件名排序，保证结果确定
+fn select_platform_asset<'a>(
+    assets: &'a [serde_json::Value],
+    platform: &str,
+    extensions: &[&str],
+) -> Option<(&'a str, &'a str)> {
+    assets
+        .iter()
+        .filter_map(|asset| {
+            let name = asset["name"].as_str()?;
+            let url = asset["browser_download_url"].as_str()?;
+            if !contains_platform_segment(name, platform) {
+                return None;
+            }
+            let lower = name.to_lowercase();
+            let rank = extensions.iter().position(|ext| lower.ends_with(ext))?;
+            Some((rank, name, url))
+        })
+        .min_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.cmp(b.1)))
+        .map(|(_, name, url)| (name, url))
+}
+
 /// 实际的下载和安装实现
 async fn download_and_install_update_impl(
     app: &AppHandle,
@@ -386,33 +819,99 @@ async fn download_and_install_update_impl(
     }
 
     let total_size = response.content_length();
+
+    // 下载文件与解压后的文件都会落在临时目录，按两倍大小预留空间
+    if let Some(total) = total_size {
+        check_free_space(&temp_dir, total.saturating_mul(2))?;
+    }
+
     let mut downloaded = 0u64;
+    let mut speed_meter = DownloadSpeedMeter::new();
     let mut file = fs::File::create(&file_path)
         .map_err(|e| format!("创建文件失败: {}", e))?;
+    let _active_download = ActiveDownloadGuard::start();
 
-    // 下载并报告进度
-    while let Some(chunk) = response.chunk().await
-        .map_err(|e| format!("下载数据失败: {}", e))? {
+    let percentage_of = |downloaded: u64| match total_size {
+        Some(total) if total > 0 => (downloaded as f64 / total as f64) * 100.0,
+        _ => 0.0,
+    };
 
-        file.write_all(&chunk)
-            .map_err(|e| format!("写入文件失败: {}", e))?;
+    loop {
+        // 下载并报告进度；暂停时停止读取数据块
+        let mut paused = false;
+        while let Some(chunk) = response.chunk().await
+            .map_err(|e| format!("下载数据失败: {}", e))? {
 
-        downloaded += chunk.len() as u64;
+            file.write_all(&chunk)
+                .map_err(|e| format!("写入文件失败: {}", e))?;
 
-        let percentage = if let Some(total) = total_size {
-            (downloaded as f64 / total as f64) * 100.0
-        } else {
-            0.0
-        };
+            downloaded += chunk.len() as u64;
 
-        let progress = UpdateProgress {
-            chunk_length: chunk.len(),
+            let bytes_per_sec = speed_meter.record(chunk.len() as u64);
+            let eta_secs = match total_size {
+                Some(total) if bytes_per_sec > 0.0 => {
+                    Some((total.saturating_sub(downloaded) as f64 / bytes_per_sec).ceil() as u64)
+                }
+                _ => None,
+            };
+
+            let progress = UpdateProgress {
+                chunk_length: chunk.len(),
+                content_length: total_size,
+                downloaded,
+                percentage: percentage_of(downloaded),
+                bytes_per_sec,
+                eta_secs,
+                paused: false,
+            };
+
+            let _ = app.emit("update_download_progress", &progress);
+
+            if DOWNLOAD_CONTROL.paused.load(std::sync::atomic::Ordering::SeqCst) {
+                paused = true;
+                break;
+            }
+        }
+        if !paused {
+            break;
+        }
+
+        // 暂停：断开当前连接释放带宽，已下载的部分保留在临时文件中
+        drop(response);
+        file.flush().map_err(|e| format!("写入文件失败: {}", e))?;
+        log::info!("⏸️ 更新下载已暂停: 已下载 {} 字节", downloaded);
+        let _ = app.emit("update_download_progress", &UpdateProgress {
+            chunk_length: 0,
             content_length: total_size,
             downloaded,
-            percentage,
-        };
+            percentage: percentage_of(downloaded),
+            bytes_per_sec: 0.0,
+            eta_secs: None,
+            paused: true,
+        });
+
+        DOWNLOAD_CONTROL.wait_resumed().await;
+        log::info!("▶️ 继续更新下载: 从 {} 字节处续传", downloaded);
+        speed_meter = DownloadSpeedMeter::new();
 
-        let _ = app.emit("update_download_progress", &progress);
+        response = client
+            .get(&update_info.download_url)
+            .header(reqwest::header::RANGE, format!("bytes={}-", downloaded))
+            .send()
+            .await
+            .map_err(|e| format!("下载请求失败: {}", e))?;
+
+        if response.status() == reqwest::StatusCode::PARTIAL_CONTENT {
+            continue;
+        }
+        if !response.status().is_success() {
+            return Err(format!("下载失败: HTTP {}", response.status()));
+        }
+        // 服务器不支持断点续传（返回完整内容），从头重新下载
+        log::warn!("⚠️ 服务器不支持断点续传，重新下载完整文件");
+        file = fs::File::create(&file_path)
+            .map_err(|e| format!("创建文件失败: {}", e))?;
+        downloaded = 0;
     }
 
     log::info!("✅ 文件下载完成: {}", file_path.display());
@@ -421,19 +920,29 @@ async fn download_and_install_update_impl(
     let _ = app.emit("update_install_started", ());
 
     // 根据平台执行不同的安装逻辑
-    install_update(&file_path).await?;
+    install_update(app, &file_path).await?;
 
     Ok(())
 }
 
+/// 读取地理位置检测超时配置（至少 1 秒）
+fn geo_timeout(state: &State<'_, AppState>) -> Duration {
+    let secs = state
+        .config
+        .lock()
+        .map(|config| config.proxy_config.geo_timeout_secs)
+        .unwrap_or(crate::network::geo::DEFAULT_GEO_TIMEOUT_SECS);
+    Duration::from_secs(secs.max(1))
+}
+
 /// 根据平台安装更新
-async fn install_update(file_path: &PathBuf) -> Result<(), String> {
+async fn install_update(app: &AppHandle, file_path: &PathBuf) -> Result<(), String> {
     log::info!("🔧 开始安装更新: {}", file_path.display());
 
     if cfg!(target_os = "macos") {
         install_macos_update(file_path).await
     } else if cfg!(target_os = "windows") {
-        install_windows_update(file_path).await
+        install_windows_update(app, file_path).await
     } else if cfg!(target_os = "linux") {
         install_linux_update(file_path).await
     } else {
@@ -454,14 +963,184 @@ async fn install_macos_update(file_path: &PathBuf) -> Result<(), String> {
     } else if file_name.ends_with(".dmg") {
         // DMG 文件需要挂载后复制
         log::info!("📦 处理 DMG 文件");
-        return Err("DMG 文件需要手动安装，请手动下载最新版本".to_string());
+        install_from_dmg(file_path).map_err(|e| {
+            log::error!("❌ DMG 自动安装失败: {}", e);
+            format!("DMG 自动安装失败（{}），请手动下载最新版本", e)
+        })
     } else {
         return Err("未知的文件格式，请手动下载最新版本".to_string());
     }
 }
 
+/// 挂载 DMG，将其中的 .app 覆盖到当前应用包，最后卸载镜像
+fn install_from_dmg(file_path: &PathBuf) -> Result<(), String> {
+    let current_exe = std::env::current_exe()
+        .map_err(|e| format!("无法获取当前可执行文件路径: {}", e))?;
+    let target_bundle = resolve_macos_target_bundle(&current_exe)?;
+    log::info!("📍 目标应用包: {}", target_bundle.display());
+
+    let mount_point = std::env::temp_dir().join(format!("sanshu_dmg_{}", std::process::id()));
+    fs::create_dir_all(&mount_point)
+        .map_err(|e| format!("创建挂载目录失败: {}", e))?;
+
+    let output = Command::new("hdiutil")
+        .arg("attach")
+        .arg(file_path)
+        .args(["-nobrowse", "-noautoopen", "-readonly", "-mountpoint"])
+        .arg(&mount_point)
+        .output()
+        .map_err(|e| format!("执行 hdiutil attach 失败: {}", e))?;
+    if !output.status.success() {
+        let _ = fs::remove_dir(&mount_point);
+        return Err(format!("挂载 DMG 失败: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    log::info!("💿 DMG 已挂载: {}", mount_point.display());
+
+    // 无论复制是否成功都要卸载镜像
+    let result = copy_app_from_mount(&mount_point, &target_bundle);
+    detach_dmg(&mount_point);
+    result
+}
+
+/// 根据当前可执行文件推导需要被替换的 .app 包路径
+/// 从隔离转移（App Translocation）的只读路径运行时，改为替换 /Applications 下的同名应用
+fn resolve_macos_target_bundle(current_exe: &std::path::Path) -> Result<PathBuf, String> {
+    let bundle = current_exe
+        .ancestors()
+        .find(|p| p.extension().map(|ext| ext == "app").unwrap_or(false))
+        .ok_or_else(|| "当前程序不在 .app 应用包中运行".to_string())?;
+
+    if bundle.to_string_lossy().contains("/AppTranslocation/") {
+        let name = bundle.file_name().ok_or_else(|| "无法获取应用包名称".to_string())?;
+        let target = PathBuf::from("/Applications").join(name);
+        log::info!("🔀 当前从隔离转移路径运行，安装到: {}", target.display());
+        return Ok(target);
+    }
+
+    Ok(bundle.to_path_buf())
+}
+
+/// 从已挂载的镜像复制 .app：先完整复制到临时位置，再与旧应用包交换，避免复制中断导致应用损坏
+fn copy_app_from_mount(mount_point: &PathBuf, target_bundle: &PathBuf) -> Result<(), String> {
+    let source_bundle = fs::read_dir(mount_point)
+        .map_err(|e| format!("读取 DMG 内容失败: {}", e))?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .find(|p| p.extension().map(|ext| ext == "app").unwrap_or(false))
+        .ok_or_else(|| "DMG 中未找到 .app 应用包".to_string())?;
+    log::info!("📦 DMG 中的应用包: {}", source_bundle.display());
+
+    let parent = target_bundle
+        .parent()
+        .ok_or_else(|| "无法获取应用包所在目录".to_string())?;
+    let name = target_bundle
+        .file_name()
+        .and_then(|n| n.to_str())
+        .ok_or_else(|| "无法获取应用包名称".to_string())?;
+    let staging = parent.join(format!(".{}.update", name));
+    let backup = parent.join(format!(".{}.old", name));
+    let _ = fs::remove_dir_all(&staging);
+    let _ = fs::remove_dir_all(&backup);
+
+    // ditto 会保留扩展属性与代码签名
+    let output = Command::new("ditto")
+        .arg(&source_bundle)
+        .arg(&staging)
+        .output()
+        .map_err(|e| format!("执行 ditto 失败: {}", e))?;
+    if !output.status.success() {
+        let _ = fs::remove_dir_all(&staging);
+        return Err(format!("复制应用包失败: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+
+    if target_bundle.exists() {
+        fs::rename(target_bundle, &backup).map_err(|e| {
+            let _ = fs::remove_dir_all(&staging);
+            format!("备份旧应用包失败: {}", e)
+        })?;
+    }
+
+    if let Err(e) = fs::rename(&staging, target_bundle) {
+        // 还原旧应用包
+        let _ = fs::rename(&backup, target_bundle);
+        let _ = fs::remove_dir_all(&staging);
+        return Err(format!("替换应用包失败: {}", e));
+    }
+
+    let _ = fs::remove_dir_all(&backup);
+    log::info!("✅ 应用包已更新: {}", target_bundle.display());
+    Ok(())
+}
+
+/// 卸载 DMG 镜像（普通卸载失败时强制卸载）
+fn detach_dmg(mount_point: &PathBuf) {
+    let detached = Command::new("hdiutil")
+        .arg("detach")
+        .arg(mount_point)
+        .arg("-quiet")
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false);
+
+    if !detached {
+        log::warn!("⚠️ 卸载 DMG 失败，尝试强制卸载: {}", mount_point.display());
+        let _ = Command::new("hdiutil")
+            .arg("detach")
+            .arg(mount_point)
+            .arg("-force")
+            .status();
+    }
+    let _ = fs::remove_dir(mount_point);
+}
+
+/// 安装成功但需要重启系统时的提示
+const REBOOT_REQUIRED_NOTE: &str = "更新已安装，需要重启系统后生效";
+
+/// 解析 MSI/EXE 静默安装的退出码
+/// 0/3010/1641 视为成功（后两者需要重启，返回提示），常见失败码给出具体原因，其余失败附带 stderr
+fn interpret_installer_exit_code(
+    kind: &str,
+    code: Option<i32>,
+    stderr: &[u8],
+) -> Result<Option<&'static str>, String> {
+    let stderr = String::from_utf8_lossy(stderr);
+    let stderr = stderr.trim();
+    let detail = if stderr.is_empty() {
+        String::new()
+    } else {
+        format!(": {}", stderr)
+    };
+
+    match code {
+        Some(0) => Ok(None),
+        // ERROR_SUCCESS_REBOOT_REQUIRED / ERROR_SUCCESS_REBOOT_INITIATED
+        Some(3010) | Some(1641) => Ok(Some(REBOOT_REQUIRED_NOTE)),
+        Some(1602) => Err(format!("{} 安装已被用户取消 (退出码 1602){}", kind, detail)),
+        Some(1603) => Err(format!(
+            "{} 安装失败 (退出码 1603)：安装过程中发生严重错误，请确认拥有管理员权限并已关闭正在运行的旧版本{}",
+            kind, detail
+        )),
+        Some(1618) => Err(format!(
+            "{} 安装失败 (退出码 1618)：另一个安装程序正在运行，请等待其完成后重试{}",
+            kind, detail
+        )),
+        Some(other) => Err(format!("{} 安装失败 (退出码 {}){}", kind, other, detail)),
+        None => Err(format!("{} 安装进程被异常终止{}", kind, detail)),
+    }
+}
+
+/// 根据安装程序的退出状态决定成功/失败，需要重启时通知前端
+fn handle_installer_output(app: &AppHandle, kind: &str, output: &std::process::Output) -> Result<(), String> {
+    let note = interpret_installer_exit_code(kind, output.status.code(), &output.stderr)?;
+    if let Some(note) = note {
+        log::info!("🔁 {} 安装完成，{}", kind, note);
+        let _ = app.emit("update_install_note", note);
+    }
+    Ok(())
+}
+
 /// Windows 安装逻辑
-async fn install_windows_update(file_path: &PathBuf) -> Result<(), String> {
+async fn install_windows_update(app: &AppHandle, file_path: &PathBuf) -> Result<(), String> {
     let file_name = file_path.file_name()
         .and_then(|n| n.to_str())
         .unwrap_or("");
@@ -478,11 +1157,7 @@ async fn install_windows_update(file_path: &PathBuf) -> Result<(), String> {
             .output()
             .map_err(|e| format!("执行 MSI 安装失败: {}", e))?;
 
-        if !output.status.success() {
-            return Err(format!("MSI 安装失败: {}", String::from_utf8_lossy(&output.stderr)));
-        }
-
-        Ok(())
+        handle_installer_output(app, "MSI", &output)
     } else if file_name.ends_with(".exe") {
         // EXE 安装包
         log::info!("📦 执行 EXE 安装");
@@ -491,11 +1166,7 @@ async fn install_windows_update(file_path: &PathBuf) -> Result<(), String> {
             .output()
             .map_err(|e| format!("执行 EXE 安装失败: {}", e))?;
 
-        if !output.status.success() {
-            return Err(format!("EXE 安装失败: {}", String::from_utf8_lossy(&output.stderr)));
-        }
-
-        Ok(())
+        handle_installer_output(app, "EXE", &output)
     } else {
         Err("未知的文件格式，请手动下载最新版本".to_string())
     }
@@ -586,6 +1257,12 @@ async fn install_from_archive(file_path: &PathBuf) -> Result<(), String> {
         log::info!("  📄 {}", file.display());
     }
 
+    // 压缩包必须包含当前主程序，否则可能匹配到了错误的资源（如文档包），保留旧版本不做替换
+    if let Err(e) = verify_archive_contains_executable(&current_exe, &extracted_files) {
+        let _ = fs::remove_dir_all(&temp_dir);
+        return Err(e);
+    }
+
     // 根据平台执行不同的替换策略
     if cfg!(target_os = "windows") {
         // Windows: 使用批处理脚本延迟替换所有文件
@@ -601,6 +1278,29 @@ async fn install_from_archive(file_path: &PathBuf) -> Result<(), String> {
     Ok(())
 }
 
+/// 校验解压出的文件中包含与当前可执行文件同名的主程序
+fn verify_archive_contains_executable(current_exe: &PathBuf, files: &[PathBuf]) -> Result<(), String> {
+    let exe_name = current_exe.file_name()
+        .and_then(|n| n.to_str())
+        .ok_or_else(|| "无法获取当前可执行文件名".to_string())?;
+
+    // Windows 文件名不区分大小写
+    let found = files.iter().any(|file| {
+        file.file_name()
+            .and_then(|n| n.to_str())
+            .map(|name| if cfg!(target_os = "windows") { name.eq_ignore_ascii_case(exe_name) } else { name == exe_name })
+            .unwrap_or(false)
+    });
+
+    if found {
+        log::info!("✅ 压缩包包含主程序: {}", exe_name);
+        Ok(())
+    } else {
+        log::error!("❌ 压缩包中缺少主程序 {}，已取消替换", exe_name);
+        Err(format!("更新包中未找到主程序 {}，可能下载了错误的文件，已保留当前版本，请手动下载最新版本", exe_name))
+    }
+}
+
 /// 解压 tar.gz 文件
 fn extract_tar_gz(archive_path: &PathBuf, extract_to: &PathBuf) -> Result<Vec<PathBuf>, String> {
     log::info!("📦 解压 tar.gz 文件: {}", archive_path.display());
@@ -643,8 +1343,17 @@ fn collect_files_in_dir(dir: &PathBuf) -> Result<Vec<PathBuf>, String> {
         for entry in entries {
             let entry = entry.map_err(|e| format!("读取目录项失败: {}", e))?;
             let path = entry.path();
+            let file_type = entry
+                .file_type()
+                .map_err(|e| format!("读取文件类型失败 {}: {}", path.display(), e))?;
+
+            // 不跟随符号链接目录，避免链接成环导致无限递归或收集到解压目录之外的文件
+            if file_type.is_symlink() && path.is_dir() {
+                log::warn!("⚠️ 跳过符号链接目录: {}", path.display());
+                continue;
+            }
 
-            if path.is_dir() {
+            if file_type.is_dir() {
                 collect_recursive(&path, files)?;
             } else {
                 log::info!("📄 发现文件: {}", path.display());
@@ -728,27 +1437,19 @@ fn extract_zip(archive_path: &PathBuf, extract_to: &PathBuf) -> Result<Vec<PathB
     Ok(extracted_files)
 }
 
-/// Windows 平台替换所有文件（使用批处理脚本延迟替换）
+/// 生成 Windows 更新批处理脚本
 ///
-/// # 参数
-/// - `app_dir`: 应用程序目录（目标目录）
-/// - `extract_dir`: 解压临时目录（源目录）
-/// - `files`: 需要替换的文件列表（在 extract_dir 中的路径）
-fn replace_all_files_windows(
+/// 每个文件依次"备份 -> 复制（占用时重试）"。第 i 个文件失败时跳到 `rollback_i`，
+/// 从该文件开始倒序恢复已处理过的文件，尚未处理的文件与旧的 `.bak` 不会被触碰；
+/// 全部成功后删除本次生成的 `.bak`。
+fn build_windows_update_script(
     app_dir: &PathBuf,
     extract_dir: &PathBuf,
-    files: &[PathBuf]
-) -> Result<(), String> {
-    log::info!("🔧 Windows 平台：准备批处理脚本替换 {} 个文件", files.len());
-
-    // 获取当前可执行文件名（用于重启）
-    let current_exe = std::env::current_exe()
-        .map_err(|e| format!("无法获取当前可执行文件路径: {}", e))?;
-    let exe_name = current_exe.file_name()
-        .and_then(|n| n.to_str())
-        .unwrap_or("等一下.exe");
-
-    let script_path = app_dir.join("update_script.bat");
+    files: &[PathBuf],
+    exe_name: &str,
+) -> Result<Vec<String>, String> {
+    let result_log_path = app_dir.join(UPDATE_RESULT_LOG);
+    let log = format!("\"{}\"", result_log_path.display());
 
     // 构建批处理脚本内容
     let mut script_lines = Vec::new();
@@ -757,11 +1458,17 @@ fn replace_all_files_windows(
     script_lines.push("@echo off".to_string());
     script_lines.push("chcp 65001 >nul".to_string());
     script_lines.push("echo 正在更新 sanshu...".to_string());
+    script_lines.push(format!("echo [%date% %time%] 开始更新 > {}", log));
     script_lines.push("timeout /t 2 /nobreak >nul".to_string());
     script_lines.push("".to_string());
 
-    // 备份和复制每个文件
-    for file in files {
+    // 每个文件的回滚命令（按文件顺序），有备份的从 .bak 恢复，原本不存在的直接删除
+    let mut rollback_steps = Vec::new();
+    // 成功后需要删除的备份
+    let mut cleanup_lines = Vec::new();
+
+    // 备份和复制每个文件（文件被占用时重试，持续失败则回滚）
+    for (index, file) in files.iter().enumerate() {
         let file_name = file.file_name()
             .and_then(|n| n.to_str())
             .ok_or_else(|| format!("无法获取文件名: {}", file.display()))?;
@@ -772,29 +1479,65 @@ fn replace_all_files_windows(
         let backup_path = app_dir.join(format!("{}.bak", file_name));
         let backup_path_str = backup_path.display().to_string();
 
+        // 备份失败时当前文件尚未改动，只需回滚之前的文件
+        let previous_rollback = match index {
+            0 => "rollback".to_string(),
+            _ => format!("rollback_{}", index - 1),
+        };
+
         // 备份旧文件（如果存在）
-        script_lines.push(format!("if exist \"{}\" (", target_path_str));
-        script_lines.push(format!("    copy /y \"{}\" \"{}\" >nul", target_path_str, backup_path_str));
-        script_lines.push(")".to_string());
+        if target_path.exists() {
+            script_lines.push(format!("copy /y \"{}\" \"{}\" >nul", target_path_str, backup_path_str));
+            script_lines.push(format!("if errorlevel 1 goto {}", previous_rollback));
+            rollback_steps.push(format!("copy /y \"{}\" \"{}\" >nul", backup_path_str, target_path_str));
+            cleanup_lines.push(format!("del /f /q \"{}\" 2>nul", backup_path_str));
+        } else {
+            rollback_steps.push(format!("if exist \"{}\" del /f /q \"{}\" >nul", target_path_str, target_path_str));
+        }
 
-        // 复制新文件
-        script_lines.push(format!("copy /y \"{}\" \"{}\"", source_path, target_path_str));
-        script_lines.push(format!("if errorlevel 1 ("));
-        script_lines.push(format!("    echo 复制 {} 失败", file_name));
-        script_lines.push(format!(") else ("));
-        script_lines.push(format!("    echo 已更新: {}", file_name));
-        script_lines.push(format!(")"));
+        // 复制新文件，失败时等待后重试（杀毒软件扫描或旧进程尚未退出时文件可能被占用）
+        script_lines.push("set /a RETRY=0".to_string());
+        script_lines.push(format!(":copy_{}", index));
+        script_lines.push(format!("copy /y \"{}\" \"{}\" >nul", source_path, target_path_str));
+        script_lines.push(format!("if not errorlevel 1 goto copied_{}", index));
+        script_lines.push("set /a RETRY+=1".to_string());
+        script_lines.push(format!("if %RETRY% geq {} goto failed_{}", UPDATE_COPY_MAX_RETRIES, index));
+        script_lines.push(format!("echo {} 被占用，{} 秒后重试 (%RETRY%/{})...", file_name, UPDATE_COPY_RETRY_DELAY_SECS, UPDATE_COPY_MAX_RETRIES));
+        script_lines.push(format!("timeout /t {} /nobreak >nul", UPDATE_COPY_RETRY_DELAY_SECS));
+        script_lines.push(format!("goto copy_{}", index));
+        script_lines.push(format!(":failed_{}", index));
+        script_lines.push(format!("echo 复制 {} 失败，文件可能被其他程序占用 >> {}", file_name, log));
+        script_lines.push(format!("goto rollback_{}", index));
+        script_lines.push(format!(":copied_{}", index));
+        script_lines.push(format!("echo 已更新: {}", file_name));
+        script_lines.push(format!("echo 已更新: {} >> {}", file_name, log));
         script_lines.push("".to_string());
 
         log::info!("📝 添加文件替换命令: {} -> {}", source_path, target_path_str);
     }
 
-    // 清理临时目录
+    script_lines.push(format!("echo {} >> {}", UPDATE_RESULT_SUCCESS, log));
+
+    // 清理本次备份和临时目录
     script_lines.push("echo 清理临时文件...".to_string());
+    script_lines.extend(cleanup_lines);
     script_lines.push(format!("rmdir /s /q \"{}\" 2>nul", extract_dir.display()));
+    script_lines.push("goto restart".to_string());
     script_lines.push("".to_string());
 
-    // 重启应用
+    // 回滚：从失败的文件开始倒序恢复（标签依次贯穿到 rollback），保留解压目录便于排查
+    for (index, step) in rollback_steps.iter().enumerate().rev() {
+        script_lines.push(format!(":rollback_{}", index));
+        script_lines.push(step.clone());
+    }
+    script_lines.push(":rollback".to_string());
+    script_lines.push(format!("echo {} >> {}", UPDATE_RESULT_FAILED, log));
+    script_lines.push(format!("echo 更新失败，已恢复旧版本，详情见 {}", log));
+    script_lines.push("timeout /t 5 /nobreak >nul".to_string());
+    script_lines.push("".to_string());
+
+    // 重启应用（无论成功还是回滚）
+    script_lines.push(":restart".to_string());
     script_lines.push("echo 重启应用...".to_string());
     let restart_exe_path = app_dir.join(exe_name);
     script_lines.push(format!("start \"\" \"{}\"", restart_exe_path.display()));
@@ -803,6 +1546,32 @@ fn replace_all_files_windows(
     // 删除脚本自身
     script_lines.push("del \"%~f0\"".to_string());
 
+    Ok(script_lines)
+}
+
+/// Windows 平台替换所有文件（使用批处理脚本延迟替换）
+///
+/// # 参数
+/// - `app_dir`: 应用程序目录（目标目录）
+/// - `extract_dir`: 解压临时目录（源目录）
+/// - `files`: 需要替换的文件列表（在 extract_dir 中的路径）
+fn replace_all_files_windows(
+    app_dir: &PathBuf,
+    extract_dir: &PathBuf,
+    files: &[PathBuf]
+) -> Result<(), String> {
+    log::info!("🔧 Windows 平台：准备批处理脚本替换 {} 个文件", files.len());
+
+    // 获取当前可执行文件名（用于重启）
+    let current_exe = std::env::current_exe()
+        .map_err(|e| format!("无法获取当前可执行文件路径: {}", e))?;
+    let exe_name = current_exe.file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("等一下.exe");
+
+    let script_path = app_dir.join("update_script.bat");
+    let script_lines = build_windows_update_script(app_dir, extract_dir, files, exe_name)?;
+
     let script_content = script_lines.join("\r\n");
 
     // 写入脚本文件（使用 UTF-8 with BOM 以支持中文）
@@ -881,66 +1650,149 @@ fn replace_all_files_unix(app_dir: &PathBuf, files: &[PathBuf]) -> Result<(), St
     Ok(())
 }
 
-/// 智能代理检测和配置
+/// Windows 更新脚本的结果日志（位于应用目录，下次启动时读取）
+const UPDATE_RESULT_LOG: &str = "update_result.log";
+const UPDATE_RESULT_SUCCESS: &str = "RESULT=success";
+const UPDATE_RESULT_FAILED: &str = "RESULT=failed";
+/// 文件被占用时的复制重试次数与间隔
+const UPDATE_COPY_MAX_RETRIES: u32 = 10;
+const UPDATE_COPY_RETRY_DELAY_SECS: u32 = 2;
+
+/// 上次 Windows 更新脚本的执行结果
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LastUpdateResult {
+    /// 更新是否成功（失败时脚本已恢复旧版本）
+    pub success: bool,
+    /// 脚本日志内容
+    pub log: String,
+}
+
+/// 读取并清除上次更新脚本的结果日志（没有日志时返回 None）
+#[tauri::command]
+pub async fn get_last_update_result() -> Result<Option<LastUpdateResult>, String> {
+    let current_exe = std::env::current_exe()
+        .map_err(|e| format!("无法获取当前可执行文件路径: {}", e))?;
+    let log_path = match current_exe.parent() {
+        Some(dir) => dir.join(UPDATE_RESULT_LOG),
+        None => return Ok(None),
+    };
+    if !log_path.exists() {
+        return Ok(None);
+    }
+
+    let bytes = fs::read(&log_path)
+        .map_err(|e| format!("读取更新结果失败: {}", e))?;
+    let log = String::from_utf8_lossy(&bytes).trim().to_string();
+    // 只报告一次
+    let _ = fs::remove_file(&log_path);
+
+    let success = log.contains(UPDATE_RESULT_SUCCESS);
+    if success {
+        log::info!("✅ 上次更新脚本执行成功");
+    } else {
+        log::warn!("⚠️ 上次更新脚本执行失败，已恢复旧版本: {}", log);
+    }
+    Ok(Some(LastUpdateResult { success, log }))
+}
+
+/// 代理决策过程（供前端展示“为什么没走代理”）
 ///
-/// 根据配置和地理位置，自动检测并配置代理
+/// 与下载/检查更新实际使用的 `decide_proxy` 为同一份逻辑，解释不会与实际行为不一致
+#[derive(Debug, Clone, Serialize)]
+pub struct ProxyDecision {
+    /// 是否启用自动检测
+    pub auto_detect: bool,
+    /// 是否启用手动代理
+    pub manual_enabled: bool,
+    /// 是否仅在中国大陆使用代理
+    pub only_for_cn: bool,
+    /// 检测到的国家代码（未启用自动检测时为空，检测失败为 UNKNOWN）
+    pub detected_country: Option<String>,
+    /// 地理位置检测失败的原因（网络不可达 / 解析失败），成功时为空
+    pub geo_failure: Option<GeoFailureKind>,
+    /// 是否满足代理使用条件（only_for_cn 判定结果，未启用自动检测时为空）
+    pub proxy_condition_met: Option<bool>,
+    /// 是否检测到本地可用代理（未执行检测时为空）
+    pub local_proxy_found: Option<bool>,
+    /// 最终使用的代理（None 表示直连）
+    pub proxy: Option<ProxyInfo>,
+    /// 决策原因说明
+    pub reason: String,
+}
+
+/// 根据配置和地理位置决定使用代理还是直连，并记录决策过程
 ///
 /// # 工作流程
-/// 1. 读取代理配置
-/// 2. 如果启用自动检测：
+/// 1. 如果启用自动检测：
 ///    - 检测IP地理位置
 ///    - 如果在中国大陆且配置了仅CN使用代理，则检测本地代理
 ///    - 否则使用直连
-/// 3. 如果启用手动代理：
+/// 2. 如果启用手动代理：
 ///    - 直接使用配置的代理
-/// 4. 否则使用直连
-///
-/// # 返回值
-/// - `Some(ProxyInfo)`: 使用代理
-/// - `None`: 使用直连
-async fn detect_and_configure_proxy(state: &State<'_, AppState>) -> Option<ProxyInfo> {
-    // 读取代理配置
-    let proxy_config = {
-        let config = state.config.lock().ok()?;
-        config.proxy_config.clone()
-    };
-
+/// 3. 否则使用直连
+async fn decide_proxy(proxy_config: &crate::config::ProxyConfig) -> ProxyDecision {
     log::info!("📋 代理配置: auto_detect={}, enabled={}, only_for_cn={}",
         proxy_config.auto_detect, proxy_config.enabled, proxy_config.only_for_cn);
 
+    let mut decision = ProxyDecision {
+        auto_detect: proxy_config.auto_detect,
+        manual_enabled: proxy_config.enabled,
+        only_for_cn: proxy_config.only_for_cn,
+        detected_country: None,
+        geo_failure: None,
+        proxy_condition_met: None,
+        local_proxy_found: None,
+        proxy: None,
+        reason: String::new(),
+    };
+
     // 如果启用自动检测
     if proxy_config.auto_detect {
         log::info!("🔍 启用自动代理检测");
 
         // 检测地理位置
-        let country = detect_geo_location().await;
+        let country = match detect_geo_location_detailed(Duration::from_secs(proxy_config.geo_timeout_secs.max(1))).await {
+            Ok(geo) => geo.country,
+            Err(failure) => {
+                decision.geo_failure = Some(failure.kind);
+                "UNKNOWN".to_string()
+            }
+        };
         log::info!("🌍 检测到国家代码: {}", country);
 
-        // 判断是否需要使用代理
-        let should_use_proxy = if proxy_config.only_for_cn {
-            // 仅在中国大陆使用代理
-            country == "CN"
-        } else {
-            // 所有地区都尝试使用代理
-            true
-        };
+        // 判断是否需要使用代理：仅CN时要求位于中国大陆，否则所有地区都尝试使用代理
+        let should_use_proxy = !proxy_config.only_for_cn || country == "CN";
+        decision.detected_country = Some(country.clone());
+        decision.proxy_condition_met = Some(should_use_proxy);
 
         if should_use_proxy {
             log::info!("✅ 满足代理使用条件，开始检测本地代理");
 
             // 检测本地可用代理
-            if let Some(proxy_info) = ProxyDetector::detect_available_proxy().await {
+            let detected = ProxyDetector::detect_available_proxy().await;
+            decision.local_proxy_found = Some(detected.is_some());
+            if let Some(proxy_info) = detected {
                 log::info!("✅ 使用自动检测的代理: {}:{} ({})",
                     proxy_info.host, proxy_info.port, proxy_info.proxy_type);
-                return Some(proxy_info);
+                decision.reason = format!(
+                    "自动检测：{}，检测到本地代理 {}:{}，使用代理",
+                    if proxy_config.only_for_cn { "位于中国大陆" } else { "所有地区均尝试代理" },
+                    proxy_info.host,
+                    proxy_info.port
+                );
+                decision.proxy = Some(proxy_info);
             } else {
                 log::warn!("⚠️ 未检测到可用代理，使用直连");
-                return None;
+                decision.reason = "自动检测：满足代理使用条件，但未检测到本地可用代理，使用直连".to_string();
             }
         } else {
             log::info!("ℹ️ 不满足代理使用条件（非CN地区），使用直连");
-            return None;
+            decision.reason = match decision.geo_failure {
+                Some(kind) => format!("自动检测：已开启“仅中国大陆使用代理”，但地理位置检测失败（{}），使用直连", kind.reason()),
+                None => format!("自动检测：已开启“仅中国大陆使用代理”，当前地区为 {}，使用直连", country),
+            };
         }
+        return decision;
     }
 
     // 如果启用手动代理
@@ -954,105 +1806,167 @@ async fn detect_and_configure_proxy(state: &State<'_, AppState>) -> Option<Proxy
 
         let proxy_info = ProxyInfo::new(
             proxy_type,
-            proxy_config.host,
+            proxy_config.host.clone(),
             proxy_config.port,
         );
 
         log::info!("✅ 使用手动代理: {}:{} ({})",
             proxy_info.host, proxy_info.port, proxy_info.proxy_type);
 
-        return Some(proxy_info);
+        decision.reason = format!("未开启自动检测，使用手动配置的代理 {}:{}", proxy_info.host, proxy_info.port);
+        decision.proxy = Some(proxy_info);
+        return decision;
     }
 
     log::info!("ℹ️ 未启用代理，使用直连");
-    None
+    decision.reason = "未开启自动检测且未启用手动代理，使用直连".to_string();
+    decision
 }
 
-/// 检测完整的地理位置信息
+/// 智能代理检测和配置
 ///
-/// 与 `detect_geo_location` 不同，此函数返回完整的 GeoLocation 结构体
-/// 包含 IP、城市、国家等详细信息
-async fn detect_geo_location_full() -> GeoLocation {
-    log::info!("🌍 开始检测完整地理位置信息");
-
-    // 创建HTTP客户端，设置较短的超时时间
-    let client = match reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(5))
-        .build()
-    {
-        Ok(c) => c,
-        Err(e) => {
-            log::warn!("⚠️ 创建HTTP客户端失败: {}", e);
-            return GeoLocation {
-                ip: "unknown".to_string(),
-                city: None,
-                region: None,
-                country: "UNKNOWN".to_string(),
-                loc: None,
-                org: None,
-                postal: None,
-                timezone: None,
-            };
-        }
+/// 根据配置和地理位置，自动检测并配置代理
+///
+/// # 返回值
+/// - `Some(ProxyInfo)`: 使用代理
+/// - `None`: 使用直连
+async fn detect_and_configure_proxy(state: &State<'_, AppState>) -> Option<ProxyInfo> {
+    // 读取代理配置
+    let proxy_config = {
+        let config = state.config.lock().ok()?;
+        config.proxy_config.clone()
     };
 
-    // 请求 ipinfo.io API
-    match client
-        .get("https://ipinfo.io/json")
-        .send()
-        .await
-    {
-        Ok(response) => {
-            if !response.status().is_success() {
-                log::warn!("⚠️ IP地理位置检测请求失败: HTTP {}", response.status());
-                return GeoLocation {
-                    ip: "unknown".to_string(),
-                    city: None,
-                    region: None,
-                    country: "UNKNOWN".to_string(),
-                    loc: None,
-                    org: None,
-                    postal: None,
-                    timezone: None,
-                };
-            }
+    decide_proxy(&proxy_config).await.proxy
+}
 
-            // 解析JSON响应
-            match response.json::<GeoLocation>().await {
-                Ok(geo) => {
-                    log::info!("✅ 检测到地理位置: {} ({}) - IP: {}",
-                        geo.country,
-                        geo.city.as_deref().unwrap_or("未知城市"),
-                        geo.ip);
-                    geo
-                }
-                Err(e) => {
-                    log::warn!("⚠️ 解析地理位置信息失败: {}", e);
-                    GeoLocation {
-                        ip: "unknown".to_string(),
-                        city: None,
-                        region: None,
-                        country: "UNKNOWN".to_string(),
-                        loc: None,
-                        org: None,
-                        postal: None,
-                        timezone: None,
-                    }
-                }
-            }
-        }
-        Err(e) => {
-            log::warn!("⚠️ IP地理位置检测网络请求失败: {}", e);
-            GeoLocation {
-                ip: "unknown".to_string(),
-                city: None,
-                region: None,
-                country: "UNKNOWN".to_string(),
-                loc: None,
-                org: None,
-                postal: None,
-                timezone: None,
-            }
-        }
+/// 解释当前配置下检查更新/下载会走代理还是直连，以及原因
+#[tauri::command]
+pub async fn explain_proxy_decision(state: State<'_, AppState>) -> Result<ProxyDecision, String> {
+    let proxy_config = {
+        let config = state.config.lock().map_err(|e| format!("获取配置失败: {}", e))?;
+        config.proxy_config.clone()
+    };
+
+    Ok(decide_proxy(&proxy_config).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_skipped_version() {
+        let info = |latest: &str| UpdateInfo {
+            available: true,
+            current_version: "1.0.0".to_string(),
+            latest_version: latest.to_string(),
+            release_notes: String::new(),
+            download_url: String::new(),
+            network_status: NetworkStatus::default(),
+            cached: false,
+            skipped: false,
+        };
+        let skipped = apply_skipped_version(info("1.2.0"), Some("v1.2.0"));
+        assert!(!skipped.available && skipped.skipped);
+        assert_eq!(skipped.latest_version, "1.2.0");
+
+        let newer = apply_skipped_version(info("1.3.0"), Some("1.2.0"));
+        assert!(newer.available && !newer.skipped);
+        assert!(apply_skipped_version(info("1.2.0"), None).available);
+    }
+
+    #[test]
+    fn test_probe_dir_writable() {
+        let dir = std::env::temp_dir();
+        assert!(probe_dir_writable(&dir).is_ok());
+        assert!(fs::read_dir(&dir).unwrap().flatten().all(|e| {
+            !e.file_name().to_string_lossy().starts_with(&format!(".sanshu-write-test-{}", std::process::id()))
+        }));
+        assert!(probe_dir_writable(&dir.join("sanshu-missing-dir-probe")).is_err());
+    }
+
+    #[test]
+    fn test_github_rate_limit_message() {
+        assert!(github_rate_limit_message(403, Some("0"), Some("1700000000")).unwrap().starts_with("GitHub 限流，请于 "));
+        assert_eq!(github_rate_limit_message(429, None, None).as_deref(), Some("GitHub 限流，请稍后重试"));
+        // 仍有剩余次数的 403 不是限流
+        assert!(github_rate_limit_message(403, Some("12"), None).is_none());
+        assert!(github_rate_limit_message(403, None, None).is_none());
+        assert!(github_rate_limit_message(404, Some("0"), None).is_none());
+    }
+
+    #[test]
+    fn test_windows_update_script_rolls_back_only_processed_files() {
+        let app_dir = std::env::temp_dir().join(format!("sanshu-update-script-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&app_dir);
+        fs::create_dir_all(&app_dir).unwrap();
+        fs::write(app_dir.join("a.exe"), "old").unwrap();
+        fs::write(app_dir.join("c.dll"), "old").unwrap();
+        let extract_dir = app_dir.join("extract");
+        let files = vec![extract_dir.join("a.exe"), extract_dir.join("b.dll"), extract_dir.join("c.dll")];
+
+        let lines = build_windows_update_script(&app_dir, &extract_dir, &files, "a.exe").unwrap();
+        let pos = |needle: &str| lines.iter().position(|l| l == needle).unwrap();
+
+        // 第 2 个文件失败只回滚它自己和之前的文件，且按倒序恢复
+        assert!(lines.contains(&"goto rollback_1".to_string()));
+        assert!(pos(":rollback_2") < pos(":rollback_1"));
+        assert!(pos(":rollback_1") < pos(":rollback_0"));
+        assert!(pos(":rollback_0") < pos(":rollback"));
+        assert!(lines[pos(":rollback_1") + 1].contains("del /f /q"));
+        assert!(lines[pos(":rollback_0") + 1].contains("a.exe.bak"));
+
+        // 成功后删除本次生成的备份
+        let success = pos(&format!("echo {} >> \"{}\"", UPDATE_RESULT_SUCCESS, app_dir.join(UPDATE_RESULT_LOG).display()));
+        let cleanup: Vec<_> = lines[success..pos(":rollback_2")].iter().filter(|l| l.ends_with(".bak\" 2>nul")).collect();
+        assert_eq!(cleanup.len(), 2);
+
+        let _ = fs::remove_dir_all(&app_dir);
+    }
+
+    #[test]
+    fn test_next_throttle_interval() {
+        assert_eq!(next_throttle_interval(UPDATE_CHECK_MIN_INTERVAL), Duration::from_secs(60));
+        assert_eq!(next_throttle_interval(Duration::from_secs(400)), UPDATE_CHECK_MAX_INTERVAL);
+    }
+
+    #[test]
+    fn test_failed_check_is_throttled() {
+        cache_update_result(&Err("网络请求失败: 403".to_string()));
+        assert!(matches!(throttled_update_info(), Some(Err(e)) if e == "网络请求失败: 403"));
+        *UPDATE_CHECK_CACHE.lock().unwrap() = None;
+        assert!(throttled_update_info().is_none());
+    }
+
+    #[test]
+    fn test_select_platform_asset() {
+        let release: serde_json::Value = serde_json::json!({
+            "assets": [
+                { "name": "sanshu-docs-windows-x86_64-extra.pdf", "browser_download_url": "https://example.com/docs.pdf" },
+                { "name": "sanshu-windows-x86_64.zip.sha256", "browser_download_url": "https://example.com/zip.sha256" },
+                { "name": "sanshu-windows-x86_64.msi", "browser_download_url": "https://example.com/win.msi" },
+                { "name": "sanshu-windows-x86_64.zip", "browser_download_url": "https://example.com/win.zip" },
+                { "name": "sanshu-macos-x86_64.tar.gz", "browser_download_url": "https://example.com/mac-x64.tar.gz" },
+                { "name": "sanshu-macos-aarch64.tar.gz", "browser_download_url": "https://example.com/mac-arm.tar.gz" },
+                { "name": "sanshu-linux-x86_64abc.tar.gz", "browser_download_url": "https://example.com/bogus.tar.gz" },
+                { "name": "sanshu-linux-x86_64.deb", "browser_download_url": "https://example.com/linux.deb" }
+            ]
+        });
+        let assets = release["assets"].as_array().unwrap();
+
+        // 默认优先压缩包，校验文件与文档不会被选中
+        let pick = select_platform_asset(assets, "windows-x86_64", &[".zip", ".msi", ".exe"]);
+        assert_eq!(pick.map(|(name, _)| name), Some("sanshu-windows-x86_64.zip"));
+        // 优先安装包
+        let pick = select_platform_asset(assets, "windows-x86_64", &[".msi", ".exe", ".zip"]);
+        assert_eq!(pick.map(|(_, url)| url), Some("https://example.com/win.msi"));
+        // 架构不会互相误匹配
+        let pick = select_platform_asset(assets, "macos-aarch64", &[".tar.gz", ".dmg"]);
+        assert_eq!(pick.map(|(_, url)| url), Some("https://example.com/mac-arm.tar.gz"));
+        // 平台标识必须是完整片段
+        let pick = select_platform_asset(assets, "linux-x86_64", &[".tar.gz", ".deb", ".rpm"]);
+        assert_eq!(pick.map(|(name, _)| name), Some("sanshu-linux-x86_64.deb"));
+        assert!(select_platform_asset(assets, "linux-aarch64", &[".tar.gz"]).is_none());
     }
 }
@@ -10,6 +10,150 @@ pub struct WindowSizeUpdate {
     pub fixed: bool,
 }
 
+/// 显示器信息（物理像素）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MonitorInfo {
+    pub name: Option<String>,
+    /// 显示器范围
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    /// 工作区（去掉任务栏/Dock 后的可用区域）
+    pub work_x: i32,
+    pub work_y: i32,
+    pub work_width: u32,
+    pub work_height: u32,
+    pub scale_factor: f64,
+    pub is_primary: bool,
+}
+
+impl MonitorInfo {
+    fn from_monitor(monitor: &tauri::Monitor, is_primary: bool) -> Self {
+        let work_area = monitor.work_area();
+        Self {
+            name: monitor.name().cloned(),
+            x: monitor.position().x,
+            y: monitor.position().y,
+            width: monitor.size().width,
+            height: monitor.size().height,
+            work_x: work_area.position.x,
+            work_y: work_area.position.y,
+            work_width: work_area.size.width,
+            work_height: work_area.size.height,
+            scale_factor: monitor.scale_factor(),
+            is_primary,
+        }
+    }
+}
+
+/// 列出所有显示器：主显示器在前，其余按左上角坐标（x, y）排序，保证多次调用顺序一致
+pub fn list_monitors(app: &tauri::AppHandle) -> Result<Vec<MonitorInfo>, String> {
+    let primary = app
+        .primary_monitor()
+        .map_err(|e| format!("获取主显示器失败: {}", e))?;
+    let monitors = app
+        .available_monitors()
+        .map_err(|e| format!("获取显示器列表失败: {}", e))?;
+
+    let is_primary = |m: &tauri::Monitor| {
+        primary
+            .as_ref()
+            .map(|p| p.position() == m.position() && p.size() == m.size())
+            .unwrap_or(false)
+    };
+    let mut infos: Vec<MonitorInfo> = monitors
+        .iter()
+        .map(|m| MonitorInfo::from_monitor(m, is_primary(m)))
+        .collect();
+    infos.sort_by_key(|m| (!m.is_primary, m.x, m.y));
+    Ok(infos)
+}
+
+/// 获取可用显示器及其工作区，供前端选择居中目标与排查窗口位置问题
+#[tauri::command]
+pub async fn get_monitors(app: tauri::AppHandle) -> Result<Vec<MonitorInfo>, String> {
+    list_monitors(&app)
+}
+
+/// 将主窗口移动到当前显示器（窗口不在任何显示器上时使用主显示器）工作区的中央并限制尺寸，
+/// 用于窗口跑到屏幕外时的手动恢复；新的位置和尺寸会写入配置
+#[tauri::command]
+pub async fn center_main_window(state: State<'_, AppState>, app: tauri::AppHandle) -> Result<(), String> {
+    let window = app
+        .get_webview_window("main")
+        .ok_or_else(|| "主窗口不存在".to_string())?;
+
+    // 窗口在屏幕外时 current_monitor 返回 None，依次退回主显示器、任意显示器
+    let monitor = match window.current_monitor().ok().flatten() {
+        Some(m) => m,
+        None => match window.primary_monitor().ok().flatten() {
+            Some(m) => m,
+            None => window
+                .available_monitors()
+                .map_err(|e| format!("获取显示器列表失败: {}", e))?
+                .into_iter()
+                .next()
+                .ok_or_else(|| "未找到可用的显示器".to_string())?,
+        },
+    };
+    let work_area = monitor.work_area();
+    let scale_factor = monitor.scale_factor();
+
+    // 尺寸先按窗口约束限制，再保证不超出工作区
+    let current = window
+        .inner_size()
+        .map_err(|e| format!("获取窗口大小失败: {}", e))?;
+    let window_scale = window.scale_factor().unwrap_or(scale_factor);
+    let window_config = state
+        .config
+        .lock()
+        .map_err(|e| format!("获取配置失败: {}", e))?
+        .ui_config
+        .window_config
+        .clone();
+    let (width, height) = window_config.clamp_size(
+        current.width as f64 / window_scale,
+        current.height as f64 / window_scale,
+    );
+    let width = width.min(work_area.size.width as f64 / scale_factor);
+    let height = height.min(work_area.size.height as f64 / scale_factor);
+
+    let x = work_area.position.x + ((work_area.size.width as f64 - width * scale_factor) / 2.0).max(0.0) as i32;
+    let y = work_area.position.y + ((work_area.size.height as f64 - height * scale_factor) / 2.0).max(0.0) as i32;
+
+    window
+        .set_size(tauri::LogicalSize::new(width, height))
+        .map_err(|e| format!("设置窗口大小失败: {}", e))?;
+    window
+        .set_position(tauri::PhysicalPosition::new(x, y))
+        .map_err(|e| format!("设置窗口位置失败: {}", e))?;
+    let _ = window.unminimize();
+    let _ = window.show();
+    let _ = window.set_focus();
+
+    {
+        let mut config = state.config.lock().map_err(|e| format!("获取配置失败: {}", e))?;
+        config.ui_config.window_config.position_x = Some(x);
+        config.ui_config.window_config.position_y = Some(y);
+        config.ui_config.window_config.update_current_size(width, height);
+    }
+    save_config(&state, &app).await.map_err(|e| format!("保存配置失败: {}", e))?;
+
+    log::info!("窗口已居中: position=({}, {}), size={}x{}", x, y, width, height);
+    Ok(())
+}
+
+/// 切换主窗口置顶并持久化，下次启动时由 setup_application 重新应用
+#[tauri::command]
+pub async fn set_window_always_on_top(
+    enabled: bool,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<(), String> {
+    crate::ui::commands::persist_and_apply_always_on_top(enabled, &state, &app).await
+}
+
 #[tauri::command]
 pub async fn apply_window_constraints(state: State<'_, AppState>, app: tauri::AppHandle) -> Result<(), String> {
     let (window_config, always_on_top) = {
@@ -19,24 +163,25 @@ pub async fn apply_window_constraints(state: State<'_, AppState>, app: tauri::Ap
 
     if let Some(window) = app.get_webview_window("main") {
         // 设置窗口约束
+        let constraints = window_config.constraints();
         if let Err(e) = window.set_min_size(Some(tauri::LogicalSize::new(
-            window_config.min_width,
-            window_config.min_height,
+            constraints.min_width,
+            constraints.min_height,
         ))) {
             return Err(format!("设置最小窗口大小失败: {}", e));
         }
 
         if let Err(e) = window.set_max_size(Some(tauri::LogicalSize::new(
-            window_config.max_width,
-            window_config.max_height,
+            constraints.max_width,
+            constraints.max_height,
         ))) {
             return Err(format!("设置最大窗口大小失败: {}", e));
         }
 
         // 如果启用了自动调整大小，设置为合适的初始大小
         if window_config.auto_resize {
-            let initial_width = window_config.min_width;
-            let initial_height = (window_config.min_height + window_config.max_height) / 2.0;
+            let initial_width = constraints.min_width;
+            let initial_height = (constraints.min_height + constraints.max_height) / 2.0;
             
             if let Err(e) = window.set_size(tauri::LogicalSize::new(initial_width, initial_height)) {
                 return Err(format!("设置窗口大小失败: {}", e));
@@ -1,5 +1,5 @@
 use crate::config::{AppState, save_config};
-use crate::constants::{validation, window as window_constants};
+use crate::constants::validation;
 use crate::log_important;
 use tauri::{AppHandle, Manager, WindowEvent};
 
@@ -15,6 +15,23 @@ pub fn setup_window_event_listeners(app_handle: &AppHandle) {
                 api.prevent_close();
                 
                 let app_handle = app_handle_clone.clone();
+
+                // 启用“关闭到托盘”时只隐藏窗口，真正退出走托盘菜单/快捷键
+                let close_to_tray = app_handle
+                    .state::<AppState>()
+                    .config
+                    .lock()
+                    .map(|config| config.ui_config.close_to_tray)
+                    .unwrap_or(false);
+                if close_to_tray {
+                    let app_handle = app_handle.clone();
+                    tauri::async_runtime::spawn(async move {
+                        if let Err(e) = crate::ui::tray::hide_to_tray(&app_handle).await {
+                            log_important!(error, "隐藏到托盘失败: {}", e);
+                        }
+                    });
+                    return;
+                }
                 
                 // 异步处理退出请求
                 tauri::async_runtime::spawn(async move {
@@ -70,14 +87,16 @@ pub fn setup_window_event_listeners(app_handle: &AppHandle) {
                             let scale_factor = window.scale_factor().unwrap_or(1.0);
                             let logical_width = width as f64 / scale_factor;
                             let logical_height = height as f64 / scale_factor;
-                            let (clamped_width, clamped_height) =
-                                window_constants::clamp_window_size(logical_width, logical_height);
 
                             {
                                 let mut config = match state.config.lock() {
                                     Ok(guard) => guard,
                                     Err(_) => return,
                                 };
+                                let (clamped_width, clamped_height) = config
+                                    .ui_config
+                                    .window_config
+                                    .clamp_size(logical_width, logical_height);
                                 config
                                     .ui_config
                                     .window_config
@@ -0,0 +1,48 @@
+use std::path::Path;
+
+/// 检查路径所在磁盘的剩余空间是否足够
+/// 路径尚不存在时沿父目录向上查找第一个存在的目录；空间不足时返回包含可用/所需大小的友好错误
+pub fn check_free_space(path: &Path, required_bytes: u64) -> Result<(), String> {
+    let existing = path
+        .ancestors()
+        .find(|p| p.exists())
+        .ok_or_else(|| format!("无法确定磁盘位置: {}", path.display()))?;
+
+    let available = fs2::available_space(existing)
+        .map_err(|e| format!("获取磁盘剩余空间失败 ({}): {}", existing.display(), e))?;
+
+    if available < required_bytes {
+        return Err(format!(
+            "磁盘空间不足：{} 所在磁盘可用 {}，至少需要 {}，请清理磁盘后重试",
+            existing.display(),
+            format_bytes(available),
+            format_bytes(required_bytes)
+        ));
+    }
+
+    log::debug!(
+        "磁盘空间检查通过: {} 可用 {}，需要 {}",
+        existing.display(),
+        format_bytes(available),
+        format_bytes(required_bytes)
+    );
+    Ok(())
+}
+
+/// 将字节数格式化为便于阅读的大小
+pub fn format_bytes(bytes: u64) -> String {
+    const KB: u64 = 1024;
+    const MB: u64 = 1024 * 1024;
+    const GB: u64 = 1024 * 1024 * 1024;
+
+    if bytes >= GB {
+        return format!("{:.2}GB", bytes as f64 / GB as f64);
+    }
+    if bytes >= MB {
+        return format!("{:.2}MB", bytes as f64 / MB as f64);
+    }
+    if bytes >= KB {
+        return format!("{:.2}KB", bytes as f64 / KB as f64);
+    }
+    format!("{}B", bytes)
+}